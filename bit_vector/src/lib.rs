@@ -1,8 +1,9 @@
+#[cfg(test)]
 mod tests;
 
 const WORD_BITS: usize = 64;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct BitVector {
     inner: Vec<u64>,
     size: usize,
@@ -10,13 +11,13 @@ pub struct BitVector {
 
 impl BitVector {
     pub fn new(size: usize) -> BitVector {
-        let inner = vec![0; (size + WORD_BITS - 1) / WORD_BITS];
+        let inner = vec![0; size.div_ceil(WORD_BITS)];
         BitVector { inner, size }
     }
 
     pub fn set_all_true(&mut self) {
         for i in self.inner.iter_mut() {
-            *i = u64::max_value();
+            *i = u64::MAX;
         }
     }
 
@@ -1,3 +1,4 @@
+
 use crate::BitVector;
 
 #[test]
@@ -1,6 +1,5 @@
 mod tests;
 
-use std::cmp::{min};
 use std::str::Chars;
 use std::slice::Iter;
 
@@ -55,7 +54,7 @@ impl<'a> Cursor<'a> {
     pub fn bump_n(&mut self, n: usize) -> char {
         match self.chars.nth(n) {
             Some(c) => {
-                self.eaten_len = min(self.eaten_len + n + 1, self.input.len());
+                self.eaten_len = self.input.len() - self.chars.as_str().len();
                 #[cfg(debug_assertions)]
                 {
                     self.prev = c;
@@ -214,7 +213,7 @@ pub fn is_id_start(c: char) -> bool {
     // We also add fast-path for ascii idents
     match c {
         'a'..='z' | 'A'..='Z' | '_' => true,
-        c => (c > '\x7f' && unicode_xid::UnicodeXID::is_xid_start(c)),
+        c => c > '\x7f' && unicode_xid::UnicodeXID::is_xid_start(c),
     }
 }
 
@@ -226,6 +225,6 @@ pub fn is_id_continue(c: char) -> bool {
     // We also add fast-path for ascii idents
     match c {
         'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => true,
-        c => (c > '\x7f' && unicode_xid::UnicodeXID::is_xid_start(c)),
+        c => c > '\x7f' && unicode_xid::UnicodeXID::is_xid_start(c),
     }
 }
@@ -48,7 +48,7 @@ pub fn tokenize(mut input: String) -> Result<VecDeque<Token>, String> {
     while !input.is_empty() {
         let (token, len) = advance_token(&input);
         if token == Unknown {
-            return Err(format!("unknown character {}", input[..len].to_string()));
+            return Err(format!("unknown character {}", &input[..len]));
         } else if token != WhiteSpace {
             deque.push_back(token);
         }
@@ -14,7 +14,7 @@ fn main() {
         input.clear();
         print!(">>> ");
         io::stdout().flush().unwrap();
-        stdin().read_line(&mut input);
+        stdin().read_line(&mut input).unwrap();
         if input.eq("exit\n") {
             break;
         }
@@ -0,0 +1,484 @@
+//! Exhaustiveness and reachability check of patterns.
+//!
+//! [Warnings for pattern matching]: http://moscova.inria.fr/~maranget/papers/warn/warn.pdf
+
+use crate::analyser::sym_resolver::{TypeInfo, VariantKind};
+use crate::ast::expr::{Expr, RangeOp, UnOp};
+use crate::ast::pattern::Pattern;
+use crate::ast::types::{PtrKind, TypeLitNum};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Constructor {
+    /// discriminant of an enum variant
+    Variant(usize),
+    Bool(bool),
+    /// the only constructor of tuples (including `()`), structs and references
+    Single,
+    /// integers and chars in `lo..=hi`, there are too many of them to be listed,
+    /// so a type is covered by ranges, which are split by `split_ranges` before use
+    IntRange(i128, i128),
+    /// other literals
+    Literal(String),
+}
+
+impl Constructor {
+    fn field_types(&self, type_info: &TypeInfo) -> Vec<TypeInfo> {
+        match (self, type_info) {
+            (Constructor::Variant(i), TypeInfo::Enum { variants, .. }) => {
+                variants.get()[*i].field_types()
+            }
+            (Constructor::Single, TypeInfo::Tuple(types)) => types.clone(),
+            (Constructor::Single, TypeInfo::Struct { fields, .. }) => fields.types(),
+            (Constructor::Single, TypeInfo::Ptr { type_info, .. }) => vec![*type_info.clone()],
+            _ => vec![],
+        }
+    }
+
+    /// Whether all the values built by `other` are built by `self`.
+    fn covers(&self, other: &Constructor) -> bool {
+        match (self, other) {
+            (Constructor::IntRange(lo1, hi1), Constructor::IntRange(lo2, hi2)) => {
+                lo1 <= lo2 && hi2 <= hi1
+            }
+            _ => self == other,
+        }
+    }
+
+    /// Format the pattern built by the constructor, e.g. `Shape::Rect(_, _)`.
+    fn fmt(&self, type_info: &TypeInfo, fields: Vec<String>) -> String {
+        match (self, type_info) {
+            (Constructor::Variant(i), TypeInfo::Enum { name, variants, .. }) => {
+                let variant = &variants.get()[*i];
+                match variant.kind {
+                    VariantKind::Unit => format!("{}::{}", name, variant.name),
+                    VariantKind::Tuple => {
+                        format!("{}::{}({})", name, variant.name, fields.join(", "))
+                    }
+                    VariantKind::Struct => {
+                        let fields: Vec<String> = variant
+                            .fields
+                            .iter()
+                            .zip(fields)
+                            .map(|((field_name, _), f)| format!("{}: {}", field_name, f))
+                            .collect();
+                        format!("{}::{} {{ {} }}", name, variant.name, fields.join(", "))
+                    }
+                }
+            }
+            (Constructor::Bool(b), _) => b.to_string(),
+            (Constructor::Single, TypeInfo::Struct { name, fields: names, .. }) => {
+                let fields: Vec<String> = names
+                    .names()
+                    .iter()
+                    .zip(fields)
+                    .map(|(field_name, f)| format!("{}: {}", field_name, f))
+                    .collect();
+                format!("{} {{ {} }}", name, fields.join(", "))
+            }
+            (Constructor::Single, TypeInfo::Ptr { kind, .. }) => match kind {
+                PtrKind::MutRef => format!("&mut {}", fields[0]),
+                _ => format!("&{}", fields[0]),
+            },
+            (Constructor::Single, _) if fields.len() == 1 => format!("({},)", fields[0]),
+            (Constructor::Single, _) => format!("({})", fields.join(", ")),
+            (Constructor::IntRange(lo, hi), t) => {
+                let fmt_int = |i: i128| match (t, char::from_u32(i as u32)) {
+                    (TypeInfo::Char, Some(c)) => format!("{:?}", c),
+                    (TypeInfo::LitNum(t), _) => {
+                        // `#i` is `i32` by default
+                        let t = if *t == TypeLitNum::I { TypeLitNum::I32 } else { *t };
+                        match int_bounds(t) {
+                            Some((min, _)) if i == min && min != 0 => format!("{}::MIN", t),
+                            Some((_, max)) if i == max => format!("{}::MAX", t),
+                            _ => i.to_string(),
+                        }
+                    }
+                    _ => i.to_string(),
+                };
+                if lo == hi {
+                    fmt_int(*lo)
+                } else {
+                    format!("{}..={}", fmt_int(*lo), fmt_int(*hi))
+                }
+            }
+            (Constructor::Literal(lit), _) => lit.clone(),
+            (c, t) => unreachable!("invalid constructor {:?} of {:?}", c, t),
+        }
+    }
+}
+
+/// All the constructors of the type, or `None` if they are too many to be listed.
+fn all_constructors(type_info: &TypeInfo) -> Option<Vec<Constructor>> {
+    match type_info {
+        TypeInfo::Enum { variants, .. } => Some((0..variants.len()).map(Constructor::Variant).collect()),
+        TypeInfo::Bool => Some(vec![Constructor::Bool(false), Constructor::Bool(true)]),
+        TypeInfo::Tuple(_) | TypeInfo::Unit | TypeInfo::Struct { .. } => {
+            Some(vec![Constructor::Single])
+        }
+        TypeInfo::Ptr {
+            kind: PtrKind::Ref | PtrKind::MutRef,
+            ..
+        } => Some(vec![Constructor::Single]),
+        TypeInfo::Never => Some(vec![]),
+        // surrogates are not chars
+        TypeInfo::Char => Some(vec![
+            Constructor::IntRange(0, 0xD7FF),
+            Constructor::IntRange(0xE000, 0x10FFFF),
+        ]),
+        TypeInfo::LitNum(t) => int_bounds(*t).map(|(lo, hi)| vec![Constructor::IntRange(lo, hi)]),
+        _ => None,
+    }
+}
+
+/// `MIN` and `MAX` of the integer type.
+///
+/// `isize` and `usize` have no bounds since their sizes depend on the target, like rustc,
+/// and `u128::MAX` does not fit in `i128`.
+fn int_bounds(t: TypeLitNum) -> Option<(i128, i128)> {
+    let bounds = match t {
+        TypeLitNum::I8 => (i8::MIN as i128, i8::MAX as i128),
+        TypeLitNum::I16 => (i16::MIN as i128, i16::MAX as i128),
+        TypeLitNum::I | TypeLitNum::I32 => (i32::MIN as i128, i32::MAX as i128),
+        TypeLitNum::I64 => (i64::MIN as i128, i64::MAX as i128),
+        TypeLitNum::I128 => (i128::MIN, i128::MAX),
+        TypeLitNum::U8 => (0, u8::MAX as i128),
+        TypeLitNum::U16 => (0, u16::MAX as i128),
+        TypeLitNum::U32 => (0, u32::MAX as i128),
+        TypeLitNum::U64 => (0, u64::MAX as i128),
+        _ => return None,
+    };
+    Some(bounds)
+}
+
+/// Split the ranges of `ctors` at the bounds of the ranges in `used`,
+/// so that every piece is either covered by or disjoint from each of `used`.
+/// Then a range is covered by the union of `used`
+/// if and only if each of its pieces is covered by one of `used`.
+fn split_ranges(ctors: Vec<Constructor>, used: &[&Constructor]) -> Vec<Constructor> {
+    let mut bounds: Vec<i128> = used
+        .iter()
+        .filter_map(|c| match c {
+            Constructor::IntRange(lo, hi) => Some([Some(*lo), hi.checked_add(1)]),
+            _ => None,
+        })
+        .flatten()
+        .flatten()
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut pieces = vec![];
+    for ctor in ctors {
+        match ctor {
+            Constructor::IntRange(start, hi) => {
+                let mut lo = start;
+                for &bound in bounds.iter().filter(|&&b| start < b && b <= hi) {
+                    pieces.push(Constructor::IntRange(lo, bound - 1));
+                    lo = bound;
+                }
+                pieces.push(Constructor::IntRange(lo, hi));
+            }
+            ctor => pieces.push(ctor),
+        }
+    }
+    pieces
+}
+
+/// Constructors of the first column of the matrix.
+fn head_ctors(matrix: &[Vec<Pat>]) -> Vec<&Constructor> {
+    matrix
+        .iter()
+        .filter_map(|r| match &r[0] {
+            Pat::Constructed(c, _) => Some(c),
+            Pat::Wild => None,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+enum Pat {
+    /// wildcards and bindings
+    Wild,
+    Constructed(Constructor, Vec<Pat>),
+}
+
+impl Pat {
+    /// The pattern has been checked against the type by the symbol resolver.
+    fn from_pattern(pattern: &Pattern, type_info: &TypeInfo) -> Pat {
+        match pattern {
+            Pattern::Identifier(_) | Pattern::Wildcard => Pat::Wild,
+            Pattern::Literal(expr) => match expr.as_ref() {
+                Expr::LitBool(b) => Pat::Constructed(Constructor::Bool(*b), vec![]),
+                expr => match literal_int(expr) {
+                    Some(i) => Pat::Constructed(Constructor::IntRange(i, i), vec![]),
+                    None => Pat::Constructed(Constructor::Literal(literal_value(expr)), vec![]),
+                },
+            },
+            Pattern::Range(lo, op, hi) => match (literal_int(lo), literal_int(hi)) {
+                (Some(lo), Some(hi)) => {
+                    let hi = if *op == RangeOp::DotDot { hi - 1 } else { hi };
+                    Pat::Constructed(Constructor::IntRange(lo, hi), vec![])
+                }
+                _ => {
+                    let range = format!("{}..{}", literal_value(lo), literal_value(hi));
+                    Pat::Constructed(Constructor::Literal(range), vec![])
+                }
+            },
+            Pattern::Path(path) => Pat::Constructed(variant(type_info, &path.segments), vec![]),
+            Pattern::TupleStruct(path, patterns) => {
+                Pat::constructed(variant(type_info, &path.segments), patterns, type_info)
+            }
+            Pattern::Struct(struct_pattern) => {
+                let (ctor, names) = match type_info {
+                    TypeInfo::Struct { fields, .. } => (Constructor::Single, fields.names()),
+                    TypeInfo::Enum { variants, .. } => {
+                        let name = struct_pattern.path.segments.last().unwrap();
+                        let (i, variant) = variants.find(name).unwrap();
+                        let names = variant.fields.into_iter().map(|(n, _)| n).collect();
+                        (Constructor::Variant(i), names)
+                    }
+                    t => unreachable!("struct pattern of type {:?}", t),
+                };
+                // fields omitted by `..` are wildcards
+                let fields = names
+                    .iter()
+                    .zip(ctor.field_types(type_info).iter())
+                    .map(|(name, t)| {
+                        match struct_pattern.fields.iter().find(|f| &f.name == name) {
+                            Some(f) => Pat::from_pattern(&f.pattern, t),
+                            None => Pat::Wild,
+                        }
+                    })
+                    .collect();
+                Pat::Constructed(ctor, fields)
+            }
+            Pattern::Tuple(patterns) => Pat::constructed(Constructor::Single, patterns, type_info),
+            Pattern::Reference(pattern, _) => {
+                Pat::constructed(Constructor::Single, std::slice::from_ref(pattern.as_ref()), type_info)
+            }
+        }
+    }
+
+    fn constructed(ctor: Constructor, patterns: &[Pattern], type_info: &TypeInfo) -> Pat {
+        let fields = patterns
+            .iter()
+            .zip(ctor.field_types(type_info).iter())
+            .map(|(p, t)| Pat::from_pattern(p, t))
+            .collect();
+        Pat::Constructed(ctor, fields)
+    }
+}
+
+fn variant(type_info: &TypeInfo, segments: &[String]) -> Constructor {
+    match type_info {
+        TypeInfo::Enum { variants, .. } => {
+            Constructor::Variant(variants.find(segments.last().unwrap()).unwrap().0)
+        }
+        t => unreachable!("variant pattern of type {:?}", t),
+    }
+}
+
+/// The value of an integer or char literal like `-1` and `'a'`.
+pub(crate) fn literal_int(expr: &Expr) -> Option<i128> {
+    match expr {
+        Expr::LitNum(lit_num) => lit_num.value.parse::<i128>().ok(),
+        Expr::LitChar(c) => Some(*c as i128),
+        Expr::Unary(unary_expr) if unary_expr.op == UnOp::Neg => {
+            literal_int(&unary_expr.expr).map(|i| -i)
+        }
+        _ => None,
+    }
+}
+
+fn literal_value(expr: &Expr) -> String {
+    match expr {
+        Expr::LitNum(lit_num) => match lit_num.value.parse::<i128>() {
+            Ok(i) => i.to_string(),
+            Err(_) => lit_num.value.clone(),
+        },
+        Expr::LitChar(c) => format!("{:?}", c),
+        Expr::Unary(unary_expr) if unary_expr.op == UnOp::Neg => {
+            format!("-{}", literal_value(&unary_expr.expr))
+        }
+        e => unreachable!("invalid literal pattern {:?}", e),
+    }
+}
+
+/// Return the rest of the row if its first pattern matches the constructor,
+/// the fields of the constructor are expanded in place.
+fn specialize(row: &[Pat], ctor: &Constructor, arity: usize) -> Option<Vec<Pat>> {
+    let mut specialized = match &row[0] {
+        Pat::Wild => vec![Pat::Wild; arity],
+        Pat::Constructed(c, fields) if c.covers(ctor) => fields.clone(),
+        Pat::Constructed(..) => return None,
+    };
+    specialized.extend_from_slice(&row[1..]);
+    Some(specialized)
+}
+
+/// Whether there is a value matched by `row` but none of the rows in `matrix`.
+/// Return such values formatted as patterns, only the first one is searched for unless `all`.
+fn useful(matrix: &[Vec<Pat>], row: &[Pat], types: &[TypeInfo], all: bool) -> Vec<Vec<String>> {
+    let head = match row.first() {
+        Some(head) => head,
+        None => {
+            return if matrix.is_empty() {
+                vec![vec![]]
+            } else {
+                vec![]
+            }
+        }
+    };
+    let type_info = &types[0];
+    match head {
+        Pat::Constructed(ctor, _) => {
+            let row = specialize(row, ctor, 0).unwrap();
+            // a range is useful if one of its pieces is
+            let pieces = split_ranges(vec![ctor.clone()], &head_ctors(matrix));
+            witnesses_of_ctors(&pieces, all, |c| {
+                useful_specialized(matrix, c, &row, types, all)
+            })
+        }
+        Pat::Wild => {
+            let used = head_ctors(matrix);
+            let is_used = |c: &Constructor| used.iter().any(|u| u.covers(c));
+            let ctors = all_constructors(type_info).map(|ctors| split_ranges(ctors, &used));
+            // the rest of the row under the constructors not in the first column
+            let default_witnesses = || {
+                let default: Vec<Vec<Pat>> = matrix
+                    .iter()
+                    .filter(|r| matches!(r[0], Pat::Wild))
+                    .map(|r| r[1..].to_vec())
+                    .collect();
+                useful(&default, &row[1..], &types[1..], all)
+            };
+            let with_head = |head: String, rest: Vec<Vec<String>>| -> Vec<Vec<String>> {
+                rest.into_iter()
+                    .map(|mut witness| {
+                        witness.insert(0, head.clone());
+                        witness
+                    })
+                    .collect()
+            };
+            match ctors {
+                // every missing constructor is a witness when all of them are wanted
+                Some(ctors) if ctors.iter().all(is_used) || all && !used.is_empty() => {
+                    witnesses_of_ctors(&ctors, all, |c| {
+                        let arity = c.field_types(type_info).len();
+                        if is_used(c) {
+                            let row = specialize(row, c, arity).unwrap();
+                            useful_specialized(matrix, c, &row, types, all)
+                        } else {
+                            let head = c.fmt(type_info, vec!["_".to_string(); arity]);
+                            with_head(head, default_witnesses())
+                        }
+                    })
+                }
+                _ => {
+                    let missing = ctors.and_then(|ctors| ctors.into_iter().find(|c| !is_used(c)));
+                    let head = match missing {
+                        Some(c) if !used.is_empty() => {
+                            let arity = c.field_types(type_info).len();
+                            c.fmt(type_info, vec!["_".to_string(); arity])
+                        }
+                        _ => "_".to_string(),
+                    };
+                    with_head(head, default_witnesses())
+                }
+            }
+        }
+    }
+}
+
+/// Search the witnesses under each of the constructors in turn,
+/// stop at the first one found unless `all`.
+fn witnesses_of_ctors(
+    ctors: &[Constructor],
+    all: bool,
+    mut witnesses_of: impl FnMut(&Constructor) -> Vec<Vec<String>>,
+) -> Vec<Vec<String>> {
+    let mut witnesses = vec![];
+    for c in ctors {
+        witnesses.extend(witnesses_of(c));
+        if !all && !witnesses.is_empty() {
+            break;
+        }
+    }
+    witnesses
+}
+
+/// `row` has been specialized by the constructor.
+fn useful_specialized(
+    matrix: &[Vec<Pat>],
+    ctor: &Constructor,
+    row: &[Pat],
+    types: &[TypeInfo],
+    all: bool,
+) -> Vec<Vec<String>> {
+    let mut field_types = ctor.field_types(&types[0]);
+    let arity = field_types.len();
+    let specialized: Vec<Vec<Pat>> = matrix
+        .iter()
+        .filter_map(|r| specialize(r, ctor, arity))
+        .collect();
+    field_types.extend_from_slice(&types[1..]);
+
+    useful(&specialized, row, &field_types, all)
+        .into_iter()
+        .map(|mut fields| {
+            let rest = fields.split_off(arity);
+            let mut witness = vec![ctor.fmt(&types[0], fields)];
+            witness.extend(rest);
+            witness
+        })
+        .collect()
+}
+
+/// Check the arms `(pattern, has guard)` of a match expression on a value of `type_info`.
+/// Return the indexes of the unreachable arms, and a pattern not covered by the arms.
+///
+/// Arms with guards do not cover any value, because their guards may be false.
+pub(crate) fn check_arms(
+    type_info: &TypeInfo,
+    arms: &[(&Pattern, bool)],
+) -> (Vec<usize>, Option<String>) {
+    let types = [type_info.clone()];
+    let mut matrix: Vec<Vec<Pat>> = vec![];
+    let mut unreachable = vec![];
+    for (i, (pattern, has_guard)) in arms.iter().enumerate() {
+        let row = vec![Pat::from_pattern(pattern, type_info)];
+        // the first arm is reachable even if the type has no values
+        if !matrix.is_empty() && useful(&matrix, &row, &types, false).is_empty() {
+            unreachable.push(i);
+        }
+        if !has_guard {
+            matrix.push(row);
+        }
+    }
+    let missing = useful(&matrix, &[Pat::Wild], &types, false)
+        .pop()
+        .map(|mut witness| witness.remove(0));
+    (unreachable, missing)
+}
+
+/// Return the patterns not covered by `pattern`, formatted like `` `A`, `B` and `C` ``.
+pub(crate) fn check_irrefutable(type_info: &TypeInfo, pattern: &Pattern) -> Option<String> {
+    let types = [type_info.clone()];
+    let matrix = vec![vec![Pat::from_pattern(pattern, type_info)]];
+    let missing: Vec<String> = useful(&matrix, &[Pat::Wild], &types, true)
+        .into_iter()
+        .map(|mut witness| format!("`{}`", witness.remove(0)))
+        .collect();
+    match missing.as_slice() {
+        [] => None,
+        [witness] => Some(witness.clone()),
+        [init @ .., last] if missing.len() <= 3 => {
+            Some(format!("{} and {}", init.join(", "), last))
+        }
+        _ => Some(format!(
+            "{} and {} more",
+            missing[..3].join(", "),
+            missing.len() - 3
+        )),
+    }
+}
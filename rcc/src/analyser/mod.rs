@@ -2,9 +2,11 @@
 //! - Symbol resolving
 //! - Type check
 //! - Flow control check
+//! - Exhaustiveness check of patterns
 //!
 
 pub mod sym_resolver;
 pub mod scope;
+mod exhaustiveness;
 #[cfg(test)]
 mod tests;
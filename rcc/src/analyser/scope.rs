@@ -2,8 +2,12 @@ use crate::analyser::sym_resolver::TypeInfo::*;
 use crate::analyser::sym_resolver::{TypeInfo, VarInfo, VarKind};
 use crate::ast::expr::BlockExpr;
 use crate::ast::file::File;
-use crate::ast::item::{Item, ItemFn, ItemStruct, ExternalItem, FnSignature};
+use crate::ast::item::{
+    ExternalItem, FnSignature, Item, ItemFn, ItemImpl, ItemStruct, ItemTrait, TraitItemFn, TypeEnum,
+};
+use crate::ast::types::{PtrKind, TypeAnnotation, TypeFnPtr};
 use crate::ast::types::TypeLitNum::*;
+use crate::ast::Visibility;
 use crate::ir::var_name::temp_local_var;
 use lazy_static::lazy_static;
 use std::cell::RefCell;
@@ -36,10 +40,129 @@ lazy_static! {
     };
 }
 
+/// A function defined in an impl block, or a function of a trait implemented by a type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssocFn {
+    /// `TypeInfo::Fn` whose `Self` types are replaced with the implemented type
+    pub type_info: TypeInfo,
+    /// whether the first parameter is `self`
+    pub is_method: bool,
+    /// the trait which the function belongs to
+    pub trait_name: Option<String>,
+}
+
+impl AssocFn {
+    fn new(item_fn: &ItemFn, self_type: &TypeAnnotation) -> AssocFn {
+        AssocFn::from_signature(
+            item_fn.vis(),
+            &TypeFnPtr::from_fn_signature(item_fn),
+            self_type,
+            item_fn.has_self_param(),
+            None,
+        )
+    }
+
+    fn from_signature(
+        vis: Visibility,
+        inner: &TypeFnPtr,
+        self_type: &TypeAnnotation,
+        is_method: bool,
+        trait_name: Option<String>,
+    ) -> AssocFn {
+        let inner = TypeFnPtr::new(
+            inner
+                .params
+                .iter()
+                .map(|t| t.replace_self(self_type))
+                .collect(),
+            inner.ret_type.replace_self(self_type),
+        );
+        AssocFn {
+            type_info: TypeInfo::Fn {
+                vis,
+                inner,
+                generics: vec![],
+            },
+            is_method,
+            trait_name,
+        }
+    }
+}
+
+/// Signatures of the functions of a trait, whose `Self` types are not replaced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitInfo {
+    pub name: String,
+    pub fns: Vec<TraitFnInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitFnInfo {
+    pub name: String,
+    pub inner: TypeFnPtr,
+    /// whether the first parameter is `self`
+    pub is_method: bool,
+    /// whether the function has a default implementation
+    pub is_provided: bool,
+}
+
+impl TraitInfo {
+    fn new(item_trait: &ItemTrait) -> TraitInfo {
+        let fns = item_trait
+            .items
+            .iter()
+            .map(|item| TraitFnInfo {
+                name: item.name().to_string(),
+                inner: TypeFnPtr::new(
+                    item.fn_params().type_annotations(),
+                    item.ret_type().clone(),
+                ),
+                is_method: item.fn_params().has_self_param(),
+                is_provided: matches!(item, TraitItemFn::Provided(_)),
+            })
+            .collect();
+        TraitInfo {
+            name: item_trait.name.clone(),
+            fns,
+        }
+    }
+
+    pub fn find_fn(&self, fn_name: &str) -> Option<&TraitFnInfo> {
+        self.fns.iter().find(|f| f.name == fn_name)
+    }
+
+    /// A trait can be made into an object if all of its functions are methods taking
+    /// `&self` or `&mut self`, and `Self` does not appear elsewhere in their signatures.
+    pub fn is_object_safe(&self) -> bool {
+        self.fns.iter().all(|f| {
+            let receiver_is_ref = matches!(
+                f.inner.params.first(),
+                Some(TypeAnnotation::Ptr(tp))
+                    if matches!(tp.ptr_kind, PtrKind::Ref | PtrKind::MutRef)
+                        && tp.type_anno.as_ref() == &TypeAnnotation::Identifier("Self".into())
+            );
+            f.is_method
+                && receiver_is_ref
+                && !f.inner.params[1..].iter().any(|t| t.contains_self())
+                && !f.inner.ret_type.contains_self()
+        })
+    }
+
+    /// Index of the function in the vtables of the trait.
+    pub fn vtable_index(&self, fn_name: &str) -> Option<usize> {
+        self.fns.iter().position(|f| f.name == fn_name)
+    }
+}
+
 pub struct Scope {
     pub scope_id: u64,
     father: Option<NonNull<Scope>>,
     pub(crate) types: HashMap<String, TypeInfo>,
+    /// type name -> (fn name -> associated fn)
+    assoc_fns: HashMap<String, HashMap<String, AssocFn>>,
+    traits: HashMap<String, TraitInfo>,
+    /// type name -> names of the traits implemented by the type
+    trait_impls: HashMap<String, Vec<String>>,
     variables: HashMap<String, Vec<VarInfo>>,
     pub cur_stmt_id: u64,
     temp_count: u64,
@@ -53,12 +176,23 @@ impl Scope {
             scope_id,
             father: None,
             types: HashMap::new(),
+            assoc_fns: HashMap::new(),
+            traits: HashMap::new(),
+            trait_impls: HashMap::new(),
             variables: HashMap::new(),
             cur_stmt_id: 0,
             temp_count: 0,
         }
     }
 
+    /// A scope defining some types temporarily, e.g. the generic parameters of a function.
+    pub fn with_types(father: &Scope, types: impl Iterator<Item = (String, TypeInfo)>) -> Scope {
+        let mut scope = Scope::new(father.scope_id);
+        scope.types.extend(types);
+        scope.father = Some(NonNull::from(father));
+        scope
+    }
+
     pub fn gen_temp_variable(&mut self, type_info: Rc<RefCell<TypeInfo>>) -> String {
         let kind = VarKind::Local;
         let ident = temp_local_var(self.temp_count, self.scope_id);
@@ -88,7 +222,7 @@ impl Scope {
                     return Some((unsafe { v.get_unchecked(0) }, s.scope_id));
                 }
                 while left < right {
-                    let mid = (left + right + 1) / 2;
+                    let mid = (left + right).div_ceil(2);
                     let stmt_id = unsafe { (*v.get_unchecked(mid)).stmt_id() };
                     // Let stmt and variable using stmt is impossible to be the same.
                     debug_assert_ne!(stmt_id, self.cur_stmt_id);
@@ -99,10 +233,28 @@ impl Scope {
                     }
                 }
                 return Some((unsafe { v.get_unchecked(left) }, s.scope_id));
-            } else if let Some(f) = s.father {
-                cur_scope = f.as_ptr();
             } else {
-                return None;
+                let f = s.father?;
+                cur_scope = f.as_ptr();
+            }
+        }
+    }
+
+    /// Whether the scope `scope_id` is this scope or one of its fathers inside the scope
+    /// `boundary`, e.g. whether a variable is declared in the body of a closure.
+    pub fn is_inside(&self, scope_id: u64, boundary: u64) -> bool {
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if s.scope_id == scope_id {
+                return true;
+            }
+            if s.scope_id == boundary {
+                return false;
+            }
+            match s.father {
+                Some(f) => cur_scope = f.as_ptr(),
+                None => return false,
             }
         }
     }
@@ -142,11 +294,104 @@ impl Scope {
         }
     }
 
+    /// Find the function defined in the impl blocks of the struct or enum named `type_name`.
+    pub fn find_assoc_fn(&self, type_name: &str, fn_name: &str) -> Option<&AssocFn> {
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if let Some(f) = s.assoc_fns.get(type_name).and_then(|fns| fns.get(fn_name)) {
+                return Some(f);
+            }
+            cur_scope = s.father?.as_ptr();
+        }
+    }
+
+    pub fn find_trait(&self, name: &str) -> Option<&TraitInfo> {
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if let Some(t) = s.traits.get(name) {
+                return Some(t);
+            }
+            cur_scope = s.father?.as_ptr();
+        }
+    }
+
+    /// Names of the traits implemented by the type named `type_name` in this scope and its fathers.
+    fn traits_implemented_by(&self, type_name: &str) -> Vec<String> {
+        let mut traits = vec![];
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if let Some(names) = s.trait_impls.get(type_name) {
+                traits.extend(names.iter().cloned());
+            }
+            match s.father {
+                Some(f) => cur_scope = f.as_ptr(),
+                None => return traits,
+            }
+        }
+    }
+
+    /// Whether the type implements the trait, a generic parameter implements
+    /// the traits in its bounds.
+    pub fn implements(&self, type_info: &TypeInfo, trait_name: &str) -> bool {
+        match type_info {
+            Param(param) => param.bounds.iter().any(|b| b == trait_name),
+            TraitObject(name) => name == trait_name,
+            t => t.impl_name().is_some_and(|name| {
+                self.traits_implemented_by(&name)
+                    .iter()
+                    .any(|n| n == trait_name)
+            }),
+        }
+    }
+
+    /// Find the function of the traits implemented by the type.
+    pub fn find_trait_fn(&self, type_info: &TypeInfo, fn_name: &str) -> Option<AssocFn> {
+        let (self_type, trait_names) = match type_info {
+            Param(param) => (
+                TypeAnnotation::Identifier(param.name.clone()),
+                param.bounds.clone(),
+            ),
+            TraitObject(name) => (TypeAnnotation::TraitObject(name.clone()), vec![name.clone()]),
+            t => {
+                let name = t.impl_name()?;
+                let traits = self.traits_implemented_by(&name);
+                (TypeAnnotation::Identifier(name), traits)
+            }
+        };
+        trait_names.iter().find_map(|trait_name| {
+            let fn_info = self.find_trait(trait_name)?.find_fn(fn_name)?;
+            Some(AssocFn::from_signature(
+                Visibility::Pub,
+                &fn_info.inner,
+                &self_type,
+                fn_info.is_method,
+                Some(trait_name.clone()),
+            ))
+        })
+    }
+
+    /// Find the function in the impl blocks of the type, or in the traits implemented by the type.
+    pub fn find_type_fn(&self, type_info: &TypeInfo, fn_name: &str) -> Option<AssocFn> {
+        type_info
+            .nominal_name()
+            .and_then(|name| self.find_assoc_fn(name, fn_name).cloned())
+            .or_else(|| self.find_trait_fn(type_info, fn_name))
+    }
+
     /// Add type definitions (functions, structs, etc.) to current scope.
     pub fn add_typedef(&mut self, item: &Item) {
         match item {
             Item::Fn(item_fn) => self.add_type_fn(item_fn),
             Item::Struct(item_struct) => self.add_type_struct(item_struct),
+            Item::Enum(item_enum) => self.add_type_enum(item_enum),
+            Item::Impl(item_impl) => self.add_type_impl(item_impl),
+            Item::Trait(item_trait) => {
+                self.traits
+                    .insert(item_trait.name.clone(), TraitInfo::new(item_trait));
+            }
             Item::ExternalBlock(item_external_block) => {
                for item in &item_external_block.external_items {
                    match item {
@@ -156,7 +401,6 @@ impl Scope {
                    }
                }
             }
-            _ => todo!(),
         }
     }
 
@@ -170,6 +414,35 @@ impl Scope {
         self.types.insert(item_struct.name().to_string(), type_info);
     }
 
+    fn add_type_enum(&mut self, item_enum: &TypeEnum) {
+        let type_info = TypeInfo::from_item_enum(item_enum);
+        self.types.insert(item_enum.name().to_string(), type_info);
+    }
+
+    /// Inherent impl blocks of types other than structs and enums, and trait impl blocks
+    /// of types other than structs, enums and primitive types are rejected
+    /// by `SymbolResolver::visit_item_impl`.
+    fn add_type_impl(&mut self, item_impl: &ItemImpl) {
+        let type_name = match &item_impl.self_type {
+            TypeAnnotation::Identifier(name) => name,
+            _ => return,
+        };
+        if let Some(trait_name) = &item_impl.trait_name {
+            self.trait_impls
+                .entry(type_name.clone())
+                .or_default()
+                .push(trait_name.clone());
+            return;
+        }
+        let fns = self.assoc_fns.entry(type_name.clone()).or_default();
+        for item_fn in item_impl.items.iter() {
+            fns.insert(
+                item_fn.name.clone(),
+                AssocFn::new(item_fn, &item_impl.self_type),
+            );
+        }
+    }
+
     pub fn set_father(&mut self, father: *mut Scope) {
         self.father = Some(unsafe { NonNull::new_unchecked(father) });
     }
@@ -195,9 +468,21 @@ impl ScopeStack {
     }
 
     pub fn enter_scope(&mut self, block_expr: &mut BlockExpr) {
-        block_expr.scope.set_father(self.cur_scope);
+        self.enter(&mut block_expr.scope);
+    }
+
+    /// Enter a scope which is not a block, e.g. the scope of a match arm.
+    pub fn enter(&mut self, scope: &mut Scope) {
+        scope.set_father(self.cur_scope);
+        self.scope_stack.push(self.cur_scope);
+        self.cur_scope = scope;
+    }
+
+    /// Enter a scope which has been entered before without changing its father,
+    /// e.g. the scope where a generic function is defined.
+    pub fn reenter(&mut self, scope: *mut Scope) {
         self.scope_stack.push(self.cur_scope);
-        self.cur_scope = &mut block_expr.scope;
+        self.cur_scope = scope;
     }
 
     pub fn exit_scope(&mut self) {
@@ -209,14 +494,6 @@ impl ScopeStack {
         }
     }
 
-    pub fn cur_scope_is_global(&mut self) -> bool {
-        if let Some(f) = &mut self.file_scope {
-            self.cur_scope == f.as_ptr()
-        } else {
-            false
-        }
-    }
-
     pub fn cur_scope(&self) -> &Scope {
         unsafe { &*self.cur_scope }
     }
@@ -1,29 +1,32 @@
-use crate::analyser::scope::{Scope, ScopeStack};
+use crate::analyser::exhaustiveness;
+use crate::analyser::scope::{AssocFn, Scope, ScopeStack, TraitInfo};
 use crate::analyser::sym_resolver::LoopKind::NotIn;
 use crate::analyser::sym_resolver::TypeInfo::Unknown;
 use crate::ast::expr::{
     ArrayExpr, ArrayIndexExpr, AssignExpr, AssignOp, BinOpExpr, BinOperator, BlockExpr, BreakExpr,
-    CallExpr, Expr, ExprKind, FieldAccessExpr, GroupedExpr, IfExpr, LhsExpr, LoopExpr, PathExpr,
-    RangeExpr, ReturnExpr, StructExpr, TupleExpr, TupleIndexExpr, UnAryExpr, UnOp, WhileExpr,
+    CallExpr, CastExpr, ClosureExpr, ContinueExpr, Expr, ExprKind, FieldAccessExpr, ForExpr, GroupedExpr, IfExpr, LhsExpr, LoopExpr, MatchArm,
+    MatchExpr, MethodCallExpr, PathExpr, RangeExpr, RangeOp, ReturnExpr, StructExpr, TupleExpr, TupleIndexExpr, UnAryExpr, UnOp, WhileExpr,
 };
 use crate::ast::expr::{ExprVisit, TypeInfoSetter};
 use crate::ast::file::File;
-use crate::ast::item::{Fields, Item, ItemExternalBlock, ItemFn, ItemStruct, TypeEnum, ExternalItemFn, FnSignature, ExternalItem};
-use crate::ast::pattern::{IdentPattern, Pattern};
+use crate::ast::item::{
+    ExternalItem, ExternalItemFn, Fields, FnParam, FnSignature, GenericParam, Item,
+    ItemExternalBlock, ItemFn, ItemImpl, ItemStruct, ItemTrait, TraitItemFn, TypeEnum,
+};
+use crate::ast::pattern::{Pattern, StructPattern};
 use crate::ast::stmt::{LetStmt, Stmt};
-use crate::ast::types::{PtrKind, TypeAnnotation, TypeFnPtr, TypeLitNum};
-use crate::ast::Visibility;
+use crate::ast::types::{PtrKind, TypeAnnotation, TypeFnPtr, TypeGeneric, TypeLitNum};
+use crate::ast::{NodeSpan, Visibility};
 use crate::rcc::RccError;
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
-use std::ptr::NonNull;
 use std::rc::Rc;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum VarKind {
-    Static,
-    Const,
     LitConst,
     LocalMut,
     Local,
@@ -59,22 +62,57 @@ pub enum TypeInfo {
     Fn {
         vis: Visibility,
         inner: TypeFnPtr,
+        generics: Vec<GenericParam>,
     },
 
     FnPtr(TypeFnPtr),
 
     Struct {
         vis: Visibility,
-        fields: NonNull<Fields>,
+        name: String,
+        fields: StructFields,
     },
 
-    Enum(TypeEnum),
+    Enum {
+        vis: Visibility,
+        name: String,
+        variants: EnumVariants,
+    },
 
     Ptr {
         kind: PtrKind,
         type_info: Box<TypeInfo>,
     },
 
+    /// `(i32, bool)`, the unit type `()` is `Unit`
+    Tuple(Vec<TypeInfo>),
+
+    /// `[elem_type; len]`
+    Array {
+        elem_type: Box<TypeInfo>,
+        len: u32,
+    },
+
+    /// A generic parameter like `T` in `fn max<T: Ord>`, which is replaced with the
+    /// type argument when the generic function is monomorphized.
+    Param(GenericParam),
+
+    /// `dyn Shape`, which is only used behind pointers
+    TraitObject(String),
+
+    /// The anonymous type of a closure expression.
+    Closure(ClosureInfo),
+
+    /// `start..end`, `start..=end`, `start..`, `..end`, `..=end` and `..`,
+    /// whose bounds are `elem_type`. `rev` if it is reversed by `.rev()`.
+    Range {
+        range_op: RangeOp,
+        has_start: bool,
+        has_end: bool,
+        elem_type: Box<TypeInfo>,
+        rev: bool,
+    },
+
     /// primitive type
     /// !
     Never,
@@ -90,16 +128,49 @@ pub enum TypeInfo {
 impl TypeInfo {
     pub(crate) fn from_type_anno(type_anno: &TypeAnnotation, cur_scope: &Scope) -> TypeInfo {
         match type_anno {
-            TypeAnnotation::Identifier(s) => cur_scope.find_def_except_fn(s),
+            TypeAnnotation::Identifier(s) => match cur_scope.find_def_except_fn(s) {
+                // missing generic arguments
+                TypeInfo::Struct { fields, .. } if fields.is_generic() => TypeInfo::Unknown,
+                t => t,
+            },
+            TypeAnnotation::Generic(tg) => match cur_scope.find_def_except_fn(&tg.name) {
+                TypeInfo::Struct { vis, name, fields }
+                    if fields.params().len() == tg.args.len() =>
+                {
+                    let args = tg
+                        .args
+                        .iter()
+                        .map(|t| TypeInfo::from_type_anno(t, cur_scope))
+                        .collect();
+                    TypeInfo::Struct {
+                        vis,
+                        name,
+                        fields: fields.instantiate(args),
+                    }
+                }
+                _ => TypeInfo::Unknown,
+            },
             TypeAnnotation::Never => TypeInfo::Never,
             TypeAnnotation::Unit => TypeInfo::Unit,
-            TypeAnnotation::Bool => TypeInfo::Bool,
-            TypeAnnotation::Str => TypeInfo::ref_str(),
-            TypeAnnotation::Char => TypeInfo::Char,
             TypeAnnotation::Ptr(tp) => TypeInfo::Ptr {
                 kind: tp.ptr_kind,
                 type_info: Box::new(TypeInfo::from_type_anno(&tp.type_anno, cur_scope)),
             },
+            TypeAnnotation::Tuple(types) => TypeInfo::Tuple(
+                types
+                    .iter()
+                    .map(|t| TypeInfo::from_type_anno(t, cur_scope))
+                    .collect(),
+            ),
+            TypeAnnotation::Array(ta) => TypeInfo::Array {
+                elem_type: Box::new(TypeInfo::from_type_anno(&ta._type, cur_scope)),
+                len: ta.len,
+            },
+            TypeAnnotation::TraitObject(name) => match cur_scope.find_trait(name) {
+                Some(_) => TypeInfo::TraitObject(name.clone()),
+                None => TypeInfo::Unknown,
+            },
+            TypeAnnotation::FnPtr(tp) => TypeInfo::FnPtr(tp.clone()),
             TypeAnnotation::Unknown => TypeInfo::Unknown,
             _ => todo!(),
         }
@@ -110,13 +181,25 @@ impl TypeInfo {
         Self::Fn {
             vis: item.vis(),
             inner: tp_fn_ptr,
+            generics: item.generics(),
         }
     }
 
+    /// The types of the fields are resolved later by `SymbolResolver::visit_item_struct`.
     pub(crate) fn from_item_struct(item: &ItemStruct) -> Self {
         Self::Struct {
             vis: item.vis(),
-            fields: NonNull::from(item.fields()),
+            name: item.name().to_string(),
+            fields: StructFields::new(item.generics().to_vec()),
+        }
+    }
+
+    /// The fields of the variants are resolved later by `SymbolResolver::visit_item_enum`.
+    pub(crate) fn from_item_enum(item: &TypeEnum) -> Self {
+        Self::Enum {
+            vis: item.vis(),
+            name: item.name().to_string(),
+            variants: EnumVariants::default(),
         }
     }
 
@@ -187,19 +270,565 @@ impl TypeInfo {
         self == other || self == &Self::Never || other == &Self::Never
     }
 
+    /// Whether `self` is `expected`, or a number literal (maybe in an array or
+    /// behind a pointer) whose type can be determined as `expected`.
+    /// e.g. `[LitNum(I); 2]` may be determined as `[u8; 2]`.
+    /// A non-generic function may be determined as a function pointer of the same signature.
+    pub fn may_be_determined_as(&self, expected: &Self) -> bool {
+        match (self, expected) {
+            (Self::LitNum(TypeLitNum::I), e) => e.is_integer(),
+            (Self::LitNum(TypeLitNum::F), e) => e.is_float(),
+            (
+                Self::Array { elem_type, len },
+                Self::Array {
+                    elem_type: expected_elem,
+                    len: expected_len,
+                },
+            ) => {
+                len == expected_len
+                    && (elem_type.may_be_determined_as(expected_elem)
+                        || *len == 0 && elem_type.is_unknown())
+            }
+            (Self::Tuple(types), Self::Tuple(expected_types)) => {
+                types.len() == expected_types.len()
+                    && types
+                        .iter()
+                        .zip(expected_types.iter())
+                        .all(|(t, e)| t.may_be_determined_as(e))
+            }
+            (
+                Self::Ptr { kind, type_info },
+                Self::Ptr {
+                    kind: expected_kind,
+                    type_info: expected_type,
+                },
+            ) => kind == expected_kind && type_info.may_be_determined_as(expected_type),
+            (
+                Self::Struct { fields, .. },
+                Self::Struct {
+                    fields: expected_fields,
+                    ..
+                },
+            ) if fields.is_generic() => {
+                fields.same_definition(expected_fields)
+                    && fields
+                        .args()
+                        .iter()
+                        .zip(expected_fields.args().iter())
+                        .all(|(t, e)| t.may_be_determined_as(e))
+            }
+            (
+                Self::Fn {
+                    inner, generics, ..
+                },
+                Self::FnPtr(expected_fn_ptr),
+            ) => generics.is_empty() && inner == expected_fn_ptr,
+            (
+                Self::Range {
+                    range_op,
+                    has_start,
+                    has_end,
+                    elem_type,
+                    rev,
+                },
+                Self::Range {
+                    range_op: expected_op,
+                    has_start: expected_start,
+                    has_end: expected_end,
+                    elem_type: expected_elem,
+                    rev: expected_rev,
+                },
+            ) => {
+                (range_op, has_start, has_end, rev)
+                    == (expected_op, expected_start, expected_end, expected_rev)
+                    && elem_type.may_be_determined_as(expected_elem)
+            }
+            (t, e) => t == e,
+        }
+    }
+
+    /// Integer and float literals whose types are not determined are `i32` and `f64`.
+    pub fn default_number_types(&self) -> TypeInfo {
+        match self {
+            TypeInfo::LitNum(TypeLitNum::I) => TypeInfo::LitNum(TypeLitNum::I32),
+            TypeInfo::LitNum(TypeLitNum::F) => TypeInfo::LitNum(TypeLitNum::F64),
+            TypeInfo::Ptr { kind, type_info } => TypeInfo::Ptr {
+                kind: *kind,
+                type_info: Box::new(type_info.default_number_types()),
+            },
+            TypeInfo::Tuple(types) => {
+                TypeInfo::Tuple(types.iter().map(|t| t.default_number_types()).collect())
+            }
+            TypeInfo::Array { elem_type, len } => TypeInfo::Array {
+                elem_type: Box::new(elem_type.default_number_types()),
+                len: *len,
+            },
+            TypeInfo::Range { elem_type, .. } => {
+                self.with_range_elem_type(elem_type.default_number_types())
+            }
+            TypeInfo::Struct { vis, name, fields } if fields.is_generic() => TypeInfo::Struct {
+                vis: *vis,
+                name: name.clone(),
+                fields: fields.instantiate(
+                    fields
+                        .args
+                        .iter()
+                        .map(|t| t.default_number_types())
+                        .collect(),
+                ),
+            },
+            t => t.clone(),
+        }
+    }
+
     pub fn is_unknown(&self) -> bool {
         self == &TypeInfo::Unknown
     }
 
+    /// Name of a struct or an enum, whose associated functions are found by the name.
+    pub fn nominal_name(&self) -> Option<&str> {
+        match self {
+            TypeInfo::Struct { name, .. } | TypeInfo::Enum { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Name of a struct, an enum or a primitive type, whose trait implementations
+    /// are found by the name, e.g. `Foo`, `i32`.
+    pub fn impl_name(&self) -> Option<String> {
+        match self {
+            TypeInfo::Struct { name, fields, .. } if !fields.is_generic() => Some(name.clone()),
+            TypeInfo::Enum { name, .. } => Some(name.clone()),
+            TypeInfo::LitNum(TypeLitNum::I | TypeLitNum::F) => None,
+            TypeInfo::LitNum(num) => Some(format!("{:?}", num)),
+            TypeInfo::Bool => Some("bool".into()),
+            TypeInfo::Char => Some("char".into()),
+            _ => None,
+        }
+    }
+
+    /// Replace the generic parameters in the type with the type arguments.
+    pub fn substitute(&self, type_args: &HashMap<String, TypeInfo>) -> TypeInfo {
+        if type_args.is_empty() {
+            return self.clone();
+        }
+        match self {
+            TypeInfo::Param(param) => match type_args.get(&param.name) {
+                Some(t) => t.clone(),
+                None => self.clone(),
+            },
+            TypeInfo::Ptr { kind, type_info } => TypeInfo::Ptr {
+                kind: *kind,
+                type_info: Box::new(type_info.substitute(type_args)),
+            },
+            TypeInfo::Tuple(types) => {
+                TypeInfo::Tuple(types.iter().map(|t| t.substitute(type_args)).collect())
+            }
+            TypeInfo::Array { elem_type, len } => TypeInfo::Array {
+                elem_type: Box::new(elem_type.substitute(type_args)),
+                len: *len,
+            },
+            TypeInfo::Struct { vis, name, fields } if fields.is_generic() => TypeInfo::Struct {
+                vis: *vis,
+                name: name.clone(),
+                fields: fields.instantiate(
+                    fields
+                        .args
+                        .iter()
+                        .map(|t| t.substitute(type_args))
+                        .collect(),
+                ),
+            },
+            TypeInfo::Closure(closure) => TypeInfo::Closure(closure.substitute(type_args)),
+            TypeInfo::Range { elem_type, .. } => {
+                self.with_range_elem_type(elem_type.substitute(type_args))
+            }
+            t => t.clone(),
+        }
+    }
+
     pub fn is_never(&self) -> bool {
         self == &TypeInfo::Never
     }
+
+    /// The range with the bounds of `elem_type`.
+    fn with_range_elem_type(&self, elem_type: TypeInfo) -> TypeInfo {
+        match self {
+            TypeInfo::Range {
+                range_op,
+                has_start,
+                has_end,
+                rev,
+                ..
+            } => TypeInfo::Range {
+                range_op: *range_op,
+                has_start: *has_start,
+                has_end: *has_end,
+                elem_type: Box::new(elem_type),
+                rev: *rev,
+            },
+            t => unreachable!("{:?} is not a range", t),
+        }
+    }
+
+    /// Types of the values stored in a struct or an enum.
+    fn member_types(&self) -> Vec<TypeInfo> {
+        match self {
+            TypeInfo::Struct { fields, .. } => fields.types(),
+            TypeInfo::Enum { variants, .. } => variants
+                .get()
+                .into_iter()
+                .flat_map(|v| v.fields.into_iter().map(|(_, t)| t))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Names and types of the fields of a struct in declaration order.
+///
+/// All the copies of a struct's `TypeInfo` share the same fields, so that the
+/// fields can be resolved after all the type definitions of a scope are added,
+/// and structs are compared by their definitions instead of their fields.
+///
+/// The fields of a generic struct like `Pair<i32>` are the fields of `Pair<T>`
+/// whose generic parameters are replaced with the type arguments.
+#[derive(Clone, Default)]
+pub struct StructFields {
+    fields: Rc<RefCell<Vec<(String, TypeInfo)>>>,
+    params: Rc<Vec<GenericParam>>,
+    args: Vec<TypeInfo>,
+}
+
+impl StructFields {
+    pub fn new(params: Vec<GenericParam>) -> StructFields {
+        StructFields {
+            fields: Rc::default(),
+            params: Rc::new(params),
+            args: vec![],
+        }
+    }
+
+    pub fn set(&self, fields: Vec<(String, TypeInfo)>) {
+        self.fields.replace(fields);
+    }
+
+    /// Return (index, type) of the field
+    pub fn find(&self, name: &str) -> Option<(usize, TypeInfo)> {
+        let fields = self.fields.borrow();
+        let (i, (_, type_info)) = fields
+            .iter()
+            .enumerate()
+            .find(|(_, (field_name, _))| field_name == name)?;
+        Some((i, type_info.substitute(&self.type_args())))
+    }
+
+    pub fn types(&self) -> Vec<TypeInfo> {
+        let type_args = self.type_args();
+        self.fields
+            .borrow()
+            .iter()
+            .map(|(_, t)| t.substitute(&type_args))
+            .collect()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.fields
+            .borrow()
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub fn params(&self) -> &[GenericParam] {
+        &self.params
+    }
+
+    pub fn is_generic(&self) -> bool {
+        !self.params.is_empty()
+    }
+
+    /// Fields of the generic struct with the type arguments.
+    pub fn instantiate(&self, args: Vec<TypeInfo>) -> StructFields {
+        debug_assert_eq!(self.params.len(), args.len());
+        StructFields {
+            fields: self.fields.clone(),
+            params: self.params.clone(),
+            args,
+        }
+    }
+
+    pub fn args(&self) -> &[TypeInfo] {
+        &self.args
+    }
+
+    /// Whether both are instances of the same generic struct.
+    pub fn same_definition(&self, other: &StructFields) -> bool {
+        Rc::ptr_eq(&self.fields, &other.fields)
+    }
+
+    /// generic parameter name -> type argument
+    fn type_args(&self) -> HashMap<String, TypeInfo> {
+        self.params
+            .iter()
+            .zip(self.args.iter())
+            .map(|(p, t)| (p.name.clone(), t.clone()))
+            .collect()
+    }
+}
+
+impl PartialEq for StructFields {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.fields, &other.fields) && self.args == other.args
+    }
+}
+
+impl Eq for StructFields {}
+
+impl Hash for StructFields {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.fields.as_ptr(), state);
+        self.args.hash(state);
+    }
+}
+
+impl Debug for StructFields {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // field types are not printed, because a struct may refer to itself by pointers
+        if self.args.is_empty() {
+            write!(f, "{:?}", self.names())
+        } else {
+            write!(f, "{:?} with {:?}", self.names(), self.args)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VariantKind {
+    /// `A`
+    Unit,
+    /// `B(i32, bool)`, names of the fields are "0", "1", ...
+    Tuple,
+    /// `C { x: i32 }`
+    Struct,
+}
+
+impl VariantKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariantKind::Unit => "unit",
+            VariantKind::Tuple => "tuple",
+            VariantKind::Struct => "struct",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VariantInfo {
+    pub name: String,
+    pub kind: VariantKind,
+    pub fields: Vec<(String, TypeInfo)>,
+}
+
+impl VariantInfo {
+    pub fn field_types(&self) -> Vec<TypeInfo> {
+        self.fields.iter().map(|(_, t)| t.clone()).collect()
+    }
+}
+
+/// Variants of an enum in declaration order, the discriminant of a variant is its index.
+///
+/// Like `StructFields`, all the copies of an enum's `TypeInfo` share the same variants.
+#[derive(Clone, Default)]
+pub struct EnumVariants(Rc<RefCell<Vec<VariantInfo>>>);
+
+impl EnumVariants {
+    pub fn set(&self, variants: Vec<VariantInfo>) {
+        self.0.replace(variants);
+    }
+
+    /// Return (discriminant, variant)
+    pub fn find(&self, name: &str) -> Option<(usize, VariantInfo)> {
+        self.0
+            .borrow()
+            .iter()
+            .enumerate()
+            .find(|(_, v)| v.name == name)
+            .map(|(i, v)| (i, v.clone()))
+    }
+
+    pub fn get(&self) -> Vec<VariantInfo> {
+        self.0.borrow().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// Whether no variant carries data.
+    pub fn is_c_like(&self) -> bool {
+        self.0.borrow().iter().all(|v| v.fields.is_empty())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.borrow().iter().map(|v| v.name.clone()).collect()
+    }
+}
+
+impl PartialEq for EnumVariants {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for EnumVariants {}
+
+impl Hash for EnumVariants {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.0.as_ptr(), state)
+    }
+}
+
+impl Debug for EnumVariants {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.names())
+    }
+}
+
+/// A variable of the enclosing scopes used in the body of a closure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    pub ident: String,
+    /// id of the scope where the variable is declared
+    pub scope_id: u64,
+    pub kind: VarKind,
+    pub type_info: TypeInfo,
+    /// The environment stores the address of the variable, or a copy of it
+    /// if the closure is a `move` closure.
+    pub by_ref: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ClosureSig {
+    params: Vec<TypeInfo>,
+    ret_type: TypeInfo,
+    captures: Vec<Capture>,
+    is_resolved: bool,
+}
+
+/// Signature and captured variables of a closure, which is identified by the scope of its body.
+///
+/// Like `EnumVariants`, all the copies of a closure's `TypeInfo` share the same signature,
+/// so that the types of parameters can be inferred when the closure is called.
+#[derive(Clone)]
+pub struct ClosureInfo {
+    scope_id: u64,
+    sig: Rc<RefCell<ClosureSig>>,
+}
+
+impl ClosureInfo {
+    pub fn new(scope_id: u64, params: Vec<TypeInfo>, ret_type: TypeInfo) -> ClosureInfo {
+        ClosureInfo {
+            scope_id,
+            sig: Rc::new(RefCell::new(ClosureSig {
+                params,
+                ret_type,
+                captures: vec![],
+                is_resolved: false,
+            })),
+        }
+    }
+
+    pub fn scope_id(&self) -> u64 {
+        self.scope_id
+    }
+
+    pub fn params(&self) -> Vec<TypeInfo> {
+        self.sig.borrow().params.clone()
+    }
+
+    pub fn ret_type(&self) -> TypeInfo {
+        self.sig.borrow().ret_type.clone()
+    }
+
+    pub fn captures(&self) -> Vec<Capture> {
+        self.sig.borrow().captures.clone()
+    }
+
+    /// Whether the body of the closure has been resolved.
+    pub fn is_resolved(&self) -> bool {
+        self.sig.borrow().is_resolved
+    }
+
+    fn resolve(&self, params: Vec<TypeInfo>, ret_type: TypeInfo) {
+        let mut sig = self.sig.borrow_mut();
+        sig.params = params;
+        sig.ret_type = ret_type;
+        sig.is_resolved = true;
+    }
+
+    fn add_capture(&self, capture: Capture) {
+        let mut sig = self.sig.borrow_mut();
+        if !sig
+            .captures
+            .iter()
+            .any(|c| c.ident == capture.ident && c.scope_id == capture.scope_id)
+        {
+            sig.captures.push(capture);
+        }
+    }
+
+    fn substitute(&self, type_args: &HashMap<String, TypeInfo>) -> ClosureInfo {
+        let sig = self.sig.borrow();
+        let captures = sig
+            .captures
+            .iter()
+            .map(|c| Capture {
+                type_info: c.type_info.substitute(type_args),
+                ..c.clone()
+            })
+            .collect();
+        ClosureInfo {
+            scope_id: self.scope_id,
+            sig: Rc::new(RefCell::new(ClosureSig {
+                params: sig.params.iter().map(|t| t.substitute(type_args)).collect(),
+                ret_type: sig.ret_type.substitute(type_args),
+                captures,
+                is_resolved: sig.is_resolved,
+            })),
+        }
+    }
+}
+
+impl PartialEq for ClosureInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.scope_id == other.scope_id
+    }
+}
+
+impl Eq for ClosureInfo {}
+
+impl Hash for ClosureInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.scope_id.hash(state)
+    }
+}
+
+impl Debug for ClosureInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{closure#{}}}", self.scope_id)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum LoopKind {
     NotIn,
     While,
+    For,
     Loop(*mut LoopExpr),
 }
 
@@ -214,13 +843,30 @@ pub struct SymbolResolver {
     scope_stack: ScopeStack,
 
     loop_kind: LoopKind,
-    loop_kind_stack: Vec<LoopKind>,
+    loop_label: Option<String>,
+    /// kinds and labels of the outer loops
+    loop_kind_stack: Vec<(LoopKind, Option<String>)>,
 
     cur_fn_ret_type: TypeInfo,
     cur_fn_ret_type_stack: Vec<TypeInfo>,
 
+    /// whether the expressions being resolved are in an `unsafe` block
+    in_unsafe: bool,
+
     // TODO: Operator override tables
     pub override_bin_ops: HashSet<(BinOperator, TypeInfo, TypeInfo)>,
+
+    /// (scope id, type name, trait name) of the trait impl blocks which have been visited
+    trait_impls: HashSet<(u64, String, String)>,
+
+    /// (scope id of the body, closure, captured by reference) of the closures being resolved,
+    /// the innermost one is the last
+    closure_stack: Vec<(u64, ClosureInfo, bool)>,
+    /// closures whose bodies are not resolved yet and the scopes where they are defined
+    pending_closures: HashMap<u64, (*mut ClosureExpr, *mut Scope)>,
+
+    /// errors of the statements and items which have been visited
+    errors: Vec<RccError>,
 }
 
 impl SymbolResolver {
@@ -228,10 +874,16 @@ impl SymbolResolver {
         SymbolResolver {
             scope_stack: ScopeStack::new(),
             loop_kind: NotIn,
+            loop_label: None,
             loop_kind_stack: vec![],
             cur_fn_ret_type: TypeInfo::Unknown,
             cur_fn_ret_type_stack: vec![],
+            in_unsafe: false,
             override_bin_ops: HashSet::new(),
+            trait_impls: HashSet::new(),
+            closure_stack: vec![],
+            pending_closures: HashMap::new(),
+            errors: vec![],
         }
     }
 
@@ -260,16 +912,12 @@ impl SymbolResolver {
                         } else if l_lit == &TypeLitNum::I && r_lit.is_integer()
                             || l_lit == &TypeLitNum::F && r_lit.is_float()
                         {
-                            if let Expr::LitNum(expr) = lhs {
-                                expr.set_type_info_ref(r_type.clone());
-                            }
+                            set_lit_num_type(lhs, r_type.clone());
                             r_type.clone()
                         } else if r_lit == &TypeLitNum::I && l_lit.is_integer()
                             || r_lit == &TypeLitNum::F && l_lit.is_float()
                         {
-                            if let Expr::LitNum(expr) = rhs {
-                                expr.set_type_info_ref(l_type.clone());
-                            }
+                            set_lit_num_type(rhs, l_type.clone());
                             l_type.clone()
                         } else {
                             Rc::new(RefCell::new(Unknown))
@@ -304,16 +952,12 @@ impl SymbolResolver {
                         } else if l_lit == &TypeLitNum::I && r_lit.is_integer()
                             || l_lit == &TypeLitNum::F && r_lit.is_float()
                         {
-                            if let Expr::LitNum(expr) = lhs {
-                                expr.set_type_info_ref(r_type.clone());
-                            }
+                            set_lit_num_type(lhs, r_type.clone());
                             Rc::new(RefCell::new(TypeInfo::Bool))
                         } else if r_lit == &TypeLitNum::I && l_lit.is_integer()
                             || r_lit == &TypeLitNum::F && l_lit.is_float()
                         {
-                            if let Expr::LitNum(expr) = rhs {
-                                expr.set_type_info_ref(l_type.clone());
-                            }
+                            set_lit_num_type(rhs, l_type.clone());
                             Rc::new(RefCell::new(TypeInfo::Bool))
                         } else {
                             Rc::new(RefCell::new(Unknown))
@@ -328,14 +972,10 @@ impl SymbolResolver {
                         return if l_lit == r_lit {
                             l_type.clone()
                         } else if l_lit == &TypeLitNum::I && r_lit.is_integer() {
-                            if let Expr::LitNum(expr) = lhs {
-                                expr.set_type_info_ref(l_type.clone());
-                            }
+                            set_lit_num_type(lhs, r_type.clone());
                             r_type.clone()
                         } else if r_lit == &TypeLitNum::I && l_lit.is_integer() {
-                            if let Expr::LitNum(expr) = rhs {
-                                expr.set_type_info_ref(l_type.clone());
-                            }
+                            set_lit_num_type(rhs, l_type.clone());
                             l_type.clone()
                         } else {
                             Rc::new(RefCell::new(Unknown))
@@ -357,14 +997,33 @@ impl SymbolResolver {
                 }
                 Rc::new(RefCell::new(Unknown))
             }
-            BinOperator::As => {
-                todo!()
-            }
         }
     }
 
+    fn enter_loop(&mut self, loop_kind: LoopKind, label: Option<String>) {
+        let outer_kind = std::mem::replace(&mut self.loop_kind, loop_kind);
+        let outer_label = std::mem::replace(&mut self.loop_label, label);
+        self.loop_kind_stack.push((outer_kind, outer_label));
+    }
+
     fn exit_loop(&mut self) {
-        self.loop_kind = self.loop_kind_stack.pop().expect("empty loop kind stack!");
+        (self.loop_kind, self.loop_label) =
+            self.loop_kind_stack.pop().expect("empty loop kind stack!");
+    }
+
+    /// The loop which `break` or `continue` jumps out of, the innermost one without label.
+    /// Loops outside of the closure being resolved are not visible.
+    fn target_loop(&self, label: &Option<String>, keyword: &str) -> Result<LoopKind, RccError> {
+        match label {
+            Some(label) => std::iter::once((&self.loop_kind, &self.loop_label))
+                .chain(self.loop_kind_stack.iter().rev().map(|(kind, label)| (kind, label)))
+                .take_while(|(kind, _)| kind.is_in_loop())
+                .find(|(_, l)| l.as_ref() == Some(label))
+                .map(|(kind, _)| *kind)
+                .ok_or_else(|| format!("use of undeclared label `{}`", label).into()),
+            None if self.loop_kind.is_in_loop() => Ok(self.loop_kind),
+            None => Err(format!("{} expr can not be out of loop block", keyword).into()),
+        }
     }
 
     fn try_determine_number_type(
@@ -374,14 +1033,132 @@ impl SymbolResolver {
         let type_info = expr.type_info();
         let type_info = type_info.borrow();
 
-        if expected_num_type.is_integer() && type_info.is_i()
-            || expected_num_type.is_float() && type_info.is_f()
+        if type_info.deref() != expected_num_type
+            && type_info.may_be_determined_as(expected_num_type)
         {
             std::mem::drop(type_info);
             expr.set_type_info(expected_num_type.clone());
         }
     }
 
+    /// Coerce `&T` to `&dyn Trait` if `expected` is a trait object implemented by `T`.
+    /// Return whether `expr` is coerced.
+    fn try_unsize(&self, expected: &TypeInfo, expr: &mut Expr) -> Result<bool, RccError> {
+        let (expected_kind, trait_name) = match expected {
+            TypeInfo::Ptr {
+                kind: kind @ (PtrKind::Ref | PtrKind::MutRef),
+                type_info,
+            } => match type_info.as_ref() {
+                TypeInfo::TraitObject(name) => (*kind, name),
+                _ => return Ok(false),
+            },
+            _ => return Ok(false),
+        };
+        let (kind, type_info) = match expr.type_info().borrow().deref() {
+            TypeInfo::Ptr { kind, type_info }
+                if (*kind == expected_kind || *kind == PtrKind::MutRef)
+                    && !matches!(type_info.as_ref(), TypeInfo::TraitObject(_)) =>
+            {
+                (*kind, type_info.default_number_types())
+            }
+            _ => return Ok(false),
+        };
+
+        let cur_scope = self.scope_stack.cur_scope();
+        if !cur_scope.find_trait(trait_name).unwrap().is_object_safe() {
+            return Err(format!("the trait `{}` cannot be made into an object", trait_name).into());
+        }
+        if !cur_scope.implements(&type_info, trait_name) {
+            return Err(format!(
+                "the trait bound `{}: {}` is not satisfied",
+                type_name(&type_info),
+                trait_name
+            )
+            .into());
+        }
+        expr.set_type_info(TypeInfo::Ptr {
+            kind,
+            type_info: Box::new(type_info),
+        });
+        wrap_unary(expr, UnOp::Unsize, expected.clone(), ExprKind::Value);
+        Ok(true)
+    }
+
+    /// Coerce a closure capturing nothing to a function pointer of the same signature,
+    /// the types of its parameters are inferred from the function pointer if needed.
+    /// Return whether `expr` is coerced.
+    fn try_closure_fn_ptr(&mut self, expected: &TypeInfo, expr: &mut Expr) -> Result<bool, RccError> {
+        let type_fn_ptr = match expected {
+            TypeInfo::FnPtr(type_fn_ptr) => type_fn_ptr,
+            _ => return Ok(false),
+        };
+        let closure = match expr.type_info().borrow().deref() {
+            TypeInfo::Closure(closure) => closure.clone(),
+            _ => return Ok(false),
+        };
+        let cur_scope = self.scope_stack.cur_scope();
+        let params: Vec<TypeInfo> = type_fn_ptr
+            .params
+            .iter()
+            .map(|t| TypeInfo::from_type_anno(t, cur_scope))
+            .collect();
+        let ret_type = TypeInfo::from_type_anno(&type_fn_ptr.ret_type, cur_scope);
+        if !closure.is_resolved() {
+            if closure.params().len() != params.len() {
+                return Ok(false);
+            }
+            self.resolve_closure(&closure, params.clone())?;
+        }
+        if !closure.captures().is_empty() {
+            return Err(
+                "closures can only be coerced to `fn` types if they do not capture any variables"
+                    .into(),
+            );
+        }
+        if closure.params() != params || !closure.ret_type().is(&ret_type) {
+            return Ok(false);
+        }
+        wrap_unary(expr, UnOp::ClosureFnPtr, expected.clone(), ExprKind::Value);
+        Ok(true)
+    }
+
+    /// Coerce `&mut T` to `&T`, and references to raw pointers,
+    /// e.g. `&mut T` to `*mut T` or `*const T`. Return whether `expr` is coerced.
+    fn try_ptr_coercion(&self, expected: &TypeInfo, expr: &mut Expr) -> bool {
+        let (expected_kind, expected_type) = match expected {
+            TypeInfo::Ptr { kind, type_info } => (*kind, type_info),
+            _ => return false,
+        };
+        let kind = match expr.type_info().borrow().deref() {
+            TypeInfo::Ptr { kind, type_info } if type_info.may_be_determined_as(expected_type) => {
+                *kind
+            }
+            _ => return false,
+        };
+        let coercible = match (kind, expected_kind) {
+            (PtrKind::MutRef, _) => expected_kind != PtrKind::MutRef,
+            (PtrKind::Ref | PtrKind::MutRawPtr, PtrKind::ConstRawPtr) => true,
+            _ => false,
+        };
+        if coercible {
+            let ptr_info = TypeInfo::Ptr {
+                kind,
+                type_info: expected_type.clone(),
+            };
+            Self::try_determine_number_type(&ptr_info, expr);
+            wrap_unary(expr, UnOp::PtrCoercion, expected.clone(), ExprKind::Value);
+        }
+        coercible
+    }
+
+    /// Implicit coercions to the expected type. Return whether `expr` is coerced.
+    fn coerce(&mut self, expected: &TypeInfo, expr: &mut Expr) -> Result<bool, RccError> {
+        if self.try_unsize(expected, expr)? || self.try_ptr_coercion(expected, expr) {
+            return Ok(true);
+        }
+        self.try_closure_fn_ptr(expected, expr)
+    }
+
     fn validate_ret_type(&self, type_info: &TypeInfo) -> Result<(), RccError> {
         if type_info.is(&self.cur_fn_ret_type) {
             Ok(())
@@ -398,124 +1175,477 @@ impl SymbolResolver {
 impl SymbolResolver {
     pub(crate) fn visit_file(&mut self, file: &mut File) -> Result<(), RccError> {
         self.scope_stack.enter_file(file);
+        self.visit_item_types(file.items.iter_mut());
         for item in file.items.iter_mut() {
-            self.visit_item(item)?;
+            if let Err(e) = self.visit_item(item) {
+                self.errors.push(e);
+            }
+        }
+        for (closure_expr, _) in std::mem::take(&mut self.pending_closures).into_values() {
+            let span = unsafe { &*closure_expr }.span.0;
+            self.errors.push(RccError::with_span(
+                "type annotations needed for the parameters of the closure",
+                span,
+            ));
+        }
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(RccError::from_errors(std::mem::take(&mut self.errors)))
         }
-        Ok(())
     }
 
     fn visit_item(&mut self, item: &mut Item) -> Result<(), RccError> {
         match item {
-            Item::Fn(item_fn) => self.visit_item_fn(item_fn),
-            Item::Struct(item_struct) => self.visit_item_struct(item_struct),
+            Item::Fn(item_fn) => {
+                let span = item_fn.span.0;
+                self.visit_item_fn(item_fn).map_err(|e| e.or_span(span))
+            }
+            // fields of structs and enums are resolved before other items
+            Item::Struct(_) | Item::Enum(_) => Ok(()),
             Item::ExternalBlock(external_block) => self.visit_item_external_block(external_block),
-            _ => unimplemented!(),
+            Item::Impl(item_impl) => {
+                let span = item_impl.span.0;
+                self.visit_item_impl(item_impl).map_err(|e| e.or_span(span))
+            }
+            Item::Trait(item_trait) => {
+                let span = item_trait.span.0;
+                self.visit_item_trait(item_trait).map_err(|e| e.or_span(span))
+            }
         }
     }
 
     fn visit_expr(&mut self, expr: &mut Expr) -> Result<(), RccError> {
         let result = match expr {
             Expr::Path(path_expr) => self.visit_path_expr(path_expr),
-            Expr::LitNum(lit_num_expr) => Ok(()),
-            Expr::LitBool(lit_bool) => Ok(()),
-            Expr::LitChar(lig_char) => Ok(()),
+            Expr::LitNum(_) => Ok(()),
+            Expr::LitBool(_) => Ok(()),
+            Expr::LitChar(_) => Ok(()),
             Expr::LitStr(s) => self.visit_lit_str(s),
             Expr::Unary(unary_expr) => self.visit_unary_expr(unary_expr),
+            Expr::Cast(cast_expr) => self.visit_cast_expr(cast_expr),
             Expr::Block(block_expr) => self.visit_block_expr(block_expr),
             Expr::Assign(assign_expr) => self.visit_assign_expr(assign_expr),
-            // Expr::Range(range_expr) => self.visit_range_expr(range_expr),
+            Expr::Range(range_expr) => self.visit_range_expr(range_expr),
             Expr::BinOp(bin_op_expr) => self.visit_bin_op_expr(bin_op_expr),
             Expr::Grouped(grouped_expr) => self.visit_grouped_expr(grouped_expr),
-            // Expr::Array(array_expr) => self.visit_array_expr(array_expr),
-            // Expr::ArrayIndex(array_index_expr) => self.visit_array_index_expr(array_index_expr),
-            // Expr::Tuple(tuple_expr) => self.visit_tuple_expr(tuple_expr),
-            // Expr::TupleIndex(tuple_index_expr) => self.visit_tuple_index_expr(tuple_index_expr),
-            // Expr::Struct(struct_expr) => self.visit_struct_expr(struct_expr),
+            Expr::Array(array_expr) => self.visit_array_expr(array_expr),
+            Expr::ArrayIndex(array_index_expr) => self.visit_array_index_expr(array_index_expr),
+            Expr::Tuple(tuple_expr) => self.visit_tuple_expr(tuple_expr),
+            Expr::TupleIndex(tuple_index_expr) => self.visit_tuple_index_expr(tuple_index_expr),
+            Expr::Struct(struct_expr) => self.visit_struct_expr(struct_expr),
             Expr::Call(call_expr) => self.visit_call_expr(call_expr),
-            // Expr::FieldAccess(field_access_expr) => self.visit_field_access_expr(field_access_expr),
+            Expr::MethodCall(method_call_expr) => self.visit_method_call_expr(method_call_expr),
+            Expr::FieldAccess(field_access_expr) => self.visit_field_access_expr(field_access_expr),
             Expr::While(while_expr) => self.visit_while_expr(while_expr),
             Expr::Loop(loop_expr) => self.visit_loop_expr(loop_expr),
+            Expr::For(for_expr) => self.visit_for_expr(for_expr),
             Expr::If(if_expr) => self.visit_if_expr(if_expr),
+            Expr::Match(match_expr) => self.visit_match_expr(match_expr),
             Expr::Return(return_expr) => self.visit_return_expr(return_expr),
             Expr::Break(break_expr) => self.visit_break_expr(break_expr),
-            _ => unimplemented!(),
+            Expr::Continue(continue_expr) => self.visit_continue_expr(continue_expr),
+            Expr::Closure(closure_expr) => self.visit_closure_expr(closure_expr),
         };
-        debug_assert_ne!(
-            ExprKind::Unknown,
-            expr.kind(),
-            "unknown expr kind: {:?}",
-            expr
-        );
-        result
+        if result.is_ok() {
+            debug_assert_ne!(
+                ExprKind::Unknown,
+                expr.kind(),
+                "unknown expr kind: {:?}",
+                expr
+            );
+        }
+        result.map_err(|e| locate_at_expr(e, expr))
     }
 
     fn visit_lhs_expr(&mut self, lhs_expr: &mut LhsExpr) -> Result<(), RccError> {
-        let r = match lhs_expr {
+        match lhs_expr {
             LhsExpr::Path(expr) => self.visit_path_expr(expr)?,
-            _ => todo!("visit lhs expr"),
+            LhsExpr::ArrayIndex(expr) => self.visit_array_index_expr(expr)?,
+            LhsExpr::TupleIndex(expr) => self.visit_tuple_index_expr(expr)?,
+            LhsExpr::FieldAccess(expr) => self.visit_field_access_expr(expr)?,
+            LhsExpr::Deref(expr) => self.visit_unary_expr(expr)?,
         };
-        Ok(r)
+        Ok(())
     }
 
     fn visit_grouped_expr(&mut self, grouped_expr: &mut GroupedExpr) -> Result<(), RccError> {
         self.visit_expr(grouped_expr)
     }
 
+    /// Generic parameters and parameters are declared in the scope of the function block,
+    /// and the body of a generic function is checked once with the generic parameters.
     fn visit_item_fn(&mut self, item_fn: &mut ItemFn) -> Result<(), RccError> {
+        // visit params of function
+        self.scope_stack.enter(&mut item_fn.fn_block.scope);
+        let declared = self.declare_generics(&item_fn.generics).and_then(|_| {
+            item_fn
+                .fn_params
+                .params
+                .iter_mut()
+                .try_for_each(|param| self.declare_fn_param(param))
+        });
+        let ret_type = TypeInfo::from_type_anno(&item_fn.ret_type, self.scope_stack.cur_scope());
+        self.scope_stack.exit_scope();
+        declared?;
+
         // enter
         let mut temp_ret_type = Unknown;
         std::mem::swap(&mut self.cur_fn_ret_type, &mut temp_ret_type);
         self.cur_fn_ret_type_stack.push(temp_ret_type);
-        self.cur_fn_ret_type =
-            TypeInfo::from_type_anno(&item_fn.ret_type, self.scope_stack.cur_scope());
+        self.cur_fn_ret_type = ret_type;
 
-        // visit params of function
-        for param in item_fn.fn_params.params.iter() {
-            match &param.pattern {
-                Pattern::Identifier(ident_pattern) => item_fn.fn_block.scope.add_variable(
-                    ident_pattern.ident(),
-                    if ident_pattern.is_mut() {
-                        VarKind::LocalMut
-                    } else {
-                        VarKind::Local
-                    },
-                    Rc::new(RefCell::new(TypeInfo::from_type_anno(
-                        &param._type,
-                        self.scope_stack.cur_scope(),
-                    ))),
-                ),
-            }
-        }
         self.visit_block_expr(&mut item_fn.fn_block)?;
-        if item_fn.fn_block.last_expr.is_some() {
-            Self::try_determine_number_type(&self.cur_fn_ret_type, &mut item_fn.fn_block);
-            let type_info = item_fn.fn_block.type_info();
-            let t = type_info.borrow();
-            let tp = t.deref();
-            self.validate_ret_type(tp)?;
-        } else if item_fn.fn_block.stmts.is_empty() {
-            if item_fn.ret_type != TypeAnnotation::Unit {
-                return Err(format!(
+        let result = self.validate_fn_block_type(item_fn);
+
+        // restore
+        self.cur_fn_ret_type = self
+            .cur_fn_ret_type_stack
+            .pop()
+            .expect("empty cur_fn_ret_type_stack!");
+        result
+    }
+
+    /// Generic parameters are types in the current scope.
+    fn declare_generics(&mut self, generics: &[GenericParam]) -> Result<(), RccError> {
+        self.validate_bounds(generics)?;
+        for param in generics {
+            self.scope_stack
+                .cur_scope_mut()
+                .types
+                .insert(param.name.clone(), TypeInfo::Param(param.clone()));
+        }
+        Ok(())
+    }
+
+    fn validate_bounds(&self, generics: &[GenericParam]) -> Result<(), RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        match generics
+            .iter()
+            .flat_map(|p| p.bounds.iter())
+            .find(|bound| cur_scope.find_trait(bound).is_none())
+        {
+            Some(bound) => Err(format!("cannot find trait `{}` in this scope", bound).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Parameters are declared in the scope of the function block,
+    /// and their patterns must be irrefutable.
+    fn declare_fn_param(&mut self, param: &mut FnParam) -> Result<(), RccError> {
+        let type_info = TypeInfo::from_type_anno(&param._type, self.scope_stack.cur_scope());
+        self.declare_param(&mut param.pattern, type_info)
+    }
+
+    fn declare_param(&mut self, pattern: &mut Pattern, type_info: TypeInfo) -> Result<(), RccError> {
+        self.declare_pattern(pattern, Rc::new(RefCell::new(type_info.clone())))?;
+        if type_info.is_unknown() || type_info.is_never() {
+            return Ok(());
+        }
+        match exhaustiveness::check_irrefutable(&type_info, pattern) {
+            Some(witness) => Err(format!(
+                "refutable pattern in function argument: {} not covered",
+                witness
+            )
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    fn validate_fn_block_type(&mut self, item_fn: &mut ItemFn) -> Result<(), RccError> {
+        if let Some(last_expr) = item_fn.fn_block.last_expr.as_mut() {
+            let ret_type = self.cur_fn_ret_type.clone();
+            if self.coerce(&ret_type, last_expr)? {
+                let type_info = last_expr.type_info();
+                item_fn.fn_block.set_type_info_ref(type_info);
+            }
+            Self::try_determine_number_type(&self.cur_fn_ret_type, &mut item_fn.fn_block);
+            let type_info = item_fn.fn_block.type_info();
+            let t = type_info.borrow();
+            let tp = t.deref();
+            self.validate_ret_type(tp)
+        } else if item_fn.fn_block.stmts.is_empty() {
+            if item_fn.ret_type != TypeAnnotation::Unit {
+                return Err(format!(
                     "invalid return type: expected `{:?}`, found `()`",
                     item_fn.ret_type
                 )
                 .into());
             }
+            Ok(())
         } else {
             let last_stmt = item_fn.fn_block.stmts.last().unwrap();
             let type_info = last_stmt.type_info();
-            self.validate_ret_type(&type_info)?;
+            self.validate_ret_type(&type_info)
         }
+    }
 
-        // restore
-        self.cur_fn_ret_type = self
-            .cur_fn_ret_type_stack
-            .pop()
-            .expect("empty cur_fn_ret_type_stack!");
+    /// Resolve the fields of the structs and enums defined in the current scope,
+    /// so that they can be used before their definitions.
+    fn visit_item_types<'a>(&mut self, items: impl Iterator<Item = &'a mut Item>) {
+        let mut type_infos = vec![];
+        for item in items {
+            let result = match item {
+                Item::Struct(item_struct) => self.visit_item_struct(item_struct),
+                Item::Enum(item_enum) => self.visit_item_enum(item_enum),
+                _ => continue,
+            };
+            match result {
+                Ok(type_info) => type_infos.push(type_info),
+                Err(e) => self.errors.push(e),
+            }
+        }
+        for type_info in type_infos.iter() {
+            if contains_type(&type_info.member_types(), type_info, &mut vec![]) {
+                let name = match type_info {
+                    TypeInfo::Struct { name, .. } | TypeInfo::Enum { name, .. } => name,
+                    _ => unreachable!(),
+                };
+                self.errors
+                    .push(format!("recursive type `{}` has infinite size", name).into());
+            }
+        }
+    }
+
+    fn visit_item_struct(&mut self, item_struct: &mut ItemStruct) -> Result<TypeInfo, RccError> {
+        let type_info = self
+            .scope_stack
+            .cur_scope()
+            .find_def_except_fn(item_struct.name());
+        let fields = match &type_info {
+            TypeInfo::Struct { fields, .. } => fields,
+            _ => unreachable!("struct should be added to the scope"),
+        };
+        // the generic parameters are only visible to the fields
+        let generics = item_struct.generics();
+        self.validate_bounds(generics)?;
+        let scope = Scope::with_types(
+            self.scope_stack.cur_scope(),
+            generics
+                .iter()
+                .map(|p| (p.name.clone(), TypeInfo::Param(p.clone()))),
+        );
+        fields.set(Self::resolve_fields(item_struct.fields(), &scope)?);
+        Ok(type_info)
+    }
+
+    fn visit_item_enum(&mut self, item_enum: &mut TypeEnum) -> Result<TypeInfo, RccError> {
+        let type_info = self
+            .scope_stack
+            .cur_scope()
+            .find_def_except_fn(item_enum.name());
+        let variants = match &type_info {
+            TypeInfo::Enum { variants, .. } => variants,
+            _ => unreachable!("enum should be added to the scope"),
+        };
+        let mut resolved: Vec<VariantInfo> = vec![];
+        for variant in item_enum.variants() {
+            if resolved.iter().any(|v| v.name == variant.name()) {
+                return Err(format!(
+                    "variant `{}` is already declared in enum `{}`",
+                    variant.name(),
+                    item_enum.name()
+                )
+                .into());
+            }
+            let kind = match variant.fields() {
+                Fields::Struct(_) => VariantKind::Struct,
+                Fields::Tuple(_) => VariantKind::Tuple,
+                Fields::None => VariantKind::Unit,
+            };
+            resolved.push(VariantInfo {
+                name: variant.name().to_string(),
+                kind,
+                fields: Self::resolve_fields(variant.fields(), self.scope_stack.cur_scope())?,
+            });
+        }
+        variants.set(resolved);
+        Ok(type_info)
+    }
+
+    /// Names and types of the fields of a struct or an enum variant.
+    fn resolve_fields(
+        fields: &Fields,
+        cur_scope: &Scope,
+    ) -> Result<Vec<(String, TypeInfo)>, RccError> {
+        let field_annos: Vec<(String, &TypeAnnotation)> = match fields {
+            Fields::Struct(struct_fields) => struct_fields
+                .iter()
+                .map(|f| (f.name.clone(), &f._type))
+                .collect(),
+            Fields::Tuple(tuple_fields) => tuple_fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| (i.to_string(), &f._type))
+                .collect(),
+            Fields::None => vec![],
+        };
+
+        let mut resolved: Vec<(String, TypeInfo)> = vec![];
+        for (name, type_anno) in field_annos {
+            if resolved.iter().any(|(n, _)| n == &name) {
+                return Err(format!("field `{}` is already declared", name).into());
+            }
+            let type_info = TypeInfo::from_type_anno(type_anno, cur_scope);
+            if type_info.is_unknown() {
+                return Err(format!("cannot find type `{:?}` of field `{}`", type_anno, name).into());
+            }
+            resolved.push((name, type_info));
+        }
+        Ok(resolved)
+    }
+
+    /// `Self` is defined in the scope of the impl block, and the associated functions
+    /// have been added to the current scope by the parser.
+    fn visit_item_impl(&mut self, item_impl: &mut ItemImpl) -> Result<(), RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        let trait_info = match &item_impl.trait_name {
+            Some(name) => match cur_scope.find_trait(name) {
+                Some(trait_info) => Some(trait_info.clone()),
+                None => return Err(format!("cannot find trait `{}` in this scope", name).into()),
+            },
+            None => None,
+        };
+        let self_type = match &item_impl.self_type {
+            TypeAnnotation::Identifier(name) | TypeAnnotation::Generic(TypeGeneric { name, .. }) => {
+                cur_scope.find_def_except_fn(name)
+            }
+            _ => TypeInfo::Unknown,
+        };
+        match &self_type {
+            TypeInfo::Struct { name, fields, .. } if fields.is_generic() => {
+                return Err(
+                    format!("impl blocks of generic struct `{}` are not supported", name).into(),
+                )
+            }
+            TypeInfo::Struct { .. } | TypeInfo::Enum { .. } => {}
+            TypeInfo::Unknown => {
+                return Err(
+                    format!("cannot find type `{:?}` in this scope", item_impl.self_type).into(),
+                )
+            }
+            t if trait_info.is_some() && t.impl_name().is_some() => {}
+            t if trait_info.is_some() => {
+                return Err(format!("cannot implement traits for type `{:?}`", t).into())
+            }
+            _ => return Err("cannot define inherent `impl` for primitive types".into()),
+        }
+        let mut names = HashSet::new();
+        if let Some(item_fn) = item_impl.items.iter().find(|f| !names.insert(&f.name)) {
+            return Err(format!("duplicate definitions with name `{}`", item_fn.name).into());
+        }
+
+        if let Some(trait_name) = &item_impl.trait_name {
+            let type_name = self_type.impl_name().unwrap();
+            let key = (cur_scope.scope_id, type_name.clone(), trait_name.clone());
+            if !self.trait_impls.insert(key) {
+                return Err(format!(
+                    "conflicting implementations of trait `{}` for type `{}`",
+                    trait_name, type_name
+                )
+                .into());
+            }
+        }
+
+        item_impl.scope.types.insert("Self".into(), self_type.clone());
+        self.scope_stack.enter(&mut item_impl.scope);
+        let checked = match &trait_info {
+            Some(trait_info) => self.check_trait_impl(item_impl, trait_info),
+            None => Ok(()),
+        };
+        if checked.is_ok() {
+            for item_fn in item_impl.items.iter_mut() {
+                let span = item_fn.span.0;
+                if let Err(e) = self.visit_item_fn(item_fn) {
+                    self.errors.push(e.or_span(span));
+                }
+            }
+        }
+        self.scope_stack.exit_scope();
+        checked
+    }
+
+    /// The functions in the impl block of a trait must be the functions of the trait
+    /// with the same signatures, whose `Self` types are replaced with the implemented type.
+    fn check_trait_impl(
+        &self,
+        item_impl: &ItemImpl,
+        trait_info: &TraitInfo,
+    ) -> Result<(), RccError> {
+        let trait_name = &trait_info.name;
+        // `Self` is the implemented type in the scope of the impl block
+        let cur_scope = self.scope_stack.cur_scope();
+        for item_fn in item_impl.items.iter() {
+            let fn_info = match trait_info.find_fn(&item_fn.name) {
+                Some(fn_info) => fn_info,
+                None => {
+                    return Err(format!(
+                        "method `{}` is not a member of trait `{}`",
+                        item_fn.name, trait_name
+                    )
+                    .into())
+                }
+            };
+            let resolve = |types: &[TypeAnnotation]| -> Vec<TypeInfo> {
+                types
+                    .iter()
+                    .map(|t| TypeInfo::from_type_anno(t, cur_scope))
+                    .collect()
+            };
+            let expected = resolve(&fn_info.inner.params);
+            let found = resolve(&item_fn.params());
+            if fn_info.is_method != item_fn.has_self_param()
+                || expected != found
+                || TypeInfo::from_type_anno(&fn_info.inner.ret_type, cur_scope)
+                    != TypeInfo::from_type_anno(&item_fn.ret_type, cur_scope)
+            {
+                return Err(format!(
+                    "method `{}` has an incompatible type for trait `{}`",
+                    item_fn.name, trait_name
+                )
+                .into());
+            }
+        }
+
+        let missing: Vec<String> = trait_info
+            .fns
+            .iter()
+            .filter(|f| !f.is_provided && item_impl.items.iter().all(|i| i.name != f.name))
+            .map(|f| format!("`{}`", f.name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "not all trait items implemented, missing: {}",
+                missing.join(", ")
+            )
+            .into());
+        }
         Ok(())
     }
 
-    fn visit_item_struct(&mut self, item_struct: &mut ItemStruct) -> Result<(), RccError> {
+    /// `Self` is a generic parameter bounded by the trait in the scope of the trait,
+    /// and the default implementations are checked once.
+    fn visit_item_trait(&mut self, item_trait: &mut ItemTrait) -> Result<(), RccError> {
+        let mut names = HashSet::new();
+        if let Some(item) = item_trait.items.iter().find(|f| !names.insert(f.name())) {
+            return Err(format!("duplicate definitions with name `{}`", item.name()).into());
+        }
+
+        let self_param = GenericParam::new("Self".into(), vec![item_trait.name.clone()]);
+        item_trait
+            .scope
+            .types
+            .insert("Self".into(), TypeInfo::Param(self_param));
+        self.scope_stack.enter(&mut item_trait.scope);
+        for item in item_trait.items.iter_mut() {
+            if let TraitItemFn::Provided(item_fn) = item {
+                let span = item_fn.span.0;
+                if let Err(e) = self.visit_item_fn(item_fn) {
+                    self.errors.push(e.or_span(span));
+                }
+            }
+        }
+        self.scope_stack.exit_scope();
         Ok(())
     }
 
@@ -542,7 +1672,10 @@ impl SymbolResolver {
         match stmt {
             Stmt::Semi => Ok(()),
             Stmt::Item(item) => self.visit_item(item),
-            Stmt::Let(let_stmt) => self.visit_let_stmt(let_stmt),
+            Stmt::Let(let_stmt) => {
+                let span = let_stmt.span.0;
+                self.visit_let_stmt(let_stmt).map_err(|e| e.or_span(span))
+            }
             Stmt::ExprStmt(expr) => {
                 self.visit_expr(expr)?;
                 let t = expr.type_info();
@@ -561,29 +1694,65 @@ impl SymbolResolver {
     }
 
     fn visit_let_stmt(&mut self, let_stmt: &mut LetStmt) -> Result<(), RccError> {
-        let expr_type_info = if let Some(expr) = &mut let_stmt.rhs {
-            self.visit_expr(expr)?;
-            if let Some(type_anno) = &let_stmt._type {
-                let anno_type_info =
-                    TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope());
-                Self::try_determine_number_type(&anno_type_info, expr);
-                let t = expr.type_info();
-                let tp = t.borrow();
-                let expr_type_info = tp.deref();
-                if !expr_type_info.is(&anno_type_info) {
-                    return Err(format!(
-                        "invalid type in let stmt: expected `{:?}`, found `{:?}`",
-                        anno_type_info, expr_type_info
-                    )
-                    .into());
-                }
-            }
-            expr.type_info()
-        } else {
-            Rc::new(RefCell::new(Unknown))
+        let anno_type_info = let_stmt
+            ._type
+            .as_ref()
+            .map(|type_anno| TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope()));
+        let result = match &mut let_stmt.rhs {
+            Some(expr) => self.visit_let_rhs(expr, anno_type_info.as_ref()),
+            None => Ok(Rc::new(RefCell::new(Unknown))),
+        };
+        // The variable is declared even if the let stmt is invalid, so that its
+        // usages will not be reported as unresolved. Its type is `!` if it is not
+        // annotated, which can be coerced into any type.
+        let expr_type_info = match &result {
+            Ok(type_info) => type_info.clone(),
+            Err(_) => Rc::new(RefCell::new(anno_type_info.unwrap_or(TypeInfo::Never))),
         };
 
-        match &let_stmt.pattern {
+        let declared = self.declare_pattern(&mut let_stmt.pattern, expr_type_info.clone());
+        result.and(declared)?;
+
+        let t = expr_type_info.borrow();
+        if t.is_unknown() || t.is_never() {
+            return Ok(());
+        }
+        match exhaustiveness::check_irrefutable(t.deref(), &let_stmt.pattern) {
+            Some(witness) => Err(format!(
+                "refutable pattern in local binding: {} not covered",
+                witness
+            )
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the pattern against the type of the matched value, and add the variables
+    /// bound by the pattern to the current scope.
+    /// Variables of a mismatched pattern are still declared with type `!`.
+    fn declare_pattern(
+        &mut self,
+        pattern: &mut Pattern,
+        type_info: Rc<RefCell<TypeInfo>>,
+    ) -> Result<(), RccError> {
+        match pattern {
+            Pattern::Identifier(ident_pattern) if ident_pattern.is_ref() => {
+                let kind = if ident_pattern.is_mut() {
+                    PtrKind::MutRef
+                } else {
+                    PtrKind::Ref
+                };
+                let ptr_info = TypeInfo::Ptr {
+                    kind,
+                    type_info: Box::new(type_info.borrow().clone()),
+                };
+                self.scope_stack.cur_scope_mut().add_variable(
+                    ident_pattern.ident(),
+                    VarKind::Local,
+                    Rc::new(RefCell::new(ptr_info)),
+                );
+                Ok(())
+            }
             Pattern::Identifier(ident_pattern) => {
                 self.scope_stack.cur_scope_mut().add_variable(
                     ident_pattern.ident(),
@@ -592,34 +1761,384 @@ impl SymbolResolver {
                     } else {
                         VarKind::Local
                     },
-                    expr_type_info,
+                    type_info,
                 );
+                Ok(())
+            }
+            Pattern::Wildcard => Ok(()),
+            Pattern::Literal(expr) => {
+                self.visit_expr(expr)?;
+                let expected = type_info.borrow().clone();
+                if expected.is_unknown() || expected.is_never() {
+                    return Ok(());
+                }
+                Self::try_determine_number_type(&expected, expr.as_mut());
+                assert_type_is(expr.as_ref(), &expected, "mismatched types in pattern")
+            }
+            Pattern::Range(lo, op, hi) => {
+                self.visit_expr(lo)?;
+                self.visit_expr(hi)?;
+                let expected = type_info.borrow().clone();
+                if expected.is_unknown() || expected.is_never() {
+                    return Ok(());
+                }
+                if !matches!(expected, TypeInfo::LitNum(_) | TypeInfo::Char) {
+                    return Err("only `char` and numeric types are allowed in range patterns".into());
+                }
+                for bound in [lo.as_mut(), hi.as_mut()] {
+                    Self::try_determine_number_type(&expected, bound);
+                    assert_type_is(bound, &expected, "mismatched types in pattern")?;
+                }
+                match (exhaustiveness::literal_int(lo), exhaustiveness::literal_int(hi)) {
+                    (Some(l), Some(h)) if *op == RangeOp::DotDotEq && l > h => Err(
+                        "lower range bound must be less than or equal to upper".into(),
+                    ),
+                    (Some(l), Some(h)) if *op == RangeOp::DotDot && l >= h => {
+                        Err("lower range bound must be less than upper".into())
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Pattern::Path(path) => {
+                let expected = type_info.borrow().clone();
+                self.variant_pattern_fields(path, VariantKind::Unit, &expected)
+                    .map(|_| ())
+            }
+            Pattern::TupleStruct(path, patterns) => {
+                let expected = type_info.borrow().clone();
+                let (types, result) =
+                    match self.variant_pattern_fields(path, VariantKind::Tuple, &expected) {
+                        Ok(fields) if fields.len() == patterns.len() => {
+                            (fields.into_iter().map(|(_, t)| t).collect(), Ok(()))
+                        }
+                        Ok(types) => (
+                            vec![TypeInfo::Never; patterns.len()],
+                            Err(format!(
+                                "this pattern has {} fields, but the corresponding tuple variant has {} fields",
+                                patterns.len(),
+                                types.len()
+                            )
+                            .into()),
+                        ),
+                        Err(e) => (vec![TypeInfo::Never; patterns.len()], Err(e)),
+                    };
+                self.declare_patterns(patterns, types, result)
+            }
+            Pattern::Struct(struct_pattern) => {
+                let expected = type_info.borrow().clone();
+                let (types, result) = match self.struct_pattern_fields(struct_pattern, &expected) {
+                    Ok(types) => (types, Ok(())),
+                    Err(e) => (vec![TypeInfo::Never; struct_pattern.fields.len()], Err(e)),
+                };
+                struct_pattern
+                    .fields
+                    .iter_mut()
+                    .zip(types)
+                    .fold(result, |result, (field, t)| {
+                        let declared = self.declare_pattern(&mut field.pattern, Rc::new(RefCell::new(t)));
+                        result.and(declared)
+                    })
+            }
+            Pattern::Reference(pattern, is_mut) => {
+                let (inner, result) = match type_info.borrow().deref() {
+                    TypeInfo::Ptr { kind, type_info }
+                        if *kind == if *is_mut { PtrKind::MutRef } else { PtrKind::Ref } =>
+                    {
+                        (type_info.deref().clone(), Ok(()))
+                    }
+                    TypeInfo::Unknown => (TypeInfo::Unknown, Ok(())),
+                    TypeInfo::Never => (TypeInfo::Never, Ok(())),
+                    t => (
+                        TypeInfo::Never,
+                        Err(format!(
+                            "mismatched types in pattern: expected `{}`, found `{}_`",
+                            type_name(t),
+                            if *is_mut { "&mut " } else { "&" }
+                        )
+                        .into()),
+                    ),
+                };
+                let declared = self.declare_pattern(pattern, Rc::new(RefCell::new(inner)));
+                result.and(declared)
+            }
+            Pattern::Tuple(patterns) => {
+                let (types, result) = match type_info.borrow().deref() {
+                    TypeInfo::Tuple(types) if types.len() == patterns.len() => {
+                        (types.clone(), Ok(()))
+                    }
+                    TypeInfo::Unit if patterns.is_empty() => (vec![], Ok(())),
+                    // `let (a, b);`
+                    TypeInfo::Unknown => (vec![TypeInfo::Unknown; patterns.len()], Ok(())),
+                    TypeInfo::Never => (vec![TypeInfo::Never; patterns.len()], Ok(())),
+                    t => (
+                        vec![TypeInfo::Never; patterns.len()],
+                        Err(format!(
+                            "mismatched types: expected a tuple with {} elements, found `{:?}`",
+                            patterns.len(),
+                            t
+                        )
+                        .into()),
+                    ),
+                };
+                self.declare_patterns(patterns, types, result)
             }
         }
-        Ok(())
     }
 
-    fn visit_pattern(&mut self, pattern: &mut Pattern) -> Result<(), RccError> {
-        Ok(())
+    fn declare_patterns(
+        &mut self,
+        patterns: &mut [Pattern],
+        types: Vec<TypeInfo>,
+        result: Result<(), RccError>,
+    ) -> Result<(), RccError> {
+        patterns
+            .iter_mut()
+            .zip(types)
+            .fold(result, |result, (pattern, t)| {
+                let declared = self.declare_pattern(pattern, Rc::new(RefCell::new(t)));
+                result.and(declared)
+            })
     }
 
-    fn visit_ident_pattern(&mut self, ident_pattern: &mut IdentPattern) -> Result<(), RccError> {
-        Ok(())
+    /// Return the fields of the variant in the pattern `Shape::Rect(w, h)`.
+    fn variant_pattern_fields(
+        &self,
+        path: &PathExpr,
+        kind: VariantKind,
+        expected: &TypeInfo,
+    ) -> Result<Vec<(String, TypeInfo)>, RccError> {
+        let name = path.segments.join("::");
+        let (enum_type, variant) = match self.find_variant(path)? {
+            Some(found) => found,
+            None => return Err(format!("cannot find enum variant `{}`", name).into()),
+        };
+        if variant.kind != kind {
+            return Err(format!(
+                "expected {} variant, found {} variant `{}`",
+                kind.name(),
+                variant.kind.name(),
+                name
+            )
+            .into());
+        }
+        if !expected.is_never() && expected != &enum_type {
+            return Err(format!(
+                "mismatched types in pattern: expected `{}`, found `{}`",
+                type_name(expected),
+                type_name(&enum_type)
+            )
+            .into());
+        }
+        Ok(variant.fields)
+    }
+
+    /// Return the types of the fields in the pattern `Point { x, y: 0, .. }`,
+    /// which is either a struct or a struct variant.
+    fn struct_pattern_fields(
+        &self,
+        struct_pattern: &StructPattern,
+        expected: &TypeInfo,
+    ) -> Result<Vec<TypeInfo>, RccError> {
+        let path = &struct_pattern.path;
+        let name = path.segments.join("::");
+        let (fields, desc) = if self.find_variant(path)?.is_some() {
+            let fields = self.variant_pattern_fields(path, VariantKind::Struct, expected)?;
+            (fields, "variant")
+        } else {
+            let type_info = match path.segments.as_slice() {
+                [ident] => self.scope_stack.cur_scope().find_def_except_fn(ident),
+                _ => TypeInfo::Unknown,
+            };
+            let fields = match &type_info {
+                TypeInfo::Struct { fields, .. } => fields.names().into_iter().zip(fields.types()).collect(),
+                _ => {
+                    return Err(format!(
+                        "cannot find struct or struct variant `{}` in this scope",
+                        name
+                    )
+                    .into())
+                }
+            };
+            if !expected.is_never() && expected != &type_info {
+                return Err(format!(
+                    "mismatched types in pattern: expected `{}`, found `{}`",
+                    type_name(expected),
+                    type_name(&type_info)
+                )
+                .into());
+            }
+            (fields, "struct")
+        };
+
+        let mut types = vec![];
+        for (i, field) in struct_pattern.fields.iter().enumerate() {
+            if struct_pattern.fields[..i].iter().any(|f| f.name == field.name) {
+                return Err(format!(
+                    "field `{}` bound multiple times in the pattern",
+                    field.name
+                )
+                .into());
+            }
+            match fields.iter().find(|(n, _)| n == &field.name) {
+                Some((_, t)) => types.push(t.clone()),
+                None => {
+                    return Err(format!(
+                        "{} `{}` does not have a field named `{}`",
+                        desc, name, field.name
+                    )
+                    .into())
+                }
+            }
+        }
+        if !struct_pattern.has_rest {
+            if let Some((n, _)) = fields
+                .iter()
+                .find(|(n, _)| struct_pattern.fields.iter().all(|f| &f.name != n))
+            {
+                return Err(format!("pattern does not mention field `{}`", n).into());
+            }
+        }
+        Ok(types)
+    }
+
+    fn visit_let_rhs(
+        &mut self,
+        expr: &mut Expr,
+        anno_type_info: Option<&TypeInfo>,
+    ) -> Result<Rc<RefCell<TypeInfo>>, RccError> {
+        self.visit_expr(expr)?;
+        if let Some(anno_type_info) = anno_type_info {
+            self.coerce(anno_type_info, expr)?;
+            Self::try_determine_number_type(anno_type_info, expr);
+            let t = expr.type_info();
+            let tp = t.borrow();
+            let expr_type_info = tp.deref();
+            if !expr_type_info.is(anno_type_info) {
+                return Err(format!(
+                    "invalid type in let stmt: expected `{:?}`, found `{:?}`",
+                    anno_type_info, expr_type_info
+                )
+                .into());
+            }
+        }
+        Ok(expr.type_info())
+    }
+
+    /// Find the enum variant referred to by a path like `Color::Red`.
+    /// Return `None` if the path does not start with an enum.
+    fn find_variant(&self, path: &PathExpr) -> Result<Option<(TypeInfo, VariantInfo)>, RccError> {
+        if path.segments.len() != 2 {
+            return Ok(None);
+        }
+        let type_info = self
+            .scope_stack
+            .cur_scope()
+            .find_def_except_fn(&path.segments[0]);
+        let found = match &type_info {
+            TypeInfo::Enum { variants, .. } => variants.find(&path.segments[1]),
+            _ => return Ok(None),
+        };
+        match found {
+            Some((_, variant)) => Ok(Some((type_info, variant))),
+            None if self
+                .scope_stack
+                .cur_scope()
+                .find_type_fn(&type_info, &path.segments[1])
+                .is_some() =>
+            {
+                Ok(None)
+            }
+            None => Err(format!(
+                "no variant named `{}` in enum `{}`",
+                path.segments[1], path.segments[0]
+            )
+            .into()),
+        }
+    }
+
+    /// Find the associated function referred to by a path like `Foo::new` or `T::zero`.
+    /// Return `None` if the path does not start with a type.
+    fn find_assoc_fn(&self, path: &PathExpr) -> Result<Option<AssocFn>, RccError> {
+        if path.segments.len() != 2 {
+            return Ok(None);
+        }
+        let cur_scope = self.scope_stack.cur_scope();
+        let type_info = cur_scope.find_def_except_fn(&path.segments[0]);
+        if type_info.is_unknown() {
+            return Ok(None);
+        }
+        match cur_scope.find_type_fn(&type_info, &path.segments[1]) {
+            Some(assoc_fn) => Ok(Some(assoc_fn)),
+            None => Err(format!(
+                "no function or associated item named `{}` found for `{}`",
+                path.segments[1], path.segments[0]
+            )
+            .into()),
+        }
     }
 
     fn visit_path_expr(&mut self, path_expr: &mut PathExpr) -> Result<(), RccError> {
+        if let Some((type_info, variant)) = self.find_variant(path_expr)? {
+            if variant.kind != VariantKind::Unit {
+                return Err(format!(
+                    "expected value, found {} variant `{}`",
+                    variant.kind.name(),
+                    path_expr.segments.join("::")
+                )
+                .into());
+            }
+            path_expr.set_type_info(type_info);
+            path_expr.expr_kind = ExprKind::Value;
+            return Ok(());
+        }
+        if let Some(assoc_fn) = self.find_assoc_fn(path_expr)? {
+            path_expr.set_type_info(assoc_fn.type_info);
+            path_expr.expr_kind = ExprKind::Value;
+            return Ok(());
+        }
         if let Some(ident) = path_expr.segments.last() {
             let cur_scope = self.scope_stack.cur_scope_mut();
-            if let Some((var_info, _scope_id)) = cur_scope.find_variable(ident) {
+            if let Some((var_info, scope_id)) = cur_scope.find_variable(ident) {
+                // local variables declared outside the closures are captured
+                if matches!(var_info.kind, VarKind::Local | VarKind::LocalMut) {
+                    for (boundary, closure, by_ref) in self.closure_stack.iter() {
+                        if !cur_scope.is_inside(scope_id, *boundary) {
+                            closure.add_capture(Capture {
+                                ident: ident.clone(),
+                                scope_id,
+                                kind: var_info.kind,
+                                type_info: var_info.type_info.borrow().clone(),
+                                by_ref: *by_ref,
+                            });
+                        }
+                    }
+                }
                 path_expr.set_type_info_ref(var_info.type_info.clone());
                 path_expr.expr_kind = match var_info.kind {
-                    VarKind::Static | VarKind::LocalMut => ExprKind::MutablePlace,
-                    VarKind::Const | VarKind::Local => ExprKind::Place,
+                    VarKind::LocalMut => ExprKind::MutablePlace,
+                    VarKind::Local => ExprKind::Place,
                     VarKind::LitConst => unreachable!(),
                 };
                 Ok(())
             } else {
                 let type_info = cur_scope.find_fn(ident);
+                if let TypeInfo::Fn { generics, .. } = &type_info {
+                    if !path_expr.generic_args.is_empty() {
+                        if generics.len() != path_expr.generic_args.len() {
+                            return Err(format!(
+                                "function takes {} generic arguments but {} generic arguments were supplied",
+                                generics.len(),
+                                path_expr.generic_args.len()
+                            )
+                            .into());
+                        }
+                        path_expr.type_args = path_expr
+                            .generic_args
+                            .iter()
+                            .map(|t| TypeInfo::from_type_anno(t, cur_scope))
+                            .collect();
+                    }
+                }
                 if !type_info.is_unknown() {
                     path_expr.set_type_info(type_info);
                     path_expr.expr_kind = ExprKind::Value;
@@ -643,15 +2162,28 @@ impl SymbolResolver {
         let type_info = unary_expr.expr.type_info();
         match unary_expr.op {
             UnOp::Deref => {
-                if let TypeInfo::Ptr { kind: _, type_info } = type_info.borrow().deref() {
+                if let TypeInfo::Ptr { kind, type_info } = type_info.borrow().deref() {
+                    if kind.is_raw() && !self.in_unsafe {
+                        return Err(
+                            "dereference of raw pointer is unsafe and requires unsafe block".into(),
+                        );
+                    }
                     unary_expr.set_type_info(*type_info.clone());
-                    unary_expr.expr_kind = unary_expr.expr.kind();
+                    unary_expr.expr_kind = match kind {
+                        PtrKind::MutRef | PtrKind::MutRawPtr => ExprKind::MutablePlace,
+                        PtrKind::Ref | PtrKind::ConstRawPtr => ExprKind::Place,
+                    };
                 } else {
-                    return Err(format!("type `{:?}` can not be dereferenced", type_info).into());
+                    let type_info = type_info.borrow().default_number_types();
+                    return Err(format!(
+                        "type `{}` can not be dereferenced",
+                        type_name(&type_info)
+                    )
+                    .into());
                 }
             }
             UnOp::Not => match type_info.borrow().deref() {
-                TypeInfo::Bool | TypeInfo::LitNum(_) => {
+                t @ (TypeInfo::Bool | TypeInfo::LitNum(_)) if !t.is_float() => {
                     unary_expr.set_type_info_ref(type_info.clone());
                     unary_expr.expr_kind = ExprKind::Value;
                 }
@@ -660,7 +2192,7 @@ impl SymbolResolver {
                 }
             },
             UnOp::Neg => match type_info.borrow().deref() {
-                TypeInfo::LitNum(_) => {
+                TypeInfo::LitNum(t) if !t.is_unsigned() => {
                     unary_expr.set_type_info_ref(type_info.clone());
                     unary_expr.expr_kind = ExprKind::Value;
                 }
@@ -678,25 +2210,111 @@ impl SymbolResolver {
                 unary_expr.expr_kind = ExprKind::Value;
             }
             UnOp::BorrowMut => {
-                todo!("borrow mut")
+                if unary_expr.expr.kind() != ExprKind::MutablePlace {
+                    return Err("cannot borrow immutable value as mutable".into());
+                }
+                unary_expr.set_type_info(TypeInfo::Ptr {
+                    kind: PtrKind::MutRef,
+                    type_info: Box::new(type_info.borrow().deref().clone()),
+                });
+                unary_expr.expr_kind = ExprKind::Value;
+            }
+            UnOp::Unsize | UnOp::ClosureFnPtr | UnOp::PtrCoercion => {
+                unreachable!("coercions are inserted after resolving the operand")
             }
         }
         Ok(())
     }
 
+    /// Primitive casts: between numbers, `bool` or `char` to integers, `u8` to `char`,
+    /// references to raw pointers, and between raw pointers and integers.
+    fn visit_cast_expr(&mut self, cast_expr: &mut CastExpr) -> Result<(), RccError> {
+        self.visit_expr(&mut cast_expr.expr)?;
+        let target = TypeInfo::from_type_anno(&cast_expr.type_anno, self.scope_stack.cur_scope());
+        let src_type = cast_expr.expr.type_info().borrow().clone();
+        // `1 as u8` is `1u8 as u8`, and `300 as u8` is `300i32 as u8`
+        let src_type = match cast_expr.expr.as_ref() {
+            Expr::LitNum(_) if src_type.may_be_determined_as(&target) => target.clone(),
+            _ => src_type.default_number_types(),
+        };
+        Self::try_determine_number_type(&src_type, cast_expr.expr.as_mut());
+
+        let valid = match (&src_type, &target) {
+            (s, t) if s == t => true,
+            (s, t) if s.is_number() && t.is_number() => true,
+            (TypeInfo::Bool | TypeInfo::Char, t) => t.is_integer(),
+            (TypeInfo::LitNum(TypeLitNum::U8), TypeInfo::Char) => true,
+            (
+                TypeInfo::Ptr {
+                    kind: s_kind,
+                    type_info: s,
+                },
+                TypeInfo::Ptr {
+                    kind: t_kind,
+                    type_info: t,
+                },
+            ) => match (s_kind, t_kind) {
+                (_, PtrKind::Ref | PtrKind::MutRef) => false,
+                (PtrKind::Ref, PtrKind::MutRawPtr) => false,
+                (PtrKind::Ref | PtrKind::MutRef, _) => s.may_be_determined_as(t),
+                _ => true,
+            },
+            (TypeInfo::Ptr { kind, .. }, t) => kind.is_raw() && t.is_integer(),
+            (s, TypeInfo::Ptr { kind, .. }) => kind.is_raw() && s.is_integer(),
+            _ => false,
+        };
+        if !valid {
+            return Err(format!(
+                "non-primitive cast: `{}` as `{}`",
+                type_name(&src_type),
+                type_name(&target)
+            )
+            .into());
+        }
+        cast_expr.set_type_info(target);
+        Ok(())
+    }
+
     fn visit_block_expr(&mut self, block_expr: &mut BlockExpr) -> Result<(), RccError> {
         self.scope_stack.enter_scope(block_expr);
-
+        let in_unsafe = self.in_unsafe;
+        self.in_unsafe |= block_expr.is_unsafe;
+
+        self.visit_item_types(block_expr.stmts.iter_mut().filter_map(|stmt| match stmt {
+            Stmt::Item(item) => Some(item),
+            _ => None,
+        }));
+
+        // Errors of statements are collected, and the type of the block is `!`
+        // if its last statement or expression is invalid, so that it will not
+        // cause more type errors.
+        let mut last_failed = false;
         for stmt in block_expr.stmts.iter_mut() {
-            self.visit_stmt(stmt)?;
+            last_failed = match self.visit_stmt(stmt) {
+                Ok(()) => false,
+                Err(e) => {
+                    self.errors.push(e);
+                    true
+                }
+            };
             self.scope_stack.cur_scope_mut().cur_stmt_id += 1;
         }
 
         if let Some(expr) = block_expr.last_expr.as_mut() {
-            self.visit_expr(expr)?;
+            let result = self.visit_expr(expr);
             self.scope_stack.cur_scope_mut().cur_stmt_id += 1;
-            let type_info = expr.type_info();
-            block_expr.set_type_info_ref(type_info);
+            match result {
+                Ok(()) => {
+                    let type_info = expr.type_info();
+                    block_expr.set_type_info_ref(type_info);
+                }
+                Err(e) => {
+                    self.errors.push(e);
+                    block_expr.set_type_info(TypeInfo::Never);
+                }
+            }
+        } else if last_failed {
+            block_expr.set_type_info(TypeInfo::Never);
         } else if block_expr.stmts.is_empty() {
             block_expr.set_type_info(TypeInfo::Unit);
         } else {
@@ -709,6 +2327,7 @@ impl SymbolResolver {
             }
         }
 
+        self.in_unsafe = in_unsafe;
         self.scope_stack.exit_scope();
         Ok(())
     }
@@ -735,6 +2354,10 @@ impl SymbolResolver {
             ExprKind::MutablePlace => {
                 self.visit_expr(&mut assign_expr.rhs)?;
                 let l_type = assign_expr.lhs.type_info();
+                let expected = l_type.borrow().clone();
+                if !expected.is_unknown() {
+                    self.coerce(&expected, &mut assign_expr.rhs)?;
+                }
                 let r_type = assign_expr.rhs.type_info();
 
                 debug_assert!(!r_type.borrow().deref().is_unknown());
@@ -774,6 +2397,12 @@ impl SymbolResolver {
                             // let mut a: f32; a = 33.2;
                             assign_expr.rhs.set_type_info_ref(l_type);
                         }
+                    } else if r_type.borrow().may_be_determined_as(l_type.borrow().deref()) {
+                        // let mut a: [i64; 2]; a = [1, 2];
+                        Self::try_determine_number_type(
+                            l_type.borrow().deref(),
+                            assign_expr.rhs.as_mut(),
+                        );
                     } else {
                         return invalid_type_error(l_type.borrow().deref(), assign_expr);
                     }
@@ -805,6 +2434,8 @@ impl SymbolResolver {
         Ok(())
     }
 
+    /// The bounds have the same type, and integer literals are `i32` if the type
+    /// is not determined by the other bound, because the types of ranges can not be annotated.
     fn visit_range_expr(&mut self, range_expr: &mut RangeExpr) -> Result<(), RccError> {
         if let Some(expr) = range_expr.lhs.as_mut() {
             self.visit_expr(expr)?;
@@ -812,9 +2443,77 @@ impl SymbolResolver {
         if let Some(expr) = range_expr.rhs.as_mut() {
             self.visit_expr(expr)?;
         }
+        if range_expr.range_op == RangeOp::DotDotEq && range_expr.rhs.is_none() {
+            return Err("inclusive range with no end".into());
+        }
+        let elem_type = match (range_expr.lhs.as_mut(), range_expr.rhs.as_mut()) {
+            (Some(lhs), Some(rhs)) => {
+                let l_type = lhs.type_info().borrow().clone();
+                let r_type = rhs.type_info().borrow().clone();
+                if l_type.may_be_determined_as(&r_type) {
+                    r_type
+                } else if r_type.may_be_determined_as(&l_type) {
+                    l_type
+                } else {
+                    return Err(format!(
+                        "mismatched types in range: `{:?}` and `{:?}`",
+                        l_type, r_type
+                    )
+                    .into());
+                }
+            }
+            (Some(bound), None) | (None, Some(bound)) => bound.type_info().borrow().clone(),
+            // `..`
+            (None, None) => TypeInfo::Unit,
+        };
+        range_expr.set_type_info(TypeInfo::Range {
+            range_op: range_expr.range_op,
+            has_start: range_expr.lhs.is_some(),
+            has_end: range_expr.rhs.is_some(),
+            elem_type: Box::new(elem_type.default_number_types()),
+            rev: false,
+        });
         Ok(())
     }
 
+    /// `.rev()` reverses a range with both bounds.
+    fn visit_range_method_call_expr(
+        &mut self,
+        method_call_expr: &mut MethodCallExpr,
+    ) -> Result<(), RccError> {
+        let receiver_type = method_call_expr.receiver.type_info().borrow().clone();
+        let method = method_call_expr.method.as_str();
+        match (&receiver_type, method) {
+            (
+                TypeInfo::Range {
+                    range_op,
+                    has_start: true,
+                    has_end: true,
+                    elem_type,
+                    rev,
+                },
+                "rev",
+            ) => {
+                if !method_call_expr.call_params.is_empty() {
+                    return Err(format!(
+                        "this function takes 0 parameters but {} parameters were supplied",
+                        method_call_expr.call_params.len()
+                    )
+                    .into());
+                }
+                method_call_expr.set_type_info(TypeInfo::Range {
+                    range_op: *range_op,
+                    has_start: true,
+                    has_end: true,
+                    elem_type: elem_type.clone(),
+                    rev: !rev,
+                });
+                Ok(())
+            }
+            (t, _) => Err(format!("no method named `{}` found for `{}`", method, type_name(t)).into()),
+        }
+    }
+
     fn visit_bin_op_expr(&mut self, bin_op_expr: &mut BinOpExpr) -> Result<(), RccError> {
         self.visit_expr(&mut bin_op_expr.lhs)?;
         self.visit_expr(&mut bin_op_expr.rhs)?;
@@ -845,41 +2544,234 @@ impl SymbolResolver {
             )
             .into())
         }
-    }
+    }
+
+    fn visit_array_expr(&mut self, array_expr: &mut ArrayExpr) -> Result<(), RccError> {
+        if array_expr.is_repeat() {
+            let len = match array_expr.len_expr.expr.as_deref() {
+                Some(Expr::LitNum(lit_num)) if lit_num.type_info().borrow().is_integer() => {
+                    lit_num.value.parse::<usize>().ok()
+                }
+                _ => None,
+            };
+            match len {
+                Some(len) => array_expr.len_expr.set_value(len),
+                None => return Err("array length must be an integer literal".into()),
+            }
+        }
+        for elem in array_expr.elems.iter_mut() {
+            self.visit_expr(elem)?;
+        }
+
+        // The first element which is not a number literal decides the type of
+        // all the elements, e.g. `[1, 2u8]` is `[u8; 2]`.
+        let elem_type = array_expr
+            .elems
+            .iter()
+            .map(|e| e.type_info().borrow().clone())
+            .find(|t| !t.is_i() && !t.is_f() && !t.is_never())
+            .or_else(|| array_expr.elems.first().map(|e| e.type_info().borrow().clone()))
+            .unwrap_or(TypeInfo::Unknown);
+        for elem in array_expr.elems.iter_mut() {
+            Self::try_determine_number_type(&elem_type, elem);
+            assert_type_is(elem, &elem_type, "mismatched types in array")
+                .map_err(|e| locate_at_expr(e, elem))?;
+        }
+        let len = *array_expr.len_expr.value().unwrap();
+        array_expr.set_type_info(TypeInfo::Array {
+            elem_type: Box::new(elem_type),
+            len: len as u32,
+        });
+        Ok(())
+    }
+
+    fn visit_array_index_expr(
+        &mut self,
+        array_index_expr: &mut ArrayIndexExpr,
+    ) -> Result<(), RccError> {
+        self.visit_expr(&mut array_index_expr.expr)?;
+        self.visit_expr(&mut array_index_expr.index_expr)?;
+
+        let usize_type = TypeInfo::LitNum(TypeLitNum::Usize);
+        Self::try_determine_number_type(&usize_type, array_index_expr.index_expr.as_mut());
+        assert_type_is(
+            array_index_expr.index_expr.as_ref(),
+            &usize_type,
+            "the type `[T]` cannot be indexed by this type",
+        )
+        .map_err(|e| locate_at_expr(e, &array_index_expr.index_expr))?;
+
+        // `a[i]` where `a` is `&[T; N]` or `&mut [T; N]` is `(*a)[i]`
+        let t = array_index_expr.expr.type_info();
+        let tp = t.borrow();
+        let (elem_type, expr_kind) = match tp.deref() {
+            TypeInfo::Array { elem_type, .. } => (elem_type, array_index_expr.expr.kind()),
+            TypeInfo::Ptr { kind, type_info } => match type_info.deref() {
+                TypeInfo::Array { elem_type, .. } => (
+                    elem_type,
+                    if *kind == PtrKind::MutRef {
+                        ExprKind::MutablePlace
+                    } else {
+                        ExprKind::Place
+                    },
+                ),
+                t => return Err(format!("cannot index into a value of type `&{:?}`", t).into()),
+            },
+            t => return Err(format!("cannot index into a value of type `{:?}`", t).into()),
+        };
+        array_index_expr.set_type_info(elem_type.deref().clone());
+        array_index_expr.expr_kind = if expr_kind == ExprKind::Value {
+            ExprKind::Place
+        } else {
+            expr_kind
+        };
+        Ok(())
+    }
+
+    fn visit_tuple_expr(&mut self, tuple_expr: &mut TupleExpr) -> Result<(), RccError> {
+        let mut types = vec![];
+        for elem in tuple_expr.elems.iter_mut() {
+            self.visit_expr(elem)?;
+            types.push(elem.type_info().borrow().clone());
+        }
+        tuple_expr.set_type_info(if types.is_empty() {
+            TypeInfo::Unit
+        } else {
+            TypeInfo::Tuple(types)
+        });
+        Ok(())
+    }
+
+    fn visit_tuple_index_expr(
+        &mut self,
+        tuple_index_expr: &mut TupleIndexExpr,
+    ) -> Result<(), RccError> {
+        self.visit_expr(&mut tuple_index_expr.expr)?;
+        let index = tuple_index_expr.index;
+        let t = tuple_index_expr.expr.type_info();
+        let tp = t.borrow();
+        match tp.deref() {
+            TypeInfo::Tuple(types) if index < types.len() => {
+                tuple_index_expr.set_type_info(types[index].clone());
+                tuple_index_expr.expr_kind = match tuple_index_expr.expr.kind() {
+                    ExprKind::Value => ExprKind::Place,
+                    kind => kind,
+                };
+                Ok(())
+            }
+            t => Err(format!("no field `{}` on type `{:?}`", index, t).into()),
+        }
+    }
+
+    /// The type arguments of a generic struct are inferred from the types of the fields.
+    fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) -> Result<(), RccError> {
+        let (mut type_info, mut fields, name) = match self.find_variant(&struct_expr.path)? {
+            Some((type_info, variant)) => {
+                let name = struct_expr.path.segments.join("::");
+                if variant.kind != VariantKind::Struct {
+                    return Err(format!("`{}` is not a struct variant", name).into());
+                }
+                (type_info, variant.fields, name)
+            }
+            None => {
+                // TODO path segmentation
+                let name = struct_expr.path.segments.last().unwrap().clone();
+                let type_info = self.scope_stack.cur_scope().find_def_except_fn(&name);
+                let fields = match &type_info {
+                    TypeInfo::Struct { fields, .. } => {
+                        fields.names().into_iter().zip(fields.types()).collect()
+                    }
+                    _ => return Err(format!("cannot find struct `{}`", name).into()),
+                };
+                (type_info, fields, name)
+            }
+        };
+
+        let mut initialized: Vec<String> = vec![];
+        for field in struct_expr.fields.iter_mut() {
+            if initialized.contains(&field.name) {
+                return Err(format!("field `{}` specified more than once", field.name).into());
+            }
+            initialized.push(field.name.clone());
+            if fields.iter().all(|(n, _)| n != &field.name) {
+                return Err(
+                    format!("struct `{}` has no field named `{}`", name, field.name).into(),
+                );
+            }
+            self.visit_expr(&mut field.expr)?;
+        }
+
+        if let TypeInfo::Struct { vis, fields: struct_fields, .. } = &type_info {
+            if struct_fields.is_generic() && struct_fields.args().is_empty() {
+                let mut type_args = HashMap::new();
+                for field in struct_expr.fields.iter() {
+                    let (_, field_type) = fields.iter().find(|(n, _)| n == &field.name).unwrap();
+                    let expr_type = field.expr.type_info();
+                    infer_type_args(field_type, expr_type.borrow().deref(), &mut type_args);
+                }
+                let mut args = vec![];
+                for param in struct_fields.params() {
+                    // number literals are determined later, e.g. by `let p: Pair<u8>`
+                    let arg = match type_args.get(&param.name) {
+                        Some(t) => t.clone(),
+                        None => {
+                            return Err(format!(
+                                "cannot infer type for type parameter `{}`",
+                                param.name
+                            )
+                            .into())
+                        }
+                    };
+                    let cur_scope = self.scope_stack.cur_scope();
+                    if let Some(bound) =
+                        param.bounds.iter().find(|b| !cur_scope.implements(&arg, b))
+                    {
+                        return Err(format!(
+                            "the trait bound `{}: {}` is not satisfied",
+                            type_name(&arg),
+                            bound
+                        )
+                        .into());
+                    }
+                    args.push(arg);
+                }
+                let struct_fields = struct_fields.instantiate(args);
+                fields = struct_fields.names().into_iter().zip(struct_fields.types()).collect();
+                type_info = TypeInfo::Struct {
+                    vis: *vis,
+                    name: name.clone(),
+                    fields: struct_fields,
+                };
+            }
+        }
 
-    fn visit_array_expr(&mut self, array_expr: &mut ArrayExpr) -> Result<(), RccError> {
-        for e in array_expr.elems.iter_mut() {
-            self.visit_expr(e)?;
+        for field in struct_expr.fields.iter_mut() {
+            let (_, field_type) = fields.iter().find(|(n, _)| n == &field.name).unwrap();
+            self.coerce(field_type, &mut field.expr)?;
+            Self::try_determine_number_type(field_type, &mut field.expr);
+            assert_type_is(&field.expr, field_type, "invalid type for struct field")
+                .map_err(|e| locate_at_expr(e, &field.expr))?;
         }
-        if let Some(expr) = array_expr.len_expr.expr.as_mut() {
-            self.visit_expr(expr)?;
+
+        let missing: Vec<String> = fields
+            .into_iter()
+            .map(|(f, _)| f)
+            .filter(|f| !initialized.contains(f))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("missing fields {:?} in struct `{}`", missing, name).into());
         }
+        struct_expr.set_type_info(type_info);
         Ok(())
     }
 
-    fn visit_array_index_expr(
-        &mut self,
-        array_index_expr: &mut ArrayIndexExpr,
-    ) -> Result<(), RccError> {
-        todo!()
-    }
-
-    fn visit_tuple_expr(&mut self, tuple_expr: &mut TupleExpr) -> Result<(), RccError> {
-        todo!()
-    }
-
-    fn visit_tuple_index_expr(
-        &mut self,
-        tuple_index_expr: &mut TupleIndexExpr,
-    ) -> Result<(), RccError> {
-        todo!()
-    }
-
-    fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) -> Result<(), RccError> {
-        todo!()
-    }
-
     fn visit_call_expr(&mut self, call_expr: &mut CallExpr) -> Result<(), RccError> {
+        if let Expr::Path(path_expr) = call_expr.expr.as_ref() {
+            if let Some((type_info, variant)) = self.find_variant(path_expr)? {
+                let name = path_expr.segments.join("::");
+                return self.visit_variant_call(call_expr, type_info, variant, &name);
+            }
+        }
         self.visit_expr(&mut call_expr.expr)?;
         if !call_expr.expr.is_callable() {
             return Err("expr is not callable".into());
@@ -889,10 +2781,36 @@ impl SymbolResolver {
         let type_info = tp.deref();
         let type_fn_ptr = match type_info {
             TypeInfo::FnPtr(fn_ptr) => fn_ptr,
-            TypeInfo::Fn { vis: _, inner } => inner,
-            _ => unreachable!("callable type can only be fn_ptr or fn"),
+            TypeInfo::Fn { inner, generics, .. } if !generics.is_empty() => {
+                let (inner, generics) = (inner.clone(), generics.clone());
+                std::mem::drop(tp);
+                return self.visit_generic_call(call_expr, &inner, &generics);
+            }
+            TypeInfo::Fn { inner, .. } => inner,
+            TypeInfo::Closure(closure) => {
+                let closure = closure.clone();
+                std::mem::drop(tp);
+                return self.visit_closure_call(call_expr, &closure);
+            }
+            _ => unreachable!("callable type can only be fn_ptr, fn or closure"),
         };
 
+        self.visit_call_params(&mut call_expr.call_params, &type_fn_ptr.params)?;
+        call_expr.set_type_info(TypeInfo::from_type_anno(
+            &type_fn_ptr.ret_type,
+            self.scope_stack.cur_scope(),
+        ));
+        Ok(())
+    }
+
+    /// The type arguments of a generic function are specified like `max::<i32>(a, b)`,
+    /// or inferred from the types of the arguments.
+    fn visit_generic_call(
+        &mut self,
+        call_expr: &mut CallExpr,
+        type_fn_ptr: &TypeFnPtr,
+        generics: &[GenericParam],
+    ) -> Result<(), RccError> {
         if call_expr.call_params.len() != type_fn_ptr.params.len() {
             return Err(format!(
                 "This function takes {} parameters but {} parameters was supplied",
@@ -901,58 +2819,382 @@ impl SymbolResolver {
             )
             .into());
         }
-        for (expr, param) in call_expr
-            .call_params
-            .iter_mut()
-            .zip(type_fn_ptr.params.iter())
-        {
+        let path_expr = match call_expr.expr.as_mut() {
+            Expr::Path(path_expr) => path_expr,
+            _ => unreachable!("generic functions can only be called by paths"),
+        };
+        let fn_scope = Scope::with_types(
+            self.scope_stack.cur_scope(),
+            generics
+                .iter()
+                .map(|p| (p.name.clone(), TypeInfo::Param(p.clone()))),
+        );
+        let param_types: Vec<TypeInfo> = type_fn_ptr
+            .params
+            .iter()
+            .map(|t| TypeInfo::from_type_anno(t, &fn_scope))
+            .collect();
+
+        let mut type_args: HashMap<String, TypeInfo> = generics
+            .iter()
+            .zip(path_expr.type_args.iter())
+            .map(|(p, t)| (p.name.clone(), t.clone()))
+            .collect();
+        let explicit = !type_args.is_empty();
+        for expr in call_expr.call_params.iter_mut() {
+            self.visit_expr(expr)?;
+        }
+        if !explicit {
+            for (expr, param_type) in call_expr.call_params.iter().zip(param_types.iter()) {
+                infer_type_args(param_type, expr.type_info().borrow().deref(), &mut type_args);
+            }
+        }
+        let cur_scope = self.scope_stack.cur_scope();
+        let mut args = vec![];
+        for param in generics {
+            let arg = match type_args.get(&param.name) {
+                Some(t) if !t.is_unknown() => t.default_number_types(),
+                _ => {
+                    return Err(format!(
+                        "cannot infer type for type parameter `{}`",
+                        param.name
+                    )
+                    .into())
+                }
+            };
+            if let TypeInfo::TraitObject(name) = &arg {
+                return Err(format!(
+                    "the size for values of type `dyn {}` cannot be known at compilation time",
+                    name
+                )
+                .into());
+            }
+            if let Some(bound) = param.bounds.iter().find(|b| !cur_scope.implements(&arg, b)) {
+                return Err(format!(
+                    "the trait bound `{}: {}` is not satisfied",
+                    type_name(&arg),
+                    bound
+                )
+                .into());
+            }
+            type_args.insert(param.name.clone(), arg.clone());
+            args.push(arg);
+        }
+
+        for (expr, param_type) in call_expr.call_params.iter_mut().zip(param_types.iter()) {
+            let expected_type = param_type.substitute(&type_args);
+            self.coerce(&expected_type, expr)?;
+            Self::try_determine_number_type(&expected_type, expr);
+            assert_type_is(expr, &expected_type, "invalid type for call expr")?;
+        }
+        let ret_type = TypeInfo::from_type_anno(&type_fn_ptr.ret_type, &fn_scope);
+        call_expr.set_type_info(ret_type.substitute(&type_args));
+        if let Expr::Path(path_expr) = call_expr.expr.as_mut() {
+            path_expr.type_args = args;
+        }
+        Ok(())
+    }
+
+    fn visit_call_params(
+        &mut self,
+        call_params: &mut [Expr],
+        params: &[TypeAnnotation],
+    ) -> Result<(), RccError> {
+        if call_params.len() != params.len() {
+            return Err(format!(
+                "This function takes {} parameters but {} parameters was supplied",
+                params.len(),
+                call_params.len(),
+            )
+            .into());
+        }
+        for (expr, param) in call_params.iter_mut().zip(params.iter()) {
             self.visit_expr(expr)?;
             let excepted_info = TypeInfo::from_type_anno(param, self.scope_stack.cur_scope());
 
+            self.coerce(&excepted_info, expr)?;
             Self::try_determine_number_type(&excepted_info, expr);
             assert_type_is(expr, &excepted_info, "invalid type for call expr")?;
         }
-        call_expr.set_type_info(TypeInfo::from_type_anno(
+        Ok(())
+    }
+
+    /// The types of the parameters which are not annotated are inferred from
+    /// the arguments of the first call.
+    fn visit_closure_call(
+        &mut self,
+        call_expr: &mut CallExpr,
+        closure: &ClosureInfo,
+    ) -> Result<(), RccError> {
+        let params = closure.params();
+        if call_expr.call_params.len() != params.len() {
+            return Err(format!(
+                "This function takes {} parameters but {} parameters was supplied",
+                params.len(),
+                call_expr.call_params.len(),
+            )
+            .into());
+        }
+        for expr in call_expr.call_params.iter_mut() {
+            self.visit_expr(expr)?;
+        }
+        if !closure.is_resolved() {
+            let params = params
+                .iter()
+                .zip(call_expr.call_params.iter())
+                .map(|(t, expr)| match t {
+                    TypeInfo::Unknown => expr.type_info().borrow().default_number_types(),
+                    t => t.clone(),
+                })
+                .collect();
+            self.resolve_closure(closure, params)?;
+        }
+        for (expr, param) in call_expr.call_params.iter_mut().zip(closure.params().iter()) {
+            self.coerce(param, expr)?;
+            Self::try_determine_number_type(param, expr);
+            assert_type_is(expr, param, "invalid type for call expr")?;
+        }
+        call_expr.set_type_info(closure.ret_type());
+        Ok(())
+    }
+
+    /// `a.get()` calls `Foo::get` if `a` is `Foo`, `&Foo` or `&mut Foo`. The receiver
+    /// is borrowed or dereferenced to match the type of `self`, like `Foo::get(&a)`.
+    fn visit_method_call_expr(
+        &mut self,
+        method_call_expr: &mut MethodCallExpr,
+    ) -> Result<(), RccError> {
+        self.visit_expr(&mut method_call_expr.receiver)?;
+        let receiver_type = method_call_expr.receiver.type_info().borrow().clone();
+        if let TypeInfo::Range { .. } = receiver_type {
+            return self.visit_range_method_call_expr(method_call_expr);
+        }
+        let mut self_type = &receiver_type;
+        let mut ptr_kinds = vec![];
+        while let TypeInfo::Ptr {
+            kind: kind @ (PtrKind::Ref | PtrKind::MutRef),
+            type_info,
+        } = self_type
+        {
+            ptr_kinds.push(*kind);
+            self_type = type_info;
+        }
+
+        let method = &method_call_expr.method;
+        let cur_scope = self.scope_stack.cur_scope();
+        let type_fn_ptr = match cur_scope.find_type_fn(self_type, method) {
+            Some(AssocFn {
+                type_info: TypeInfo::Fn { inner, .. },
+                is_method: true,
+                ..
+            }) => inner,
+            Some(_) => {
+                return Err(format!(
+                    "`{}::{}` is an associated function, not a method",
+                    type_name(self_type),
+                    method
+                )
+                .into())
+            }
+            None => {
+                return Err(match self_type {
+                    TypeInfo::Param(param) => format!(
+                        "no method named `{}` found for type parameter `{}`",
+                        method, param.name
+                    ),
+                    t if t.impl_name().is_some() => {
+                        format!("no method named `{}` found for `{}`", method, type_name(t))
+                    }
+                    _ => format!(
+                        "no method named `{}` found for type `{:?}`",
+                        method, receiver_type
+                    ),
+                }
+                .into())
+            }
+        };
+
+        // adjust the receiver
+        let self_param = TypeInfo::from_type_anno(&type_fn_ptr.params[0], cur_scope);
+        let receiver = method_call_expr.receiver.as_mut();
+        match self_param {
+            TypeInfo::Ptr { kind, .. } if ptr_kinds.is_empty() => {
+                if kind == PtrKind::MutRef && receiver.kind() != ExprKind::MutablePlace {
+                    return Err("cannot borrow immutable value as mutable".into());
+                }
+                let op = if kind == PtrKind::MutRef {
+                    UnOp::BorrowMut
+                } else {
+                    UnOp::Borrow
+                };
+                wrap_unary(receiver, op, self_param, ExprKind::Value);
+            }
+            TypeInfo::Ptr { kind, .. } => {
+                if kind == PtrKind::MutRef && ptr_kinds.last() != Some(&PtrKind::MutRef) {
+                    return Err("cannot borrow data in a `&` reference as mutable".into());
+                }
+                deref_receiver(receiver, &ptr_kinds[..ptr_kinds.len() - 1]);
+            }
+            _ => deref_receiver(receiver, &ptr_kinds),
+        }
+
+        self.visit_call_params(&mut method_call_expr.call_params, &type_fn_ptr.params[1..])?;
+        method_call_expr.set_type_info(TypeInfo::from_type_anno(
             &type_fn_ptr.ret_type,
             self.scope_stack.cur_scope(),
         ));
         Ok(())
     }
 
+    /// `Shape::Rect(1, 2)`
+    fn visit_variant_call(
+        &mut self,
+        call_expr: &mut CallExpr,
+        type_info: TypeInfo,
+        variant: VariantInfo,
+        name: &str,
+    ) -> Result<(), RccError> {
+        if variant.kind != VariantKind::Tuple {
+            return Err(format!("`{}` is not a tuple variant", name).into());
+        }
+        if call_expr.call_params.len() != variant.fields.len() {
+            return Err(format!(
+                "This enum variant takes {} fields but {} fields was supplied",
+                variant.fields.len(),
+                call_expr.call_params.len(),
+            )
+            .into());
+        }
+        for (expr, (_, field_type)) in call_expr.call_params.iter_mut().zip(variant.fields.iter()) {
+            self.visit_expr(expr)?;
+            Self::try_determine_number_type(field_type, expr);
+            assert_type_is(expr, field_type, "invalid type for enum variant field")?;
+        }
+        call_expr.set_type_info(type_info);
+        Ok(())
+    }
+
     fn visit_field_access_expr(
         &mut self,
         field_access_expr: &mut FieldAccessExpr,
     ) -> Result<(), RccError> {
-        Ok(())
+        self.visit_expr(&mut field_access_expr.lhs)?;
+        let field_name = match field_access_expr.field_name() {
+            Some(name) => name.to_string(),
+            None => return Err("expected field name".into()),
+        };
+        let t = field_access_expr.lhs.type_info();
+        let tp = t.borrow();
+        // fields are accessed through references automatically, e.g. `self.x`
+        let mut lhs_type = tp.deref();
+        let mut ptr_kinds = vec![];
+        while let TypeInfo::Ptr {
+            kind: kind @ (PtrKind::Ref | PtrKind::MutRef),
+            type_info,
+        } = lhs_type
+        {
+            ptr_kinds.push(*kind);
+            lhs_type = type_info;
+        }
+        let expr_kind = if ptr_kinds.is_empty() {
+            field_access_expr.lhs.kind()
+        } else if ptr_kinds.iter().all(|k| k == &PtrKind::MutRef) {
+            ExprKind::MutablePlace
+        } else {
+            ExprKind::Place
+        };
+        let field_type = match lhs_type {
+            TypeInfo::Struct { fields, .. } => fields.find(&field_name).map(|(_, t)| t),
+            _ => None,
+        };
+        match field_type {
+            Some(type_info) => {
+                field_access_expr.set_type_info(type_info);
+                field_access_expr.expr_kind = expr_kind;
+                Ok(())
+            }
+            None => match lhs_type {
+                TypeInfo::Struct { name, .. } => {
+                    Err(format!("no field `{}` on type `{}`", field_name, name).into())
+                }
+                t => Err(format!("no field `{}` on type `{:?}`", field_name, t).into()),
+            },
+        }
     }
 
     fn visit_while_expr(&mut self, while_expr: &mut WhileExpr) -> Result<(), RccError> {
         self.visit_expr(&mut while_expr.0)?;
         // store loop kind
-        self.loop_kind_stack.push(self.loop_kind);
-        self.loop_kind = LoopKind::While;
+        self.enter_loop(LoopKind::While, while_expr.2.clone());
         assert_type_is(
             &*while_expr.0,
             &TypeInfo::Bool,
             "invalid type in while condition",
-        )?;
+        )
+        .map_err(|e| locate_at_expr(e, &while_expr.0))?;
 
         self.visit_block_expr(&mut while_expr.1)?;
+        // restore loop kind
+        self.exit_loop();
+
         assert_type_is(
             &*while_expr.1,
             &TypeInfo::Unit,
             "invalid type in while block",
-        )?;
+        )
+    }
+
+    /// The pattern is declared in the scope of the block like the parameters of functions.
+    fn visit_for_expr(&mut self, for_expr: &mut ForExpr) -> Result<(), RccError> {
+        self.visit_expr(&mut for_expr.expr)?;
+        let elem_type = match for_expr.expr.type_info().borrow().deref() {
+            TypeInfo::Range {
+                has_start: true,
+                elem_type,
+                ..
+            } if elem_type.is_integer() => *elem_type.clone(),
+            t => {
+                let e = format!("`{}` is not an iterator", type_name(t)).into();
+                return Err(locate_at_expr(e, &for_expr.expr));
+            }
+        };
+
+        self.scope_stack.enter(&mut for_expr.block.scope);
+        let declared = self.declare_pattern(
+            &mut for_expr.pattern,
+            Rc::new(RefCell::new(elem_type.clone())),
+        );
+        self.scope_stack.exit_scope();
+        declared?;
+        if let Some(witness) = exhaustiveness::check_irrefutable(&elem_type, &for_expr.pattern) {
+            return Err(format!(
+                "refutable pattern in `for` loop binding: {} not covered",
+                witness
+            )
+            .into());
+        }
 
+        // store loop kind
+        self.enter_loop(LoopKind::For, for_expr.label.clone());
+        let result = self.visit_block_expr(&mut for_expr.block);
         // restore loop kind
         self.exit_loop();
-        Ok(())
+        result?;
+
+        assert_type_is(
+            &*for_expr.block,
+            &TypeInfo::Unit,
+            "invalid type in for block",
+        )
     }
 
     fn visit_loop_expr(&mut self, loop_expr: &mut LoopExpr) -> Result<(), RccError> {
-        self.loop_kind_stack.push(self.loop_kind);
-        self.loop_kind = LoopKind::Loop(loop_expr);
-        self.visit_block_expr(&mut loop_expr.expr)?;
+        self.enter_loop(LoopKind::Loop(loop_expr), loop_expr.label.clone());
+        let result = self.visit_block_expr(&mut loop_expr.expr);
+        if result.is_err() {
+            self.exit_loop();
+            return result;
+        }
         // never return, example: `let a = loop {};`
         let t = loop_expr.type_info();
         let tp = t.borrow();
@@ -981,11 +3223,11 @@ impl SymbolResolver {
             let tp = t.borrow();
             let cond_type_info = tp.deref();
             if !cond_type_info.is(&TypeInfo::Bool) {
-                return Err(format!(
+                let e = format!(
                     "invalid type of condition expr: expected `bool`, found: {:?}",
                     cond_type_info
-                )
-                .into());
+                );
+                return Err(locate_at_expr(e.into(), cond));
             }
         }
 
@@ -1018,17 +3260,173 @@ impl SymbolResolver {
         Ok(())
     }
 
+    fn visit_match_expr(&mut self, match_expr: &mut MatchExpr) -> Result<(), RccError> {
+        self.visit_expr(&mut match_expr.expr)?;
+        let expr_type = match_expr.expr.type_info();
+        for arm in match_expr.arms.iter_mut() {
+            self.scope_stack.enter(&mut arm.scope);
+            let result = self.visit_match_arm(arm, expr_type.clone());
+            self.scope_stack.exit_scope();
+            result?;
+        }
+
+        // The type of the match expr is the type of the arms which are not `!`,
+        // number literals like `0` are determined by the other arms.
+        let arm_types: Vec<TypeInfo> = match_expr
+            .arms
+            .iter()
+            .map(|arm| arm.expr.type_info().borrow().clone())
+            .collect();
+        let match_type = arm_types
+            .iter()
+            .find(|t| !t.is_never() && !t.is_i() && !t.is_f())
+            .or_else(|| arm_types.iter().find(|t| !t.is_never()))
+            .cloned()
+            .unwrap_or(TypeInfo::Never);
+        for arm in match_expr.arms.iter_mut() {
+            Self::try_determine_number_type(&match_type, &mut arm.expr);
+            let t = arm.expr.type_info();
+            let tp = t.borrow();
+            if !match_type.eq_or_never(tp.deref()) {
+                let e = format!(
+                    "different type of match arms: `{:?}`, `{:?}`",
+                    match_type, tp
+                );
+                return Err(locate_at_expr(e.into(), &arm.expr));
+            }
+        }
+        match_expr.set_type_info(match_type);
+
+        let arms: Vec<(&Pattern, bool)> = match_expr
+            .arms
+            .iter()
+            .map(|arm| (&arm.pattern, arm.guard.is_some()))
+            .collect();
+        let (unreachable, missing) = exhaustiveness::check_arms(&expr_type.borrow(), &arms);
+        if let Some(i) = unreachable.first() {
+            return Err(RccError::with_span(
+                "unreachable pattern",
+                match_expr.arms[*i].pattern_span.0,
+            ));
+        }
+        match missing {
+            Some(witness) => {
+                Err(format!("non-exhaustive patterns: `{}` not covered", witness).into())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn visit_match_arm(
+        &mut self,
+        arm: &mut MatchArm,
+        type_info: Rc<RefCell<TypeInfo>>,
+    ) -> Result<(), RccError> {
+        self.declare_pattern(&mut arm.pattern, type_info)
+            .map_err(|e| e.or_span(arm.pattern_span.0))?;
+        if let Some(guard) = arm.guard.as_mut() {
+            self.visit_expr(guard)?;
+            assert_type_is(guard, &TypeInfo::Bool, "invalid type of match guard")
+                .map_err(|e| locate_at_expr(e, guard))?;
+        }
+        self.visit_expr(&mut arm.expr)
+    }
+
+    /// The body of a closure is resolved when the types of all the parameters are known,
+    /// otherwise it is resolved when the closure is called or coerced to a function pointer.
+    fn visit_closure_expr(&mut self, closure_expr: &mut ClosureExpr) -> Result<(), RccError> {
+        let item_fn = &closure_expr.item_fn;
+        let cur_scope = self.scope_stack.cur_scope();
+        let params: Vec<TypeInfo> = item_fn
+            .fn_params
+            .params
+            .iter()
+            .map(|param| TypeInfo::from_type_anno(&param._type, cur_scope))
+            .collect();
+        let ret_type = TypeInfo::from_type_anno(&item_fn.ret_type, cur_scope);
+        let scope_id = item_fn.fn_block.scope.scope_id;
+        let closure = ClosureInfo::new(scope_id, params.clone(), ret_type);
+        closure_expr.set_type_info(TypeInfo::Closure(closure.clone()));
+
+        let scope: *mut Scope = self.scope_stack.cur_scope_mut();
+        self.pending_closures
+            .insert(scope_id, (closure_expr as *mut ClosureExpr, scope));
+        if params.iter().all(|t| !t.is_unknown()) {
+            self.resolve_closure(&closure, params)?;
+        }
+        Ok(())
+    }
+
+    /// Resolve the body of the closure in the scope where it is defined, the return type
+    /// is the type of the body if it is not annotated.
+    fn resolve_closure(&mut self, closure: &ClosureInfo, params: Vec<TypeInfo>) -> Result<(), RccError> {
+        let (closure_expr, scope) = match self.pending_closures.remove(&closure.scope_id()) {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        let closure_expr = unsafe { &mut *closure_expr };
+        let by_ref = !closure_expr.is_move;
+        let item_fn = &mut closure_expr.item_fn;
+
+        self.scope_stack.reenter(scope);
+        self.scope_stack.enter(&mut item_fn.fn_block.scope);
+        let declared = item_fn
+            .fn_params
+            .params
+            .iter_mut()
+            .zip(params.iter())
+            .try_for_each(|(param, t)| self.declare_param(&mut param.pattern, t.clone()));
+        self.scope_stack.exit_scope();
+
+        let ret_type = closure.ret_type();
+        let cur_fn_ret_type = std::mem::replace(&mut self.cur_fn_ret_type, ret_type.clone());
+        self.enter_loop(NotIn, None);
+        self.closure_stack
+            .push((closure.scope_id(), closure.clone(), by_ref));
+        let result = declared
+            .and_then(|_| self.visit_block_expr(&mut item_fn.fn_block))
+            .and_then(|_| {
+                let ret_type = self.cur_fn_ret_type.clone();
+                if ret_type.is_unknown() {
+                    let block_type = item_fn.fn_block.type_info().borrow().default_number_types();
+                    Self::try_determine_number_type(&block_type, &mut item_fn.fn_block);
+                    Ok(block_type)
+                } else {
+                    self.validate_fn_block_type(item_fn).map(|_| ret_type)
+                }
+            });
+        self.closure_stack.pop();
+        self.exit_loop();
+        self.cur_fn_ret_type = cur_fn_ret_type;
+        self.scope_stack.exit_scope();
+
+        closure.resolve(params, result?);
+        Ok(())
+    }
+
     fn visit_return_expr(&mut self, return_expr: &mut ReturnExpr) -> Result<(), RccError> {
         match return_expr.0.as_mut() {
             Some(expr) => {
                 self.visit_expr(expr)?;
+                // the first return expr determines the return type of a closure without
+                // annotation
+                if self.cur_fn_ret_type.is_unknown() {
+                    self.cur_fn_ret_type = expr.type_info().borrow().default_number_types();
+                }
+                let ret_type = self.cur_fn_ret_type.clone();
+                self.coerce(&ret_type, expr)?;
                 Self::try_determine_number_type(&self.cur_fn_ret_type, expr.as_mut());
                 let type_info = expr.type_info();
                 let t = type_info.borrow();
                 let tp = t.deref();
                 self.validate_ret_type(tp)
             }
-            None => self.validate_ret_type(&TypeInfo::Unit),
+            None => {
+                if self.cur_fn_ret_type.is_unknown() {
+                    self.cur_fn_ret_type = TypeInfo::Unit;
+                }
+                self.validate_ret_type(&TypeInfo::Unit)
+            }
         }
     }
 
@@ -1057,12 +3455,9 @@ impl SymbolResolver {
             }
         }
 
-        if !self.loop_kind.is_in_loop() {
-            return Err("break expr can not be out of loop block".into());
-        }
-
-        if let Some(expr) = break_expr.0.as_mut() {
-            return match self.loop_kind {
+        let loop_kind = self.target_loop(&break_expr.label, "break")?;
+        if let Some(expr) = break_expr.expr.as_mut() {
+            return match loop_kind {
                 LoopKind::Loop(loop_expr) => {
                     self.visit_expr(expr)?;
                     Self::try_determine_number_type(
@@ -1073,11 +3468,191 @@ impl SymbolResolver {
                 }
                 _ => Err("only loop can return values".into()),
             };
-        } else if let LoopKind::Loop(loop_expr) = self.loop_kind {
+        } else if let LoopKind::Loop(loop_expr) = loop_kind {
             return try_set_type_info(loop_expr, Rc::new(RefCell::new(TypeInfo::Unit)));
         }
         Ok(())
     }
+
+    fn visit_continue_expr(&mut self, continue_expr: &mut ContinueExpr) -> Result<(), RccError> {
+        self.target_loop(&continue_expr.0, "continue").map(|_| ())
+    }
+}
+
+/// Whether the types contain the struct or enum `target` without indirection.
+fn contains_type(types: &[TypeInfo], target: &TypeInfo, visited: &mut Vec<TypeInfo>) -> bool {
+    types.iter().any(|type_info| match type_info {
+        TypeInfo::Struct { .. } | TypeInfo::Enum { .. } => {
+            if type_info == target {
+                true
+            } else if visited.contains(type_info) {
+                false
+            } else {
+                visited.push(type_info.clone());
+                contains_type(&type_info.member_types(), target, visited)
+            }
+        }
+        TypeInfo::Tuple(types) => contains_type(types, target, visited),
+        TypeInfo::Array { elem_type, .. } => {
+            contains_type(std::slice::from_ref(elem_type.as_ref()), target, visited)
+        }
+        _ => false,
+    })
+}
+
+/// Attach the span of `expr` to the error if it has no location yet.
+pub(crate) fn locate_at_expr(e: RccError, expr: &Expr) -> RccError {
+    match expr.span() {
+        Some(span) => e.or_span(span),
+        None => e,
+    }
+}
+
+/// Set the type of a number literal, which may be negated like `-1` in `x == -1`.
+fn set_lit_num_type(expr: &mut Expr, type_info: Rc<RefCell<TypeInfo>>) {
+    match expr {
+        Expr::LitNum(lit_num) => lit_num.set_type_info_ref(type_info),
+        Expr::Unary(unary) if unary.op == UnOp::Neg && matches!(*unary.expr, Expr::LitNum(_)) => {
+            set_lit_num_type(&mut unary.expr, type_info.clone());
+            unary.set_type_info_ref(type_info);
+        }
+        _ => {}
+    }
+}
+
+/// Replace `expr` with the resolved unary expr `op expr`.
+fn wrap_unary(expr: &mut Expr, op: UnOp, type_info: TypeInfo, expr_kind: ExprKind) {
+    let span = expr.span();
+    let inner = std::mem::replace(expr, Expr::LitBool(false));
+    let mut unary_expr = UnAryExpr::new(op, inner);
+    unary_expr.set_type_info(type_info);
+    unary_expr.expr_kind = expr_kind;
+    if let Some(span) = span {
+        unary_expr.span = NodeSpan(span);
+    }
+    *expr = Expr::Unary(unary_expr);
+}
+
+/// Dereference the receiver of a method call through the pointers of `ptr_kinds`.
+fn deref_receiver(receiver: &mut Expr, ptr_kinds: &[PtrKind]) {
+    for kind in ptr_kinds {
+        let type_info = match receiver.type_info().borrow().deref() {
+            TypeInfo::Ptr { type_info, .. } => *type_info.clone(),
+            _ => unreachable!(),
+        };
+        let expr_kind = if kind == &PtrKind::MutRef {
+            ExprKind::MutablePlace
+        } else {
+            ExprKind::Place
+        };
+        wrap_unary(receiver, UnOp::Deref, type_info, expr_kind);
+    }
+}
+
+/// Name of the type in error messages, e.g. `Foo`, `i32`, `T`.
+fn type_name(type_info: &TypeInfo) -> String {
+    match type_info {
+        TypeInfo::Param(param) => param.name.clone(),
+        TypeInfo::Ptr { kind, type_info } => {
+            let prefix = match kind {
+                PtrKind::Ref => "&",
+                PtrKind::MutRef => "&mut ",
+                PtrKind::ConstRawPtr => "*const ",
+                PtrKind::MutRawPtr => "*mut ",
+            };
+            format!("{}{}", prefix, type_name(type_info))
+        }
+        // names of the range types in std
+        TypeInfo::Range {
+            range_op,
+            has_start,
+            has_end,
+            elem_type,
+            rev,
+        } => {
+            let name = match (has_start, has_end, range_op) {
+                (false, false, _) => return "RangeFull".into(),
+                (true, true, RangeOp::DotDot) => "Range",
+                (true, true, RangeOp::DotDotEq) => "RangeInclusive",
+                (true, false, _) => "RangeFrom",
+                (false, true, RangeOp::DotDot) => "RangeTo",
+                (false, true, RangeOp::DotDotEq) => "RangeToInclusive",
+            };
+            let name = format!("{}<{}>", name, type_name(elem_type));
+            if *rev {
+                format!("Rev<{}>", name)
+            } else {
+                name
+            }
+        }
+        TypeInfo::Tuple(types) => {
+            let names: Vec<String> = types.iter().map(type_name).collect();
+            format!("({})", names.join(", "))
+        }
+        TypeInfo::Array { elem_type, len } => format!("[{}; {}]", type_name(elem_type), len),
+        TypeInfo::LitNum(TypeLitNum::I) => "{integer}".into(),
+        TypeInfo::LitNum(TypeLitNum::F) => "{float}".into(),
+        TypeInfo::Unit => "()".into(),
+        TypeInfo::Never => "!".into(),
+        TypeInfo::Str => "str".into(),
+        t => t.impl_name().unwrap_or_else(|| format!("{:?}", t)),
+    }
+}
+
+/// Bind the generic parameters in `param_type` to the types in `arg_type` at the same
+/// positions. An integer or float literal is replaced by a type determined elsewhere.
+fn infer_type_args(
+    param_type: &TypeInfo,
+    arg_type: &TypeInfo,
+    type_args: &mut HashMap<String, TypeInfo>,
+) {
+    match (param_type, arg_type) {
+        (TypeInfo::Param(param), t) => match type_args.get(&param.name) {
+            Some(TypeInfo::LitNum(TypeLitNum::I)) if t.is_integer() => {
+                type_args.insert(param.name.clone(), t.clone());
+            }
+            Some(TypeInfo::LitNum(TypeLitNum::F)) if t.is_float() => {
+                type_args.insert(param.name.clone(), t.clone());
+            }
+            Some(_) => {}
+            None if t.is_never() || t.is_unknown() => {}
+            None => {
+                type_args.insert(param.name.clone(), t.clone());
+            }
+        },
+        (
+            TypeInfo::Ptr { kind, type_info },
+            TypeInfo::Ptr {
+                kind: arg_kind,
+                type_info: arg_info,
+            },
+        ) if kind == arg_kind => infer_type_args(type_info, arg_info, type_args),
+        (TypeInfo::Tuple(types), TypeInfo::Tuple(arg_types)) if types.len() == arg_types.len() => {
+            for (t, a) in types.iter().zip(arg_types.iter()) {
+                infer_type_args(t, a, type_args);
+            }
+        }
+        (
+            TypeInfo::Array { elem_type, len },
+            TypeInfo::Array {
+                elem_type: arg_elem,
+                len: arg_len,
+            },
+        ) if len == arg_len => infer_type_args(elem_type, arg_elem, type_args),
+        (
+            TypeInfo::Struct { name, fields, .. },
+            TypeInfo::Struct {
+                name: arg_name,
+                fields: arg_fields,
+                ..
+            },
+        ) if name == arg_name && fields.args().len() == arg_fields.args().len() => {
+            for (t, a) in fields.args().iter().zip(arg_fields.args().iter()) {
+                infer_type_args(t, a, type_args);
+            }
+        }
+        _ => {}
+    }
 }
 
 pub(super) fn assert_type_is<T: ExprVisit>(
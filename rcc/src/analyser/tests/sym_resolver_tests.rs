@@ -1,21 +1,24 @@
 use crate::analyser::sym_resolver::SymbolResolver;
 use crate::analyser::tests::get_ast_file;
+use crate::lexer::token::Span;
 use crate::rcc::RccError;
 
+/// Compare error messages only, locations are checked in `span_test`.
 fn file_validate(inputs: &[&str], expecteds: &[Result<(), RccError>]) {
     assert_eq!(inputs.len(), expecteds.len());
     for (i, (input, expected)) in inputs.iter().zip(expecteds).enumerate() {
         let mut sym_resolver = SymbolResolver::new();
         let ast_file = get_ast_file(input);
-        match ast_file {
-            Ok(mut f) => {
-                let actual = sym_resolver.visit_file(&mut f);
-                assert_eq!(expected, &actual, "{}th test case", i);
-            }
-            Err(e) => {
-                assert_eq!(expected, &Err(e), "{}th test case", i);
-            }
-        }
+        let actual = match ast_file {
+            Ok(mut f) => sym_resolver.visit_file(&mut f),
+            Err(e) => Err(e),
+        };
+        assert_eq!(
+            expected.as_ref().map(|_| ()).map_err(|e| e.to_string()),
+            actual.map_err(|e| e.to_string()),
+            "{}th test case",
+            i
+        );
 
     }
 }
@@ -34,8 +37,70 @@ fn ident_not_found_test() {
     .unwrap();
     assert_eq!(1, ast_file.scope.types.len());
     assert_eq!(
-        Err("identifier `a` not found".into()),
-        sym_resolver.visit_file(&mut ast_file)
+        Err("identifier `a` not found".to_string()),
+        sym_resolver.visit_file(&mut ast_file).map_err(|e| e.to_string())
+    );
+}
+
+#[test]
+fn span_test() {
+    fn resolve_err(input: &str) -> RccError {
+        let mut ast_file = get_ast_file(input).unwrap();
+        SymbolResolver::new().visit_file(&mut ast_file).unwrap_err()
+    }
+
+    let input = "fn main() { let mut a = 1; a = b + 1; }";
+    assert_eq!(
+        RccError::with_span("identifier `b` not found", Span::new(31, 32)),
+        resolve_err(input)
+    );
+
+    let input = "fn main() { let a: i64 = 2i32; }";
+    assert_eq!(
+        RccError::with_span(
+            "invalid type in let stmt: expected `LitNum(i64)`, found `LitNum(i32)`",
+            Span::new(12, 30)
+        ),
+        resolve_err(input)
+    );
+
+    let input = "fn main() { let a = 2; a = 3; }";
+    assert_eq!(
+        RccError::with_span("lhs is not mutable", Span::new(23, 28)),
+        resolve_err(input)
+    );
+
+    let input = "fn foo() -> i32 { 1 } fn main() { if foo() + 1 { } }";
+    assert_eq!(
+        RccError::with_span(
+            "invalid type of condition expr: expected `bool`, found: LitNum(i32)",
+            Span::new(37, 46)
+        ),
+        resolve_err(input)
+    );
+
+    let input = "fn foo() -> i32 { }";
+    assert_eq!(
+        RccError::with_span(
+            "invalid return type: expected `i32`, found `()`",
+            Span::new(0, 15)
+        ),
+        resolve_err(input)
+    );
+
+    let input = "fn main() { let a = match 5 { 0..=9 => 1, 2..=3 => 2, _ => 3 }; }";
+    assert_eq!(
+        RccError::with_span("unreachable pattern", Span::new(42, 47)),
+        resolve_err(input)
+    );
+
+    let input = "enum E { A } struct P { x: i32 } fn main() { let p = P { x: 1 }; match p { E::A => {} _ => {} } }";
+    assert_eq!(
+        RccError::with_span(
+            "mismatched types in pattern: expected `P`, found `E`",
+            Span::new(75, 79)
+        ),
+        resolve_err(input)
     );
 }
 
@@ -189,6 +254,31 @@ fn bin_op_test() {
     );
 }
 
+#[test]
+fn unary_op_test() {
+    file_validate(
+        &[
+            r#"
+        fn main() {
+            let a: i8 = -3;
+            let b = -a;
+            let c = -2.5f64;
+            let d: u8 = 3;
+            let e = !d;
+            let f = !(a < b) || !true;
+        }
+    "#,
+            "fn neg(x: u32) -> u32 { -x }",
+            "fn not(x: f32) -> f32 { !x }",
+        ],
+        &[
+            Ok(()),
+            Err("cannot apply unary operator `-` to type `LitNum(u32)`".into()),
+            Err("cannot apply unary operator `!` to type `LitNum(f32)`".into()),
+        ],
+    );
+}
+
 #[test]
 fn block_test() {
     file_validate(
@@ -464,3 +554,1022 @@ extern "C" {
 }
     "#], &[Ok(()), Err("error in parsing: except ;".into())]);
 }
+
+#[test]
+fn multi_error_test() {
+    let input = r#"fn main() {
+    let c: bool = 2;
+    let mut a = 1;
+    a = d;
+}
+fn foo(a: i32) -> i32 { a + true }
+fn bar() -> i32 {
+    let x = y + 1;
+    while x {}
+    x
+}
+"#;
+    let mut ast_file = get_ast_file(input).unwrap();
+    let result = SymbolResolver::new().visit_file(&mut ast_file);
+    // usages of `x` are not reported
+    assert_eq!(
+        Err(RccError::Multiple(vec![
+            RccError::with_span(
+                "invalid type in let stmt: expected `Bool`, found `LitNum(#i)`",
+                Span::new(16, 32)
+            ),
+            RccError::with_span("identifier `d` not found", Span::new(60, 61)),
+            RccError::with_span(
+                "invalid operand type `LitNum(i32)` and `Bool` for `+`",
+                Span::new(89, 97)
+            ),
+            RccError::with_span("identifier `y` not found", Span::new(130, 131)),
+        ])),
+        result
+    );
+}
+
+#[test]
+fn struct_test() {
+    file_validate(
+        &[
+            r#"
+    struct Point { x: i32, y: i32 }
+    struct Line { a: Point, b: Point }
+    fn main() {
+        let mut l = Line { a: Point { x: 1, y: 2 }, b: Point { y: 3, x: 4 } };
+        l.a.x = l.b.y;
+        let x: i32 = l.a.x;
+    }
+            "#,
+            r#"
+    struct Point { x: i32, y: i32 }
+    fn main() {
+        let p = Point { x: 1 };
+    }
+            "#,
+            r#"
+    struct Point { x: i32, y: i32 }
+    fn main() {
+        let p = Point { x: 1, y: 2, z: 3 };
+    }
+            "#,
+            r#"
+    struct Point { x: i32, y: i32 }
+    fn main() {
+        let p = Point { x: 1, y: true };
+    }
+            "#,
+            r#"
+    struct Point { x: i32, y: i32 }
+    fn main() {
+        let p = Point { x: 1, y: 2 };
+        let z = p.z;
+    }
+            "#,
+            r#"
+    struct List { next: List }
+            "#,
+            r#"
+    struct Point { x: i32, x: i32 }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("missing fields [\"y\"] in struct `Point`".into()),
+            Err("struct `Point` has no field named `z`".into()),
+            Err("invalid type for struct field: expected LitNum(i32), found Bool".into()),
+            Err("no field `z` on type `Point`".into()),
+            Err("recursive type `List` has infinite size".into()),
+            Err("field `x` is already declared".into()),
+        ],
+    );
+}
+
+#[test]
+fn array_test() {
+    file_validate(
+        &[
+            r#"
+    fn main() {
+        let mut a: [u8; 3] = [1, 2, 3];
+        a[0] = 4;
+        let i: usize = 2;
+        let c: u8 = a[i];
+        let r = &mut a;
+        r[1] += 3;
+        let m = [[1, 2], [3, 4]];
+        let x: i32 = m[1][0];
+        let b: [i32; 4] = [x; 4];
+    }
+            "#,
+            r#"
+    fn main() {
+        let a = [1, true];
+    }
+            "#,
+            r#"
+    fn main() {
+        let a = [1, 2];
+        let i: i32 = 0;
+        let b = a[i];
+    }
+            "#,
+            r#"
+    fn main() {
+        let a = [1, 2];
+        a[0] = 3;
+    }
+            "#,
+            r#"
+    fn main() {
+        let mut a = [1, 2];
+        let r = &a;
+        r[0] = 3;
+    }
+            "#,
+            r#"
+    fn main() {
+        let a = 1;
+        let b = a[0];
+    }
+            "#,
+            r#"
+    fn main() {
+        let a: [i32; 2] = [1, 2, 3];
+    }
+            "#,
+            r#"
+    fn main() {
+        let a = [1, 2];
+        let r = &mut a;
+    }
+            "#,
+            r#"
+    fn main() {
+        let n = 3;
+        let a = [0; n];
+    }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("mismatched types in array: expected Bool, found LitNum(#i)".into()),
+            Err(
+                "the type `[T]` cannot be indexed by this type: expected LitNum(usize), found LitNum(i32)"
+                    .into(),
+            ),
+            Err("lhs is not mutable".into()),
+            Err("lhs is not mutable".into()),
+            Err("cannot index into a value of type `LitNum(#i)`".into()),
+            Err(concat!(
+                "invalid type in let stmt: expected `Array { elem_type: LitNum(i32), len: 2 }`, ",
+                "found `Array { elem_type: LitNum(#i), len: 3 }`"
+            )
+            .into()),
+            Err("cannot borrow immutable value as mutable".into()),
+            Err("array length must be an integer literal".into()),
+        ],
+    );
+}
+
+#[test]
+fn tuple_test() {
+    file_validate(
+        &[
+            r#"
+    fn pair(a: i32) -> (i32, (bool, u8)) {
+        (a, (true, 2))
+    }
+    fn main() {
+        let mut t = pair(1);
+        t.1.1 += 3;
+        let (a, (b, mut c)): (i32, (bool, u8)) = t;
+        c = t.1.1;
+        let u: () = ();
+        let x: i32 = (a, 2).1;
+    }
+            "#,
+            r#"
+    fn main() {
+        let t = (1, 2);
+        let c = t.2;
+    }
+            "#,
+            r#"
+    fn main() {
+        let (a, b) = (1, 2, 3);
+    }
+            "#,
+            r#"
+    fn main() {
+        let t = (1, 2);
+        t.0 = 3;
+    }
+            "#,
+            r#"
+    fn foo((a, b): (i32, i32)) -> i32 { a + b }
+            "#,
+            r#"
+    fn foo((a, 0): (i32, i32)) {}
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("no field `2` on type `Tuple([LitNum(#i), LitNum(#i)])`".into()),
+            Err(concat!(
+                "mismatched types: expected a tuple with 2 elements, ",
+                "found `Tuple([LitNum(#i), LitNum(#i), LitNum(#i)])`"
+            )
+            .into()),
+            Err("lhs is not mutable".into()),
+            Ok(()),
+            Err("refutable pattern in function argument: `(_, i32::MIN..=-1)` and `(_, 1..=i32::MAX)` not covered".into()),
+        ],
+    );
+}
+
+#[test]
+fn enum_test() {
+    file_validate(
+        &[
+            r#"
+    enum Shape {
+        Circle(i32),
+        Rect(i32, i32),
+        Dot,
+        Square { side: i32 },
+    }
+    fn area(s: Shape) -> i32 {
+        match s {
+            Shape::Circle(r) => 3 * r * r,
+            Shape::Rect(w, h) if w == h => w * w,
+            Shape::Rect(w, h) => w * h,
+            Shape::Dot => 0,
+            _ => 1,
+        }
+    }
+    fn main() {
+        let a = area(Shape::Rect(2, 3)) + area(Shape::Square { side: 2 });
+        let b = match (a, true) {
+            (0, _) => 0,
+            (-1, false) => 1,
+            (_, _) => 2,
+        };
+    }
+            "#,
+            r#"
+    enum E { A, B(i32) }
+    fn main() {
+        let x = match E::A {
+            E::A => 1,
+        };
+    }
+            "#,
+            r#"
+    enum E { A, B(i32) }
+    fn main() {
+        let x = match E::A {
+            _ => 1,
+            E::A => 2,
+        };
+    }
+            "#,
+            r#"
+    enum E { A, B(i32) }
+    fn main() {
+        let E::B(x) = E::B(1);
+    }
+            "#,
+            r#"
+    enum E { A, B(i32) }
+    fn main() {
+        let e = E::C;
+    }
+            "#,
+            r#"
+    enum E { A, B(i32) }
+    fn main() {
+        let e = E::B(1, 2);
+    }
+            "#,
+            r#"
+    enum E { A, B(i32) }
+    fn main() {
+        match E::A {
+            E::A => {}
+            E::B(x, y) => {}
+        }
+    }
+            "#,
+            r#"
+    enum E { A, A(i32) }
+            "#,
+            r#"
+    fn main() {
+        let x = match true {
+            true => 1,
+            false => true,
+        };
+    }
+            "#,
+            r#"
+    enum D { A, B, C, D }
+    fn main() {
+        let D::A = D::B;
+    }
+            "#,
+            r#"
+    enum F { A, B, C, D, E }
+    fn main() {
+        let (true, F::A) = (true, F::B);
+    }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("non-exhaustive patterns: `E::B(_)` not covered".into()),
+            Err("unreachable pattern".into()),
+            Err("refutable pattern in local binding: `E::A` not covered".into()),
+            Err("no variant named `C` in enum `E`".into()),
+            Err("This enum variant takes 1 fields but 2 fields was supplied".into()),
+            Err("this pattern has 2 fields, but the corresponding tuple variant has 1 fields".into()),
+            Err("variant `A` is already declared in enum `E`".into()),
+            Err("different type of match arms: `Bool`, `LitNum(#i)`".into()),
+            Err("refutable pattern in local binding: `D::B`, `D::C` and `D::D` not covered".into()),
+            Err("refutable pattern in local binding: `(false, _)`, `(true, F::B)`, `(true, F::C)` and 2 more not covered".into()),
+        ],
+    );
+}
+
+#[test]
+fn pattern_test() {
+    file_validate(
+        &[
+            r#"
+    struct Point { x: i32, y: i32 }
+    enum Shape { Circle { r: i32 }, Dot }
+    fn norm(&Point { x, y }: &Point, (a, ref b): (i32, u8)) -> i32 {
+        x * x + y * y
+    }
+    fn area(s: &Shape) -> i32 {
+        match s {
+            &Shape::Circle { r: 0..=9 } => 1,
+            &Shape::Circle { .. } => 2,
+            &Shape::Dot => 0,
+        }
+    }
+    fn main() {
+        let p = Point { x: 1, y: 2 };
+        let Point { ref x, .. } = p;
+        let c = match 'q' {
+            'a'..='z' => 1,
+            _ => 0,
+        };
+    }
+            "#,
+            r#"
+    fn main() {
+        let a = match 5 {
+            0..=9 => 1,
+            5 => 2,
+            _ => 3,
+        };
+    }
+            "#,
+            r#"
+    fn main() {
+        let a = match true {
+            0..=1 => 1,
+            _ => 0,
+        };
+    }
+            "#,
+            r#"
+    fn main() {
+        let a = match 5 {
+            9..=0 => 1,
+            _ => 3,
+        };
+    }
+            "#,
+            r#"
+    struct Point { x: i32, y: i32 }
+    fn main() {
+        let Point { x } = Point { x: 1, y: 2 };
+    }
+            "#,
+            r#"
+    struct Point { x: i32, y: i32 }
+    fn main() {
+        let Point { x, z, .. } = Point { x: 1, y: 2 };
+    }
+            "#,
+            r#"
+    struct Point { x: i32, y: i32 }
+    fn main() {
+        let Point { x, x: a, .. } = Point { x: 1, y: 2 };
+    }
+            "#,
+            r#"
+    fn main() {
+        let &a = 1;
+    }
+            "#,
+            r#"
+    fn foo(&mut a: &i32) {}
+            "#,
+            r#"
+    struct Point { x: i32, y: i32 }
+    fn foo(Point { x: 0, .. }: Point) {}
+            "#,
+            r#"
+    fn main() {
+        let Foo { x } = 1;
+    }
+            "#,
+            r#"
+    fn main() {
+        let a: u8 = 1;
+        let b = match a {
+            0..=127 => 1,
+            128..=255 => 2,
+        };
+        let c: i8 = -1;
+        let d = match c {
+            -128..=-1 => 1,
+            0 => 2,
+            1..=100 => 3,
+            101..=127 => 4,
+        };
+    }
+            "#,
+            r#"
+    fn main() {
+        let e = match 'q' {
+            'a'..='z' => 1,
+            'A'..='Z' => 2,
+        };
+    }
+            "#,
+            r#"
+    fn main() {
+        let a: u8 = 1;
+        let b = match a {
+            0..=127 => 1,
+            129..=255 => 2,
+        };
+    }
+            "#,
+            r#"
+    fn main() {
+        let c: i8 = -1;
+        let d = match c {
+            0..=127 => 1,
+            -100..=-1 => 2,
+        };
+    }
+            "#,
+            r#"
+    fn main() {
+        let a: u8 = 1;
+        let b = match a {
+            0..=9 => 1,
+            10..=255 => 2,
+            5..=20 => 3,
+        };
+    }
+            "#,
+            r#"
+    fn main() {
+        let a: usize = 1;
+        let b = match a {
+            0..=9 => 1,
+            10..=18446744073709551615 => 2,
+        };
+    }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("unreachable pattern".into()),
+            Err("only `char` and numeric types are allowed in range patterns".into()),
+            Err("lower range bound must be less than or equal to upper".into()),
+            Err("pattern does not mention field `y`".into()),
+            Err("struct `Point` does not have a field named `z`".into()),
+            Err("field `x` bound multiple times in the pattern".into()),
+            Err("mismatched types in pattern: expected `{integer}`, found `&_`".into()),
+            Err("mismatched types in pattern: expected `&i32`, found `&mut _`".into()),
+            Err("refutable pattern in function argument: `Point { x: i32::MIN..=-1, y: _ }` and `Point { x: 1..=i32::MAX, y: _ }` not covered".into()),
+            Err("cannot find struct or struct variant `Foo` in this scope".into()),
+            Ok(()),
+            Err("non-exhaustive patterns: `'\\0'..='@'` not covered".into()),
+            Err("non-exhaustive patterns: `128` not covered".into()),
+            Err("non-exhaustive patterns: `i8::MIN..=-101` not covered".into()),
+            Err("unreachable pattern".into()),
+            Err("non-exhaustive patterns: `_` not covered".into()),
+        ],
+    );
+}
+
+#[test]
+fn impl_test() {
+    file_validate(
+        &[
+            r#"
+    struct Point { x: i32, y: i32 }
+    impl Point {
+        fn new(x: i32, y: i32) -> Self { Self { x, y } }
+        fn sum(&self) -> i32 { self.x + self.y }
+        fn scale(&mut self, k: i32) { self.x = self.x * k; }
+        fn add(self, other: Point) -> Point { Point::new(self.x + other.x, self.y) }
+    }
+    enum Shape { Dot, Square(i32) }
+    impl Shape {
+        fn square(side: i32) -> Self { Self::Square(side) }
+        fn is_dot(&self) -> bool { match self { &Shape::Dot => true, _ => false } }
+    }
+    fn main() {
+        let mut p = Point::new(1, 2);
+        p.scale(3);
+        let r = &mut p;
+        r.scale(2);
+        r.x = 1;
+        let s: i32 = p.sum() + Point::sum(&p) + p.add(Point::new(0, 0)).sum();
+        let b: bool = Shape::square(1).is_dot();
+    }
+            "#,
+            r#"
+    struct Point { x: i32 }
+    impl Point { fn get(&self) -> i32 { self.x } }
+    fn main() { let p = Point { x: 1 }; p.set(); }
+            "#,
+            r#"
+    struct Point { x: i32 }
+    impl Point { fn new() -> Point { Point { x: 0 } } }
+    fn main() { let p = Point::new(); p.new(); }
+            "#,
+            r#"
+    struct Point { x: i32 }
+    fn main() { let p = Point::new(); }
+            "#,
+            r#"
+    struct Point { x: i32 }
+    impl Point { fn set(&mut self) { self.x = 0; } }
+    fn main() { let p = Point { x: 1 }; p.set(); }
+            "#,
+            r#"
+    struct Point { x: i32 }
+    impl Point { fn set(&mut self) { self.x = 0; } }
+    fn main() { let mut p = Point { x: 1 }; let r = &p; r.set(); }
+            "#,
+            r#"
+    struct Point { x: i32 }
+    impl Point { fn set(&self) { self.x = 0; } }
+            "#,
+            r#"
+    struct Point { x: i32 }
+    impl Point { fn get(&self) -> i32 { self.x } }
+    fn main() { let p = Point { x: 1 }; let a: bool = p.get(); }
+            "#,
+            r#"
+    impl i32 {}
+            "#,
+            r#"
+    impl Foo {}
+            "#,
+            r#"
+    struct Point { x: i32 }
+    impl Point { fn a() {} fn a() {} }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("no method named `set` found for `Point`".into()),
+            Err("`Point::new` is an associated function, not a method".into()),
+            Err("no function or associated item named `new` found for `Point`".into()),
+            Err("cannot borrow immutable value as mutable".into()),
+            Err("cannot borrow data in a `&` reference as mutable".into()),
+            Err("lhs is not mutable".into()),
+            Err("invalid type in let stmt: expected `Bool`, found `LitNum(i32)`".into()),
+            Err("cannot define inherent `impl` for primitive types".into()),
+            Err("cannot find type `Foo` in this scope".into()),
+            Err("duplicate definitions with name `a`".into()),
+        ],
+    );
+}
+
+#[test]
+fn trait_test() {
+    file_validate(
+        &[
+            r#"
+    trait Shape {
+        fn new(side: i32) -> Self;
+        fn area(&self) -> i32;
+        fn double(&self) -> i32 { self.area() * 2 }
+    }
+    struct Square { side: i32 }
+    impl Shape for Square {
+        fn new(side: i32) -> Square { Square { side } }
+        fn area(&self) -> i32 { self.side * self.side }
+    }
+    trait Zero { fn zero() -> Self; }
+    impl Zero for i32 { fn zero() -> i32 { 0 } }
+    fn main() {
+        let s = Square::new(2);
+        let a: i32 = s.area() + s.double() + Square::new(1).area() + i32::zero();
+    }
+            "#,
+            r#"
+    trait Shape { fn area(&self) -> i32; }
+    struct Square { side: i32 }
+    impl Shape for Square {}
+            "#,
+            r#"
+    trait Shape { fn area(&self) -> i32; }
+    struct Square { side: i32 }
+    impl Shape for Square { fn area(&self) -> bool { true } }
+            "#,
+            r#"
+    trait Shape { fn area(&self) -> i32; }
+    struct Square { side: i32 }
+    impl Shape for Square { fn area(&self) -> i32 { 0 } fn side(&self) -> i32 { 0 } }
+            "#,
+            r#"
+    struct Square { side: i32 }
+    impl Shape for Square {}
+            "#,
+            r#"
+    trait Zero { fn zero() -> Self; }
+    impl Zero for i32 { fn zero() -> i32 { 0 } }
+    impl Zero for i32 { fn zero() -> i32 { 0 } }
+            "#,
+            r#"
+    trait Shape { fn area(&self) -> i32; fn area(&self) -> i32; }
+            "#,
+            r#"
+    trait Shape { fn area(&self) -> i32; }
+    fn main() { let a = 1; a.area(); }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("not all trait items implemented, missing: `area`".into()),
+            Err("method `area` has an incompatible type for trait `Shape`".into()),
+            Err("method `side` is not a member of trait `Shape`".into()),
+            Err("cannot find trait `Shape` in this scope".into()),
+            Err("conflicting implementations of trait `Zero` for type `i32`".into()),
+            Err("duplicate definitions with name `area`".into()),
+            Err("no method named `area` found for type `LitNum(#i)`".into()),
+        ],
+    );
+}
+
+#[test]
+fn generic_test() {
+    file_validate(
+        &[
+            r#"
+    trait Num { fn zero() -> Self; fn add(self, other: Self) -> Self; }
+    impl Num for i8 { fn zero() -> i8 { 0 } fn add(self, other: i8) -> i8 { self + other } }
+    struct Pair<T> { a: T, b: T }
+    fn sum<T: Num>(p: Pair<T>) -> T { T::zero().add(p.a).add(p.b) }
+    fn first<T>(a: T, b: T) -> T { a }
+    fn main() {
+        let p: Pair<i8> = Pair { a: 1, b: 2 };
+        let s: i8 = sum(p);
+        let a: i32 = first(1, 2);
+        let b: u8 = first::<u8>(1, 2);
+        let c: (i32, bool) = first((1, true), (2, false));
+    }
+            "#,
+            r#"
+    fn first<T>(a: i32) -> i32 { a }
+    fn main() { first(1); }
+            "#,
+            r#"
+    trait Num { fn zero() -> Self; }
+    fn zero<T: Num>(a: T) -> T { T::zero() }
+    fn main() { zero(true); }
+            "#,
+            r#"
+    fn first<T>(a: T, b: T) -> T { a }
+    fn main() { first(1, true); }
+            "#,
+            r#"
+    fn first<T>(a: T) -> T { a }
+    fn main() { first::<i32, i32>(1); }
+            "#,
+            r#"
+    fn first<T>(a: T) -> T { a.get() }
+            "#,
+            r#"
+    fn first<T: Num>(a: T) -> T { a }
+            "#,
+            r#"
+    struct Pair<T> { a: T }
+    impl Pair { fn get(&self) -> i32 { 0 } }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("cannot infer type for type parameter `T`".into()),
+            Err("the trait bound `bool: Num` is not satisfied".into()),
+            Err("invalid type for call expr: expected LitNum(i32), found Bool".into()),
+            Err("function takes 1 generic arguments but 2 generic arguments were supplied".into()),
+            Err("no method named `get` found for type parameter `T`".into()),
+            Err("cannot find trait `Num` in this scope".into()),
+            Err("impl blocks of generic struct `Pair` are not supported".into()),
+        ],
+    );
+}
+
+#[test]
+fn trait_object_test() {
+    file_validate(
+        &[
+            r#"
+    trait Shape { fn area(&self) -> i32; fn scale(&mut self, k: i32); }
+    struct Square { side: i32 }
+    impl Shape for Square {
+        fn area(&self) -> i32 { self.side * self.side }
+        fn scale(&mut self, k: i32) { self.side = self.side * k; }
+    }
+    struct Holder { s: &dyn Shape }
+    fn area(s: &dyn Shape) -> i32 { s.area() }
+    fn erase<T: Shape>(t: &T) -> &dyn Shape { t }
+    fn main() {
+        let mut sq = Square { side: 2 };
+        let m: &mut dyn Shape = &mut sq;
+        m.scale(2);
+        let a: i32 = area(&sq) + erase(&sq).area();
+        let h = Holder { s: &sq };
+        let b: i32 = h.s.area();
+    }
+            "#,
+            r#"
+    trait Zero { fn zero() -> Self; }
+    impl Zero for i32 { fn zero() -> i32 { 0 } }
+    fn main() { let a = 1; let z: &dyn Zero = &a; }
+            "#,
+            r#"
+    trait Shape { fn area(&self) -> i32; }
+    fn main() { let a = true; let s: &dyn Shape = &a; }
+            "#,
+            r#"
+    trait Shape { fn area(&self) -> i32; }
+    impl Shape for i32 { fn area(&self) -> i32 { 0 } }
+    fn area<T: Shape>(t: &T) -> i32 { t.area() }
+    fn main() { let a = 1; let s: &dyn Shape = &a; area(s); }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("the trait `Zero` cannot be made into an object".into()),
+            Err("the trait bound `bool: Shape` is not satisfied".into()),
+            Err("the size for values of type `dyn Shape` cannot be known at compilation time".into()),
+        ],
+    );
+}
+
+#[test]
+fn fn_ptr_test() {
+    file_validate(
+        &[
+            r#"
+    fn add(a: i32, b: i32) -> i32 { a + b }
+    fn mul(a: i32, b: i32) -> i32 { a * b }
+    fn apply(f: fn(i32, i32) -> i32, a: i32) -> i32 { f(a, a) }
+    fn choose(b: bool) -> fn(i32, i32) -> i32 { if b { add } else { mul } }
+    struct Op { f: fn(i32, i32) -> i32 }
+    fn main() {
+        let mut f: fn(i32, i32) -> i32 = add;
+        f = mul;
+        let a: i32 = f(1, 2) + apply(add, 3) + choose(true)(1, 1);
+        let op = Op { f: mul };
+        let b: i32 = (op.f)(2, 3);
+    }
+            "#,
+            r#"
+    fn add(a: i32, b: i32) -> i32 { a + b }
+    fn main() { let f: fn(i32) -> i32 = add; }
+            "#,
+            r#"
+    fn main() { let a = 1; a(2); }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("invalid type in let stmt: expected `FnPtr(TypeFnPtr { params: [i32], ret_type: i32 })`, found `Fn { vis: Priv, inner: TypeFnPtr { params: [i32, i32], ret_type: i32 }, generics: [] }`".into()),
+            Err("expr is not callable".into()),
+        ],
+    );
+}
+
+#[test]
+fn closure_test() {
+    file_validate(
+        &[
+            r#"
+    fn apply(f: fn(i32) -> i32, a: i32) -> i32 { f(a) }
+    fn main() {
+        let mut count = 0;
+        let y = 2;
+        let mut inc = |d| { count += d; count + y };
+        let a: i32 = inc(1) + apply(|x| x * 2, 3);
+        let add = move |x: i32| -> i32 { x + y };
+        let b: i32 = add(a);
+    }
+            "#,
+            r#"
+    fn apply(f: fn(i32) -> i32, a: i32) -> i32 { f(a) }
+    fn main() { let y = 2; let a = apply(|x| x + y, 1); }
+            "#,
+            r#"
+    fn main() { let f = |x| x; }
+            "#,
+            r#"
+    fn main() { let f = |x: i32| x; let a = f(true); }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("closures can only be coerced to `fn` types if they do not capture any variables".into()),
+            Err("type annotations needed for the parameters of the closure".into()),
+            Err("invalid type for call expr: expected LitNum(i32), found Bool".into()),
+        ],
+    );
+}
+
+#[test]
+fn for_test() {
+    file_validate(
+        &[
+            r#"
+    fn main() {
+        let n: u8 = 3;
+        let mut a: u8 = 0;
+        for i in 0..n {
+            a += i;
+        }
+        let r = 1..=5;
+        for i in r {
+            let b: i32 = i;
+        }
+        for _ in (0..=10).rev() {
+            break;
+        }
+    }
+            "#,
+            r#"
+    fn main() { for i in ..3 {} }
+            "#,
+            r#"
+    fn main() { for i in (0..).rev() {} }
+            "#,
+            r#"
+    fn main() { let a = true; for i in 0..a {} }
+            "#,
+            r#"
+    fn main() { for (a, b) in 0..3 {} }
+            "#,
+            r#"
+    fn main() { for i in 0..3 { break i; } }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("`RangeTo<i32>` is not an iterator".into()),
+            Err("no method named `rev` found for `RangeFrom<i32>`".into()),
+            Err("mismatched types in range: `LitNum(#i)` and `Bool`".into()),
+            Err("mismatched types: expected a tuple with 2 elements, found `LitNum(i32)`".into()),
+            Err("only loop can return values".into()),
+        ],
+    );
+}
+
+#[test]
+fn label_test() {
+    file_validate(
+        &[
+            r#"
+    fn main() {
+        let a = 'outer: loop {
+            while true {
+                for i in 0..3 {
+                    continue 'outer;
+                }
+                break 'outer 3;
+            }
+        };
+        'a: loop {
+            let f = || loop {
+                break 'a;
+            };
+        }
+    }
+            "#,
+            r#"
+    fn main() { 'a: loop { break 'b; } }
+            "#,
+            r#"
+    fn main() { continue; }
+            "#,
+            r#"
+    fn main() { 'a: while true { loop { break 'a 1; } } }
+            "#,
+            r#"
+    fn main() { 'a: loop { let f = || { continue 'a; }; } }
+            "#,
+            r#"
+    fn main() { let a = 'a: loop { loop { break 'a 1; } break 'a true; }; }
+            "#,
+        ],
+        &[
+            Err("use of undeclared label `'a`".into()),
+            Err("use of undeclared label `'b`".into()),
+            Err("continue expr can not be out of loop block".into()),
+            Err("only loop can return values".into()),
+            Err("use of undeclared label `'a`".into()),
+            Err("invalid type for break expr: expected `LitNum(#i)`, found Bool".into()),
+        ],
+    );
+}
+
+#[test]
+fn ptr_test() {
+    file_validate(
+        &[
+            r#"
+    fn main() {
+        let mut a = 3;
+        let b = &mut a;
+        *b = 4;
+        *b += 1;
+        let c: *const i32 = b;
+        let d: *mut i32 = &mut a;
+        let e = unsafe { *c + *d };
+        unsafe {
+            *d = e;
+        }
+    }
+            "#,
+            r#"
+    fn main() { let a = 3; let b = &a; *b = 4; }
+            "#,
+            r#"
+    fn main() { let mut a = 3; let b: *mut i32 = &mut a; let c = *b; }
+            "#,
+            r#"
+    fn main() { let a = 3; let b: *mut i32 = &a; }
+            "#,
+            r#"
+    fn main() { let a = 3; let b = *a; }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("lhs is not mutable".into()),
+            Err("dereference of raw pointer is unsafe and requires unsafe block".into()),
+            Err("invalid type in let stmt: expected `Ptr { kind: MutRawPtr, type_info: LitNum(i32) }`, found `Ptr { kind: Ref, type_info: LitNum(#i) }`".into()),
+            Err("type `i32` can not be dereferenced".into()),
+        ],
+    );
+}
+
+#[test]
+fn cast_test() {
+    file_validate(
+        &[
+            r#"
+    fn main() {
+        let a = 300;
+        let b: u8 = a as u8;
+        let c: i64 = b as i64 + true as i64 + 'a' as i64;
+        let d: char = 65u8 as char;
+        let e: f64 = a as f64;
+        let f: i8 = e as i8;
+        let p = &a as *const i32;
+        let q: usize = p as usize;
+        let r = q as *mut u8 as *const i8;
+    }
+            "#,
+            r#"
+    fn main() { let a = 3 as bool; }
+            "#,
+            r#"
+    fn main() { let a = 66i32 as char; }
+            "#,
+            r#"
+    fn main() { let a = 3; let b = &a as &u8; }
+            "#,
+        ],
+        &[
+            Ok(()),
+            Err("non-primitive cast: `i32` as `bool`".into()),
+            Err("non-primitive cast: `i32` as `char`".into()),
+            Err("non-primitive cast: `&i32` as `&u8`".into()),
+        ],
+    );
+}
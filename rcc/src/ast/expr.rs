@@ -1,11 +1,13 @@
 use crate::analyser::scope::Scope;
 use crate::analyser::sym_resolver::TypeInfo;
 use crate::ast::expr::Expr::Path;
+use crate::ast::item::ItemFn;
+use crate::ast::pattern::Pattern;
 use crate::ast::stmt::Stmt;
-use crate::ast::types::TypeLitNum;
-use crate::ast::{FromToken, TokenStart};
+use crate::ast::types::{TypeAnnotation, TypeLitNum};
+use crate::ast::{FromToken, NodeSpan, TokenStart};
 use crate::from_token;
-use crate::lexer::token::Token;
+use crate::lexer::token::{Span, Token};
 use crate::rcc::RccError;
 use std::borrow::BorrowMut;
 use std::cell::RefCell;
@@ -24,7 +26,7 @@ pub trait ExprVisit {
     fn is_callable(&self) -> bool {
         let type_info = self.type_info();
         let t = type_info.deref().borrow();
-        matches!(t.deref(), &TypeInfo::Fn {..} | &TypeInfo::FnPtr(_))
+        matches!(t.deref(), &TypeInfo::Fn {..} | &TypeInfo::FnPtr(_) | &TypeInfo::Closure(_))
     }
 }
 
@@ -53,35 +55,63 @@ pub enum Expr {
     Assign(AssignExpr),
     Range(RangeExpr),
     BinOp(BinOpExpr),
+    Cast(CastExpr),
     Grouped(GroupedExpr),
     Array(ArrayExpr),
     ArrayIndex(ArrayIndexExpr),
     Tuple(TupleExpr),
     TupleIndex(TupleIndexExpr),
     Struct(StructExpr),
-    EnumVariant,
     Call(CallExpr),
-    MethodCall,
+    MethodCall(MethodCallExpr),
     FieldAccess(FieldAccessExpr),
     While(WhileExpr),
     Loop(LoopExpr),
-    For,
+    For(ForExpr),
     If(IfExpr),
-    Match,
+    Match(MatchExpr),
     Return(ReturnExpr),
     Break(BreakExpr),
+    Continue(ContinueExpr),
+    Closure(ClosureExpr),
 }
 
 impl Expr {
     pub fn with_block(&self) -> bool {
         matches!(self,
-            Self::Block(_) | Self::Struct(_) | Self::While(_) |
-            Self::Loop(_)  | Self::If(_) | Self::Match | Self::For)
+            Self::Block(_) | Self::While(_) |
+            Self::Loop(_)  | Self::If(_) | Self::Match(_) | Self::For(_))
     }
+    /// Location of the expression, `None` if the expression does not record its span.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::LitNum(e) => Some(e.span.0),
+            Self::Path(e) => Some(e.span.0),
+            Self::Unary(e) => Some(e.span.0),
+            Self::Assign(e) => Some(e.span.0),
+            Self::BinOp(e) => Some(e.span.0),
+            Self::Cast(e) => Some(e.span.0),
+            Self::Call(e) => Some(e.span.0),
+            Self::MethodCall(e) => Some(e.span.0),
+            Self::Array(e) => Some(e.span.0),
+            Self::ArrayIndex(e) => Some(e.span.0),
+            Self::Tuple(e) => Some(e.span.0),
+            Self::TupleIndex(e) => Some(e.span.0),
+            Self::Struct(e) => Some(e.span.0),
+            Self::FieldAccess(e) => Some(e.span.0),
+            Self::Match(e) => Some(e.span.0),
+            Self::Closure(e) => Some(e.span.0),
+            Self::Grouped(e) => e.span(),
+            _ => None,
+        }
+    }
+
     pub fn is_with_block_token_start(tk: &Token) -> bool {
         matches!(
             tk,
             Token::LeftCurlyBraces
+                | Token::Unsafe
+                | Token::Lifetime(_)
                 | Token::While
                 | Token::Loop
                 | Token::For
@@ -108,22 +138,27 @@ impl ExprVisit for Expr {
             Self::Unary(e) => e.type_info(),
             Self::Block(e) => e.type_info(),
             Self::Assign(e) => e.type_info(),
-            // Self::Range(e) => e.ret_type(),
+            Self::Range(e) => e.type_info(),
             Self::BinOp(e) => e.type_info(),
+            Self::Cast(e) => e.type_info(),
             Self::Grouped(e) => e.type_info(),
-            // Self::Array(e) => e.ret_type(),
-            // Self::ArrayIndex(e) => e.ret_type(),
-            // Self::Tuple(e) => e.ret_type(),
-            // Self::TupleIndex(e) => e.ret_type(),
-            // Self::Struct(e) => e.ret_type(),
+            Self::Array(e) => e.type_info(),
+            Self::ArrayIndex(e) => e.type_info(),
+            Self::Tuple(e) => e.type_info(),
+            Self::TupleIndex(e) => e.type_info(),
+            Self::Struct(e) => e.type_info(),
             Self::Call(e) => e.type_info(),
-            // Self::FieldAccess(e) => e.ret_type(),
+            Self::MethodCall(e) => e.type_info(),
+            Self::FieldAccess(e) => e.type_info(),
             Self::While(e) => e.type_info(),
             Self::Loop(e) => e.type_info(),
+            Self::For(e) => e.type_info(),
             Self::If(e) => e.type_info(),
+            Self::Match(e) => e.type_info(),
             Self::Return(e) => e.type_info(),
             Self::Break(e) => e.type_info(),
-            _ => unimplemented!("{:?}", self),
+            Self::Continue(e) => e.type_info(),
+            Self::Closure(e) => e.type_info(),
         }
     }
 
@@ -136,15 +171,27 @@ impl ExprVisit for Expr {
             Self::Unary(u) => u.kind(),
             Self::Block(b) => b.kind(),
             Self::Assign(a) => a.kind(),
+            Self::Range(r) => r.kind(),
             Self::BinOp(b) => b.kind(),
+            Self::Cast(c) => c.kind(),
             Self::Grouped(e) => e.kind(),
             Self::Call(c) => c.kind(),
+            Self::MethodCall(m) => m.kind(),
+            Self::Array(a) => a.kind(),
+            Self::ArrayIndex(a) => a.kind(),
+            Self::Tuple(t) => t.kind(),
+            Self::TupleIndex(t) => t.kind(),
+            Self::Struct(s) => s.kind(),
+            Self::FieldAccess(f) => f.kind(),
             Self::While(w) => w.kind(),
             Self::Loop(l) => l.kind(),
+            Self::For(f) => f.kind(),
             Self::If(i) => i.kind(),
+            Self::Match(m) => m.kind(),
             Self::Return(r) => r.kind(),
             Self::Break(b) => b.kind(),
-            _ => unimplemented!("{:?}", self),
+            Self::Continue(c) => c.kind(),
+            Self::Closure(c) => c.kind(),
         }
     }
 }
@@ -159,17 +206,29 @@ impl TypeInfoSetter for Expr {
                 l.set_type_info(type_info);
             }
             Self::Unary(u) => u.set_type_info(type_info),
+            Self::Range(r) => r.set_type_info(type_info),
             Self::BinOp(b) => b.set_type_info(type_info),
+            Self::Array(a) => a.set_type_info(type_info),
+            Self::ArrayIndex(a) => a.set_type_info(type_info),
+            Self::Tuple(t) => t.set_type_info(type_info),
+            Self::TupleIndex(t) => t.set_type_info(type_info),
+            Self::Struct(s) => s.set_type_info(type_info),
+            Self::FieldAccess(f) => f.set_type_info(type_info),
+            Self::Match(m) => m.set_type_info(type_info),
             e => unimplemented!("set type_info on {:?}", e),
         }
     }
     fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
         match self {
-            Self::Path(p) => {}
+            Self::Path(_) => {}
             Self::LitNum(l) => {
                 l.set_type_info_ref(type_info);
             }
             Self::Unary(u) => u.set_type_info_ref(type_info),
+            Self::ArrayIndex(a) => a.set_type_info_ref(type_info),
+            Self::TupleIndex(t) => t.set_type_info_ref(type_info),
+            Self::FieldAccess(f) => f.set_type_info_ref(type_info),
+            Self::Match(m) => m.set_type_info_ref(type_info),
             e => unimplemented!("set type_info on {:?}", e),
         }
     }
@@ -181,7 +240,7 @@ pub enum LhsExpr {
     ArrayIndex(ArrayIndexExpr),
     TupleIndex(TupleIndexExpr),
     FieldAccess(FieldAccessExpr),
-    Deref(Box<Expr>),
+    Deref(UnAryExpr),
 }
 
 impl LhsExpr {
@@ -190,7 +249,7 @@ impl LhsExpr {
             Expr::Path(p) => Ok(LhsExpr::Path(p)),
             Expr::Unary(u) => {
                 if u.op == UnOp::Deref {
-                    Ok(LhsExpr::Deref(u.expr))
+                    Ok(LhsExpr::Deref(u))
                 } else {
                     Err("invalid lhs expr".into())
                 }
@@ -203,22 +262,13 @@ impl LhsExpr {
         }
     }
 
-    pub fn set_type_info(&mut self, type_info: TypeInfo) {
-        match self {
-            Self::Path(p) => {
-                p.type_info.replace(type_info);
-            }
-            Self::ArrayIndex(a) => unimplemented!("set array index type info"),
-            Self::TupleIndex(t) => unimplemented!("set tuple index type info"),
-            Self::FieldAccess(f) => unimplemented!("set tuple field type info"),
-            Self::Deref(e) => unimplemented!("set tuple deref type info"),
-        }
-    }
-
     pub fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
         match self {
             LhsExpr::Path(p) => p.set_type_info_ref(type_info),
-            _ => todo!(),
+            LhsExpr::ArrayIndex(a) => a.set_type_info_ref(type_info),
+            LhsExpr::TupleIndex(t) => t.set_type_info_ref(type_info),
+            LhsExpr::FieldAccess(f) => f.set_type_info_ref(type_info),
+            LhsExpr::Deref(e) => e.set_type_info_ref(type_info),
         }
     }
 }
@@ -227,14 +277,20 @@ impl ExprVisit for LhsExpr {
     fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
         match self {
             LhsExpr::Path(expr) => expr.type_info(),
-            _ => todo!(),
+            LhsExpr::ArrayIndex(expr) => expr.type_info(),
+            LhsExpr::TupleIndex(expr) => expr.type_info(),
+            LhsExpr::FieldAccess(expr) => expr.type_info(),
+            LhsExpr::Deref(expr) => expr.type_info(),
         }
     }
 
     fn kind(&self) -> ExprKind {
         match self {
             LhsExpr::Path(expr) => expr.kind(),
-            _ => todo!(),
+            LhsExpr::ArrayIndex(expr) => expr.kind(),
+            LhsExpr::TupleIndex(expr) => expr.kind(),
+            LhsExpr::FieldAccess(expr) => expr.kind(),
+            LhsExpr::Deref(expr) => expr.kind(),
         }
     }
 }
@@ -259,16 +315,27 @@ impl<V> ConstantExpr<V> {
             const_value: None,
         }
     }
+
+    pub fn value(&self) -> Option<&V> {
+        self.const_value.as_ref()
+    }
+
+    pub fn set_value(&mut self, value: V) {
+        self.const_value = Some(value);
+    }
 }
 
 impl TokenStart for Expr {
     fn is_token_start(tk: &Token) -> bool {
         matches!(tk,
             Token::Identifier(_) | Token::Literal {..} |Token::LitString(_) | Token::True | Token::False |
+            Token::SelfValue | Token::SelfType |
             Token::DotDot |
             Token::LeftCurlyBraces | Token::LeftParen | Token::LeftSquareBrackets |
             Token::For | Token::Loop | Token::While |
-            Token::If | Token::Match | Token::Break | Token::Return
+            Token::Lifetime(_) | Token::Unsafe |
+            Token::If | Token::Match | Token::Break | Token::Continue | Token::Return |
+            Token::Or | Token::OrOr | Token::Move
         ) || UnAryExpr::is_token_start(tk)
             || RangeExpr::is_token_start(tk)
     }
@@ -278,6 +345,8 @@ pub struct BlockExpr {
     pub stmts: Vec<Stmt>,
     pub last_expr: Option<Box<Expr>>,
     pub scope: Scope,
+    /// `unsafe { ... }`
+    pub is_unsafe: bool,
     type_info: Rc<RefCell<TypeInfo>>,
 }
 
@@ -287,10 +356,12 @@ impl BlockExpr {
             stmts: vec![],
             last_expr: None,
             scope: Scope::new(scope_id),
+            is_unsafe: false,
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
         }
     }
 
+    #[cfg(test)]
     pub fn expr_without_block(mut self, expr: Expr) -> Self {
         debug_assert!(!expr.with_block());
         self.last_expr = Some(Box::new(expr));
@@ -338,6 +409,9 @@ impl TypeInfoSetter for BlockExpr {
 
 impl Debug for BlockExpr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_unsafe {
+            write!(f, "unsafe ")?;
+        }
         match &self.last_expr {
             Some(expr) => write!(f, "{{ {:?} {:?} }}", self.stmts, expr),
             None => write!(f, "{{ {:?} }}", self.stmts),
@@ -347,7 +421,9 @@ impl Debug for BlockExpr {
 
 impl PartialEq for BlockExpr {
     fn eq(&self, other: &Self) -> bool {
-        self.stmts.eq(&other.stmts) && self.last_expr.eq(&other.last_expr)
+        self.stmts.eq(&other.stmts)
+            && self.last_expr.eq(&other.last_expr)
+            && self.is_unsafe == other.is_unsafe
     }
 }
 
@@ -357,6 +433,7 @@ impl From<Vec<Stmt>> for BlockExpr {
             stmts,
             last_expr: None,
             scope: Scope::new(0),
+            is_unsafe: false,
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
         }
     }
@@ -366,13 +443,16 @@ impl From<Vec<Stmt>> for BlockExpr {
 pub struct LitNumExpr {
     pub value: String,
     type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
 }
 
 impl LitNumExpr {
+    #[cfg(test)]
     pub fn new(value: String, ret_type: TypeLitNum) -> LitNumExpr {
         LitNumExpr {
             value,
             type_info: Rc::new(RefCell::new(TypeInfo::LitNum(ret_type))),
+            span: NodeSpan::default(),
         }
     }
 
@@ -380,6 +460,7 @@ impl LitNumExpr {
         LitNumExpr {
             type_info: Rc::new(RefCell::new(TypeInfo::LitNum(TypeLitNum::I))),
             value,
+            span: NodeSpan::default(),
         }
     }
 
@@ -390,7 +471,7 @@ impl LitNumExpr {
 
     pub fn get_lit_type(&mut self) -> TypeLitNum {
         if let TypeInfo::LitNum(t) = self.type_info.borrow().deref() {
-            return t.clone();
+            return *t;
         }
         panic!("TypeInfo must be lit num")
     }
@@ -426,6 +507,7 @@ impl From<i32> for LitNumExpr {
         LitNumExpr {
             type_info: Rc::new(RefCell::new(TypeInfo::LitNum(TypeLitNum::I))),
             value: num.to_string(),
+            span: NodeSpan::default(),
         }
     }
 }
@@ -433,16 +515,24 @@ impl From<i32> for LitNumExpr {
 #[derive(PartialEq, Debug)]
 pub struct PathExpr {
     pub segments: Vec<String>,
+    /// `i32` in `max::<i32>`
+    pub generic_args: Vec<TypeAnnotation>,
     type_info: Rc<RefCell<TypeInfo>>,
+    /// type arguments of a generic function, inferred by the symbol resolver
+    pub type_args: Vec<TypeInfo>,
     pub expr_kind: ExprKind,
+    pub span: NodeSpan,
 }
 
 impl PathExpr {
     pub fn new() -> Self {
         PathExpr {
             segments: vec![],
+            generic_args: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            type_args: vec![],
             expr_kind: ExprKind::Unknown,
+            span: NodeSpan::default(),
         }
     }
 }
@@ -471,8 +561,11 @@ impl From<Vec<String>> for PathExpr {
     fn from(segments: Vec<String>) -> Self {
         PathExpr {
             segments,
+            generic_args: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            type_args: vec![],
             expr_kind: ExprKind::Unknown,
+            span: NodeSpan::default(),
         }
     }
 }
@@ -481,8 +574,11 @@ impl From<Vec<&str>> for PathExpr {
     fn from(segments: Vec<&str>) -> Self {
         PathExpr {
             segments: segments.iter().map(|s| s.to_string()).collect(),
+            generic_args: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            type_args: vec![],
             expr_kind: ExprKind::Unknown,
+            span: NodeSpan::default(),
         }
     }
 }
@@ -491,8 +587,11 @@ impl From<&str> for PathExpr {
     fn from(s: &str) -> Self {
         PathExpr {
             segments: s.split("::").map(|s| s.to_string()).collect(),
+            generic_args: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            type_args: vec![],
             expr_kind: ExprKind::Unknown,
+            span: NodeSpan::default(),
         }
     }
 }
@@ -503,6 +602,7 @@ pub struct UnAryExpr {
     pub expr: Box<Expr>,
     type_info: Rc<RefCell<TypeInfo>>,
     pub expr_kind: ExprKind,
+    pub span: NodeSpan,
 }
 
 impl UnAryExpr {
@@ -512,6 +612,7 @@ impl UnAryExpr {
             expr: Box::new(expr),
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
             expr_kind: ExprKind::Unknown,
+            span: NodeSpan::default(),
         }
     }
 }
@@ -536,7 +637,17 @@ impl TokenStart for UnAryExpr {
 }
 
 impl TypeInfoSetter for UnAryExpr {
+    /// The operand of `&a` is also determined, e.g. `a` is `[i64; 2]` if `&a` is `&[i64; 2]`.
     fn set_type_info(&mut self, type_info: TypeInfo) {
+        if let (UnOp::Borrow | UnOp::BorrowMut, TypeInfo::Ptr { type_info: t, .. }) =
+            (&self.op, &type_info)
+        {
+            let operand_info = self.expr.type_info();
+            let may_be_operand_type = operand_info.borrow().may_be_determined_as(t);
+            if may_be_operand_type && operand_info.borrow().deref() != t.deref() {
+                self.expr.set_type_info(*t.clone());
+            }
+        }
         self.type_info.replace(type_info);
     }
 
@@ -557,6 +668,14 @@ pub enum UnOp {
     Borrow,
     /// `& mut`
     BorrowMut,
+    /// Implicit coercion from `&T` to `&dyn Trait`, inserted by the symbol resolver
+    Unsize,
+    /// Implicit coercion from a closure capturing nothing to a function pointer,
+    /// inserted by the symbol resolver
+    ClosureFnPtr,
+    /// Implicit coercion between pointers to the same type, e.g. from `&mut T` to `*const T`,
+    /// inserted by the symbol resolver
+    PtrCoercion,
 }
 
 impl Debug for UnOp {
@@ -570,6 +689,9 @@ impl Debug for UnOp {
                 Self::Neg => "-",
                 Self::Borrow => "&",
                 Self::BorrowMut => "& mut",
+                Self::Unsize => "unsize",
+                Self::ClosureFnPtr => "closure_fn_ptr",
+                Self::PtrCoercion => "ptr_coercion",
             }
         )
     }
@@ -592,6 +714,7 @@ pub struct AssignExpr {
     pub lhs: LhsExpr,
     pub assign_op: AssignOp,
     pub rhs: Box<Expr>,
+    pub span: NodeSpan,
 }
 
 impl AssignExpr {
@@ -600,6 +723,7 @@ impl AssignExpr {
             lhs,
             assign_op,
             rhs: Box::new(rhs),
+            span: NodeSpan::default(),
         }
     }
 }
@@ -665,6 +789,7 @@ pub struct RangeExpr {
     pub lhs: Option<Box<Expr>>,
     pub range_op: RangeOp,
     pub rhs: Option<Box<Expr>>,
+    type_info: Rc<RefCell<TypeInfo>>,
 }
 
 impl RangeExpr {
@@ -673,14 +798,17 @@ impl RangeExpr {
             lhs: None,
             range_op,
             rhs: None,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
         }
     }
 
+    #[cfg(test)]
     pub fn lhs(mut self, lhs: Expr) -> Self {
         self.set_lhs(lhs);
         self
     }
 
+    #[cfg(test)]
     pub fn rhs(mut self, rhs: Expr) -> Self {
         self.set_rhs(rhs);
         self
@@ -695,6 +823,37 @@ impl RangeExpr {
     }
 }
 
+impl ExprVisit for RangeExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for RangeExpr {
+    /// Types of the bounds are determined by the type of the range,
+    /// e.g. bounds of `0..3` are `u8` if the range is `Range<u8>`.
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        if let TypeInfo::Range { elem_type, .. } = &type_info {
+            for bound in self.lhs.iter_mut().chain(self.rhs.iter_mut()) {
+                let bound_info = bound.type_info();
+                let may_be_elem_type = bound_info.borrow().may_be_determined_as(elem_type);
+                if may_be_elem_type && bound_info.borrow().deref() != elem_type.deref() {
+                    bound.set_type_info(*elem_type.clone());
+                }
+            }
+        }
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
+
 impl TokenStart for RangeExpr {
     fn is_token_start(tk: &Token) -> bool {
         tk == &Token::DotDotEq || tk == &Token::DotDot
@@ -702,7 +861,7 @@ impl TokenStart for RangeExpr {
 }
 
 from_token! {
-    #[derive(StrEnum, Debug, PartialEq)]
+    #[derive(StrEnum, Debug, Clone, Copy, Eq, PartialEq, Hash)]
     pub enum RangeOp {
         /// Range operators
         #[strenum("..")]
@@ -720,6 +879,7 @@ pub struct BinOpExpr {
     pub bin_op: BinOperator,
     pub rhs: Box<Expr>,
     type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
 }
 
 impl BinOpExpr {
@@ -729,6 +889,7 @@ impl BinOpExpr {
             bin_op,
             rhs: Box::new(rhs),
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
         }
     }
 }
@@ -755,6 +916,46 @@ impl TypeInfoSetter for BinOpExpr {
     }
 }
 
+/// `expr as T`
+#[derive(Debug, PartialEq)]
+pub struct CastExpr {
+    pub expr: Box<Expr>,
+    pub type_anno: TypeAnnotation,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
+}
+
+impl CastExpr {
+    pub fn new(expr: Expr, type_anno: TypeAnnotation) -> Self {
+        CastExpr {
+            expr: Box::new(expr),
+            type_anno,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
+        }
+    }
+}
+
+impl ExprVisit for CastExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for CastExpr {
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
+
 from_token! {
     #[derive(StrEnum, PartialEq, Eq, Clone, Copy, Hash)]
     pub enum BinOperator {
@@ -796,9 +997,6 @@ from_token! {
         #[strenum("||")]
         OrOr,
 
-        /// Type cast operator
-        As,
-
         /// Comparison operators
         #[strenum("==")]
         EqEq,
@@ -823,7 +1021,7 @@ from_token! {
 impl BinOperator {
     pub fn prec_lt(&self, other: &BinOperator) -> Result<bool, RccError> {
         let l_prec = Precedence::from_bin_op(self);
-        let r_prec = Precedence::from_bin_op(&other);
+        let r_prec = Precedence::from_bin_op(other);
         if l_prec == r_prec && l_prec == Precedence::Cmp {
             Err("Chained comparison operator require parentheses".into())
         } else {
@@ -839,6 +1037,28 @@ impl BinOperator {
             Ok(&l_prec >= p)
         }
     }
+
+    /// `<`, `<=`, `>` and `>=`
+    pub fn is_ordering(&self) -> bool {
+        matches!(
+            self,
+            BinOperator::Lt | BinOperator::Le | BinOperator::Gt | BinOperator::Ge
+        )
+    }
+
+    /// `a op b` equals `b op a`
+    pub fn is_commutative(&self) -> bool {
+        matches!(
+            self,
+            BinOperator::Plus
+                | BinOperator::Star
+                | BinOperator::And
+                | BinOperator::Or
+                | BinOperator::Caret
+                | BinOperator::EqEq
+                | BinOperator::Ne
+        )
+    }
 }
 
 impl Debug for BinOperator {
@@ -864,13 +1084,11 @@ pub enum Precedence {
     Shift,
     Add,
     Multi,
-    As,
 }
 
 impl Precedence {
     pub fn from_bin_op(op: &BinOperator) -> Self {
         match op {
-            BinOperator::As => Self::As,
             BinOperator::Star | BinOperator::Slash | BinOperator::Percent => Self::Multi,
             BinOperator::Plus | BinOperator::Minus => Self::Add,
             BinOperator::Shl | BinOperator::Shr => Self::Shift,
@@ -892,30 +1110,75 @@ impl Precedence {
 /// GroupExpr -> `(` Expr `)`
 pub type GroupedExpr = Box<Expr>;
 
+/// `[a, b, c]`, or `[a; 3]` if `len_expr` is an expression
 #[derive(Debug, PartialEq)]
 pub struct ArrayExpr {
     pub elems: Vec<Expr>,
     pub len_expr: ConstantExpr<usize>,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
 }
 
 impl ArrayExpr {
     pub fn new(elems: Vec<Expr>, len_expr: ConstantExpr<usize>) -> Self {
-        ArrayExpr { elems, len_expr }
+        ArrayExpr {
+            elems,
+            len_expr,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
+        }
     }
 
     pub fn elems(elems: Vec<Expr>) -> ArrayExpr {
         let length = elems.len();
-        ArrayExpr {
-            elems,
-            len_expr: ConstantExpr::<usize>::const_value(length),
+        ArrayExpr::new(elems, ConstantExpr::<usize>::const_value(length))
+    }
+
+    /// `[a; 3]`
+    pub fn is_repeat(&self) -> bool {
+        self.len_expr.expr.is_some()
+    }
+}
+
+impl ExprVisit for ArrayExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for ArrayExpr {
+    /// Types of the elements are determined by the type of the array,
+    /// e.g. elements of `[1, 2]` are `u8` if the array is `[u8; 2]`.
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        if let TypeInfo::Array { elem_type, .. } = &type_info {
+            for elem in self.elems.iter_mut() {
+                let elem_info = elem.type_info();
+                let may_be_elem_type = elem_info.borrow().may_be_determined_as(elem_type);
+                if may_be_elem_type && elem_info.borrow().deref() != elem_type.deref() {
+                    elem.set_type_info(*elem_type.clone());
+                }
+            }
         }
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
     }
 }
 
+/// `a[b]`
 #[derive(Debug, PartialEq)]
 pub struct ArrayIndexExpr {
     pub expr: Box<Expr>,
     pub index_expr: Box<Expr>,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub expr_kind: ExprKind,
+    pub span: NodeSpan,
 }
 
 impl ArrayIndexExpr {
@@ -923,20 +1186,192 @@ impl ArrayIndexExpr {
         ArrayIndexExpr {
             expr: Box::new(expr),
             index_expr: Box::new(index_expr),
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            expr_kind: ExprKind::Unknown,
+            span: NodeSpan::default(),
         }
     }
 }
 
+impl ExprVisit for ArrayIndexExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        self.expr_kind
+    }
+}
+
+impl TypeInfoSetter for ArrayIndexExpr {
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
+
+/// `(a, b)`, `(a,)`, or `()` which is the unit value
 #[derive(Debug, PartialEq)]
-pub struct TupleExpr(pub Vec<Expr>);
+pub struct TupleExpr {
+    pub elems: Vec<Expr>,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
+}
+
+impl TupleExpr {
+    pub fn new(elems: Vec<Expr>) -> TupleExpr {
+        TupleExpr {
+            elems,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
+        }
+    }
+}
+
+impl ExprVisit for TupleExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for TupleExpr {
+    /// Like arrays, number literals in the tuple are determined by the type of the tuple.
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        if let TypeInfo::Tuple(types) = &type_info {
+            for (elem, t) in self.elems.iter_mut().zip(types.iter()) {
+                let elem_info = elem.type_info();
+                let may_be_elem_type = elem_info.borrow().may_be_determined_as(t);
+                if may_be_elem_type && elem_info.borrow().deref() != t {
+                    elem.set_type_info(t.clone());
+                }
+            }
+        }
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
 
+/// `a.0`
 #[derive(Debug, PartialEq)]
 pub struct TupleIndexExpr {
-    // TODO
+    pub expr: Box<Expr>,
+    pub index: usize,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub expr_kind: ExprKind,
+    pub span: NodeSpan,
+}
+
+impl TupleIndexExpr {
+    pub fn new(expr: Expr, index: usize) -> TupleIndexExpr {
+        TupleIndexExpr {
+            expr: Box::new(expr),
+            index,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            expr_kind: ExprKind::Unknown,
+            span: NodeSpan::default(),
+        }
+    }
+}
+
+impl ExprVisit for TupleIndexExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        self.expr_kind
+    }
+}
+
+impl TypeInfoSetter for TupleIndexExpr {
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
 }
 
+/// StructExpr -> PathExpr `{` ( StructExprField , )* StructExprField? `}`
+/// # Examples
+/// `Point { x: 1, y }`
 #[derive(Debug, PartialEq)]
-pub struct StructExpr;
+pub struct StructExpr {
+    pub path: PathExpr,
+    pub fields: Vec<StructExprField>,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
+}
+
+impl StructExpr {
+    pub fn new(path: PathExpr, fields: Vec<StructExprField>) -> StructExpr {
+        StructExpr {
+            path,
+            fields,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
+        }
+    }
+}
+
+impl ExprVisit for StructExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for StructExpr {
+    /// Like arrays, number literals in the fields of a generic struct are determined
+    /// by the type arguments, e.g. `Pair { a: 1, b: 2 }` is `Pair<u8>`.
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        if let TypeInfo::Struct { fields, .. } = &type_info {
+            for field in self.fields.iter_mut() {
+                let field_type = match fields.find(&field.name) {
+                    Some((_, t)) => t,
+                    None => continue,
+                };
+                let expr_info = field.expr.type_info();
+                let may_be_field_type = expr_info.borrow().may_be_determined_as(&field_type);
+                if may_be_field_type && expr_info.borrow().deref() != &field_type {
+                    field.expr.set_type_info(field_type);
+                }
+            }
+        }
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
+
+/// `x: 1` or the shorthand `y` in `Point { x: 1, y }`
+#[derive(Debug, PartialEq)]
+pub struct StructExprField {
+    pub name: String,
+    pub expr: Expr,
+}
+
+impl StructExprField {
+    pub fn new(name: String, expr: Expr) -> StructExprField {
+        StructExprField { name, expr }
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct ReturnExpr(pub Option<Box<Expr>>);
@@ -951,8 +1386,12 @@ impl ExprVisit for ReturnExpr {
     }
 }
 
+/// `break 'label expr`, both the label and the expr are optional.
 #[derive(Debug, PartialEq)]
-pub struct BreakExpr(pub Option<Box<Expr>>);
+pub struct BreakExpr {
+    pub label: Option<String>,
+    pub expr: Option<Box<Expr>>,
+}
 
 impl ExprVisit for BreakExpr {
     fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
@@ -964,11 +1403,64 @@ impl ExprVisit for BreakExpr {
     }
 }
 
+/// `continue 'label`, the label is optional.
+#[derive(Debug, PartialEq)]
+pub struct ContinueExpr(pub Option<String>);
+
+impl ExprVisit for ContinueExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        Rc::new(RefCell::new(TypeInfo::Never))
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+/// `move |a: i32, b| -> i32 { a + b + c }`
+///
+/// The closure is compiled into a function named `{{closure}}` whose block is the body,
+/// and the variables it captures are collected in its type info by the symbol resolver.
+/// The types of parameters and the return type may be omitted.
+#[derive(Debug, PartialEq)]
+pub struct ClosureExpr {
+    pub is_move: bool,
+    pub item_fn: ItemFn,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
+}
+
+impl ClosureExpr {
+    pub fn new(is_move: bool, item_fn: ItemFn) -> ClosureExpr {
+        ClosureExpr {
+            is_move,
+            item_fn,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
+        }
+    }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+}
+
+impl ExprVisit for ClosureExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct CallExpr {
     pub expr: Box<Expr>,
     pub call_params: CallParams,
     type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
 }
 
 pub type CallParams = Vec<Expr>;
@@ -979,6 +1471,7 @@ impl CallExpr {
             expr: Box::new(expr),
             call_params: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
         }
     }
 
@@ -1002,10 +1495,52 @@ impl ExprVisit for CallExpr {
     }
 }
 
+/// `receiver.method(call_params)`
+///
+/// The receiver is borrowed or dereferenced by the symbol resolver to match
+/// the type of `self` parameter of the method.
+#[derive(Debug, PartialEq)]
+pub struct MethodCallExpr {
+    pub receiver: Box<Expr>,
+    pub method: String,
+    pub call_params: CallParams,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
+}
+
+impl MethodCallExpr {
+    pub fn new(receiver: Expr, method: String, call_params: CallParams) -> Self {
+        MethodCallExpr {
+            receiver: Box::new(receiver),
+            method,
+            call_params,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
+        }
+    }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+}
+
+impl ExprVisit for MethodCallExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct FieldAccessExpr {
     pub lhs: Box<Expr>,
     pub rhs: Box<Expr>,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub expr_kind: ExprKind,
+    pub span: NodeSpan,
 }
 
 impl FieldAccessExpr {
@@ -1013,10 +1548,43 @@ impl FieldAccessExpr {
         FieldAccessExpr {
             lhs: Box::new(lhs),
             rhs: Box::new(rhs),
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            expr_kind: ExprKind::Unknown,
+            span: NodeSpan::default(),
+        }
+    }
+
+    /// Name of the accessed field, `None` if rhs is not an identifier.
+    pub fn field_name(&self) -> Option<&str> {
+        match self.rhs.as_ref() {
+            Expr::Path(path_expr) if path_expr.segments.len() == 1 => {
+                Some(&path_expr.segments[0])
+            }
+            _ => None,
         }
     }
 }
 
+impl ExprVisit for FieldAccessExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        self.expr_kind
+    }
+}
+
+impl TypeInfoSetter for FieldAccessExpr {
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct IfExpr {
     pub conditions: Vec<Expr>,
@@ -1033,6 +1601,7 @@ impl IfExpr {
         }
     }
 
+    #[cfg(test)]
     pub fn from_exprs(conditions: Vec<Expr>, blocks: Vec<BlockExpr>) -> IfExpr {
         IfExpr {
             conditions,
@@ -1079,8 +1648,96 @@ impl TypeInfoSetter for IfExpr {
     }
 }
 
+/// `match expr { pattern if guard => expr, ... }`
+#[derive(Debug, PartialEq)]
+pub struct MatchExpr {
+    pub expr: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub span: NodeSpan,
+}
+
+impl MatchExpr {
+    pub fn new(expr: Expr, arms: Vec<MatchArm>) -> MatchExpr {
+        MatchExpr {
+            expr: Box::new(expr),
+            arms,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            span: NodeSpan::default(),
+        }
+    }
+}
+
+impl ExprVisit for MatchExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for MatchExpr {
+    /// The arms of number literal types are also determined, e.g. `match a { _ => 1 }` is `u8`.
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        for arm in self.arms.iter_mut() {
+            let arm_info = arm.expr.type_info();
+            let may_be_arm_type = arm_info.borrow().may_be_determined_as(&type_info);
+            if may_be_arm_type && arm_info.borrow().deref() != &type_info {
+                arm.expr.set_type_info(type_info.clone());
+            }
+        }
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.set_type_info(type_info.borrow().clone());
+        self.type_info = type_info;
+    }
+}
+
+/// Variables bound by the pattern are declared in the scope of the arm,
+/// so that they are visible in the guard and the expression.
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub expr: Expr,
+    pub scope: Scope,
+    /// location of the pattern, since patterns do not record their spans
+    pub pattern_span: NodeSpan,
+}
+
+impl MatchArm {
+    pub fn new(scope_id: u64, pattern: Pattern, guard: Option<Expr>, expr: Expr) -> MatchArm {
+        MatchArm {
+            pattern,
+            guard,
+            expr,
+            scope: Scope::new(scope_id),
+            pattern_span: NodeSpan::default(),
+        }
+    }
+}
+
+impl Debug for MatchArm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.guard {
+            Some(guard) => write!(f, "{:?} if {:?} => {:?}", self.pattern, guard, self.expr),
+            None => write!(f, "{:?} => {:?}", self.pattern, self.expr),
+        }
+    }
+}
+
+impl PartialEq for MatchArm {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.eq(&other.pattern) && self.guard.eq(&other.guard) && self.expr.eq(&other.expr)
+    }
+}
+
+/// `'label: while cond { ... }`, field 2 is the optional label.
 #[derive(Debug, PartialEq)]
-pub struct WhileExpr(pub Box<Expr>, pub Box<BlockExpr>);
+pub struct WhileExpr(pub Box<Expr>, pub Box<BlockExpr>, pub Option<String>);
 
 impl ExprVisit for WhileExpr {
     fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
@@ -1092,8 +1749,40 @@ impl ExprVisit for WhileExpr {
     }
 }
 
+/// `for pattern in expr { ... }`, where `expr` is a range.
+/// The pattern is declared in the scope of the block.
+#[derive(Debug, PartialEq)]
+pub struct ForExpr {
+    pub label: Option<String>,
+    pub pattern: Pattern,
+    pub expr: Box<Expr>,
+    pub block: Box<BlockExpr>,
+}
+
+impl ForExpr {
+    pub fn new(pattern: Pattern, expr: Expr, block: BlockExpr) -> ForExpr {
+        ForExpr {
+            label: None,
+            pattern,
+            expr: Box::new(expr),
+            block: Box::new(block),
+        }
+    }
+}
+
+impl ExprVisit for ForExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        Rc::new(RefCell::new(TypeInfo::Unit))
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct LoopExpr {
+    pub label: Option<String>,
     pub expr: Box<BlockExpr>,
     type_info: Rc<RefCell<TypeInfo>>,
 }
@@ -1101,6 +1790,7 @@ pub struct LoopExpr {
 impl LoopExpr {
     pub fn new(expr: BlockExpr) -> LoopExpr {
         LoopExpr {
+            label: None,
             expr: Box::new(expr),
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
         }
@@ -1,6 +1,5 @@
 use crate::ast::item::Item;
 use crate::analyser::scope::Scope;
-use crate::analyser::scope::BULITIN_SCOPE;
 use std::fmt::{Debug, Formatter};
 
 pub struct File {
@@ -18,6 +17,7 @@ impl File {
         }
     }
 
+    #[cfg(test)]
     pub fn items(mut self, items: Vec<Item>) -> File {
         self.items = items;
         self
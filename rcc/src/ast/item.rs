@@ -1,9 +1,11 @@
+use crate::analyser::scope::Scope;
 use crate::ast::expr::BlockExpr;
 use crate::ast::pattern::Pattern;
 use crate::ast::types::TypeAnnotation;
-use crate::ast::{NamedASTNode, TokenStart, Visibility};
+use crate::ast::{NodeSpan, TokenStart, Visibility};
 use crate::lexer::token::Token;
 use crate::rcc::RccError;
+use std::fmt::{Debug, Formatter};
 
 #[derive(Debug, PartialEq)]
 pub enum Item {
@@ -16,17 +18,11 @@ pub enum Item {
     /// enum Color { Red, Yellow }
     Enum(TypeEnum),
 
-    /// type Int = i32;
-    Type,
-
-    /// const A: i32 = 2;
-    Const,
-
-    /// static B: i32 = 3;
-    Static,
-
     /// impl Foo { ... }
-    Impl,
+    Impl(ItemImpl),
+
+    /// trait Shape { fn area(&self) -> i32; }
+    Trait(ItemTrait),
 
     /// extern "C" {}
     ExternalBlock(ItemExternalBlock),
@@ -44,33 +40,46 @@ impl TokenStart for Item {
                 | Token::Struct
                 | Token::Enum
                 | Token::Impl
+                | Token::Trait
         )
     }
 }
 
-impl NamedASTNode for Item {
-    fn ident_name(&self) -> &str {
-        match self {
-            Self::Fn(item_fn) => item_fn.ident_name(),
-            _ => unimplemented!(),
-        }
-    }
-}
-
 pub trait FnSignature {
     fn vis(&self) -> Visibility;
     fn name(&self) -> String;
     fn params(&self) -> Vec<TypeAnnotation>;
     fn ret_type(&self) -> TypeAnnotation;
+
+    fn generics(&self) -> Vec<GenericParam> {
+        vec![]
+    }
+}
+
+/// `T: Shape + Copy` in `fn foo<T: Shape + Copy>()` or `where T: Shape + Copy`
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct GenericParam {
+    pub name: String,
+    /// names of the traits
+    pub bounds: Vec<String>,
+}
+
+impl GenericParam {
+    pub fn new(name: String, bounds: Vec<String>) -> GenericParam {
+        GenericParam { name, bounds }
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct ItemFn {
     vis: Visibility,
     pub name: String,
+    pub generics: Vec<GenericParam>,
     pub fn_params: FnParams,
     pub ret_type: TypeAnnotation,
     pub fn_block: BlockExpr,
+    /// span of the function signature
+    pub span: NodeSpan,
 }
 
 impl ItemFn {
@@ -84,11 +93,22 @@ impl ItemFn {
         ItemFn {
             vis,
             name,
+            generics: vec![],
             fn_params,
             ret_type,
             fn_block,
+            span: NodeSpan::default(),
         }
     }
+
+    /// Whether the function is a method, whose first parameter is `self`.
+    pub fn has_self_param(&self) -> bool {
+        self.fn_params.has_self_param()
+    }
+
+    pub fn is_generic(&self) -> bool {
+        !self.generics.is_empty()
+    }
 }
 
 impl FnSignature for ItemFn {
@@ -107,6 +127,10 @@ impl FnSignature for ItemFn {
     fn ret_type(&self) -> TypeAnnotation {
         self.ret_type.clone()
     }
+
+    fn generics(&self) -> Vec<GenericParam> {
+        self.generics.clone()
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -129,6 +153,16 @@ impl FnParams {
     pub fn push(&mut self, param: FnParam) {
         self.params.push(param);
     }
+
+    pub fn has_self_param(&self) -> bool {
+        match self.params.first() {
+            Some(FnParam {
+                pattern: Pattern::Identifier(ident_pattern),
+                ..
+            }) => ident_pattern.ident() == "self",
+            _ => false,
+        }
+    }
 }
 
 impl From<Vec<FnParam>> for FnParams {
@@ -155,19 +189,15 @@ impl TokenStart for FnParam {
     }
 }
 
-impl NamedASTNode for ItemFn {
-    fn ident_name(&self) -> &str {
-        &self.name
-    }
-}
-
 /// # Examples
 /// `struct Student { name: String, age: u32 }`
 /// `pub struct Teacher(String, u32);`
+/// `struct Pair<T> { a: T, b: T }`
 #[derive(Debug, PartialEq)]
 pub struct ItemStruct {
     vis: Visibility,
     name: String,
+    generics: Vec<GenericParam>,
     fields: Fields,
 }
 
@@ -176,10 +206,20 @@ impl ItemStruct {
         ItemStruct {
             vis,
             name,
+            generics: vec![],
             fields: Fields::None,
         }
     }
 
+    pub fn generic_params(mut self, generics: Vec<GenericParam>) -> Self {
+        self.generics = generics;
+        self
+    }
+
+    pub fn generics(&self) -> &[GenericParam] {
+        &self.generics
+    }
+
     pub fn struct_fields(mut self, struct_fields: Vec<StructField>) -> Self {
         self.fields = Fields::Struct(struct_fields);
         self
@@ -215,12 +255,53 @@ pub struct TypeEnum {
     enum_items: Vec<EnumVariant>,
 }
 
+impl TypeEnum {
+    pub fn new(vis: Visibility, name: String) -> Self {
+        TypeEnum {
+            vis,
+            name,
+            enum_items: vec![],
+        }
+    }
+
+    pub fn enum_items(mut self, enum_items: Vec<EnumVariant>) -> Self {
+        self.enum_items = enum_items;
+        self
+    }
+
+    pub fn variants(&self) -> &[EnumVariant] {
+        &self.enum_items
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn vis(&self) -> Visibility {
+        self.vis
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct EnumVariant {
     name: String,
     fields: Fields,
 }
 
+impl EnumVariant {
+    pub fn new(name: String, fields: Fields) -> Self {
+        EnumVariant { name, fields }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &Fields {
+        &self.fields
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Fields {
     /// `struct Foo {a: i32}`
@@ -244,6 +325,140 @@ pub struct TupleField {
     pub _type: TypeAnnotation,
 }
 
+/// `impl Foo { fn new() -> Self { Foo } fn get(&self) -> i32 { 0 } }`
+/// `impl Shape for Foo { fn area(&self) -> i32 { 0 } }`
+///
+/// `Self` is defined in the scope of the impl block, and the associated functions
+/// are added to the scope where the impl block is.
+pub struct ItemImpl {
+    pub trait_name: Option<String>,
+    pub self_type: TypeAnnotation,
+    pub items: Vec<ItemFn>,
+    pub scope: Scope,
+    /// span of `impl Type` or `impl Trait for Type`
+    pub span: NodeSpan,
+}
+
+impl ItemImpl {
+    pub fn new(self_type: TypeAnnotation, items: Vec<ItemFn>, scope_id: u64) -> ItemImpl {
+        ItemImpl {
+            trait_name: None,
+            self_type,
+            items,
+            scope: Scope::new(scope_id),
+            span: NodeSpan::default(),
+        }
+    }
+
+    pub fn for_trait(mut self, trait_name: String) -> ItemImpl {
+        self.trait_name = Some(trait_name);
+        self
+    }
+}
+
+impl Debug for ItemImpl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.trait_name {
+            Some(name) => write!(f, "impl {} for {:?} {:?}", name, self.self_type, self.items),
+            None => write!(f, "impl {:?} {:?}", self.self_type, self.items),
+        }
+    }
+}
+
+impl PartialEq for ItemImpl {
+    fn eq(&self, other: &Self) -> bool {
+        self.trait_name == other.trait_name
+            && self.self_type == other.self_type
+            && self.items == other.items
+    }
+}
+
+/// `trait Shape { fn area(&self) -> i32; fn double(&self) -> i32 { self.area() * 2 } }`
+///
+/// `Self` is defined in the scope of the trait as a generic parameter bounded by the trait,
+/// so that the default implementations are checked once, and generated for each implementor.
+pub struct ItemTrait {
+    vis: Visibility,
+    pub name: String,
+    pub items: Vec<TraitItemFn>,
+    pub scope: Scope,
+    /// span of `trait Name`
+    pub span: NodeSpan,
+}
+
+impl ItemTrait {
+    pub fn new(vis: Visibility, name: String, items: Vec<TraitItemFn>, scope_id: u64) -> ItemTrait {
+        ItemTrait {
+            vis,
+            name,
+            items,
+            scope: Scope::new(scope_id),
+            span: NodeSpan::default(),
+        }
+    }
+}
+
+impl Debug for ItemTrait {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "trait {} {:?}", self.name, self.items)
+    }
+}
+
+impl PartialEq for ItemTrait {
+    fn eq(&self, other: &Self) -> bool {
+        self.vis == other.vis && self.name == other.name && self.items == other.items
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TraitItemFn {
+    /// `fn area(&self) -> i32;`
+    Required(FnDecl),
+    /// `fn double(&self) -> i32 { self.area() * 2 }`
+    Provided(Box<ItemFn>),
+}
+
+impl TraitItemFn {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Required(fn_decl) => &fn_decl.name,
+            Self::Provided(item_fn) => &item_fn.name,
+        }
+    }
+
+    pub fn fn_params(&self) -> &FnParams {
+        match self {
+            Self::Required(fn_decl) => &fn_decl.fn_params,
+            Self::Provided(item_fn) => &item_fn.fn_params,
+        }
+    }
+
+    pub fn ret_type(&self) -> &TypeAnnotation {
+        match self {
+            Self::Required(fn_decl) => &fn_decl.ret_type,
+            Self::Provided(item_fn) => &item_fn.ret_type,
+        }
+    }
+}
+
+/// A function signature without body.
+#[derive(Debug, PartialEq)]
+pub struct FnDecl {
+    pub name: String,
+    pub fn_params: FnParams,
+    pub ret_type: TypeAnnotation,
+}
+
+impl FnDecl {
+    pub fn new(name: String, fn_params: FnParams, ret_type: TypeAnnotation) -> FnDecl {
+        FnDecl {
+            name,
+            fn_params,
+            ret_type,
+        }
+    }
+}
+
 /// `extern "C" { fn foo(); }`
 #[derive(Debug, PartialEq)]
 pub struct ItemExternalBlock {
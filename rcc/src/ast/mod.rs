@@ -1,4 +1,4 @@
-use crate::lexer::token::Token;
+use crate::lexer::token::{Span, Token};
 use crate::ast::file::File;
 
 pub mod file;
@@ -25,7 +25,7 @@ macro_rules! from_token {
               $variant,)*
         }
 
-        impl crate::ast::FromToken for $name {
+        impl $crate::ast::FromToken for $name {
             fn from_token(tk: Token) -> Option<Self> {
                 match tk {
                     $(Token::$variant => Some(Self::$variant),)*
@@ -50,8 +50,15 @@ from_token! {
 }
 
 
-pub trait NamedASTNode {
-    fn ident_name(&self) -> &str;
+/// Location of an AST node in the source file.
+/// It is only used in diagnostics, so it is ignored when comparing AST nodes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodeSpan(pub Span);
+
+impl PartialEq for NodeSpan {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
 }
 
 pub trait TokenStart {
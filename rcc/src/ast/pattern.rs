@@ -1,14 +1,79 @@
+use crate::ast::expr::{Expr, PathExpr, RangeOp};
 use crate::ast::TokenStart;
 use crate::lexer::token::Token;
 
 #[derive(Debug, PartialEq)]
 pub enum Pattern {
     Identifier(IdentPattern),
+    /// `_`
+    Wildcard,
+    /// `1`, `-1`, `'a'`, `true`
+    Literal(Box<Expr>),
+    /// `1..=9`, `'a'..'z'`
+    Range(Box<Expr>, RangeOp, Box<Expr>),
+    /// `Color::Red`
+    Path(PathExpr),
+    /// `Shape::Circle(r)`
+    TupleStruct(PathExpr, Vec<Pattern>),
+    /// `Point { x, y: 0, .. }`, `Shape::Square { side }`
+    Struct(StructPattern),
+    /// `(a, mut b)`
+    Tuple(Vec<Pattern>),
+    /// `&a`, `&mut (a, b)`
+    Reference(Box<Pattern>, bool),
+}
+
+impl Pattern {
+    /// Whether the pattern binds a reference to a part of the matched value,
+    /// e.g. `(ref a, b)`.
+    pub fn has_ref_binding(&self) -> bool {
+        match self {
+            Pattern::Identifier(ident_pattern) => ident_pattern.is_ref(),
+            Pattern::TupleStruct(_, patterns) | Pattern::Tuple(patterns) => {
+                patterns.iter().any(|p| p.has_ref_binding())
+            }
+            Pattern::Struct(struct_pattern) => struct_pattern
+                .fields
+                .iter()
+                .any(|f| f.pattern.has_ref_binding()),
+            Pattern::Reference(pattern, _) => pattern.has_ref_binding(),
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range(..) | Pattern::Path(_) => {
+                false
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StructPattern {
+    pub path: PathExpr,
+    pub fields: Vec<FieldPattern>,
+    /// `..`
+    pub has_rest: bool,
+}
+
+/// `x: 0`, or `x` which is short for `x: x`
+#[derive(Debug, PartialEq)]
+pub struct FieldPattern {
+    pub name: String,
+    pub pattern: Pattern,
 }
 
 impl TokenStart for Pattern {
     fn is_token_start(tk: &Token) -> bool {
         IdentPattern::is_token_start(tk)
+            || matches!(
+                tk,
+                Token::LeftParen
+                    | Token::Mut
+                    | Token::Ref
+                    | Token::And
+                    | Token::AndAnd
+                    | Token::Literal { .. }
+                    | Token::True
+                    | Token::False
+                    | Token::Minus
+            )
     }
 }
 
@@ -16,23 +81,40 @@ impl TokenStart for Pattern {
 pub struct IdentPattern {
     ident: String,
     is_mut: bool,
+    /// `ref a` and `ref mut a` bind references, the binding itself
+    /// of `ref mut a` is not mutable
+    is_ref: bool,
 }
 
 impl IdentPattern {
     pub fn new_mut(ident: String) -> Self {
         IdentPattern {
             ident,
-            is_mut: true
+            is_mut: true,
+            is_ref: false,
         }
     }
 
     pub fn new_const(ident: String) -> Self {
         IdentPattern {
             ident,
-            is_mut: false
+            is_mut: false,
+            is_ref: false,
+        }
+    }
+
+    pub fn new_ref(ident: String, is_mut: bool) -> Self {
+        IdentPattern {
+            ident,
+            is_mut,
+            is_ref: true,
         }
     }
 
+    pub fn is_ref(&self) -> bool {
+        self.is_ref
+    }
+
     pub fn is_mut(&self) -> bool {
         self.is_mut
     }
@@ -2,6 +2,7 @@ use super::pattern::Pattern;
 use crate::analyser::sym_resolver::TypeInfo;
 use crate::ast::expr::{Expr, ExprVisit};
 use crate::ast::item::Item;
+use crate::ast::NodeSpan;
 use crate::ast::stmt::Stmt::ExprStmt;
 use crate::ast::types::TypeAnnotation;
 use std::ops::Deref;
@@ -51,6 +52,7 @@ pub struct LetStmt {
     pub pattern: Pattern,
     pub _type: Option<TypeAnnotation>,
     pub rhs: Option<Expr>,
+    pub span: NodeSpan,
 }
 
 impl LetStmt {
@@ -59,6 +61,7 @@ impl LetStmt {
             pattern,
             _type: None,
             rhs: None,
+            span: NodeSpan::default(),
         }
     }
 
@@ -71,10 +74,4 @@ impl LetStmt {
         self.rhs = Some(expr);
         self
     }
-
-    pub fn is_mut(&self) -> bool {
-        match &self.pattern {
-            Pattern::Identifier(i) => i.is_mut(),
-        }
-    }
 }
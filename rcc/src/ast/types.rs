@@ -1,4 +1,4 @@
-use crate::ast::item::{ExternalItemFn, ItemFn, FnSignature};
+use crate::ast::item::FnSignature;
 use crate::ast::types::TypeAnnotation::{Identifier, Tuple};
 use std::fmt::{Debug, Formatter};
 use strenum::StrEnum;
@@ -9,6 +9,9 @@ pub enum TypeAnnotation {
     ///  `struct Foo;`, `enum Color(String);`, etc.
     Identifier(String),
 
+    /// `Pair<i32>`
+    Generic(TypeGeneric),
+
     /// `()`, `(i32, char)`, ...
     /// `()` is also called unit type
     Tuple(TypeTuple),
@@ -24,13 +27,12 @@ pub enum TypeAnnotation {
 
     Ptr(TypePtr),
 
+    /// `dyn Shape`
+    TraitObject(String),
+
     /// !
     Never,
     Unit,
-
-    Bool,
-    Str,
-    Char,
     Unknown,
 }
 
@@ -46,32 +48,96 @@ impl From<&str> for TypeAnnotation {
     }
 }
 
+impl TypeAnnotation {
+    /// Replace `Self` in the type with the implemented type of an impl block.
+    pub fn replace_self(&self, self_type: &TypeAnnotation) -> TypeAnnotation {
+        match self {
+            Identifier(s) if s == "Self" => self_type.clone(),
+            Self::Generic(tg) => Self::Generic(TypeGeneric::new(
+                tg.name.clone(),
+                tg.args.iter().map(|t| t.replace_self(self_type)).collect(),
+            )),
+            Tuple(types) => Tuple(types.iter().map(|t| t.replace_self(self_type)).collect()),
+            Self::Array(ta) => Self::Array(TypeArray::new(ta._type.replace_self(self_type), ta.len)),
+            Self::Slice(ts) => Self::Slice(Box::new(ts.replace_self(self_type))),
+            Self::FnPtr(fp) => Self::FnPtr(TypeFnPtr::new(
+                fp.params.iter().map(|t| t.replace_self(self_type)).collect(),
+                fp.ret_type.replace_self(self_type),
+            )),
+            Self::Ptr(tp) => Self::Ptr(TypePtr::new(
+                tp.ptr_kind,
+                tp.type_anno.replace_self(self_type),
+            )),
+            t => t.clone(),
+        }
+    }
+
+    /// Whether `Self` appears in the type, e.g. `(i32, &Self)`.
+    pub fn contains_self(&self) -> bool {
+        match self {
+            Identifier(s) => s == "Self",
+            Self::Generic(tg) => tg.args.iter().any(|t| t.contains_self()),
+            Tuple(types) => types.iter().any(|t| t.contains_self()),
+            Self::Array(ta) => ta._type.contains_self(),
+            Self::Slice(ts) => ts.contains_self(),
+            Self::FnPtr(fp) => {
+                fp.params.iter().any(|t| t.contains_self()) || fp.ret_type.contains_self()
+            }
+            Self::Ptr(tp) => tp.type_anno.contains_self(),
+            _ => false,
+        }
+    }
+}
+
 impl Debug for TypeAnnotation {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Identifier(s) => f.write_str(&s),
+            Self::Identifier(s) => f.write_str(s),
+            Self::Generic(tg) => {
+                let args: Vec<String> = tg.args.iter().map(|t| format!("{:?}", t)).collect();
+                write!(f, "{}<{}>", tg.name, args.join(", "))
+            }
             Self::Tuple(tp) => write!(f, "({:?})", tp),
             Self::Array(ta) => write!(f, "[{:?}; {}]", ta._type, ta.len),
             Self::Slice(ts) => write!(f, "[{:?}]", ts),
             Self::FnPtr(fptr) => write!(f, "{:?}", fptr),
             Self::Ptr(ptr) => write!(f, "{:?}", ptr),
+            Self::TraitObject(name) => write!(f, "dyn {}", name),
             Self::Never => write!(f, "!"),
             Self::Unit => write!(f, "()"),
-            Self::Bool => write!(f, "bool"),
-            Self::Str => write!(f, "&str"),
-            Self::Char => write!(f, "char"),
             Self::Unknown => write!(f, "[unknown]"),
         }
     }
 }
 
 pub type TypeTuple = Vec<TypeAnnotation>;
+
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct TypeGeneric {
+    pub name: String,
+    pub args: Vec<TypeAnnotation>,
+}
+
+impl TypeGeneric {
+    pub fn new(name: String, args: Vec<TypeAnnotation>) -> TypeGeneric {
+        TypeGeneric { name, args }
+    }
+}
 pub type TypeSlice = Box<TypeAnnotation>;
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct TypeArray {
-    _type: Box<TypeAnnotation>,
-    len: u32,
+    pub _type: Box<TypeAnnotation>,
+    pub len: u32,
+}
+
+impl TypeArray {
+    pub fn new(_type: TypeAnnotation, len: u32) -> TypeArray {
+        TypeArray {
+            _type: Box::new(_type),
+            len,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
@@ -106,6 +172,12 @@ pub enum PtrKind {
     ConstRawPtr,
 }
 
+impl PtrKind {
+    pub fn is_raw(&self) -> bool {
+        matches!(self, PtrKind::MutRawPtr | PtrKind::ConstRawPtr)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct TypePtr {
     pub ptr_kind: PtrKind,
@@ -156,6 +228,11 @@ impl TypeLitNum {
         use TypeLitNum::*;
         matches!(self, F | F32 | F64)
     }
+
+    pub fn is_unsigned(&self) -> bool {
+        use TypeLitNum::*;
+        matches!(self, U8 | U16 | U32 | U64 | U128 | Usize)
+    }
 }
 
 impl Debug for TypeLitNum {
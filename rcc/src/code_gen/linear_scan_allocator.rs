@@ -0,0 +1,276 @@
+//! Linear scan register allocation.
+//!
+//! [Linear Scan Register Allocation]: http://web.cs.ucla.edu/~palsberg/course/cs132/linearscan.pdf
+
+use crate::code_gen::{assign_fp_offsets, next_fp_offset, Allocator, Location};
+use crate::ir::cfg::CFG;
+use crate::ir::dataflow::live_variable::LiveVariableAnalysis;
+use crate::ir::{IRInst, IRType, Operand};
+use std::collections::{HashMap, HashSet};
+
+/// Caller saved registers. a0-a7 are reserved for arguments,
+/// a4 and a5 are also used as scratch registers by the code generator,
+/// and so is t6 for the large offsets of the stack slots.
+const TEMP_REGS: [&str; 6] = ["t0", "t1", "t2", "t3", "t4", "t5"];
+
+/// Callee saved registers. s0 is the frame pointer.
+const SAVED_REGS: [&str; 11] = [
+    "s1", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11",
+];
+
+/// Live interval of a variable, positions of instructions start from 1,
+/// and 0 is the function entry.
+#[derive(Debug, PartialEq)]
+pub struct LiveInterval {
+    pub var_name: String,
+    pub start: usize,
+    pub end: usize,
+    /// The variable is live across a function call,
+    /// so it must not be held in a caller saved register.
+    pub cross_call: bool,
+}
+
+pub struct LinearScanAllocator<'cfg> {
+    cfg: &'cfg CFG,
+    addr_size: u32,
+    offset: u32,
+    var_offsets: HashMap<String, u32>,
+    var_regs: HashMap<String, &'static str>,
+    spilled_vars: Vec<String>,
+    saved_regs: Vec<&'static str>,
+}
+
+impl<'cfg> LinearScanAllocator<'cfg> {
+    pub(crate) fn new(cfg: &CFG, addr_size: u32) -> LinearScanAllocator<'_> {
+        debug_assert!(addr_size == 32 || addr_size == 64);
+        let mut allocator = LinearScanAllocator {
+            cfg,
+            addr_size,
+            offset: 0,
+            var_offsets: HashMap::new(),
+            var_regs: HashMap::new(),
+            spilled_vars: vec![],
+            saved_regs: vec![],
+        };
+        let intervals = allocator.build_intervals();
+        allocator.linear_scan(intervals);
+        let (var_offsets, offset) = assign_fp_offsets(
+            cfg,
+            &allocator.saved_regs,
+            allocator.spilled_vars.iter().map(String::as_str),
+            addr_size,
+        );
+        allocator.var_offsets = var_offsets;
+        allocator.offset = offset;
+        allocator
+    }
+
+    /// Variables which must be stored in memory.
+    fn memory_vars(&self) -> HashSet<String> {
+        let mut vars: HashSet<String> = self
+            .cfg
+            .iter_inst()
+            .filter_map(|inst| match inst {
+                IRInst::LoadAddr {
+                    symbol: Operand::Place(p),
+                    ..
+                } => Some(p.label.clone()),
+                _ => None,
+            })
+            .collect();
+        for (name, (_, ir_type)) in self.cfg.local_infos.iter() {
+            if !self.fits_in_reg(ir_type) {
+                vars.insert(name.clone());
+            }
+        }
+        vars
+    }
+
+    fn fits_in_reg(&self, ir_type: &IRType) -> bool {
+        let size = ir_type.byte_size(self.addr_size);
+        size > 0
+            && size <= self.addr_size / 8
+            && !matches!(ir_type, IRType::F32 | IRType::F64)
+            && !ir_type.is_aggregate()
+    }
+
+    /// Build live intervals sorted by start point.
+    pub fn build_intervals(&self) -> Vec<LiveInterval> {
+        let mut analysis = LiveVariableAnalysis::new(self.cfg);
+        analysis.apply();
+
+        let var_count = self.cfg.local_infos.len();
+        let mut starts = vec![usize::MAX; var_count];
+        let mut ends = vec![0usize; var_count];
+
+        macro_rules! update {
+            ($id:expr, $pos:expr) => {
+                starts[$id] = starts[$id].min($pos);
+                ends[$id] = ends[$id].max($pos);
+            };
+        }
+
+        // arguments are defined in the function entry
+        for i in 0..self.cfg.fn_args.len() {
+            let arg_name = self.cfg.get_name_of_fn_arg(i).unwrap();
+            let (id, _) = self.cfg.local_infos.get(&arg_name).unwrap();
+            update!(*id, 0);
+        }
+
+        let mut call_positions = vec![];
+        let mut pos = 1;
+        for bb in self.cfg.basic_blocks.iter() {
+            let bb_start = pos;
+            for inst in bb.instructions.iter() {
+                if let Some(id) = inst.dest().and_then(|dest| analysis.var_id(dest)) {
+                    update!(id, pos);
+                }
+                for src in inst.src_operands() {
+                    if let Operand::Place(p) = src {
+                        if let Some(id) = analysis.var_id(p) {
+                            update!(id, pos);
+                        }
+                    }
+                }
+                if matches!(inst, IRInst::Call { .. }) || inst.calls_helper(self.addr_size) {
+                    call_positions.push(pos);
+                }
+                pos += 1;
+            }
+            let bb_end = if pos > bb_start { pos - 1 } else { bb_start };
+
+            for id in 0..var_count {
+                if analysis.in_states[bb.id].get(id).unwrap() {
+                    update!(id, bb_start);
+                }
+                if analysis.out_states[bb.id].get(id).unwrap() {
+                    update!(id, bb_end);
+                }
+            }
+        }
+
+        let mut intervals: Vec<LiveInterval> = self
+            .cfg
+            .local_infos
+            .iter()
+            .filter(|(_, (id, _))| starts[*id] != usize::MAX)
+            .map(|(name, (id, _))| {
+                let (start, end) = (starts[*id], ends[*id]);
+                LiveInterval {
+                    var_name: name.clone(),
+                    start,
+                    end,
+                    cross_call: call_positions.iter().any(|p| start < *p && *p < end),
+                }
+            })
+            .collect();
+        intervals.sort_by(|a, b| (a.start, &a.var_name).cmp(&(b.start, &b.var_name)));
+        intervals
+    }
+
+    fn linear_scan(&mut self, intervals: Vec<LiveInterval>) {
+        let memory_vars = self.memory_vars();
+
+        // free registers, the first one is used first
+        let mut free_temp_regs: Vec<&'static str> = TEMP_REGS.iter().rev().copied().collect();
+        let mut free_saved_regs: Vec<&'static str> = SAVED_REGS.iter().rev().copied().collect();
+        // (end, interval index, register)
+        let mut active: Vec<(usize, usize, &'static str)> = vec![];
+        let mut used_saved_regs: HashSet<&'static str> = HashSet::new();
+
+        for (i, interval) in intervals.iter().enumerate() {
+            if memory_vars.contains(&interval.var_name) {
+                self.spilled_vars.push(interval.var_name.clone());
+                continue;
+            }
+
+            // expire old intervals
+            active.retain(|(end, _, reg)| {
+                if *end < interval.start {
+                    if SAVED_REGS.contains(reg) {
+                        free_saved_regs.push(reg);
+                    } else {
+                        free_temp_regs.push(reg);
+                    }
+                    false
+                } else {
+                    true
+                }
+            });
+
+            let free_reg = if interval.cross_call {
+                free_saved_regs.pop()
+            } else {
+                free_temp_regs.pop().or_else(|| free_saved_regs.pop())
+            };
+
+            match free_reg {
+                Some(reg) => {
+                    active.push((interval.end, i, reg));
+                    self.var_regs.insert(interval.var_name.clone(), reg);
+                }
+                None => {
+                    // spill the interval which ends last
+                    let spill = active
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (_, _, reg))| {
+                            !interval.cross_call || SAVED_REGS.contains(reg)
+                        })
+                        .max_by_key(|(_, (end, _, _))| *end)
+                        .map(|(idx, (end, _, _))| (idx, *end));
+                    match spill {
+                        Some((idx, end)) if end > interval.end => {
+                            let (_, spill_idx, reg) = active.remove(idx);
+                            let spill_name = &intervals[spill_idx].var_name;
+                            self.var_regs.remove(spill_name);
+                            self.spilled_vars.push(spill_name.clone());
+                            active.push((interval.end, i, reg));
+                            self.var_regs.insert(interval.var_name.clone(), reg);
+                        }
+                        _ => self.spilled_vars.push(interval.var_name.clone()),
+                    }
+                }
+            }
+        }
+
+        for reg in self.var_regs.values() {
+            if SAVED_REGS.contains(reg) {
+                used_saved_regs.insert(*reg);
+            }
+        }
+        self.saved_regs = SAVED_REGS
+            .iter()
+            .filter(|reg| used_saved_regs.contains(*reg))
+            .copied()
+            .collect();
+    }
+}
+
+impl<'cfg> Allocator for LinearScanAllocator<'cfg> {
+    fn get_frame_size(&self) -> u32 {
+        self.offset
+    }
+
+    fn get_fp_offset(&mut self, var_name: &str, ir_type: &IRType) -> u32 {
+        match self.var_offsets.get(var_name) {
+            Some(offset) => *offset,
+            None => {
+                self.offset = next_fp_offset(self.offset, ir_type, self.addr_size);
+                self.var_offsets.insert(var_name.to_string(), self.offset);
+                self.offset
+            }
+        }
+    }
+
+    fn get_location(&mut self, var_name: &str, ir_type: &IRType) -> Location {
+        match self.var_regs.get(var_name) {
+            Some(reg) => Location::Reg(reg),
+            None => Location::FpOffset(self.get_fp_offset(var_name, ir_type)),
+        }
+    }
+
+    fn get_saved_regs(&self) -> Vec<&'static str> {
+        self.saved_regs.clone()
+    }
+}
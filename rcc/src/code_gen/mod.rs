@@ -1,27 +1,114 @@
-pub mod riscv32;
+pub(crate) mod linear_scan_allocator;
+pub mod riscv;
 pub(crate) mod simple_allocator;
+#[cfg(test)]
+mod tests;
 
 use strenum::StrEnum;
 use crate::ir::cfg::CFG;
 use crate::rcc::OptimizeLevel;
+use crate::code_gen::linear_scan_allocator::LinearScanAllocator;
 use crate::code_gen::simple_allocator::SimpleAllocator;
+use crate::ir::var_name::{FP, RA};
 use crate::ir::IRType;
+use std::collections::HashMap;
 
-#[derive(StrEnum)]
+#[derive(StrEnum, Copy, Clone)]
 pub enum TargetPlatform {
-    Riscv32
+    Riscv32,
+    Riscv64,
+}
+
+impl TargetPlatform {
+    /// Bit width of an address.
+    pub fn addr_size(&self) -> u32 {
+        match self {
+            TargetPlatform::Riscv32 => 32,
+            TargetPlatform::Riscv64 => 64,
+        }
+    }
+}
+
+/// Where a variable is stored.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Location {
+    Reg(&'static str),
+    /// offset from fp
+    FpOffset(u32),
 }
 
 pub trait Allocator {
+    /// Bytes of ra, s0, the callee saved registers and the locals in memory,
+    /// which is aligned to 16 bytes with the outgoing arguments by the code generator.
     fn get_frame_size(&self) -> u32;
 
     /// Return offset from fp
     fn get_fp_offset(&mut self, var_name: &str, ir_type: &IRType) -> u32;
+
+    /// Return the register or the stack slot allocated for the variable
+    fn get_location(&mut self, var_name: &str, ir_type: &IRType) -> Location {
+        Location::FpOffset(self.get_fp_offset(var_name, ir_type))
+    }
+
+    /// Callee saved registers which should be saved in the function entry
+    /// and restored in the function exit.
+    fn get_saved_regs(&self) -> Vec<&'static str> {
+        vec![]
+    }
+}
+
+/// Return the offset from fp of a new stack slot for `ir_type`,
+/// where `offset` is the offset of the last allocated slot.
+/// A slot occupies `[fp - offset, fp - offset + size)`, and is naturally aligned,
+/// since fp is aligned to 16 bytes.
+pub(crate) fn next_fp_offset(offset: u32, ir_type: &IRType, addr_size: u32) -> u32 {
+    (offset + ir_type.byte_size(addr_size)).next_multiple_of(slot_align(ir_type, addr_size))
+}
+
+/// Aggregates are at least aligned to a register,
+/// so that the ones returned in registers can be loaded by registers.
+fn slot_align(ir_type: &IRType, addr_size: u32) -> u32 {
+    if ir_type.is_aggregate() {
+        ir_type.align(addr_size).max(addr_size / 8)
+    } else {
+        ir_type.align(addr_size)
+    }
+}
+
+/// Assign the stack slots of ra, s0, the callee saved registers `saved_regs` and
+/// the local variables `vars` in the order of the function entry, so that the frame
+/// size is known before generating the function.
+/// Return the offsets from fp and the bytes taken by the slots.
+pub(crate) fn assign_fp_offsets<'a>(
+    cfg: &CFG,
+    saved_regs: &[&str],
+    vars: impl Iterator<Item = &'a str>,
+    addr_size: u32,
+) -> (HashMap<String, u32>, u32) {
+    let mut slots: Vec<(&str, IRType)> = vec![];
+    if !cfg.is_leaf_on(addr_size) {
+        slots.push((RA, IRType::Addr));
+    }
+    slots.push((FP, IRType::Addr));
+    slots.extend(saved_regs.iter().map(|reg| (*reg, IRType::Addr)));
+    slots.extend(vars.map(|var| (var, cfg.local_infos[var].1.clone())));
+
+    let mut offset = 0;
+    let mut offsets = HashMap::new();
+    for (name, ir_type) in slots {
+        offset = next_fp_offset(offset, &ir_type, addr_size);
+        offsets.insert(name.to_string(), offset);
+    }
+    (offsets, offset)
 }
 
-pub fn create_allocator<'cfg>(opt_level: OptimizeLevel, cfg: &'cfg CFG, addr_size: u32) -> Box<dyn Allocator + 'cfg>  {
+pub fn create_allocator<'cfg>(
+    opt_level: OptimizeLevel,
+    cfg: &'cfg CFG,
+    addr_size: u32,
+) -> Box<dyn Allocator + 'cfg> {
     match opt_level {
         OptimizeLevel::Zero => Box::new(SimpleAllocator::new(cfg, addr_size)),
-        OptimizeLevel::One => todo!()
+        OptimizeLevel::One => Box::new(LinearScanAllocator::new(cfg, addr_size)),
     }
-}
\ No newline at end of file
+}
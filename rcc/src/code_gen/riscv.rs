@@ -0,0 +1,1843 @@
+//! b(byte): 8bit
+//! h(half word): 16bit
+//! w(word): 32bit
+//! d(double word): 64bit
+use crate::analyser::sym_resolver::VarKind;
+use crate::ast::expr::BinOperator;
+use crate::code_gen::{create_allocator, Allocator, Location};
+use crate::ir::cfg::{CFG, CFGIR};
+use crate::ir::var_name::{branch_name, FP, PANIC_BOUNDS_CHECK, RA};
+use crate::ir::{CastOp, IRInst, IRType, Jump, Operand, Place};
+use crate::rcc::{OptimizeLevel, RccError};
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+
+/// Labels of the float constants in `.rodata`, keyed by their sizes and bits.
+type FloatConsts = HashMap<(u32, u64), String>;
+
+/// Scratch register of the offsets out of the 12-bit immediates of loads, stores and `addi`,
+/// which is never allocated to variables.
+const OFFSET_SCRATCH: &str = "t6";
+
+/// Code generator of RV32 and RV64, which differ in `addr_size`, i.e. XLEN.
+pub struct RiscvCodeGen<'w, W: Write> {
+    cfg_ir: CFGIR,
+    output: &'w mut BufWriter<W>,
+    opt_level: OptimizeLevel,
+    addr_size: u32,
+    float_consts: FloatConsts,
+}
+
+impl<'w, W: 'w + Write> RiscvCodeGen<'w, W> {
+    pub fn new(
+        cfg_ir: CFGIR,
+        output: &'w mut BufWriter<W>,
+        opt_level: OptimizeLevel,
+        addr_size: u32,
+    ) -> RiscvCodeGen<'w, W> {
+        debug_assert!(addr_size == 32 || addr_size == 64);
+        RiscvCodeGen {
+            cfg_ir,
+            output,
+            opt_level,
+            addr_size,
+            float_consts: HashMap::new(),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), RccError> {
+        self.gen_read_only_local_str()?;
+        self.gen_vtables()?;
+        self.gen_float_consts()?;
+        self.gen_functions()?;
+        self.gen_panic_routines()?;
+        Ok(())
+    }
+
+    /// Failed bounds checks exit with code 101, which is the exit code of a panicking Rust program.
+    fn gen_panic_routines(&mut self) -> Result<(), RccError> {
+        let calls_panic = self.cfg_ir.cfgs.iter().any(|cfg| {
+            cfg.iter_inst().any(|inst| {
+                matches!(inst, IRInst::Call { callee: Operand::FnLabel(f), .. } if f == PANIC_BOUNDS_CHECK)
+            })
+        });
+        if calls_panic {
+            writeln!(self.output, "{}:", PANIC_BOUNDS_CHECK)?;
+            writeln!(self.output, "\tli\ta0,101")?;
+            writeln!(self.output, "\tcall\texit")?;
+        }
+        Ok(())
+    }
+
+    fn gen_read_only_local_str(&mut self) -> Result<(), RccError> {
+        if !self.cfg_ir.ro_local_strs.is_empty() {
+            writeln!(self.output, "\t.text")?;
+            writeln!(self.output, "\t.section\t.rodata")?;
+            for s in self.cfg_ir.ro_local_strs.iter() {
+                writeln!(self.output, "{}:", s.0)?;
+                writeln!(self.output, "\t.string \"{}\"", s.1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Each entry of a vtable is the address of a function.
+    fn gen_vtables(&mut self) -> Result<(), RccError> {
+        if !self.cfg_ir.vtables.is_empty() {
+            let (align, directive) = if self.addr_size == 64 {
+                (3, "dword")
+            } else {
+                (2, "word")
+            };
+            writeln!(self.output, "\t.section\t.rodata")?;
+            writeln!(self.output, "\t.align\t{}", align)?;
+            for (label, fns) in self.cfg_ir.vtables.iter() {
+                writeln!(self.output, "{}:", label)?;
+                for f in fns {
+                    writeln!(self.output, "\t.{}\t{}", directive, f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Float immediates can not be encoded in instructions, so they are loaded from `.rodata`.
+    fn gen_float_consts(&mut self) -> Result<(), RccError> {
+        for cfg in self.cfg_ir.cfgs.iter() {
+            for inst in cfg.iter_inst() {
+                for operand in inst.src_operands() {
+                    let key = match float_const_key(operand) {
+                        Some(key) if !self.float_consts.contains_key(&key) => key,
+                        _ => continue,
+                    };
+                    let label = format!(".LF{}", self.float_consts.len());
+                    if self.float_consts.is_empty() {
+                        writeln!(self.output, "\t.section\t.rodata")?;
+                    }
+                    let (size, bits) = key;
+                    writeln!(self.output, "\t.align\t{}", size.trailing_zeros())?;
+                    writeln!(self.output, "{}:", label)?;
+                    // little endian
+                    writeln!(self.output, "\t.word\t{}", bits as u32)?;
+                    if size == 8 {
+                        writeln!(self.output, "\t.word\t{}", (bits >> 32) as u32)?;
+                    }
+                    self.float_consts.insert(key, label);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn gen_functions(&mut self) -> Result<(), RccError> {
+        writeln!(self.output, "\t.text")?;
+        for cfg in self.cfg_ir.cfgs.iter() {
+            let mut func_gen = FuncCodeGen::new(
+                cfg,
+                self.output,
+                self.opt_level,
+                self.addr_size,
+                &self.float_consts,
+            );
+            func_gen.gen_function()?;
+        }
+        Ok(())
+    }
+}
+
+struct FuncCodeGen<'w: 'codegen, 'codegen, W: Write> {
+    cfg: &'codegen CFG,
+    output: &'w mut BufWriter<W>,
+    allocator: Box<dyn Allocator + 'codegen>,
+    frame_size: u32,
+    addr_size: u32,
+    float_consts: &'codegen FloatConsts,
+}
+
+impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
+    fn new(
+        cfg: &'codegen CFG,
+        output: &'w mut BufWriter<W>,
+        opt_level: OptimizeLevel,
+        addr_size: u32,
+        float_consts: &'codegen FloatConsts,
+    ) -> FuncCodeGen<'w, 'codegen, W> {
+        let allocator = create_allocator(opt_level, cfg, addr_size);
+        // the outgoing arguments are at the bottom of the frame, and sp is aligned to 16 bytes
+        let frame_size = (allocator.get_frame_size() + outgoing_args_size(cfg, addr_size))
+            .next_multiple_of(16);
+        FuncCodeGen {
+            cfg,
+            output,
+            allocator,
+            frame_size,
+            addr_size,
+            float_consts,
+        }
+    }
+
+    /// # Calling convention and stack frame of RC
+    ///
+    /// [calling convention of minidecaf]: https://decaf-lang.github.io/minidecaf-tutorial/docs/lab9/calling.html
+    /// [RISC-V ELF psABI specification]: https://github.com/riscv/riscv-elf-psabi-doc/blob/master/riscv-elf.md
+    /// [Volume I: RISC-V User-Level ISA V2.1draft]: https://riscv.org/wp-content/uploads/2015/01/riscv-calling.pdf
+    /// [RICS-V ISA Specifications]: https://riscv.org/technical/specifications/
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// fn foo(arg0: i32, arg1: i32, arg2: i32, arg3: i32,
+    ///        arg4: i32, arg5: i32, arg6: i32, arg7: i32,
+    ///        arg8: i32, arg9: i32) {
+    /// }
+    /// a0: arg0, a1: arg1, ...
+    ///
+    ///
+    /// High Address
+    ///
+    /// |  ...   |
+    /// +--------+     |
+    /// |  arg9  |     |-- stack frame of foo's caller
+    /// +--------+     |
+    /// |  arg8  |     |
+    /// +--------+ <---- fp(s0)
+    /// |   ra   |     |
+    /// +--------+     |
+    /// | old fp |     |-- stack frame of function foo
+    /// +--------+     |
+    /// |  arg0  |     |
+    /// |  arg1  |     |
+    /// |  ...   |     |
+    /// |  arg7  |     |
+    /// +--------+     |
+    /// | callee |     |
+    /// | saved  |     |
+    /// |(s1-s11)|     |
+    /// +--------+     |
+    /// | locals |     |
+    /// +--------+     |
+    /// |outgoing|     |
+    /// |  args  |     |
+    /// +--------+ <---- sp
+    ///
+    /// Low Address
+    /// ```
+    ///
+    /// The arguments are assigned to registers and the stack by `ArgAssigner`.
+    fn gen_function(&mut self) -> Result<(), RccError> {
+        if self.cfg.func_is_global {
+            writeln!(self.output, "\t.globl  {}", self.cfg.func_name)?;
+        }
+        writeln!(self.output, "{}:", self.cfg.func_name)?;
+        if !self.cfg.basic_blocks.is_empty() {
+            self.gen_function_entry()?;
+            self.gen_save_args()?;
+            self.gen_instructions()?;
+            self.gen_exit_function()?;
+        }
+        writeln!(self.output, "\tret")?;
+        Ok(())
+    }
+
+    fn gen_function_entry(&mut self) -> Result<(), RccError> {
+        debug_assert!(self.frame_size >= 8);
+        let (_, store) = reg_insts(self.addr_size);
+        let frame_size = self.frame_size as i32;
+        // set sp
+        self.add_imm("sp", "sp", -frame_size)?;
+        if !self.cfg.is_leaf_on(self.addr_size) {
+            // save ra
+            let offset = self.allocator.get_fp_offset(RA, &IRType::Addr);
+            debug_assert_eq!(self.addr_size / 8, offset);
+            let mem = self.mem("sp", frame_size - offset as i32)?;
+            writeln!(self.output, "\t{}\tra,{}", store, mem)?;
+        }
+        // save old fp(s0)
+        let offset = self.allocator.get_fp_offset(FP, &IRType::Addr);
+        let mem = self.mem("sp", frame_size - offset as i32)?;
+        writeln!(self.output, "\t{}\ts0,{}", store, mem)?;
+        // set fp
+        self.add_imm("s0", "sp", frame_size)?;
+        // save callee saved registers
+        for reg in self.allocator.get_saved_regs() {
+            let offset = self.allocator.get_fp_offset(reg, &IRType::Addr);
+            let mem = self.mem("s0", -(offset as i32))?;
+            writeln!(self.output, "\t{}\t{},{}", store, reg, mem)?;
+        }
+        Ok(())
+    }
+
+    fn gen_exit_function(&mut self) -> Result<(), RccError> {
+        let (load, _) = reg_insts(self.addr_size);
+        let frame_size = self.frame_size as i32;
+        // restore callee saved registers
+        for reg in self.allocator.get_saved_regs() {
+            let offset = self.allocator.get_fp_offset(reg, &IRType::Addr);
+            let mem = self.mem("s0", -(offset as i32))?;
+            writeln!(self.output, "\t{}\t{},{}", load, reg, mem)?;
+        }
+        if !self.cfg.is_leaf_on(self.addr_size) {
+            // restore ra
+            let offset = self.allocator.get_fp_offset(RA, &IRType::Addr);
+            debug_assert_eq!(self.addr_size / 8, offset);
+            let mem = self.mem("sp", frame_size - offset as i32)?;
+            writeln!(self.output, "\t{}\tra,{}", load, mem)?;
+        }
+        // restore old fp
+        let offset = self.allocator.get_fp_offset(FP, &IRType::Addr);
+        let mem = self.mem("sp", frame_size - offset as i32)?;
+        writeln!(self.output, "\t{}\ts0,{}", load, mem)?;
+        // restore sp
+        self.add_imm("sp", "sp", frame_size)?;
+        Ok(())
+    }
+
+    /// The arguments in memory are saved first with the scratch registers t0 and t1,
+    /// then the ones allocated to registers, since t0-t5 may be allocated to them.
+    fn gen_save_args(&mut self) -> Result<(), RccError> {
+        let args: Vec<(String, IRType)> = (0..self.cfg.fn_args.len())
+            .map(|i| {
+                let arg_name = self.cfg.get_name_of_fn_arg(i).unwrap();
+                let (_, ir_type) = self.cfg.local_infos.get(&arg_name).unwrap();
+                (arg_name, ir_type.clone())
+            })
+            .collect();
+        let types: Vec<IRType> = args.iter().map(|(_, ir_type)| ir_type.clone()).collect();
+        let (passes, _) = assign_args(&types, self.addr_size);
+        let mut in_regs = vec![];
+        for ((arg_name, ir_type), pass) in args.iter().zip(passes) {
+            let size = ir_type.byte_size(self.addr_size);
+            match pass {
+                ArgPass::Whole(loc) => match self.allocator.get_location(arg_name, ir_type) {
+                    Location::Reg(reg) => in_regs.push((reg, loc)),
+                    Location::FpOffset(offset) => {
+                        let reg = self.arg_reg(loc, "t1")?;
+                        self.store_part(&reg, ir_type, size, -(offset as i32), "t0")?;
+                    }
+                },
+                ArgPass::Parts(parts) => {
+                    let offset = -(self.allocator.get_fp_offset(arg_name, ir_type) as i32);
+                    for (part_offset, part_type, loc) in parts {
+                        let reg = self.arg_reg(loc, "t1")?;
+                        let part_size = part_type.byte_size(self.addr_size).min(size - part_offset);
+                        self.store_part(&reg, &part_type, part_size, offset + part_offset as i32, "t0")?;
+                    }
+                }
+                ArgPass::ByRef(loc, _) => {
+                    let base = self.arg_reg(loc, "t1")?;
+                    let offset = -(self.allocator.get_fp_offset(arg_name, ir_type) as i32);
+                    self.copy_memory((&base, 0), ("s0", offset), ir_type, "t0")?;
+                }
+            }
+        }
+        let (load, _) = reg_insts(self.addr_size);
+        for (reg, loc) in in_regs {
+            match loc {
+                ArgLoc::Reg(i) => writeln!(self.output, "\tmv\t{},a{}", reg, i)?,
+                ArgLoc::Stack(offset) => {
+                    let mem = self.mem("s0", offset as i32)?;
+                    writeln!(self.output, "\t{}\t{},{}", load, reg, mem)?;
+                }
+                ArgLoc::FloatReg(_) => unreachable!("floats are never allocated to registers"),
+            }
+        }
+        Ok(())
+    }
+
+    /// The register holding the argument at `loc` in the callee,
+    /// which is loaded into `scratch` if it is on the stack of the caller.
+    fn arg_reg(&mut self, loc: ArgLoc, scratch: &str) -> Result<String, RccError> {
+        Ok(match loc {
+            ArgLoc::Reg(i) => format!("a{}", i),
+            ArgLoc::FloatReg(i) => format!("fa{}", i),
+            ArgLoc::Stack(offset) => {
+                let (load, _) = reg_insts(self.addr_size);
+                let mem = self.mem("s0", offset as i32)?;
+                writeln!(self.output, "\t{}\t{},{}", load, scratch, mem)?;
+                scratch.to_string()
+            }
+        })
+    }
+
+    /// Store `size` bytes of `reg` holding a part of `part_type` to `offset(s0)`.
+    fn store_part(
+        &mut self,
+        reg: &str,
+        part_type: &IRType,
+        size: u32,
+        offset: i32,
+        scratch: &str,
+    ) -> Result<(), RccError> {
+        if part_type.is_float() {
+            let (_, store, _) = float_insts(size);
+            let mem = self.mem("s0", offset)?;
+            writeln!(self.output, "\t{}\t{},{}", store, reg, mem)?;
+            Ok(())
+        } else {
+            self.store_bytes(reg, size, offset, scratch)
+        }
+    }
+
+    fn gen_instructions(&mut self) -> Result<(), RccError> {
+        let exit_label = branch_name(self.cfg.func_scope_id, self.cfg.func_instance, self.cfg.basic_blocks.len());
+        let mut jump_to_exit = false;
+        for bb in self.cfg.basic_blocks.iter() {
+            if !bb.predecessors.is_empty() {
+                writeln!(self.output, "{}:", branch_name(self.cfg.func_scope_id, self.cfg.func_instance, bb.id))?;
+            }
+            for inst in bb.instructions.iter() {
+                self.gen_instruction(inst)?;
+                if let IRInst::Ret(_) = inst {
+                    // return from the middle of the function
+                    if bb.id != self.cfg.basic_blocks.len() - 1 {
+                        writeln!(self.output, "\tj\t{}", exit_label)?;
+                        jump_to_exit = true;
+                    }
+                }
+            }
+        }
+        if jump_to_exit {
+            writeln!(self.output, "{}:", exit_label)?;
+        }
+        Ok(())
+    }
+
+    fn gen_instruction(&mut self, inst: &IRInst) -> Result<(), RccError> {
+        match inst {
+            // aggregates are returned like the first argument, in float registers
+            // or in a0 and a1
+            IRInst::Ret(o @ Operand::Place(p)) if p.ir_type.is_aggregate() => {
+                if let Some(parts) = float_ret_parts(&p.ir_type, self.addr_size) {
+                    for (part_offset, part_type, loc) in parts {
+                        let reg = self.arg_reg(loc, "a5")?;
+                        self.load_part(&reg, o, part_offset, &part_type)?;
+                    }
+                } else {
+                    let offset = self.allocator.get_fp_offset(&p.label, &p.ir_type) as i32;
+                    let (load, _) = reg_insts(self.addr_size);
+                    let xlen = self.addr_size / 8;
+                    let mem = self.mem("s0", -offset)?;
+                    writeln!(self.output, "\t{}\ta0,{}", load, mem)?;
+                    if p.ir_type.byte_size(self.addr_size) > xlen {
+                        let mem = self.mem("s0", -offset + xlen as i32)?;
+                        writeln!(self.output, "\t{}\ta1,{}", load, mem)?;
+                    }
+                }
+            }
+            IRInst::Ret(o) if o.is_float() => self.load_float("fa0", o)?,
+            IRInst::Ret(o) if is_double_word_operand(o, self.addr_size) => {
+                self.load_pair(("a0", "a1"), o)?;
+            }
+            IRInst::Ret(o) => self.load_data("a0", o)?,
+            IRInst::LoadData { dest, src } => match dest.kind {
+                VarKind::Local | VarKind::LocalMut if dest.ir_type.is_float() => {
+                    self.load_float("ft0", src)?;
+                    self.store_float(dest, "ft0")?;
+                }
+                VarKind::Local | VarKind::LocalMut
+                    if is_double_word(&dest.ir_type, self.addr_size) =>
+                {
+                    let pair = self.load_pair(("a4", "a5"), src)?;
+                    self.store_pair(&dest.label, &dest.ir_type, pair)?;
+                }
+                VarKind::Local | VarKind::LocalMut if dest.ir_type.is_aggregate() => {
+                    if let Operand::FnRetPlace(_) = src {
+                        let dest_offset =
+                            -(self.allocator.get_fp_offset(&dest.label, &dest.ir_type) as i32);
+                        let size = dest.ir_type.byte_size(self.addr_size);
+                        if let Some(parts) = float_ret_parts(&dest.ir_type, self.addr_size) {
+                            for (part_offset, part_type, loc) in parts {
+                                let reg = self.arg_reg(loc, "a5")?;
+                                let part_size = part_type.byte_size(self.addr_size);
+                                let offset = dest_offset + part_offset as i32;
+                                self.store_part(&reg, &part_type, part_size, offset, "a5")?;
+                            }
+                        } else {
+                            let xlen = self.addr_size / 8;
+                            self.store_bytes("a0", size.min(xlen), dest_offset, "a5")?;
+                            if size > xlen {
+                                let offset = dest_offset + xlen as i32;
+                                self.store_bytes("a1", size - xlen, offset, "a5")?;
+                            }
+                        }
+                    } else if let Operand::Place(src) = src {
+                        let src_offset = self.allocator.get_fp_offset(&src.label, &src.ir_type);
+                        let dest_offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
+                        self.copy_memory(
+                            ("s0", -(src_offset as i32)),
+                            ("s0", -(dest_offset as i32)),
+                            &dest.ir_type,
+                            "a5",
+                        )?;
+                    }
+                }
+                VarKind::Local | VarKind::LocalMut => {
+                    let reg = self.dest_reg(dest);
+                    self.load_data(reg, src)?;
+                    self.store_dest(dest, reg)?;
+                }
+                _ => unimplemented!(),
+            },
+            IRInst::BinOp {
+                op,
+                dest,
+                src1,
+                src2,
+            } => {
+                if src1.is_float() {
+                    self.float_bin_op(op, dest, src1, src2)?;
+                } else if is_double_word_operand(src1, self.addr_size) {
+                    self.double_word_bin_op(op, dest, src1, src2)?;
+                } else if src2.is_imm() {
+                    let reg1 = self.load_operand("a5", src1)?;
+                    self.bin_op_imm(op, dest, reg1, src2, is_signed_operand(src1))?;
+                } else {
+                    let reg1 = self.load_operand("a4", src1)?;
+                    let reg2 = self.load_operand("a5", src2)?;
+                    self.bin_op(op, dest, reg1, reg2, is_signed_operand(src1))?;
+                }
+            }
+            IRInst::Call { callee, args } => match callee {
+                Operand::FnLabel(fn_name) => {
+                    self.pass_fn_args(args)?;
+                    writeln!(self.output, "\tcall\t{}", fn_name)?;
+                }
+                // a0-a7 are never allocated to variables, so the function pointer
+                // is not clobbered by the arguments, and t0 is free after passing them.
+                callee @ Operand::Place(_) => {
+                    self.pass_fn_args(args)?;
+                    let reg = self.load_operand("t0", callee)?;
+                    writeln!(self.output, "\tjalr\t{}", reg)?;
+                }
+                _ => unreachable!(),
+            },
+            IRInst::Jump { label } => {
+                writeln!(self.output, "\tj\t{}", branch_name(self.cfg.func_scope_id, self.cfg.func_instance, *label))?;
+            }
+            IRInst::JumpIfCond {
+                cond,
+                src1,
+                src2,
+                label,
+            } if src1.is_float() => {
+                self.load_float("ft0", src1)?;
+                self.load_float("ft1", src2)?;
+                let (_, _, fmt) = float_insts(src1.byte_size(self.addr_size));
+                let (inst, rs1, rs2, branch) = match cond {
+                    Jump::JEq => ("feq", "ft0", "ft1", "bnez"),
+                    Jump::JNe => ("feq", "ft0", "ft1", "beqz"),
+                    Jump::JLt => ("flt", "ft0", "ft1", "bnez"),
+                    Jump::JGe => ("fle", "ft1", "ft0", "bnez"),
+                };
+                writeln!(self.output, "\t{}.{}\ta5,{},{}", inst, fmt, rs1, rs2)?;
+                writeln!(self.output, "\t{}\ta5,{}", branch, branch_name(self.cfg.func_scope_id, self.cfg.func_instance, *label))?;
+            }
+            IRInst::JumpIfCond {
+                cond,
+                src1,
+                src2,
+                label,
+            } if is_double_word_operand(src1, self.addr_size) => {
+                let a = self.load_pair(("a2", "a3"), src1)?;
+                let b = self.load_pair(("a4", "a5"), src2)?;
+                match cond {
+                    Jump::JEq | Jump::JNe => self.double_word_eq("a5", a, b)?,
+                    Jump::JLt | Jump::JGe => {
+                        self.double_word_lt("a5", a, b, is_signed_operand(src1))?
+                    }
+                }
+                let branch = match cond {
+                    Jump::JEq | Jump::JLt => "bnez",
+                    Jump::JNe | Jump::JGe => "beqz",
+                };
+                writeln!(self.output, "\t{}\ta5,{}", branch, branch_name(self.cfg.func_scope_id, self.cfg.func_instance, *label))?;
+            }
+            IRInst::JumpIfCond {
+                cond,
+                src1,
+                src2,
+                label,
+            } => {
+                let reg1 = self.load_operand("a4", src1)?;
+                let reg2 = self.load_operand("a5", src2)?;
+                let signed = is_signed_operand(src1);
+                let inst = match cond {
+                    Jump::JEq => "beq",
+                    Jump::JGe if signed => "ble",
+                    Jump::JGe => "bleu",
+                    Jump::JLt if signed => "bgt",
+                    Jump::JLt => "bgtu",
+                    Jump::JNe => "bne",
+                };
+                writeln!(self.output, "\t{}\t{},{},{}", inst, reg2, reg1, branch_name(self.cfg.func_scope_id, self.cfg.func_instance, *label))?;
+            }
+            IRInst::JumpIf { cond, label } => {
+                let reg = self.load_operand("a5", cond)?;
+                writeln!(self.output, "\tbnez\t{},{}", reg, branch_name(self.cfg.func_scope_id, self.cfg.func_instance, *label))?;
+            }
+            IRInst::JumpIfNot { cond, label } => {
+                let reg = self.load_operand("a5", cond)?;
+                writeln!(self.output, "\tbeqz\t{},{}", reg, branch_name(self.cfg.func_scope_id, self.cfg.func_instance, *label))?;
+            }
+            IRInst::LoadAddr { dest, symbol } => match symbol {
+                Operand::Place(p) if p.kind == VarKind::LitConst => {
+                    let rd = self.dest_reg(dest);
+                    writeln!(self.output, "\tla\t{},{}", rd, p.label)?;
+                    self.store_dest(dest, rd)?;
+                }
+                Operand::Place(p) => {
+                    let offset = self.allocator.get_fp_offset(&p.label, &p.ir_type);
+                    let rd = self.dest_reg(dest);
+                    self.add_imm(rd, "s0", -(offset as i32))?;
+                    self.store_dest(dest, rd)?;
+                }
+                _ => unimplemented!(),
+            },
+            IRInst::Load { dest, addr, offset } => {
+                let base = self.load_operand("a4", addr)?;
+                if dest.ir_type.is_aggregate() {
+                    let dest_offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
+                    self.copy_memory(
+                        (base, *offset as i32),
+                        ("s0", -(dest_offset as i32)),
+                        &dest.ir_type,
+                        "a5",
+                    )?;
+                } else if dest.ir_type.is_float() {
+                    let (load, _, _) = float_insts(dest.ir_type.byte_size(self.addr_size));
+                    let mem = self.mem(base, *offset as i32)?;
+                    writeln!(self.output, "\t{}\tft0,{}", load, mem)?;
+                    self.store_float(dest, "ft0")?;
+                } else if is_double_word(&dest.ir_type, self.addr_size) {
+                    let mem = self.mem(base, *offset as i32)?;
+                    writeln!(self.output, "\tlw\ta2,{}", mem)?;
+                    let mem = self.mem(base, *offset as i32 + 4)?;
+                    writeln!(self.output, "\tlw\ta3,{}", mem)?;
+                    self.store_pair(&dest.label, &dest.ir_type, ("a2", "a3"))?;
+                } else {
+                    let rd = self.dest_reg(dest);
+                    let inst = load_inst(&dest.ir_type, self.addr_size);
+                    let mem = self.mem(base, *offset as i32)?;
+                    writeln!(self.output, "\t{}\t{},{}", inst, rd, mem)?;
+                    self.store_dest(dest, rd)?;
+                }
+            }
+            IRInst::Store { addr, offset, src } => {
+                let base = self.load_operand("a4", addr)?;
+                match src {
+                    Operand::Place(p) if p.ir_type.is_aggregate() => {
+                        let src_offset = self.allocator.get_fp_offset(&p.label, &p.ir_type);
+                        self.copy_memory(
+                            ("s0", -(src_offset as i32)),
+                            (base, *offset as i32),
+                            &p.ir_type,
+                            "a5",
+                        )?;
+                    }
+                    Operand::Unit | Operand::Never => {}
+                    src if src.is_float() => {
+                        self.load_float("ft0", src)?;
+                        let (_, store, _) = float_insts(src.byte_size(self.addr_size));
+                        let mem = self.mem(base, *offset as i32)?;
+                        writeln!(self.output, "\t{}\tft0,{}", store, mem)?;
+                    }
+                    src if is_double_word_operand(src, self.addr_size) => {
+                        let (lo, hi) = self.load_pair(("a2", "a3"), src)?;
+                        let mem = self.mem(base, *offset as i32)?;
+                        writeln!(self.output, "\tsw\t{},{}", lo, mem)?;
+                        let mem = self.mem(base, *offset as i32 + 4)?;
+                        writeln!(self.output, "\tsw\t{},{}", hi, mem)?;
+                    }
+                    src => {
+                        let reg = self.load_operand("a5", src)?;
+                        let size = src.byte_size(self.addr_size);
+                        self.store_data(size, reg, *offset as i32, base)?;
+                    }
+                }
+            }
+            IRInst::Cast {
+                op: op @ (CastOp::IntToFloat | CastOp::FloatToInt | CastOp::FloatCast),
+                dest,
+                src,
+            } => self.float_cast(*op, dest, src)?,
+            IRInst::Cast { op, dest, src }
+                if is_double_word(&dest.ir_type, self.addr_size)
+                    || is_double_word_operand(src, self.addr_size) =>
+            {
+                self.double_word_cast(*op, dest, src)?;
+            }
+            IRInst::Cast { op, dest, src } => {
+                let src_type = match src {
+                    Operand::Place(p) => &p.ir_type,
+                    _ => unreachable!("constant casts are folded"),
+                };
+                let rs = self.load_operand("a5", src)?;
+                let rd = self.dest_reg(dest);
+                self.cast(*op, rd, rs, src_type, &dest.ir_type)?;
+                self.store_dest(dest, rd)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// An integer narrower than a register is extended by shifting it to the top
+    /// of the register and back, or masked with `andi` if it is an unsigned byte.
+    /// On riscv64, 32-bit integers are kept sign-extended whatever their signedness,
+    /// so they are truncated by `addiw` and only `u32` is extended by shifts.
+    fn cast(
+        &mut self,
+        op: CastOp,
+        rd: &str,
+        rs: &str,
+        src_type: &IRType,
+        dest_type: &IRType,
+    ) -> Result<(), RccError> {
+        let (bits, signed) = match op {
+            CastOp::SExt | CastOp::ZExt => (
+                src_type.byte_size(self.addr_size) * 8,
+                op == CastOp::SExt,
+            ),
+            CastOp::Trunc => (
+                dest_type.byte_size(self.addr_size) * 8,
+                dest_type.is_signed(),
+            ),
+            CastOp::IntToFloat | CastOp::FloatToInt | CastOp::FloatCast => {
+                unreachable!("float casts are generated by `float_cast`")
+            }
+        };
+        if bits >= self.addr_size || (bits == 32 && op == CastOp::SExt) {
+            if rd != rs {
+                writeln!(self.output, "\tmv\t{},{}", rd, rs)?;
+            }
+        } else if bits == 32 && op == CastOp::Trunc {
+            writeln!(self.output, "\taddiw\t{},{},0", rd, rs)?;
+        } else if bits == 8 && !signed {
+            writeln!(self.output, "\tandi\t{},{},255", rd, rs)?;
+        } else {
+            let shift = self.addr_size - bits;
+            let shr = if signed { "srai" } else { "srli" };
+            writeln!(self.output, "\tslli\t{},{},{}", rd, rs, shift)?;
+            writeln!(self.output, "\t{}\t{},{},{}", shr, rd, rd, shift)?;
+        }
+        Ok(())
+    }
+
+    /// Copy an aggregate from `src` to `dest`, both are (base register, offset).
+    /// The `scratch` register holds the data being copied.
+    fn copy_memory(
+        &mut self,
+        src: (&str, i32),
+        dest: (&str, i32),
+        ir_type: &IRType,
+        scratch: &str,
+    ) -> Result<(), RccError> {
+        let size = ir_type.byte_size(self.addr_size);
+        let (unit, load, store) = match ir_type.align(self.addr_size).min(self.addr_size / 8) {
+            1 => (1, "lbu", "sb"),
+            2 => (2, "lhu", "sh"),
+            4 => (4, "lw", "sw"),
+            _ => (8, "ld", "sd"),
+        };
+        for i in (0..size as i32).step_by(unit) {
+            let mem = self.mem(src.0, src.1 + i)?;
+            writeln!(self.output, "\t{}\t{},{}", load, scratch, mem)?;
+            let mem = self.mem(dest.0, dest.1 + i)?;
+            writeln!(self.output, "\t{}\t{},{}", store, scratch, mem)?;
+        }
+        Ok(())
+    }
+
+    /// The arguments on the stack are stored first with the scratch register a5,
+    /// then the floats are passed, since loading a float constant clobbers a5,
+    /// and the integer registers are set at last.
+    fn pass_fn_args(&mut self, args: &[Operand]) -> Result<(), RccError> {
+        let types: Vec<IRType> = args.iter().map(Operand::ir_type).collect();
+        let (passes, _) = assign_args(&types, self.addr_size);
+        let (_, store) = reg_insts(self.addr_size);
+        for (arg, pass) in args.iter().zip(passes.iter()) {
+            match pass {
+                ArgPass::Whole(ArgLoc::Stack(offset)) => {
+                    self.load_data("a5", arg)?;
+                    let mem = self.mem("sp", *offset as i32)?;
+                    writeln!(self.output, "\t{}\ta5,{}", store, mem)?;
+                }
+                ArgPass::Parts(parts) => {
+                    for (part_offset, part_type, loc) in parts {
+                        if let ArgLoc::Stack(offset) = loc {
+                            self.load_part("a5", arg, *part_offset, part_type)?;
+                            let mem = self.mem("sp", *offset as i32)?;
+                            writeln!(self.output, "\t{}\ta5,{}", store, mem)?;
+                        }
+                    }
+                }
+                // the callee may modify the argument passed by reference
+                ArgPass::ByRef(loc, copy) => {
+                    let src = match arg {
+                        Operand::Place(p) => self.allocator.get_fp_offset(&p.label, &p.ir_type),
+                        o => unreachable!("{:?} is not in memory", o),
+                    };
+                    self.copy_memory(("s0", -(src as i32)), ("sp", *copy as i32), &arg.ir_type(), "a5")?;
+                    if let ArgLoc::Stack(offset) = loc {
+                        self.add_imm("a5", "sp", *copy as i32)?;
+                        let mem = self.mem("sp", *offset as i32)?;
+                        writeln!(self.output, "\t{}\ta5,{}", store, mem)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (arg, pass) in args.iter().zip(passes.iter()) {
+            match pass {
+                ArgPass::Whole(ArgLoc::FloatReg(i)) => self.load_float(&format!("fa{}", i), arg)?,
+                ArgPass::Parts(parts) => {
+                    for (part_offset, part_type, loc) in parts {
+                        if let ArgLoc::FloatReg(i) = loc {
+                            self.load_part(&format!("fa{}", i), arg, *part_offset, part_type)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (arg, pass) in args.iter().zip(passes.iter()) {
+            match pass {
+                ArgPass::Whole(ArgLoc::Reg(i)) => self.load_data(&format!("a{}", i), arg)?,
+                ArgPass::Parts(parts) => {
+                    for (part_offset, part_type, loc) in parts {
+                        if let ArgLoc::Reg(i) = loc {
+                            self.load_part(&format!("a{}", i), arg, *part_offset, part_type)?;
+                        }
+                    }
+                }
+                ArgPass::ByRef(ArgLoc::Reg(i), copy) => {
+                    self.add_imm(&format!("a{}", i), "sp", *copy as i32)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the part of `part_type` at `offset` of an argument into `reg`,
+    /// which is a word of a 64-bit integer or a float, or a field of an aggregate.
+    /// The address of a float constant is loaded into `reg` first.
+    fn load_part(
+        &mut self,
+        reg: &str,
+        operand: &Operand,
+        offset: u32,
+        part_type: &IRType,
+    ) -> Result<(), RccError> {
+        let load = if part_type.is_float() {
+            float_insts(part_type.byte_size(self.addr_size)).0
+        } else {
+            load_inst(part_type, self.addr_size)
+        };
+        if let Some(key) = float_const_key(operand) {
+            writeln!(self.output, "\tla\t{},{}", reg, self.float_consts[&key])?;
+            writeln!(self.output, "\t{}\t{},{}({})", load, reg, offset, reg)?;
+            return Ok(());
+        }
+        match self.asm_operand(operand) {
+            AsmOperand::Imm64(lo, hi) => {
+                let word = if offset == 0 { lo } else { hi };
+                writeln!(self.output, "\tli\t{},{}", reg, word)?;
+            }
+            AsmOperand::FpOffset(fp_offset) => {
+                let mem = self.mem("s0", offset as i32 - fp_offset as i32)?;
+                writeln!(self.output, "\t{}\t{},{}", load, reg, mem)?;
+            }
+            asm_operand => unreachable!("{:?} is not in memory", asm_operand),
+        }
+        Ok(())
+    }
+
+    /// Floats are never allocated to registers, and float constants are loaded
+    /// from `.rodata` by the address in a5.
+    fn load_float(&mut self, freg: &str, operand: &Operand) -> Result<(), RccError> {
+        let (load, _, fmt) = float_insts(operand.byte_size(self.addr_size));
+        match operand {
+            Operand::F32(_) | Operand::F64(_) => {
+                let label = &self.float_consts[&float_const_key(operand).unwrap()];
+                writeln!(self.output, "\tla\ta5,{}", label)?;
+                writeln!(self.output, "\t{}\t{},0(a5)", load, freg)?;
+            }
+            Operand::Place(p) => {
+                let offset = self.allocator.get_fp_offset(&p.label, &p.ir_type);
+                let mem = self.mem("s0", -(offset as i32))?;
+                writeln!(self.output, "\t{}\t{},{}", load, freg, mem)?;
+            }
+            Operand::FnRetPlace(_) => {
+                if freg != "fa0" {
+                    writeln!(self.output, "\tfmv.{}\t{},fa0", fmt, freg)?;
+                }
+            }
+            o => unreachable!("{:?} is not a float", o),
+        }
+        Ok(())
+    }
+
+    fn store_float(&mut self, dest: &Place, freg: &str) -> Result<(), RccError> {
+        let offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
+        let (_, store, _) = float_insts(dest.ir_type.byte_size(self.addr_size));
+        let mem = self.mem("s0", -(offset as i32))?;
+        writeln!(self.output, "\t{}\t{},{}", store, freg, mem)?;
+        Ok(())
+    }
+
+    /// Comparisons of floats set the integer `dest` by `flt`, `fle` and `feq`.
+    fn float_bin_op(
+        &mut self,
+        op: &BinOperator,
+        dest: &Place,
+        src1: &Operand,
+        src2: &Operand,
+    ) -> Result<(), RccError> {
+        self.load_float("ft0", src1)?;
+        self.load_float("ft1", src2)?;
+        let (_, _, fmt) = float_insts(src1.byte_size(self.addr_size));
+        let (inst, rs1, rs2) = match op {
+            BinOperator::Plus => ("fadd", "ft0", "ft1"),
+            BinOperator::Minus => ("fsub", "ft0", "ft1"),
+            BinOperator::Star => ("fmul", "ft0", "ft1"),
+            BinOperator::Slash => ("fdiv", "ft0", "ft1"),
+            BinOperator::Lt => ("flt", "ft0", "ft1"),
+            BinOperator::Le => ("fle", "ft0", "ft1"),
+            BinOperator::Gt => ("flt", "ft1", "ft0"),
+            BinOperator::Ge => ("fle", "ft1", "ft0"),
+            BinOperator::EqEq | BinOperator::Ne => ("feq", "ft0", "ft1"),
+            _ => unimplemented!("{:?} of floats", op),
+        };
+        if dest.ir_type.is_float() {
+            writeln!(self.output, "\t{}.{}\tft0,{},{}", inst, fmt, rs1, rs2)?;
+            self.store_float(dest, "ft0")
+        } else {
+            let rd = self.dest_reg(dest);
+            writeln!(self.output, "\t{}.{}\t{},{},{}", inst, fmt, rd, rs1, rs2)?;
+            if *op == BinOperator::Ne {
+                writeln!(self.output, "\txori\t{},{},1", rd, rd)?;
+            }
+            self.store_dest(dest, rd)
+        }
+    }
+
+    /// Floats are converted to integers rounding towards zero like Rust,
+    /// but the results narrower than 32 bits are truncated instead of saturated.
+    /// Conversions of 64-bit integers call the helpers of libgcc on riscv32, e.g. `__floatdidf`,
+    /// and use `fcvt.d.l` and the like on riscv64.
+    fn float_cast(&mut self, op: CastOp, dest: &Place, src: &Operand) -> Result<(), RccError> {
+        let src_size = src.byte_size(self.addr_size);
+        let dest_size = dest.ir_type.byte_size(self.addr_size);
+        let xlen = self.addr_size / 8;
+        match op {
+            CastOp::IntToFloat => {
+                let (_, _, fmt) = float_insts(dest_size);
+                let signed = is_signed_operand(src);
+                if src_size > xlen {
+                    self.load_pair(("a0", "a1"), src)?;
+                    let un = if signed { "" } else { "un" };
+                    writeln!(self.output, "\tcall\t__float{}di{}f", un, fmt)?;
+                    return self.store_float(dest, "fa0");
+                }
+                let rs = self.load_operand("a5", src)?;
+                let int_fmt = int_fmt(src_size, signed);
+                writeln!(self.output, "\tfcvt.{}.{}\tft0,{}", fmt, int_fmt, rs)?;
+                self.store_float(dest, "ft0")
+            }
+            CastOp::FloatToInt => {
+                let (_, _, fmt) = float_insts(src_size);
+                if dest_size > xlen {
+                    self.load_float("fa0", src)?;
+                    let uns = if dest.ir_type.is_signed() { "" } else { "uns" };
+                    writeln!(self.output, "\tcall\t__fix{}{}fdi", uns, fmt)?;
+                    return self.store_pair(&dest.label, &dest.ir_type, ("a0", "a1"));
+                }
+                self.load_float("ft0", src)?;
+                let int_fmt = int_fmt(dest_size, dest.ir_type.is_signed());
+                let rd = self.dest_reg(dest);
+                // `fcvt` only saturates to 32 bits, so narrower integers are clamped
+                // to their range first, and NaN becomes 0.
+                let narrow_bounds = match dest.ir_type {
+                    IRType::I8 => Some((i8::MIN as i32, i8::MAX as i32)),
+                    IRType::U8 => Some((0, u8::MAX as i32)),
+                    IRType::I16 => Some((i16::MIN as i32, i16::MAX as i32)),
+                    IRType::U16 => Some((0, u16::MAX as i32)),
+                    _ => None,
+                };
+                if let Some((min, max)) = narrow_bounds {
+                    writeln!(self.output, "\tfeq.{}\ta6,ft0,ft0", fmt)?;
+                    writeln!(self.output, "\tli\ta7,{}", min)?;
+                    writeln!(self.output, "\tfcvt.{}.w\tft1,a7", fmt)?;
+                    writeln!(self.output, "\tfmax.{}\tft0,ft0,ft1", fmt)?;
+                    writeln!(self.output, "\tli\ta7,{}", max)?;
+                    writeln!(self.output, "\tfcvt.{}.w\tft1,a7", fmt)?;
+                    writeln!(self.output, "\tfmin.{}\tft0,ft0,ft1", fmt)?;
+                }
+                writeln!(self.output, "\tfcvt.{}.{}\t{},ft0,rtz", int_fmt, fmt, rd)?;
+                if narrow_bounds.is_some() {
+                    writeln!(self.output, "\tneg\ta6,a6")?;
+                    writeln!(self.output, "\tand\t{},{},a6", rd, rd)?;
+                }
+                self.store_dest(dest, rd)
+            }
+            CastOp::FloatCast => {
+                self.load_float("ft0", src)?;
+                let (_, _, src_fmt) = float_insts(src_size);
+                let (_, _, fmt) = float_insts(dest_size);
+                writeln!(self.output, "\tfcvt.{}.{}\tft0,ft0", fmt, src_fmt)?;
+                self.store_float(dest, "ft0")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Return the registers holding the low and high words of a 64-bit integer,
+    /// which is loaded into the `scratch` pair unless it is returned in a0 and a1.
+    fn load_pair<'r>(
+        &mut self,
+        scratch: (&'r str, &'r str),
+        operand: &Operand,
+    ) -> Result<(&'r str, &'r str), RccError> {
+        match self.asm_operand(operand) {
+            AsmOperand::Imm64(lo, hi) => {
+                writeln!(self.output, "\tli\t{},{}", scratch.0, lo)?;
+                writeln!(self.output, "\tli\t{},{}", scratch.1, hi)?;
+            }
+            AsmOperand::FpOffset(offset) => {
+                let mem = self.mem("s0", -(offset as i32))?;
+                writeln!(self.output, "\tlw\t{},{}", scratch.0, mem)?;
+                let mem = self.mem("s0", -(offset as i32) + 4)?;
+                writeln!(self.output, "\tlw\t{},{}", scratch.1, mem)?;
+            }
+            AsmOperand::FnRet(_) => return Ok(("a0", "a1")),
+            asm_operand => unreachable!("{:?} is not a 64-bit integer", asm_operand),
+        }
+        Ok(scratch)
+    }
+
+    /// 64-bit integers are never allocated to registers.
+    fn store_pair(&mut self, label: &str, ir_type: &IRType, pair: (&str, &str)) -> Result<(), RccError> {
+        let offset = -(self.allocator.get_fp_offset(label, ir_type) as i32);
+        let mem = self.mem("s0", offset)?;
+        writeln!(self.output, "\tsw\t{},{}", pair.0, mem)?;
+        let mem = self.mem("s0", offset + 4)?;
+        writeln!(self.output, "\tsw\t{},{}", pair.1, mem)?;
+        Ok(())
+    }
+
+    /// `src1` is loaded into a2 and a3, `src2` into a4 and a5, and a6 and a7 hold
+    /// the intermediate results. Multiplications, divisions and remainders call
+    /// the helpers of libgcc, which take the arguments in a0-a3.
+    fn double_word_bin_op(
+        &mut self,
+        op: &BinOperator,
+        dest: &Place,
+        src1: &Operand,
+        src2: &Operand,
+    ) -> Result<(), RccError> {
+        let signed = is_signed_operand(src1);
+        if let BinOperator::Star | BinOperator::Slash | BinOperator::Percent = op {
+            self.load_pair(("a0", "a1"), src1)?;
+            self.load_pair(("a2", "a3"), src2)?;
+            let helper = match (op, signed) {
+                (BinOperator::Star, _) => "__muldi3",
+                (BinOperator::Slash, true) => "__divdi3",
+                (BinOperator::Slash, false) => "__udivdi3",
+                (BinOperator::Percent, true) => "__moddi3",
+                _ => "__umoddi3",
+            };
+            writeln!(self.output, "\tcall\t{}", helper)?;
+            return self.store_pair(&dest.label, &dest.ir_type, ("a0", "a1"));
+        }
+        let (lo1, hi1) = self.load_pair(("a2", "a3"), src1)?;
+        if let BinOperator::Shl | BinOperator::Shr = op {
+            self.double_word_shift(op, (lo1, hi1), src2, signed)?;
+            return self.store_pair(&dest.label, &dest.ir_type, ("a2", "a3"));
+        }
+        let (lo2, hi2) = self.load_pair(("a4", "a5"), src2)?;
+        let comparison = match op {
+            BinOperator::Lt => Some((false, (lo1, hi1), (lo2, hi2))),
+            BinOperator::Ge => Some((true, (lo1, hi1), (lo2, hi2))),
+            BinOperator::Gt => Some((false, (lo2, hi2), (lo1, hi1))),
+            BinOperator::Le => Some((true, (lo2, hi2), (lo1, hi1))),
+            _ => None,
+        };
+        if let Some((negate, a, b)) = comparison {
+            let rd = self.dest_reg(dest);
+            self.double_word_lt(rd, a, b, signed)?;
+            if negate {
+                writeln!(self.output, "\txori\t{},{},1", rd, rd)?;
+            }
+            return self.store_dest(dest, rd);
+        }
+        if let BinOperator::EqEq | BinOperator::Ne = op {
+            let rd = self.dest_reg(dest);
+            self.double_word_eq(rd, (lo1, hi1), (lo2, hi2))?;
+            if *op == BinOperator::Ne {
+                writeln!(self.output, "\txori\t{},{},1", rd, rd)?;
+            }
+            return self.store_dest(dest, rd);
+        }
+        match op {
+            BinOperator::Plus => {
+                // carry out of the low word
+                writeln!(self.output, "\tadd\ta2,{},{}", lo1, lo2)?;
+                writeln!(self.output, "\tsltu\ta6,a2,{}", lo2)?;
+                writeln!(self.output, "\tadd\ta3,{},{}", hi1, hi2)?;
+                writeln!(self.output, "\tadd\ta3,a3,a6")?;
+            }
+            BinOperator::Minus => {
+                // borrow of the low word
+                writeln!(self.output, "\tsltu\ta6,{},{}", lo1, lo2)?;
+                writeln!(self.output, "\tsub\ta2,{},{}", lo1, lo2)?;
+                writeln!(self.output, "\tsub\ta3,{},{}", hi1, hi2)?;
+                writeln!(self.output, "\tsub\ta3,a3,a6")?;
+            }
+            BinOperator::And | BinOperator::Or | BinOperator::Caret => {
+                let inst = match op {
+                    BinOperator::And => "and",
+                    BinOperator::Or => "or",
+                    _ => "xor",
+                };
+                writeln!(self.output, "\t{}\ta2,{},{}", inst, lo1, lo2)?;
+                writeln!(self.output, "\t{}\ta3,{},{}", inst, hi1, hi2)?;
+            }
+            _ => unimplemented!("{:?} of 64-bit integers", op),
+        }
+        self.store_pair(&dest.label, &dest.ir_type, ("a2", "a3"))
+    }
+
+    /// Shift the pair `(lo, hi)` into a2 and a3. Shifts by a variable amount
+    /// shift both words by the amount mod 32, then select the words by the bit 5
+    /// of the amount with a mask, so that no branch is needed.
+    fn double_word_shift(
+        &mut self,
+        op: &BinOperator,
+        (lo, hi): (&str, &str),
+        amount: &Operand,
+        signed: bool,
+    ) -> Result<(), RccError> {
+        let sr = if signed { "sra" } else { "srl" };
+        let imm = match self.asm_operand(amount) {
+            AsmOperand::Imm(imm) | AsmOperand::Imm64(imm, _) => Some(imm.parse::<u32>().unwrap() & 63),
+            _ => None,
+        };
+        match (op, imm) {
+            (_, Some(0)) => {
+                writeln!(self.output, "\tmv\ta2,{}", lo)?;
+                writeln!(self.output, "\tmv\ta3,{}", hi)?;
+            }
+            (BinOperator::Shl, Some(n)) if n < 32 => {
+                writeln!(self.output, "\tsrli\ta6,{},{}", lo, 32 - n)?;
+                writeln!(self.output, "\tslli\ta3,{},{}", hi, n)?;
+                writeln!(self.output, "\tor\ta3,a3,a6")?;
+                writeln!(self.output, "\tslli\ta2,{},{}", lo, n)?;
+            }
+            (BinOperator::Shl, Some(n)) => {
+                writeln!(self.output, "\tslli\ta3,{},{}", lo, n - 32)?;
+                writeln!(self.output, "\tli\ta2,0")?;
+            }
+            (_, Some(n)) if n < 32 => {
+                writeln!(self.output, "\tslli\ta6,{},{}", hi, 32 - n)?;
+                writeln!(self.output, "\tsrli\ta2,{},{}", lo, n)?;
+                writeln!(self.output, "\tor\ta2,a2,a6")?;
+                writeln!(self.output, "\t{}i\ta3,{},{}", sr, hi, n)?;
+            }
+            (_, Some(n)) => {
+                writeln!(self.output, "\t{}i\ta2,{},{}", sr, hi, n - 32)?;
+                if signed {
+                    writeln!(self.output, "\tsrai\ta3,{},31", hi)?;
+                } else {
+                    writeln!(self.output, "\tli\ta3,0")?;
+                }
+            }
+            (_, None) => {
+                let n = if is_double_word_operand(amount, self.addr_size) {
+                    self.load_pair(("a4", "a5"), amount)?.0
+                } else {
+                    self.load_operand("a4", amount)?
+                };
+                // the words shifted by `n mod 32` are in (a2, a3) for Shl, (a3, a2) for Shr
+                let (near, far) = if *op == BinOperator::Shl {
+                    writeln!(self.output, "\tsll\ta3,{},{}", hi, n)?;
+                    writeln!(self.output, "\tsrli\ta6,{},1", lo)?;
+                    writeln!(self.output, "\txori\ta7,{},31", n)?;
+                    writeln!(self.output, "\tsrl\ta6,a6,a7")?;
+                    writeln!(self.output, "\tor\ta3,a3,a6")?;
+                    writeln!(self.output, "\tsll\ta2,{},{}", lo, n)?;
+                    ("a2", "a3")
+                } else {
+                    writeln!(self.output, "\tsrl\ta2,{},{}", lo, n)?;
+                    writeln!(self.output, "\tslli\ta6,{},1", hi)?;
+                    writeln!(self.output, "\txori\ta7,{},31", n)?;
+                    writeln!(self.output, "\tsll\ta6,a6,a7")?;
+                    writeln!(self.output, "\tor\ta2,a2,a6")?;
+                    writeln!(self.output, "\t{}\ta3,{},{}", sr, hi, n)?;
+                    ("a3", "a2")
+                };
+                // a6 = -1 if n >= 32, then far = near and near = 0 (or the sign of sra)
+                writeln!(self.output, "\tslli\ta6,{},26", n)?;
+                writeln!(self.output, "\tsrai\ta6,a6,31")?;
+                writeln!(self.output, "\txor\ta7,{},{}", far, near)?;
+                writeln!(self.output, "\tand\ta7,a7,a6")?;
+                writeln!(self.output, "\txor\t{},{},a7", far, far)?;
+                if *op == BinOperator::Shr && signed {
+                    writeln!(self.output, "\tsrai\ta7,{},31", near)?;
+                    writeln!(self.output, "\txor\ta7,a7,{}", near)?;
+                    writeln!(self.output, "\tand\ta7,a7,a6")?;
+                    writeln!(self.output, "\txor\t{},{},a7", near, near)?;
+                } else {
+                    writeln!(self.output, "\tnot\ta6,a6")?;
+                    writeln!(self.output, "\tand\t{},{},a6", near, near)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// rd = a == b
+    fn double_word_eq(&mut self, rd: &str, a: (&str, &str), b: (&str, &str)) -> Result<(), RccError> {
+        writeln!(self.output, "\txor\ta6,{},{}", a.0, b.0)?;
+        writeln!(self.output, "\txor\ta7,{},{}", a.1, b.1)?;
+        writeln!(self.output, "\tor\ta6,a6,a7")?;
+        writeln!(self.output, "\tseqz\t{},a6", rd)?;
+        Ok(())
+    }
+
+    /// rd = a < b, the high words are compared by the signedness,
+    /// and the low words are compared unsigned if the high words are equal.
+    fn double_word_lt(
+        &mut self,
+        rd: &str,
+        a: (&str, &str),
+        b: (&str, &str),
+        signed: bool,
+    ) -> Result<(), RccError> {
+        let slt = if signed { "slt" } else { "sltu" };
+        writeln!(self.output, "\tsltu\ta6,{},{}", a.0, b.0)?;
+        writeln!(self.output, "\txor\ta7,{},{}", a.1, b.1)?;
+        writeln!(self.output, "\tseqz\ta7,a7")?;
+        writeln!(self.output, "\tand\ta6,a6,a7")?;
+        writeln!(self.output, "\t{}\ta7,{},{}", slt, a.1, b.1)?;
+        writeln!(self.output, "\tor\t{},a7,a6", rd)?;
+        Ok(())
+    }
+
+    /// Narrower integers are extended into the high word by their signedness,
+    /// and the low word is truncated like a 32-bit integer.
+    fn double_word_cast(&mut self, op: CastOp, dest: &Place, src: &Operand) -> Result<(), RccError> {
+        match op {
+            CastOp::SExt => {
+                let rs = self.load_operand("a4", src)?;
+                writeln!(self.output, "\tsrai\ta5,{},31", rs)?;
+                self.store_pair(&dest.label, &dest.ir_type, (rs, "a5"))
+            }
+            CastOp::ZExt => {
+                let rs = self.load_operand("a4", src)?;
+                self.store_pair(&dest.label, &dest.ir_type, (rs, "zero"))
+            }
+            CastOp::Trunc => {
+                let (lo, _) = self.load_pair(("a4", "a5"), src)?;
+                let rd = self.dest_reg(dest);
+                self.cast(CastOp::Trunc, rd, lo, &IRType::I64, &dest.ir_type)?;
+                self.store_dest(dest, rd)
+            }
+            _ => unreachable!("float casts are generated by `float_cast`"),
+        }
+    }
+
+    /// The memory operand `offset(base)` of loads and stores. The offset out of
+    /// the 12-bit immediate is added to `base` in t6 first.
+    fn mem(&mut self, base: &str, offset: i32) -> Result<String, RccError> {
+        if is_imm12(offset) {
+            Ok(format!("{}({})", offset, base))
+        } else {
+            writeln!(self.output, "\tli\t{},{}", OFFSET_SCRATCH, offset)?;
+            writeln!(self.output, "\tadd\t{},{},{}", OFFSET_SCRATCH, base, OFFSET_SCRATCH)?;
+            Ok(format!("0({})", OFFSET_SCRATCH))
+        }
+    }
+
+    /// `rd = rs + imm`, the `imm` out of the 12-bit immediate is loaded into t6 first.
+    fn add_imm(&mut self, rd: &str, rs: &str, imm: i32) -> Result<(), RccError> {
+        if is_imm12(imm) {
+            writeln!(self.output, "\taddi\t{},{},{}", rd, rs, imm)?;
+        } else {
+            writeln!(self.output, "\tli\t{},{}", OFFSET_SCRATCH, imm)?;
+            writeln!(self.output, "\tadd\t{},{},{}", rd, rs, OFFSET_SCRATCH)?;
+        }
+        Ok(())
+    }
+
+    /// Return the register holding the operand.
+    /// The operand is loaded into `scratch_reg` if it is not allocated to a register.
+    fn load_operand<'r>(&mut self, scratch_reg: &'r str, operand: &Operand) -> Result<&'r str, RccError> {
+        if let Operand::Place(p) = operand {
+            if let VarKind::Local | VarKind::LocalMut = p.kind {
+                if let Location::Reg(reg) = self.allocator.get_location(&p.label, &p.ir_type) {
+                    return Ok(reg);
+                }
+            }
+        }
+        self.load_data(scratch_reg, operand)?;
+        Ok(scratch_reg)
+    }
+
+    /// Immediates of `u32` are sign-extended on riscv64 like the values of them in registers,
+    /// and 64-bit immediates are not split into words.
+    fn asm_operand(&mut self, operand: &Operand) -> AsmOperand {
+        if self.addr_size == 64 {
+            match operand {
+                Operand::U32(i) => return AsmOperand::Imm((*i as i32).to_string()),
+                Operand::I64(i) => return AsmOperand::Imm(i.to_string()),
+                Operand::U64(i) => return AsmOperand::Imm((*i as i64).to_string()),
+                Operand::Usize(i) => return AsmOperand::Imm((*i as i64).to_string()),
+                _ => {}
+            }
+        } else if let Operand::Usize(i) = operand {
+            // usize of the host may be wider than the target
+            return AsmOperand::Imm((*i as u32).to_string());
+        }
+        AsmOperand::from_operand(operand, &mut *self.allocator)
+    }
+
+    /// Return the register allocated to `dest`, or the scratch register a5.
+    fn dest_reg(&mut self, dest: &Place) -> &'static str {
+        match self.allocator.get_location(&dest.label, &dest.ir_type) {
+            Location::Reg(reg) => reg,
+            Location::FpOffset(_) => "a5",
+        }
+    }
+
+    /// Store the value in `reg` to `dest`
+    fn store_dest(&mut self, dest: &Place, reg: &str) -> Result<(), RccError> {
+        match self.allocator.get_location(&dest.label, &dest.ir_type) {
+            Location::Reg(dest_reg) => {
+                if dest_reg != reg {
+                    writeln!(self.output, "\tmv\t{},{}", dest_reg, reg)?;
+                }
+            }
+            Location::FpOffset(offset) => {
+                let size = dest.ir_type.byte_size(self.addr_size);
+                self.store_data(size, reg, -(offset as i32), "s0")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_data(&mut self, reg_name: &str, operand: &Operand) -> Result<(), RccError> {
+        let asm_operand = self.asm_operand(operand);
+        let size = operand.byte_size(self.addr_size);
+        match asm_operand {
+            AsmOperand::Imm(s) => {
+                writeln!(self.output, "\tli\t{},{}", reg_name, s)?;
+            }
+            AsmOperand::FpOffset(offset) => {
+                let inst = match operand {
+                    Operand::Place(p) => load_inst(&p.ir_type, self.addr_size),
+                    _ => unreachable!(),
+                };
+                let mem = self.mem("s0", -(offset as i32))?;
+                writeln!(self.output, "\t{}\t{},{}", inst, reg_name, mem)?;
+            }
+            AsmOperand::Reg(reg) => {
+                if reg != reg_name {
+                    writeln!(self.output, "\tmv\t{},{}", reg_name, reg)?;
+                }
+            }
+            AsmOperand::Label(label) => {
+                writeln!(self.output, "\tla\t{},{}", reg_name, label)?;
+            }
+            AsmOperand::Never | AsmOperand::Unit => {}
+            AsmOperand::FnRet(_ir_type) => match size {
+                1 | 2 | 4 | 8 => {
+                    if reg_name != "a0" {
+                        writeln!(self.output, "\tmv\t{},a0", reg_name)?;
+                    }
+                }
+                _ => todo!(),
+            },
+            _ => unimplemented!("{:?}", asm_operand),
+        }
+        Ok(())
+    }
+
+    /// sb(store byte), sh(store half-word), sw(store word), sd(store double word)
+    fn store_data(
+        &mut self,
+        src_byte_size: u32,
+        src_reg_name: &str,
+        offset: i32,
+        tar_reg_name: &str,
+    ) -> Result<(), RccError> {
+        let inst = match src_byte_size {
+            1 => "sb",
+            2 => "sh",
+            4 => "sw",
+            8 => "sd",
+            _ => todo!(),
+        };
+        let mem = self.mem(tar_reg_name, offset)?;
+        writeln!(self.output, "\t{}\t{},{}", inst, src_reg_name, mem)?;
+        Ok(())
+    }
+
+    /// Store the low `size` bytes of `reg` to `offset(s0)` by the widest stores first,
+    /// the `scratch` register holds the bytes shifted down for the narrower ones,
+    /// e.g. the third byte if `size` is 3.
+    fn store_bytes(&mut self, reg: &str, size: u32, offset: i32, scratch: &str) -> Result<(), RccError> {
+        let mut stored = 0;
+        for width in [8, 4, 2, 1] {
+            if width > self.addr_size / 8 || size - stored < width {
+                continue;
+            }
+            if stored == 0 {
+                self.store_data(width, reg, offset, "s0")?;
+            } else {
+                writeln!(self.output, "\tsrli\t{},{},{}", scratch, reg, stored * 8)?;
+                self.store_data(width, scratch, offset + stored as i32, "s0")?;
+            }
+            stored += width;
+        }
+        Ok(())
+    }
+
+    /// `signed` is the signedness of the operands, which decides the instructions
+    /// of comparisons, `/`, `%` and `>>`.
+    /// On riscv64, the arithmetic of integers no wider than 32 bits is done by the
+    /// `addw` family, which sign-extend the 32-bit results.
+    fn bin_op(
+        &mut self,
+        op: &BinOperator,
+        dest: &Place,
+        reg_src1: &str,
+        reg_src2: &str,
+        signed: bool,
+    ) -> Result<(), RccError> {
+        match dest.kind {
+            VarKind::LocalMut | VarKind::Local => {
+                let rd = self.dest_reg(dest);
+                let slt = if signed { "slt" } else { "sltu" };
+                match op {
+                    BinOperator::Gt => {
+                        writeln!(self.output, "\t{}\t{},{},{}", slt, rd, reg_src2, reg_src1)?;
+                    }
+                    BinOperator::Le => {
+                        writeln!(self.output, "\t{}\t{},{},{}", slt, rd, reg_src2, reg_src1)?;
+                        writeln!(self.output, "\txori\t{},{},1", rd, rd)?;
+                    }
+                    BinOperator::Ge => {
+                        writeln!(self.output, "\t{}\t{},{},{}", slt, rd, reg_src1, reg_src2)?;
+                        writeln!(self.output, "\txori\t{},{},1", rd, rd)?;
+                    }
+                    BinOperator::EqEq => {
+                        writeln!(self.output, "\tsub\t{},{},{}", rd, reg_src1, reg_src2)?;
+                        writeln!(self.output, "\tseqz\t{},{}", rd, rd)?;
+                    }
+                    BinOperator::Ne => {
+                        writeln!(self.output, "\tsub\t{},{},{}", rd, reg_src1, reg_src2)?;
+                        writeln!(self.output, "\tsnez\t{},{}", rd, rd)?;
+                    }
+                    _ => {
+                        let inst = match op {
+                            BinOperator::Plus => "add",
+                            BinOperator::Star => "mul",
+                            BinOperator::Minus => "sub",
+                            BinOperator::Slash if signed => "div",
+                            BinOperator::Slash => "divu",
+                            BinOperator::Percent if signed => "rem",
+                            BinOperator::Percent => "remu",
+                            BinOperator::And => "and",
+                            BinOperator::Or => "or",
+                            BinOperator::Caret => "xor",
+                            BinOperator::Shl => "sll",
+                            BinOperator::Shr if signed => "sra",
+                            BinOperator::Shr => "srl",
+                            BinOperator::Lt => slt,
+                            _ => todo!(),
+                        };
+                        let w = self.w_suffix(op, &dest.ir_type);
+                        writeln!(self.output, "\t{}{}\t{},{},{}", inst, w, rd, reg_src1, reg_src2)?;
+                    }
+                }
+                self.wrap_sub_word(op, rd, &dest.ir_type)?;
+                self.store_dest(dest, rd)?;
+            }
+            _ => unimplemented!(),
+        }
+        Ok(())
+    }
+
+    /// The results of `+`, `-`, `*`, `/` and `<<` on integers narrower than 32 bits
+    /// may be out of their ranges, so they are wrapped and extended like the casts.
+    fn wrap_sub_word(&mut self, op: &BinOperator, rd: &str, ir_type: &IRType) -> Result<(), RccError> {
+        let arithmetic = matches!(
+            op,
+            BinOperator::Plus
+                | BinOperator::Minus
+                | BinOperator::Star
+                | BinOperator::Slash
+                | BinOperator::Shl
+        );
+        if arithmetic && !ir_type.is_float() && ir_type.byte_size(self.addr_size) < 4 {
+            self.cast(CastOp::Trunc, rd, rd, &IRType::I32, ir_type)?;
+        }
+        Ok(())
+    }
+
+    /// `w` of `addw` and the like, if `op` on `ir_type` is done by them on riscv64.
+    fn w_suffix(&self, op: &BinOperator, ir_type: &IRType) -> &'static str {
+        let has_w = matches!(
+            op,
+            BinOperator::Plus
+                | BinOperator::Minus
+                | BinOperator::Star
+                | BinOperator::Slash
+                | BinOperator::Percent
+                | BinOperator::Shl
+                | BinOperator::Shr
+        );
+        if has_w && self.addr_size == 64 && ir_type.byte_size(self.addr_size) <= 4 {
+            "w"
+        } else {
+            ""
+        }
+    }
+
+    fn bin_op_imm(
+        &mut self,
+        op: &BinOperator,
+        dest: &Place,
+        reg_src1: &str,
+        src2: &Operand,
+        signed: bool,
+    ) -> Result<(), RccError> {
+        let asm_src2 = self.asm_operand(src2);
+        match asm_src2 {
+            AsmOperand::Imm(s) => match dest.kind {
+                VarKind::LocalMut | VarKind::Local => {
+                    let imm: i64 = s.parse().unwrap();
+                    // instructions with 12-bit immediate
+                    let inst_imm = match op {
+                        BinOperator::Plus => Some(("addi", imm)),
+                        BinOperator::Minus => Some(("addi", -imm)),
+                        BinOperator::And => Some(("andi", imm)),
+                        BinOperator::Or => Some(("ori", imm)),
+                        BinOperator::Caret => Some(("xori", imm)),
+                        BinOperator::Lt if signed => Some(("slti", imm)),
+                        BinOperator::Lt => Some(("sltiu", imm)),
+                        BinOperator::Shl => Some(("slli", imm)),
+                        BinOperator::Shr if signed => Some(("srai", imm)),
+                        BinOperator::Shr => Some(("srli", imm)),
+                        _ => None,
+                    };
+                    match inst_imm {
+                        Some((inst, imm)) if (-2048..2048).contains(&imm) => {
+                            let rd = self.dest_reg(dest);
+                            let w = self.w_suffix(op, &dest.ir_type);
+                            writeln!(self.output, "\t{}{}\t{},{},{}", inst, w, rd, reg_src1, imm)?;
+                            self.wrap_sub_word(op, rd, &dest.ir_type)?;
+                            self.store_dest(dest, rd)?;
+                        }
+                        _ => {
+                            self.load_data("a4", src2)?;
+                            self.bin_op(op, dest, reg_src1, "a4", signed)?;
+                        }
+                    }
+                }
+                _ => unimplemented!(),
+            },
+            _ => todo!(),
+        }
+        Ok(())
+    }
+}
+
+/// (load, store, format) of the float of `byte_size`, e.g. `flw`, `fsw` and `s` of `fadd.s` for f32.
+fn float_insts(byte_size: u32) -> (&'static str, &'static str, &'static str) {
+    match byte_size {
+        4 => ("flw", "fsw", "s"),
+        8 => ("fld", "fsd", "d"),
+        _ => unreachable!("invalid size of float: {}", byte_size),
+    }
+}
+
+/// Format of the integer of `byte_size` in `fcvt`, e.g. `w` of `fcvt.s.w` for i32.
+fn int_fmt(byte_size: u32, signed: bool) -> &'static str {
+    match (byte_size, signed) {
+        (8, true) => "l",
+        (8, false) => "lu",
+        (_, true) => "w",
+        (_, false) => "wu",
+    }
+}
+
+/// Where a part of an argument is passed, the offsets on the stack are from sp of the caller.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum ArgLoc {
+    /// a0-a7
+    Reg(u32),
+    /// fa0-fa7
+    FloatReg(u32),
+    Stack(u32),
+}
+
+/// How an argument is passed.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ArgPass {
+    /// An integer no wider than a register, or a float in a float register.
+    Whole(ArgLoc),
+    /// (offset, type, location) of the words of an argument in memory or of a 64-bit integer,
+    /// or of the fields of an aggregate in float registers.
+    Parts(Vec<(u32, IRType, ArgLoc)>),
+    /// The address of the copy at the offset from sp of the caller, which is made by the caller.
+    ByRef(ArgLoc, u32),
+}
+
+/// Assign arguments to registers and the stack by the calling convention of ilp32d
+/// on riscv32, or lp64d on riscv64, where XLEN is the width of a register.
+///
+/// - A float is passed in a float register if there is one left.
+/// - An aggregate of a float, two floats, or a float and an integer, is passed in
+///   float registers and an integer register if there are enough of them left.
+/// - Others are passed in integer registers, an argument of no more than 2*XLEN bits
+///   takes two of them, and the high half is on the stack if there is only one left.
+/// - Arguments wider than 2*XLEN bits are passed by reference.
+/// - Arguments on the stack are aligned to their alignment and at least to XLEN bits.
+struct ArgAssigner {
+    addr_size: u32,
+    next_reg: u32,
+    next_float_reg: u32,
+    stack_size: u32,
+}
+
+impl ArgAssigner {
+    fn new(addr_size: u32) -> ArgAssigner {
+        ArgAssigner {
+            addr_size,
+            next_reg: 0,
+            next_float_reg: 0,
+            stack_size: 0,
+        }
+    }
+
+    fn assign(&mut self, ir_type: &IRType) -> ArgPass {
+        if ir_type.is_float() && self.next_float_reg < 8 {
+            self.next_float_reg += 1;
+            return ArgPass::Whole(ArgLoc::FloatReg(self.next_float_reg - 1));
+        }
+        if let Some(fields) = ir_type.float_fields(self.addr_size) {
+            let floats = fields.iter().filter(|(_, t)| t.is_float()).count() as u32;
+            let ints = fields.len() as u32 - floats;
+            if self.next_float_reg + floats <= 8 && self.next_reg + ints <= 8 {
+                let parts = fields
+                    .into_iter()
+                    .map(|(offset, t)| {
+                        let loc = if t.is_float() {
+                            self.next_float_reg += 1;
+                            ArgLoc::FloatReg(self.next_float_reg - 1)
+                        } else {
+                            self.next_reg += 1;
+                            ArgLoc::Reg(self.next_reg - 1)
+                        };
+                        (offset, t, loc)
+                    })
+                    .collect();
+                return ArgPass::Parts(parts);
+            }
+        }
+        let xlen = self.addr_size / 8;
+        let size = ir_type.byte_size(self.addr_size);
+        let align = ir_type.align(self.addr_size);
+        // a float on riscv64 is passed in the low word, which is not loaded by `ld`
+        let word = if size <= 4 { IRType::U32 } else { reg_type(self.addr_size) };
+        match size {
+            0 => ArgPass::Parts(vec![]),
+            _ if size <= xlen && !ir_type.is_aggregate() && !ir_type.is_float() => {
+                ArgPass::Whole(self.reg_or_stack(xlen, xlen))
+            }
+            _ if size <= xlen => ArgPass::Parts(vec![(0, word, self.reg_or_stack(xlen, xlen))]),
+            _ if size <= 2 * xlen => {
+                let lo = self.reg_or_stack(2 * xlen, align);
+                let hi = match lo {
+                    ArgLoc::Stack(offset) => ArgLoc::Stack(offset + xlen),
+                    _ => self.reg_or_stack(xlen, xlen),
+                };
+                ArgPass::Parts(vec![(0, word.clone(), lo), (xlen, word, hi)])
+            }
+            _ => ArgPass::ByRef(self.reg_or_stack(xlen, xlen), 0),
+        }
+    }
+
+    /// The next integer register, or the next slot of `size` bytes on the stack.
+    fn reg_or_stack(&mut self, size: u32, align: u32) -> ArgLoc {
+        if self.next_reg < 8 {
+            self.next_reg += 1;
+            return ArgLoc::Reg(self.next_reg - 1);
+        }
+        let xlen = self.addr_size / 8;
+        let offset = self.stack_size.next_multiple_of(align.clamp(xlen, 16));
+        self.stack_size = offset + size.next_multiple_of(xlen);
+        ArgLoc::Stack(offset)
+    }
+}
+
+/// Return how the arguments of `types` are passed, and the bytes of the outgoing arguments
+/// on the stack, where the copies of the ones passed by reference are after the others.
+pub(crate) fn assign_args(types: &[IRType], addr_size: u32) -> (Vec<ArgPass>, u32) {
+    let mut assigner = ArgAssigner::new(addr_size);
+    let mut passes: Vec<ArgPass> = types.iter().map(|t| assigner.assign(t)).collect();
+    let mut stack_size = assigner.stack_size;
+    for (pass, ir_type) in passes.iter_mut().zip(types) {
+        if let ArgPass::ByRef(_, copy) = pass {
+            let align = ir_type.align(addr_size).clamp(addr_size / 8, 16);
+            *copy = stack_size.next_multiple_of(align);
+            stack_size = *copy + ir_type.byte_size(addr_size);
+        }
+    }
+    (passes, stack_size)
+}
+
+/// Parts of an aggregate returned in float registers, which is returned like the first argument.
+fn float_ret_parts(ir_type: &IRType, addr_size: u32) -> Option<Vec<(u32, IRType, ArgLoc)>> {
+    ir_type.float_fields(addr_size)?;
+    match ArgAssigner::new(addr_size).assign(ir_type) {
+        ArgPass::Parts(parts) => Some(parts),
+        pass => unreachable!("{:?} is not in float registers", pass),
+    }
+}
+
+/// Bytes of the outgoing arguments on the stack of the calls in `cfg`.
+fn outgoing_args_size(cfg: &CFG, addr_size: u32) -> u32 {
+    cfg.iter_inst()
+        .filter_map(|inst| match inst {
+            IRInst::Call { args, .. } => {
+                let types: Vec<IRType> = args.iter().map(Operand::ir_type).collect();
+                Some(assign_args(&types, addr_size).1)
+            }
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Whether `imm` fits in the 12-bit signed immediate of I-type and S-type instructions.
+fn is_imm12(imm: i32) -> bool {
+    (-2048..2048).contains(&imm)
+}
+
+/// The unsigned integer as wide as a register.
+fn reg_type(addr_size: u32) -> IRType {
+    if addr_size == 64 {
+        IRType::U64
+    } else {
+        IRType::U32
+    }
+}
+
+/// (load, store) of a register, e.g. `lw` and `sw` on riscv32.
+fn reg_insts(addr_size: u32) -> (&'static str, &'static str) {
+    if addr_size == 64 {
+        ("ld", "sd")
+    } else {
+        ("lw", "sw")
+    }
+}
+
+/// `i64` and `u64`, which are held in pairs of registers on riscv32, the low word first.
+fn is_double_word(ir_type: &IRType, addr_size: u32) -> bool {
+    addr_size == 32 && matches!(ir_type, IRType::I64 | IRType::U64)
+}
+
+fn is_double_word_operand(operand: &Operand, addr_size: u32) -> bool {
+    match operand {
+        Operand::I64(_) | Operand::U64(_) => addr_size == 32,
+        Operand::Place(p) => is_double_word(&p.ir_type, addr_size),
+        Operand::FnRetPlace(ir_type) => is_double_word(ir_type, addr_size),
+        _ => false,
+    }
+}
+
+fn is_signed_operand(operand: &Operand) -> bool {
+    match operand {
+        Operand::I8(_) | Operand::I16(_) | Operand::I32(_) | Operand::I64(_) | Operand::Isize(_) => true,
+        Operand::Place(p) => p.ir_type.is_signed(),
+        Operand::FnRetPlace(ir_type) => ir_type.is_signed(),
+        _ => false,
+    }
+}
+
+fn float_const_key(operand: &Operand) -> Option<(u32, u64)> {
+    match operand {
+        Operand::F32(f) => Some((4, f.to_bits() as u64)),
+        Operand::F64(f) => Some((8, f.to_bits())),
+        _ => None,
+    }
+}
+
+/// lb(load byte), lbu(load byte unsigned), lh(load half-word), lw(load word), ld(load double word)...
+/// `u32` is loaded by `lw` on riscv64 too, since 32-bit integers are kept sign-extended.
+fn load_inst(ir_type: &IRType, addr_size: u32) -> &'static str {
+    match ir_type.byte_size(addr_size) {
+        1 => match ir_type {
+            IRType::I8 => "lb",
+            _ => "lbu",
+        },
+        2 => match ir_type {
+            IRType::I16 => "lh",
+            _ => "lhu",
+        },
+        4 => "lw",
+        8 => "ld",
+        _ => todo!(),
+    }
+}
+
+#[derive(Debug)]
+pub enum AsmOperand {
+    Imm(String),
+    Imm64(String, String),
+    Reg(String),
+    FpOffset(u32),
+    /// address of a symbol
+    Label(String),
+    Never,
+    Unit,
+    FnRet(IRType),
+}
+
+impl AsmOperand {
+    pub fn from_operand(operand: &Operand, allocator: &mut dyn Allocator) -> AsmOperand {
+        match operand {
+            Operand::Bool(b) => Self::Imm((*b as u8).to_string()),
+            Operand::Char(c) => Self::Imm((*c as u32).to_string()),
+            Operand::I8(i) => Self::Imm(i.to_string()),
+            Operand::I16(i) => Self::Imm(i.to_string()),
+            Operand::I32(i) => Self::Imm(i.to_string()),
+            Operand::I64(i) => Self::Imm64((*i as i32).to_string(), ((*i >> 32) as i32).to_string()),
+            Operand::U8(i) => Self::Imm(i.to_string()),
+            Operand::U16(i) => Self::Imm(i.to_string()),
+            Operand::U32(i) => Self::Imm(i.to_string()),
+            Operand::U64(i) => Self::Imm64((*i as i32).to_string(), ((*i >> 32) as i32).to_string()),
+            Operand::Isize(i) => Self::Imm(i.to_string()),
+            Operand::Usize(i) => Self::Imm(i.to_string()),
+            Operand::Place(p) => {
+                match p.kind {
+                    VarKind::Local | VarKind::LocalMut => {
+                        match allocator.get_location(&p.label, &p.ir_type) {
+                            Location::Reg(reg) => Self::Reg(reg.to_string()),
+                            Location::FpOffset(offset) => Self::FpOffset(offset),
+                        }
+                    }
+                    // todo
+                    _ => Self::Unit,
+                }
+            }
+            Operand::FnLabel(label) => Self::Label(label.clone()),
+            Operand::Unit => Self::Unit,
+            Operand::Never => Self::Never,
+            Operand::FnRetPlace(ir_type) => Self::FnRet(ir_type.clone()),
+            _ => unimplemented!("{:?}", operand),
+        }
+    }
+}
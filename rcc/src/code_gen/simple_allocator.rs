@@ -1,51 +1,43 @@
-use crate::code_gen::Allocator;
+use crate::code_gen::{assign_fp_offsets, next_fp_offset, Allocator};
 use crate::ir::cfg::CFG;
 use crate::ir::IRType;
 use std::collections::HashMap;
 
-pub struct SimpleAllocator<'cfg> {
-    cfg: &'cfg CFG,
+pub struct SimpleAllocator {
     addr_size: u32,
     offset: u32,
     var_offsets: HashMap<String, u32>,
 }
 
-impl<'cfg> SimpleAllocator<'cfg> {
+impl SimpleAllocator {
     pub(crate) fn new(cfg: &CFG, addr_size: u32) -> SimpleAllocator {
         debug_assert!(addr_size == 32 || addr_size == 64);
-        SimpleAllocator {
+        let mut locals: Vec<(&String, &(usize, IRType))> = cfg.local_infos.iter().collect();
+        locals.sort_by_key(|(_, (id, _))| *id);
+        let (var_offsets, offset) = assign_fp_offsets(
             cfg,
+            &[],
+            locals.into_iter().map(|(name, _)| name.as_str()),
+            addr_size,
+        );
+        SimpleAllocator {
             addr_size,
-            offset: 0,
-            var_offsets: HashMap::new(),
+            offset,
+            var_offsets,
         }
     }
 }
 
-impl<'cfg> Allocator for SimpleAllocator<'cfg> {
+impl Allocator for SimpleAllocator {
     fn get_frame_size(&self) -> u32 {
-        // s0
-        let mut frame_size = self.addr_size / 8;
-        if !self.cfg.is_leaf {
-            frame_size *= 2;
-        }
-        // locals
-        for (_id, ir_type) in self.cfg.local_infos.values() {
-            frame_size += ir_type.byte_size(self.addr_size);
-        }
-        if frame_size % 8 == 0 {
-            frame_size
-        } else {
-            (frame_size / 8 + 1) * 8
-        }
+        self.offset
     }
 
     fn get_fp_offset(&mut self, var_name: &str, ir_type: &IRType) -> u32 {
         match self.var_offsets.get(var_name) {
             Some(offset) => *offset,
             None => {
-                let size = ir_type.byte_size(self.addr_size);
-                self.offset += size;
+                self.offset = next_fp_offset(self.offset, ir_type, self.addr_size);
                 self.var_offsets.insert(var_name.to_string(), self.offset);
                 self.offset
             }
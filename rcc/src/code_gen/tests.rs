@@ -0,0 +1,203 @@
+use crate::code_gen::linear_scan_allocator::LinearScanAllocator;
+use crate::code_gen::riscv::{assign_args, ArgLoc, ArgPass};
+use crate::code_gen::{Allocator, Location};
+use crate::ir::cfg::CFG;
+use crate::ir::tests::ir_build;
+use crate::ir::IRType;
+use std::rc::Rc;
+
+fn get_cfgs(input: &str) -> Vec<CFG> {
+    let ir = ir_build(input).unwrap();
+    ir.funcs.into_iter().map(CFG::new).collect()
+}
+
+#[test]
+fn live_interval_test() {
+    let cfgs = get_cfgs(
+        r#"
+        fn foo(x: i32) -> i32 {
+            let mut a = x;
+            let b = 3;
+            while a > 0 {
+                a -= 1;
+            }
+            a + b
+        }
+    "#,
+    );
+    let allocator = LinearScanAllocator::new(&cfgs[0], 32);
+    let intervals = allocator.build_intervals();
+    let find = |name: &str| intervals.iter().find(|i| i.var_name == name).unwrap();
+
+    // (1) a = x  (2) b = 3  (3) if 0 >= a goto  (4) a = a - 1  (5) goto  (6) $t = a + b  (7) ret
+    let x = find("x_2");
+    assert_eq!((0, 1), (x.start, x.end));
+    let a = find("a_2");
+    assert_eq!((1, 6), (a.start, a.end));
+    let b = find("b_2");
+    assert_eq!((2, 6), (b.start, b.end));
+    assert!(intervals.iter().all(|i| !i.cross_call));
+}
+
+#[test]
+fn cross_call_test() {
+    let cfgs = get_cfgs(
+        r#"
+        fn bar() -> i32 {
+            3
+        }
+        fn foo(x: i32) -> i32 {
+            let a = x * 2;
+            let b = bar();
+            a + b
+        }
+    "#,
+    );
+    let cfg = &cfgs[1];
+    let mut allocator = LinearScanAllocator::new(cfg, 32);
+    let a = allocator.get_location("a_3", &IRType::I32);
+    let b = allocator.get_location("b_3", &IRType::I32);
+    // a is live across the call of bar
+    assert_eq!(Location::Reg("s1"), a);
+    assert_eq!(Location::Reg("t0"), b);
+    assert_eq!(vec!["s1"], allocator.get_saved_regs());
+    // ra, s0 and s1, which is aligned to 16 bytes by the code generator
+    assert_eq!(12, allocator.get_frame_size());
+}
+
+#[test]
+fn spill_test() {
+    let vars: Vec<String> = (0..20).map(|i| format!("v{}", i)).collect();
+    let mut input = String::from("fn foo() -> i32 {\n");
+    for (i, v) in vars.iter().enumerate() {
+        input.push_str(&format!("let mut {} = {};\n", v, i));
+    }
+    for v in vars.iter() {
+        input.push_str(&format!("{} += 1;\n", v));
+    }
+    input.push_str(&vars.join(" + "));
+    input.push_str("\n}");
+
+    let cfgs = get_cfgs(&input);
+    let mut allocator = LinearScanAllocator::new(&cfgs[0], 32);
+    let mut reg_count = 0;
+    let mut spilled_count = 0;
+    for v in vars.iter() {
+        match allocator.get_location(&format!("{}_2", v), &IRType::I32) {
+            Location::Reg(_) => reg_count += 1,
+            Location::FpOffset(_) => spilled_count += 1,
+        }
+    }
+    // only 7 temporary registers and 11 saved registers are available
+    assert!(reg_count <= 18);
+    assert!(spilled_count >= 2);
+    assert_eq!(11, allocator.get_saved_regs().len());
+}
+
+#[test]
+fn helper_call_test() {
+    let cfgs = get_cfgs(
+        r#"
+        fn foo(x: i64, y: i32) -> i64 {
+            let a = y + 1;
+            let b = x * 3;
+            b + a as i64
+        }
+    "#,
+    );
+    let cfg = &cfgs[0];
+    // the multiplication of i64 calls `__muldi3` on riscv32
+    assert!(cfg.is_leaf);
+    assert!(!cfg.is_leaf_on(32));
+    assert!(cfg.is_leaf_on(64));
+    let mut allocator = LinearScanAllocator::new(cfg, 32);
+    // a is live across the call, and i64 is never allocated to registers
+    assert_eq!(Location::Reg("s1"), allocator.get_location("a_2", &IRType::I32));
+    assert!(matches!(allocator.get_location("b_2", &IRType::I64), Location::FpOffset(_)));
+}
+
+#[test]
+fn stack_args_test() {
+    let pair = IRType::Aggregate(Rc::new(vec![IRType::I32, IRType::I32]));
+    let mut types = vec![IRType::I32; 7];
+    types.extend([IRType::I64, pair, IRType::I64]);
+    let (passes, stack_size) = assign_args(&types, 32);
+    assert_eq!(ArgPass::Whole(ArgLoc::Reg(6)), passes[6]);
+    // the high word of the i64 is on the stack if only a7 is left
+    assert_eq!(
+        ArgPass::Parts(vec![(0, IRType::U32, ArgLoc::Reg(7)), (4, IRType::U32, ArgLoc::Stack(0))]),
+        passes[7]
+    );
+    assert_eq!(
+        ArgPass::Parts(vec![(0, IRType::U32, ArgLoc::Stack(4)), (4, IRType::U32, ArgLoc::Stack(8))]),
+        passes[8]
+    );
+    // i64 on the stack is aligned to 8 bytes
+    assert_eq!(
+        ArgPass::Parts(vec![(0, IRType::U32, ArgLoc::Stack(16)), (4, IRType::U32, ArgLoc::Stack(20))]),
+        passes[9]
+    );
+    assert_eq!(24, stack_size);
+}
+
+#[test]
+fn float_args_test() {
+    let tagged = IRType::Aggregate(Rc::new(vec![IRType::F32, IRType::U8]));
+    let vec2 = IRType::Aggregate(Rc::new(vec![IRType::F64, IRType::F64]));
+    let big = IRType::Aggregate(Rc::new(vec![IRType::I32, IRType::I32, IRType::I32, IRType::U8]));
+
+    let (passes, stack_size) = assign_args(&[tagged.clone(), vec2.clone(), big.clone()], 32);
+    assert_eq!(
+        ArgPass::Parts(vec![(0, IRType::F32, ArgLoc::FloatReg(0)), (4, IRType::U8, ArgLoc::Reg(0))]),
+        passes[0]
+    );
+    assert_eq!(
+        ArgPass::Parts(vec![(0, IRType::F64, ArgLoc::FloatReg(1)), (8, IRType::F64, ArgLoc::FloatReg(2))]),
+        passes[1]
+    );
+    // aggregates in float registers are also returned in them
+    assert!(!vec2.is_returned_by_sret(32));
+    assert!(big.is_returned_by_sret(32));
+    // the copy of the aggregate passed by reference is made in the outgoing arguments
+    assert_eq!(ArgPass::ByRef(ArgLoc::Reg(1), 0), passes[2]);
+    assert_eq!(16, stack_size);
+
+    // floats are passed in integer registers if there is no float register left
+    let mut types = vec![IRType::F64; 8];
+    types.extend([IRType::F32, tagged, vec2]);
+    let (passes, stack_size) = assign_args(&types, 32);
+    assert_eq!(ArgPass::Whole(ArgLoc::FloatReg(7)), passes[7]);
+    assert_eq!(ArgPass::Parts(vec![(0, IRType::U32, ArgLoc::Reg(0))]), passes[8]);
+    assert_eq!(
+        ArgPass::Parts(vec![(0, IRType::U32, ArgLoc::Reg(1)), (4, IRType::U32, ArgLoc::Reg(2))]),
+        passes[9]
+    );
+    assert_eq!(ArgPass::ByRef(ArgLoc::Reg(3), 0), passes[10]);
+    assert_eq!(16, stack_size);
+}
+
+#[test]
+fn lp64d_args_test() {
+    let big = IRType::Aggregate(Rc::new(vec![IRType::I32, IRType::I32, IRType::I32, IRType::U8]));
+    let triple = IRType::Aggregate(Rc::new(vec![IRType::I64, IRType::I64, IRType::I64]));
+    let mut types = vec![IRType::I32; 7];
+    types.extend([big.clone(), IRType::I64, triple]);
+    let (passes, stack_size) = assign_args(&types, 64);
+    // an aggregate of no more than 16 bytes takes two registers on riscv64
+    assert_eq!(
+        ArgPass::Parts(vec![(0, IRType::U64, ArgLoc::Reg(7)), (8, IRType::U64, ArgLoc::Stack(0))]),
+        passes[7]
+    );
+    assert_eq!(ArgPass::Whole(ArgLoc::Stack(8)), passes[8]);
+    assert_eq!(ArgPass::ByRef(ArgLoc::Stack(16), 24), passes[9]);
+    assert_eq!(48, stack_size);
+    assert!(!big.is_returned_by_sret(64));
+    assert!(big.is_returned_by_sret(32));
+
+    // f32 in an integer register is a word, which is not loaded by `ld`
+    let mut types = vec![IRType::F32; 9];
+    types.push(IRType::F64);
+    let (passes, _) = assign_args(&types, 64);
+    assert_eq!(ArgPass::Parts(vec![(0, IRType::U32, ArgLoc::Reg(0))]), passes[8]);
+    assert_eq!(ArgPass::Parts(vec![(0, IRType::U64, ArgLoc::Reg(1))]), passes[9]);
+}
@@ -1,7 +1,8 @@
+use crate::analyser::sym_resolver::VarKind;
 use crate::ir::linear_ir::{Func, LinearIR};
 use crate::ir::var_name::local_var;
-use crate::ir::{IRInst, IRType};
-use std::collections::{BTreeSet, HashMap, LinkedList};
+use crate::ir::{IRInst, IRType, Operand};
+use std::collections::{BTreeMap, BTreeSet, HashMap, LinkedList};
 
 /// Control FLow Graph's immediate representation
 pub struct CFGIR {
@@ -9,6 +10,8 @@ pub struct CFGIR {
 
     /// label, value
     pub ro_local_strs: HashMap<String, String>,
+    /// label, symbols of the functions
+    pub vtables: BTreeMap<String, Vec<String>>,
 }
 
 impl CFGIR {
@@ -17,6 +20,7 @@ impl CFGIR {
         CFGIR {
             cfgs,
             ro_local_strs: linear_ir.ro_local_strs,
+            vtables: linear_ir.vtables,
         }
     }
 }
@@ -29,6 +33,7 @@ pub struct CFG {
     /// function information
     pub func_name: String,
     pub func_scope_id: u64,
+    pub func_instance: usize,
     pub func_is_global: bool,
     pub fn_args: Vec<(String, IRType)>,
     pub is_leaf: bool,
@@ -47,7 +52,7 @@ impl CFG {
     /// Instructions like `(n) if cond goto n+1` will be deleted in this pass.
     pub fn new(mut func: Func) -> CFG {
         let (leaders, is_leaf) = get_leaders_and_is_leaf(&func);
-        let local_infos = get_local_infos(&func);
+        let local_infos = get_local_infos(&func.fn_args, func.block_scope_id, func.insts.iter());
 
         // generate basic blocks and label map
         let mut label_map = HashMap::new();
@@ -105,6 +110,7 @@ impl CFG {
                         Some(vec![*label])
                     }
                 }
+                IRInst::Ret(_) => None,
                 _ => {
                     if i < last_bb_id {
                         Some(vec![i + 1])
@@ -127,6 +133,7 @@ impl CFG {
             local_infos,
             func_name: func.name,
             func_scope_id: func.block_scope_id,
+            func_instance: func.instance,
             func_is_global: func.is_global,
             fn_args: func.fn_args,
             is_leaf,
@@ -136,37 +143,50 @@ impl CFG {
     pub fn succ_of(&self, bb_id: usize) -> Vec<usize> {
         debug_assert!(bb_id < self.basic_blocks.len(), "bb_id out of range");
 
-        match self
-            .basic_blocks
-            .get(bb_id)
-            .unwrap()
-            .instructions
-            .back()
-            .unwrap()
-        {
-            IRInst::Jump { label } => vec![*label],
+        match self.basic_blocks.get(bb_id).unwrap().instructions.back() {
+            Some(IRInst::Jump { label }) => vec![*label],
 
-            IRInst::JumpIf { label, .. }
-            | IRInst::JumpIfNot { label, .. }
-            | IRInst::JumpIfCond { label, .. } => {
+            Some(IRInst::JumpIf { label, .. })
+            | Some(IRInst::JumpIfNot { label, .. })
+            | Some(IRInst::JumpIfCond { label, .. }) => {
                 let mut succ = vec![*label];
                 if bb_id < self.basic_blocks.len() - 1 {
                     succ.push(bb_id + 1);
                 }
                 succ
             }
-            _ => vec![],
+            Some(IRInst::Ret(_)) => vec![],
+            // basic blocks emptied by optimization passes fall through
+            _ => {
+                if bb_id < self.basic_blocks.len() - 1 {
+                    vec![bb_id + 1]
+                } else {
+                    vec![]
+                }
+            }
         }
     }
 
+    /// Recompute `local_infos` after instructions are changed by optimization passes.
+    pub fn update_local_infos(&mut self) {
+        self.local_infos =
+            get_local_infos(&self.fn_args, self.func_scope_id, self.iter_inst());
+    }
+
     pub fn get_name_of_fn_arg(&self, i: usize) -> Option<String> {
         let (raw_name, _) = self.fn_args.get(i)?;
         Some(local_var(raw_name, self.func_scope_id))
     }
 
-    pub fn iter_inst(&self) -> CFGIterMut {
+    pub fn iter_inst(&self) -> CFGIterMut<'_> {
         CFGIterMut::new(self)
     }
+
+    /// The function calls nothing, including the runtime helpers
+    /// which some instructions are lowered to on the target.
+    pub fn is_leaf_on(&self, addr_size: u32) -> bool {
+        self.is_leaf && !self.iter_inst().any(|inst| inst.calls_helper(addr_size))
+    }
 }
 
 fn get_leaders_and_is_leaf(func: &Func) -> (BTreeSet<usize>, bool) {
@@ -192,6 +212,9 @@ fn get_leaders_and_is_leaf(func: &Func) -> (BTreeSet<usize>, bool) {
             IRInst::Call { .. } => {
                 is_leaf = false;
             }
+            IRInst::Ret(_) => {
+                leaders.insert(i + 2);
+            }
             _ => {}
         }
     }
@@ -200,26 +223,45 @@ fn get_leaders_and_is_leaf(func: &Func) -> (BTreeSet<usize>, bool) {
     (leaders, is_leaf)
 }
 
-fn get_local_infos(func: &Func) -> HashMap<String, (usize, IRType)> {
+fn get_local_infos<'a>(
+    fn_args: &[(String, IRType)],
+    func_scope_id: u64,
+    insts: impl Iterator<Item = &'a IRInst>,
+) -> HashMap<String, (usize, IRType)> {
     let mut local_infos = HashMap::new();
     let mut next_id: usize = 0;
-    for arg in &func.fn_args {
-        let var_name = local_var(&arg.0, func.block_scope_id);
-        local_infos.insert(var_name, (next_id, arg.1));
+    for arg in fn_args {
+        let var_name = local_var(&arg.0, func_scope_id);
+        local_infos.insert(var_name, (next_id, arg.1.clone()));
+        next_id += 1;
     }
 
-    for inst in func.insts.iter() {
+    for inst in insts {
         match inst {
             IRInst::BinOp { dest, .. }
             | IRInst::LoadData { dest, .. }
-            | IRInst::LoadAddr { dest, .. } => {
-                if !local_infos.contains_key(&dest.label) {
-                    local_infos.insert(dest.label.clone(), (next_id, dest.ir_type));
-                    next_id += 1;
-                }
+            | IRInst::LoadAddr { dest, .. }
+            | IRInst::Load { dest, .. }
+            | IRInst::Cast { dest, .. }
+                if !local_infos.contains_key(&dest.label) =>
+            {
+                local_infos.insert(dest.label.clone(), (next_id, dest.ir_type.clone()));
+                next_id += 1;
             }
             _ => {}
         }
+        // a variable whose address is taken may never be assigned directly,
+        // e.g. a struct written field by field
+        if let IRInst::LoadAddr {
+            symbol: Operand::Place(symbol),
+            ..
+        } = inst
+        {
+            if symbol.kind != VarKind::LitConst && !local_infos.contains_key(&symbol.label) {
+                local_infos.insert(symbol.label.clone(), (next_id, symbol.ir_type.clone()));
+                next_id += 1;
+            }
+        }
     }
     local_infos
 }
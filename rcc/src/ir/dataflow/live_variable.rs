@@ -1,17 +1,20 @@
+use crate::analyser::sym_resolver::VarKind;
 use crate::ir::cfg::{BasicBlock, CFG};
 use crate::ir::{IRInst, Operand, Place};
 use bit_vector::BitVector;
-use std::collections::VecDeque;
 
 trait AnalysisDomain {
     fn bottom_value(cfg: &CFG) -> Self;
 }
 
+/// Backward dataflow analysis.
+///
+/// OUT[B] = ∪ IN[S] (S is a successor of B)
+/// IN[B] = use[B] ∪ (OUT[B] - def[B])
 pub struct LiveVariableAnalysis<'cfg> {
     cfg: &'cfg CFG,
     pub in_states: Vec<BitVector>,
     pub out_states: Vec<BitVector>,
-    in_changed: bool,
 }
 
 impl AnalysisDomain for BitVector {
@@ -25,34 +28,51 @@ impl<'cfg> LiveVariableAnalysis<'cfg> {
         LiveVariableAnalysis {
             cfg,
             in_states: vec![Self::init_value(cfg); cfg.basic_blocks.len()],
-            out_states: vec![Self::init_value(cfg); cfg.basic_blocks.len()],
-            in_changed: true,
+            out_states: vec![Self::boundary(cfg); cfg.basic_blocks.len()],
         }
     }
 
     pub fn apply(&mut self) {
-        let mut visited = BitVector::new(self.cfg.basic_blocks.len());
-        let mut queue = VecDeque::<usize>::new();
-        while self.in_changed {
-            visited.set_all_false();
-            self.in_changed = false;
-            queue.clear();
-            queue.push_back(self.cfg.basic_blocks.len() - 1);
-            while !queue.is_empty() {
-                let bid = queue.pop_front().unwrap();
-                let bb = self.cfg.basic_blocks.get(bid).unwrap();
-
-                visited.set(bid, true);
-
-                self.out_states[bid] = self.join_succ(bb);
-                for ir_inst in bb.instructions.iter() {
-                    self.gen_kill(bid, ir_inst);
+        let mut in_changed = true;
+        while in_changed {
+            in_changed = false;
+            for bb in self.cfg.basic_blocks.iter().rev() {
+                let bid = bb.id;
+                let out_state = self.join_succ(bb);
+                let mut in_state = out_state.clone();
+                for ir_inst in bb.instructions.iter().rev() {
+                    self.transfer(ir_inst, &mut in_state);
                 }
+                if in_state != self.in_states[bid] {
+                    in_changed = true;
+                    self.in_states[bid] = in_state;
+                }
+                self.out_states[bid] = out_state;
+            }
+        }
+    }
+
+    /// Id of the variable in the bit vectors, `None` if it is not a local variable.
+    pub fn var_id(&self, place: &Place) -> Option<usize> {
+        match place.kind {
+            VarKind::Local | VarKind::LocalMut => {
+                self.cfg.local_infos.get(&place.label).map(|info| info.0)
+            }
+            _ => None,
+        }
+    }
 
-                for p in bb.predecessors.iter() {
-                    if !visited.get(*p).unwrap() {
-                        queue.push_back(*p);
-                    }
+    /// Compute the live variables before `inst` from the live variables after it.
+    pub fn transfer(&self, inst: &IRInst, state: &mut BitVector) {
+        // kill
+        if let Some(dest_id) = inst.dest().and_then(|dest| self.var_id(dest)) {
+            state.set(dest_id, false);
+        }
+        // gen
+        for src in inst.src_operands() {
+            if let Operand::Place(p) = src {
+                if let Some(src_id) = self.var_id(p) {
+                    state.set(src_id, true);
                 }
             }
         }
@@ -70,64 +90,15 @@ impl<'cfg> LiveVariableAnalysis<'cfg> {
         let bid = basic_block.id;
 
         let succs = self.cfg.succ_of(bid);
-        let res = succs
+        if succs.is_empty() {
+            return Self::boundary(self.cfg);
+        }
+        succs
             .iter()
             .map(|s_bid| self.in_states.get(*s_bid).unwrap())
             .fold(BitVector::bottom_value(self.cfg), |mut acc, x| {
                 acc.set_bitor(x);
                 acc
-            });
-        res
-    }
-
-    fn gen_kill(&mut self, bid: usize, inst: &IRInst) {
-        macro_rules! gen {
-            ($cxt:tt, $dest:tt, $in_state:ident) => {
-                let dest_id = $cxt.cfg.local_infos.get(&$dest.label).unwrap().0;
-                $in_state.set(dest_id, true);
-            };
-        }
-
-        macro_rules! kill {
-            ($cxt:tt, $src:tt, $in_state:ident) => {
-                if let Operand::Place(p) = $src {
-                    let src_id = $cxt.cfg.local_infos.get(&p.label).unwrap().0;
-                    $in_state.set(src_id, false);
-                }
-            };
-        }
-
-        let out_state = self.out_states.get_mut(bid).unwrap();
-        let in_state = self.in_states.get_mut(bid).unwrap();
-        in_state.clone_from(out_state);
-        match inst {
-            IRInst::LoadAddr { .. } => {
-                todo!()
-            }
-            IRInst::LoadData { dest, src } => {
-                gen!(self, dest, in_state);
-                kill!(self, src, in_state);
-            }
-            IRInst::BinOp {
-                dest, src1, src2, ..
-            } => {
-                gen!(self, dest, in_state);
-                kill!(self, src1, in_state);
-                kill!(self, src2, in_state);
-            }
-            IRInst::JumpIf { cond, .. } | IRInst::JumpIfNot { cond, .. } => {
-                kill!(self, cond, in_state);
-            }
-            IRInst::JumpIfCond { src1, src2, .. } => {
-                kill!(self, src1, in_state);
-                kill!(self, src2, in_state);
-            }
-            IRInst::Call {args, ..} => {
-                for arg in args {
-                    kill!(self, arg, in_state);
-                }
-            }
-            _ => {}
-        }
+            })
     }
 }
@@ -1,2 +1,3 @@
-mod live_variable;
+pub(crate) mod live_variable;
+#[cfg(test)]
 mod tests;
@@ -48,3 +48,41 @@ fn multiple_blocks_test() {
     analysis.apply();
     println!("{:?}", analysis.in_states);
 }
+
+#[test]
+fn loop_test() {
+    let cfg = get_cfg(
+        r#"
+        fn foo(x: i32) -> i32 {
+            let mut a = x;
+            let mut b = 0;
+            while a > 0 {
+                b += a;
+                a -= 1;
+            }
+            b
+        }
+    "#,
+    )
+    .unwrap();
+    let mut analysis = LiveVariableAnalysis::new(&cfg);
+    analysis.apply();
+
+    let x = cfg.local_infos.get("x_2").unwrap().0;
+    let a = cfg.local_infos.get("a_2").unwrap().0;
+    let b = cfg.local_infos.get("b_2").unwrap().0;
+    // entry block
+    assert!(analysis.in_states[0].get(x).unwrap());
+    assert!(!analysis.in_states[0].get(a).unwrap());
+    assert!(!analysis.in_states[0].get(b).unwrap());
+    // a and b are live around the loop
+    for bid in 1..cfg.basic_blocks.len() - 1 {
+        assert!(analysis.in_states[bid].get(a).unwrap());
+        assert!(analysis.in_states[bid].get(b).unwrap());
+        assert!(!analysis.in_states[bid].get(x).unwrap());
+    }
+    // exit block
+    let last = cfg.basic_blocks.len() - 1;
+    assert!(analysis.in_states[last].get(b).unwrap());
+    assert!(!analysis.in_states[last].get(a).unwrap());
+}
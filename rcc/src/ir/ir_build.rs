@@ -1,46 +1,122 @@
-use crate::analyser::scope::ScopeStack;
-use crate::analyser::sym_resolver::{TypeInfo, VarKind};
+use crate::analyser::scope::{Scope, ScopeStack};
+use crate::analyser::sym_resolver::{locate_at_expr, Capture, ClosureInfo, TypeInfo, VarKind};
 use crate::ast::expr::{
     ArrayExpr, ArrayIndexExpr, AssignExpr, AssignOp, BinOpExpr, BinOperator, BlockExpr, BreakExpr,
-    CallExpr, Expr, ExprKind, ExprVisit, FieldAccessExpr, GroupedExpr, IfExpr, LhsExpr, LitNumExpr,
-    LoopExpr, PathExpr, RangeExpr, ReturnExpr, StructExpr, TupleExpr, TupleIndexExpr, UnAryExpr,
+    CallExpr, CastExpr, ClosureExpr, ContinueExpr, Expr, ExprKind, ExprVisit, FieldAccessExpr, ForExpr, GroupedExpr, IfExpr, LhsExpr, LitNumExpr,
+    LoopExpr, MatchExpr, MethodCallExpr, PathExpr, RangeExpr, RangeOp, ReturnExpr, StructExpr, TupleExpr, TupleIndexExpr, UnAryExpr,
     UnOp, WhileExpr,
 };
 use crate::ast::file::File;
-use crate::ast::item::{Item, ItemFn, ItemStruct};
-use crate::ast::pattern::{IdentPattern, Pattern};
+use crate::ast::item::{Item, ItemFn, ItemImpl, ItemTrait, TraitItemFn};
+use crate::ast::pattern::{IdentPattern, Pattern, StructPattern};
 use crate::ast::stmt::{LetStmt, Stmt};
-use crate::ast::types::TypeLitNum;
+use crate::ast::types::{PtrKind, TypeLitNum};
 use crate::ast::AST;
 use crate::ir;
-use crate::ir::linear_ir::LinearIR;
+use crate::ir::linear_ir::{
+    assoc_fn_symbol, closure_symbol, generic_fn_symbol, param_name, trait_fn_symbol, LinearIR,
+};
+use crate::ir::var_name::{CLOSURE_ENV, PANIC_BOUNDS_CHECK, SRET};
 use crate::ir::Jump::*;
 use crate::ir::{IRInst, IRType, Jump, Operand, Place};
-use crate::rcc::{OptimizeLevel, RccError};
+use crate::rcc::RccError;
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::rc::Rc;
 
+/// The value matched by a pattern.
+#[derive(Clone)]
+enum MatchedValue {
+    Operand(Operand),
+    /// value stored at `addr + offset`
+    Memory(Operand, u32),
+}
+
 pub struct IRBuilder {
     ir_output: LinearIR,
     fn_ret_temp_var: Vec<Place>,
 
     scope_stack: ScopeStack,
 
-    // (place = loop expr, break link)
-    loop_var_stack: Vec<(Option<Place>, usize)>,
+    /// the loops being generated, the innermost one is the last
+    loop_var_stack: Vec<LoopContext>,
+
+    /// byte size of an address in the target platform
+    addr_size: u32,
+
+    /// generic parameter name -> type argument of the function instance being generated
+    type_args: HashMap<String, TypeInfo>,
+    /// generic functions and default methods of traits, whose instances are generated
+    /// after all the items of the file
+    templates: HashMap<String, FnTemplate>,
+    /// (symbol, template, type arguments) of the instances to generate
+    pending_instances: Vec<(String, String, Vec<TypeInfo>)>,
+    /// symbols of the generated instances
+    instances: HashSet<String>,
+
+    /// symbol of the function being generated, closures are named after the functions
+    /// defining them
+    fn_symbol: String,
+    /// closures to generate after the functions defining them
+    pending_closures: Vec<PendingClosure>,
+    /// (address of the environment, environment type, captured variables) of the closure
+    /// being generated
+    closure_env: Option<(Place, IRType, Vec<Capture>)>,
+}
+
+/// A closure and the context of the function instance where it is defined.
+struct LoopContext {
+    label: Option<String>,
+    /// the place of the value of `loop`
+    break_place: Option<Place>,
+    /// back patch link of the jumps out of the loop
+    break_link: usize,
+    /// back patch link of the jumps to the next iteration
+    continue_link: usize,
+}
+
+impl LoopContext {
+    fn new(label: Option<String>, break_place: Option<Place>, break_link: usize) -> LoopContext {
+        LoopContext {
+            label,
+            break_place,
+            break_link,
+            continue_link: 0,
+        }
+    }
+}
+
+struct PendingClosure {
+    closure_expr: *mut ClosureExpr,
+    closure: ClosureInfo,
+    scope: *mut Scope,
+    fn_symbol: String,
+    type_args: HashMap<String, TypeInfo>,
+}
 
-    optimize_level: OptimizeLevel,
+/// A generic function and the scope where it is defined.
+struct FnTemplate {
+    item_fn: *mut ItemFn,
+    scope: *mut Scope,
+    params: Vec<String>,
 }
 
 impl IRBuilder {
-    pub fn new(optimize_level: OptimizeLevel) -> IRBuilder {
+    pub fn new(addr_size: u32) -> IRBuilder {
         IRBuilder {
             ir_output: LinearIR::new(),
             fn_ret_temp_var: vec![],
             scope_stack: ScopeStack::new(),
             loop_var_stack: vec![],
-            optimize_level,
+            addr_size,
+            type_args: HashMap::new(),
+            templates: HashMap::new(),
+            pending_instances: vec![],
+            instances: HashSet::new(),
+            fn_symbol: String::new(),
+            pending_closures: vec![],
+            closure_env: None,
         }
     }
 
@@ -51,10 +127,16 @@ impl IRBuilder {
         Ok(output)
     }
 
+    /// IR type of the type info, whose generic parameters are replaced with the type
+    /// arguments of the function instance being generated.
+    fn ir_type(&self, type_info: &TypeInfo) -> Result<IRType, RccError> {
+        IRType::from_type_info(&type_info.substitute(&self.type_args))
+    }
+
     fn gen_temp_var(&mut self, type_info: Rc<RefCell<TypeInfo>>) -> Place {
         let t = type_info.borrow();
         let tp = t.deref();
-        let ir_type = IRType::from_type_info(tp).unwrap();
+        let ir_type = self.ir_type(tp).unwrap();
         std::mem::drop(t);
         let label = self
             .scope_stack
@@ -63,9 +145,55 @@ impl IRBuilder {
         Place::local(label, ir_type)
     }
 
+    /// Generate a temp variable of type `&type_info`.
+    fn gen_ptr_temp_var(&mut self, type_info: &Rc<RefCell<TypeInfo>>) -> Place {
+        let ptr_info = TypeInfo::Ptr {
+            kind: PtrKind::Ref,
+            type_info: Box::new(type_info.borrow().clone()),
+        };
+        self.gen_temp_var(Rc::new(RefCell::new(ptr_info)))
+    }
+
+    /// Generate a temp variable of type `*const ()`, like the type-erased
+    /// data pointers and the vtable pointers of trait objects.
+    fn gen_erased_ptr_temp_var(&mut self) -> Place {
+        let ptr_info = TypeInfo::Ptr {
+            kind: PtrKind::ConstRawPtr,
+            type_info: Box::new(TypeInfo::Unit),
+        };
+        self.gen_temp_var(Rc::new(RefCell::new(ptr_info)))
+    }
+
+    /// Generate a temp variable holding the address of `place`.
+    fn gen_addr_of(&mut self, place: Place, type_info: &Rc<RefCell<TypeInfo>>) -> Operand {
+        let addr = self.gen_ptr_temp_var(type_info);
+        self.ir_output
+            .add_instructions(IRInst::load_addr(addr.clone(), Operand::Place(place)));
+        Operand::Place(addr)
+    }
+
+    /// Large aggregate results are written to the address in the hidden argument,
+    /// others are returned in registers.
+    fn gen_ret(&mut self, operand: Operand) {
+        self.gen_write_back();
+        match operand {
+            Operand::Place(p) if p.ir_type.is_returned_by_sret(self.addr_size) => {
+                let scope_id = self.ir_output.cur_func_mut().block_scope_id;
+                let sret = Place::variable(SRET, scope_id, VarKind::Local, IRType::Addr);
+                self.ir_output.add_instructions(IRInst::store(
+                    Operand::Place(sret),
+                    0,
+                    Operand::Place(p),
+                ));
+                self.ir_output.add_instructions(IRInst::Ret(Operand::Unit));
+            }
+            operand => self.ir_output.add_instructions(IRInst::Ret(operand)),
+        }
+    }
+
     fn gen_variable(&mut self, ident: &str, var_kind: VarKind) -> Place {
         let res = self.scope_stack.cur_scope().find_variable(ident).unwrap();
-        let ir_type = IRType::from_var_info(res.0).unwrap();
+        let ir_type = self.ir_type(&res.0.type_info.borrow()).unwrap();
         Place::variable(ident, res.1, var_kind, ir_type)
     }
 
@@ -74,53 +202,313 @@ impl IRBuilder {
         for item in file.items.iter_mut() {
             self.visit_item(item)?;
         }
+        self.gen_instances()
+    }
+
+    /// Generic functions are monomorphized for the type arguments they are called with,
+    /// and instances may call other generic functions. Closures are generated after
+    /// the functions defining them.
+    fn gen_instances(&mut self) -> Result<(), RccError> {
+        loop {
+            if let Some(pending) = self.pending_closures.pop() {
+                self.gen_closure(pending)?;
+            } else if let Some((symbol, key, args)) = self.pending_instances.pop() {
+                let template = &self.templates[&key];
+                let (item_fn, scope) = (template.item_fn, template.scope);
+                self.type_args = template.params.iter().cloned().zip(args).collect();
+                self.scope_stack.reenter(scope);
+                self.gen_fn(symbol, unsafe { &mut *item_fn })?;
+                self.scope_stack.exit_scope();
+            } else {
+                break;
+            }
+        }
+        self.type_args.clear();
         Ok(())
     }
 
+    /// The captured variables are copied from the environment when the closure is called,
+    /// and the mutable ones are written back when it returns.
+    fn gen_closure(&mut self, pending: PendingClosure) -> Result<(), RccError> {
+        self.type_args = pending.type_args;
+        self.fn_symbol = pending.fn_symbol;
+        let symbol = closure_symbol(&self.fn_symbol, pending.closure.scope_id());
+        let captures = pending.closure.captures();
+        let env = if captures.is_empty() {
+            None
+        } else {
+            let env_type = self.ir_type(&TypeInfo::Closure(pending.closure.clone()))?;
+            Some((env_type, captures))
+        };
+        let item_fn = &mut unsafe { &mut *pending.closure_expr }.item_fn;
+        self.scope_stack.reenter(pending.scope);
+        let result = self.gen_fn_body(
+            symbol,
+            item_fn,
+            pending.closure.params(),
+            pending.closure.ret_type(),
+            env,
+        );
+        self.scope_stack.exit_scope();
+        result
+    }
+
+    /// Write the mutable captured variables back to the environment of the closure
+    /// being generated.
+    fn gen_write_back(&mut self) {
+        let (env, env_type, captures) = match &self.closure_env {
+            Some(closure_env) => closure_env.clone(),
+            None => return,
+        };
+        for (i, capture) in captures.iter().enumerate() {
+            if capture.kind != VarKind::LocalMut {
+                continue;
+            }
+            let offset = env_type.field_offset(i, self.addr_size);
+            let var = self.gen_captured_variable(capture);
+            if capture.by_ref {
+                let addr = self.gen_erased_ptr_temp_var();
+                self.ir_output.add_instructions(IRInst::load(
+                    addr.clone(),
+                    Operand::Place(env.clone()),
+                    offset,
+                ));
+                self.ir_output
+                    .add_instructions(IRInst::store(Operand::Place(addr), 0, Operand::Place(var)));
+            } else {
+                self.ir_output.add_instructions(IRInst::store(
+                    Operand::Place(env.clone()),
+                    offset,
+                    Operand::Place(var),
+                ));
+            }
+        }
+    }
+
+    fn gen_captured_variable(&self, capture: &Capture) -> Place {
+        let ir_type = self.ir_type(&capture.type_info).unwrap();
+        Place::variable(&capture.ident, capture.scope_id, capture.kind, ir_type)
+    }
+
+    fn add_template(&mut self, key: String, item_fn: &mut ItemFn, params: Vec<String>) {
+        let template = FnTemplate {
+            item_fn,
+            scope: self.scope_stack.cur_scope_mut(),
+            params,
+        };
+        self.templates.insert(key, template);
+    }
+
+    fn add_instance(&mut self, symbol: String, key: String, args: Vec<TypeInfo>) {
+        if self.instances.insert(symbol.clone()) {
+            self.pending_instances.push((symbol, key, args));
+        }
+    }
+
     fn visit_item(&mut self, item: &mut Item) -> Result<(), RccError> {
         match item {
-            Item::Fn(item_fn) => self.visit_item_fn(item_fn),
-            Item::Struct(item_struct) => self.visit_item_struct(item_struct),
-            Item::ExternalBlock(item_block) => {
+            Item::Fn(item_fn) => {
+                let span = item_fn.span.0;
+                self.visit_item_fn(item_fn).map_err(|e| e.or_span(span))
+            }
+            // layouts of structs and enums are computed from their type infos
+            Item::Struct(_) | Item::Enum(_) => Ok(()),
+            Item::ExternalBlock(_) => {
                 // do nothing
                 Ok(())
             }
-            _ => unimplemented!(),
+            Item::Impl(item_impl) => self.visit_item_impl(item_impl),
+            Item::Trait(item_trait) => {
+                self.visit_item_trait(item_trait);
+                Ok(())
+            }
         }
     }
 
+    /// Generic functions are generated when they are used.
     fn visit_item_fn(&mut self, item_fn: &mut ItemFn) -> Result<(), RccError> {
-        self.ir_output.add_func(item_fn)?;
-
         let info = self.scope_stack.cur_scope().find_fn(&item_fn.name);
         assert_eq!(info, TypeInfo::from_fn_signature(item_fn));
+        if item_fn.is_generic() {
+            let params = item_fn.generics.iter().map(|p| p.name.clone()).collect();
+            self.add_template(item_fn.name.clone(), item_fn, params);
+            return Ok(());
+        }
+        // functions nested in generic functions are visited by each instance
+        if !self.instances.insert(item_fn.name.clone()) {
+            return Ok(());
+        }
+        self.gen_fn(item_fn.name.clone(), item_fn)
+    }
 
-        let ret_info = TypeInfo::from_type_anno(&item_fn.ret_type, self.scope_stack.cur_scope());
-        // visit function block
-        let dest = self.gen_temp_var(Rc::new(RefCell::new(ret_info)));
-        self.fn_ret_temp_var.push(dest.clone());
+    /// Functions in impl blocks are named by their mangled symbols, and the default methods
+    /// of a trait not overridden by the impl block are generated for the implemented type.
+    fn visit_item_impl(&mut self, item_impl: &mut ItemImpl) -> Result<(), RccError> {
+        let self_type = match item_impl.scope.types.get("Self") {
+            Some(self_type) => self_type.clone(),
+            None => unreachable!("`Self` should be defined by the symbol resolver"),
+        };
+        let type_name = self_type.impl_name().unwrap();
+        self.scope_stack.enter(&mut item_impl.scope);
+        for item_fn in item_impl.items.iter_mut() {
+            let symbol = match &item_impl.trait_name {
+                Some(trait_name) => trait_fn_symbol(&type_name, trait_name, &item_fn.name),
+                None => assoc_fn_symbol(&type_name, &item_fn.name),
+            };
+            self.gen_fn(symbol, item_fn)?;
+        }
+        self.scope_stack.exit_scope();
 
-        let operand = self.visit_block_expr(&mut item_fn.fn_block, Some(dest), false)?;
+        if let Some(trait_name) = &item_impl.trait_name {
+            let trait_info = self.scope_stack.cur_scope().find_trait(trait_name).unwrap();
+            let provided: Vec<String> = trait_info
+                .fns
+                .iter()
+                .filter(|f| f.is_provided && item_impl.items.iter().all(|i| i.name != f.name))
+                .map(|f| f.name.clone())
+                .collect();
+            for fn_name in provided {
+                self.add_instance(
+                    trait_fn_symbol(&type_name, trait_name, &fn_name),
+                    format!("{}::{}", trait_name, fn_name),
+                    vec![self_type.clone()],
+                );
+            }
+        }
+        Ok(())
+    }
 
-        if item_fn.fn_block.last_expr.is_none() && item_fn.fn_block.stmts.is_empty() {
-            self.ir_output.add_instructions(IRInst::Ret(Operand::Unit));
-        } else if !item_fn.fn_block.last_stmt_is_return() {
-            self.ir_output.add_instructions(IRInst::Ret(operand));
+    /// The default methods are generic functions of `Self`.
+    fn visit_item_trait(&mut self, item_trait: &mut ItemTrait) {
+        self.scope_stack.enter(&mut item_trait.scope);
+        for item in item_trait.items.iter_mut() {
+            if let TraitItemFn::Provided(item_fn) = item {
+                let key = format!("{}::{}", item_trait.name, item_fn.name);
+                self.add_template(key, item_fn, vec!["Self".into()]);
+            }
         }
+        self.scope_stack.exit_scope();
+    }
 
-        self.fn_ret_temp_var.pop();
-        Ok(())
+    fn gen_fn(&mut self, fn_name: String, item_fn: &mut ItemFn) -> Result<(), RccError> {
+        // generic parameters are defined in the scope of the function block
+        let fn_scope = &item_fn.fn_block.scope;
+        let ret_info = TypeInfo::from_type_anno(&item_fn.ret_type, fn_scope);
+        let param_infos: Vec<TypeInfo> = item_fn
+            .fn_params
+            .params
+            .iter()
+            .map(|param| TypeInfo::from_type_anno(&param._type, fn_scope))
+            .collect();
+        let fn_symbol = std::mem::replace(&mut self.fn_symbol, fn_name.clone());
+        let result = self.gen_fn_body(fn_name, item_fn, param_infos, ret_info, None);
+        self.fn_symbol = fn_symbol;
+        result
     }
 
-    fn visit_item_struct(&mut self, item_struct: &mut ItemStruct) -> Result<(), RccError> {
-        unimplemented!()
+    /// `env` is the type of the environment and the captured variables of a closure
+    /// capturing something.
+    fn gen_fn_body(
+        &mut self,
+        fn_name: String,
+        item_fn: &mut ItemFn,
+        param_infos: Vec<TypeInfo>,
+        ret_info: TypeInfo,
+        env: Option<(IRType, Vec<Capture>)>,
+    ) -> Result<(), RccError> {
+        let param_types = param_infos
+            .iter()
+            .map(|t| self.ir_type(t))
+            .collect::<Result<Vec<IRType>, RccError>>()?;
+        self.ir_output.add_func(
+            fn_name,
+            item_fn,
+            env.is_some(),
+            &param_types,
+            &self.ir_type(&ret_info)?,
+            self.addr_size,
+        )?;
+
+        // bind the variables in the patterns of parameters
+        let scope_id = item_fn.fn_block.scope.scope_id;
+        self.scope_stack.enter(&mut item_fn.fn_block.scope);
+        let params = item_fn.fn_params.params.iter().enumerate();
+        for (((i, param), ir_type), type_info) in params.zip(param_types).zip(param_infos) {
+            let name = param_name(i, param);
+            let (is_variable, var_kind) = match &param.pattern {
+                Pattern::Identifier(p) if !p.is_ref() && p.is_mut() => (true, VarKind::LocalMut),
+                Pattern::Identifier(p) if !p.is_ref() => (true, VarKind::Local),
+                _ => (false, VarKind::Local),
+            };
+            let var = Place::variable(&name, scope_id, var_kind, ir_type.clone());
+            if is_variable {
+                continue;
+            }
+            let value = if ir_type.is_aggregate() {
+                let addr = self.gen_addr_of(var, &Rc::new(RefCell::new(type_info.clone())));
+                MatchedValue::Memory(addr, 0)
+            } else {
+                MatchedValue::Operand(Operand::Place(var))
+            };
+            self.gen_destructure(&param.pattern, &type_info, &value)?;
+        }
+        self.scope_stack.exit_scope();
+
+        // copy the captured variables from the environment
+        let closure_env = env.map(|(env_type, captures)| {
+            let env = Place::variable(CLOSURE_ENV, scope_id, VarKind::Local, IRType::Addr);
+            for (i, capture) in captures.iter().enumerate() {
+                let offset = env_type.field_offset(i, self.addr_size);
+                let var = self.gen_captured_variable(capture);
+                if capture.by_ref {
+                    let addr = self.gen_erased_ptr_temp_var();
+                    self.ir_output.add_instructions(IRInst::load(
+                        addr.clone(),
+                        Operand::Place(env.clone()),
+                        offset,
+                    ));
+                    self.ir_output
+                        .add_instructions(IRInst::load(var, Operand::Place(addr), 0));
+                } else {
+                    self.ir_output.add_instructions(IRInst::load(
+                        var,
+                        Operand::Place(env.clone()),
+                        offset,
+                    ));
+                }
+            }
+            (env, env_type, captures)
+        });
+        let closure_env = std::mem::replace(&mut self.closure_env, closure_env);
+
+        // visit function block
+        let dest = self.gen_temp_var(Rc::new(RefCell::new(ret_info)));
+        self.fn_ret_temp_var.push(dest.clone());
+
+        let result = self
+            .visit_block_expr(&mut item_fn.fn_block, Some(dest), false)
+            .map(|operand| {
+                if item_fn.fn_block.last_expr.is_none() && item_fn.fn_block.stmts.is_empty() {
+                    self.gen_write_back();
+                    self.ir_output.add_instructions(IRInst::Ret(Operand::Unit));
+                } else if !item_fn.fn_block.last_stmt_is_return() {
+                    self.gen_ret(operand);
+                }
+            });
+
+        self.fn_ret_temp_var.pop();
+        self.closure_env = closure_env;
+        result
     }
 
     fn visit_stmt(&mut self, stmt: &mut Stmt) -> Result<(), RccError> {
         match stmt {
             Stmt::Semi => Ok(()),
             Stmt::Item(item) => self.visit_item(item),
-            Stmt::Let(let_stmt) => self.visit_let_stmt(let_stmt),
+            Stmt::Let(let_stmt) => {
+                let span = let_stmt.span.0;
+                self.visit_let_stmt(let_stmt).map_err(|e| e.or_span(span))
+            }
             Stmt::ExprStmt(expr) => {
                 let operand = self.visit_expr(expr, None, false)?;
                 debug_assert!(operand.is_unit_or_never(), "{:?}", expr);
@@ -130,22 +518,328 @@ impl IRBuilder {
     }
 
     fn visit_let_stmt(&mut self, let_stmt: &mut LetStmt) -> Result<(), RccError> {
-        let is_mut = let_stmt.is_mut();
         if let Some(rhs) = &mut let_stmt.rhs {
             match &let_stmt.pattern {
-                Pattern::Identifier(ident_pattern) => {
-                    let ident = ident_pattern.ident();
-                    let dest = self.gen_variable(
-                        ident,
-                        if is_mut {
-                            VarKind::LocalMut
-                        } else {
-                            VarKind::Local
-                        },
-                    );
-                    let rhs = self.visit_expr(rhs, Some(dest), false)?;
+                Pattern::Identifier(ident_pattern) if !ident_pattern.is_ref() => {
+                    let dest = self.gen_pattern_variable(ident_pattern);
+                    self.visit_expr(rhs, Some(dest), false)?;
+                }
+                pattern => {
+                    let type_info = rhs.type_info().borrow().clone();
+                    let value = self.visit_matched_expr(rhs, pattern.has_ref_binding())?;
+                    self.gen_destructure(pattern, &type_info, &value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Aggregates are matched in place, and scalars are stored in temp variables
+    /// unless they are places borrowed by `ref` bindings.
+    fn visit_matched_expr(
+        &mut self,
+        expr: &mut Expr,
+        has_ref_binding: bool,
+    ) -> Result<MatchedValue, RccError> {
+        let type_info = expr.type_info();
+        let is_place = matches!(expr.kind(), ExprKind::Place | ExprKind::MutablePlace);
+        if self.ir_type(&type_info.borrow())?.is_aggregate()
+            || (has_ref_binding && is_place)
+        {
+            let (addr, offset) = self.visit_place_addr(expr)?;
+            return Ok(MatchedValue::Memory(addr, offset));
+        }
+        let temp = self.gen_temp_var(type_info);
+        let operand = match self.visit_expr(expr, Some(temp.clone()), false)? {
+            operand if operand.is_imm() => {
+                self.ir_output
+                    .add_instructions(IRInst::load_data(temp.clone(), operand));
+                Operand::Place(temp)
+            }
+            operand => operand,
+        };
+        Ok(MatchedValue::Operand(operand))
+    }
+
+    /// Return the operand holding the matched value of type `type_info`.
+    fn gen_matched_operand(&mut self, value: &MatchedValue, type_info: &TypeInfo) -> Operand {
+        match value {
+            MatchedValue::Operand(operand) => operand.clone(),
+            MatchedValue::Memory(addr, offset) => {
+                let temp = self.gen_temp_var(Rc::new(RefCell::new(type_info.clone())));
+                self.ir_output
+                    .add_instructions(IRInst::load(temp.clone(), addr.clone(), *offset));
+                Operand::Place(temp)
+            }
+        }
+    }
+
+    /// Return the address of the matched value.
+    fn gen_matched_addr(&mut self, value: &MatchedValue, type_info: &TypeInfo) -> Operand {
+        let type_info = Rc::new(RefCell::new(type_info.clone()));
+        match value {
+            MatchedValue::Operand(Operand::Place(place)) => {
+                self.gen_addr_of(place.clone(), &type_info)
+            }
+            MatchedValue::Operand(operand) => unreachable!("address of {:?}", operand),
+            MatchedValue::Memory(addr, 0) => addr.clone(),
+            MatchedValue::Memory(addr, offset) => {
+                let dest = self.gen_ptr_temp_var(&type_info);
+                self.ir_output.add_instructions(IRInst::bin_op(
+                    BinOperator::Plus,
+                    dest.clone(),
+                    addr.clone(),
+                    Operand::Usize(*offset as usize),
+                ));
+                Operand::Place(dest)
+            }
+        }
+    }
+
+    /// Return the value referred to by the matched reference of type `type_info`.
+    fn deref_matched_value(
+        &mut self,
+        value: &MatchedValue,
+        type_info: &TypeInfo,
+    ) -> (MatchedValue, TypeInfo) {
+        let inner = match type_info {
+            TypeInfo::Ptr { type_info, .. } => type_info.deref().clone(),
+            t => unreachable!("reference pattern of type {:?}", t),
+        };
+        let ptr = self.gen_matched_operand(value, type_info);
+        (MatchedValue::Memory(ptr, 0), inner)
+    }
+
+    /// Return the index of the variant if the path is like `Color::Red`.
+    fn variant_index(&self, segments: &[String]) -> Option<usize> {
+        if segments.len() != 2 {
+            return None;
+        }
+        match self.scope_stack.cur_scope().find_def_except_fn(&segments[0]) {
+            TypeInfo::Enum { variants, .. } => variants.find(&segments[1]).map(|(i, _)| i),
+            _ => None,
+        }
+    }
+
+    /// Symbol of the associated function referred to by a path like `Foo::new` or `T::zero`.
+    fn assoc_fn_symbol(&self, segments: &[String]) -> Option<String> {
+        if segments.len() != 2 {
+            return None;
+        }
+        let type_info = self
+            .scope_stack
+            .cur_scope()
+            .find_def_except_fn(&segments[0]);
+        self.type_fn_symbol(&type_info, &segments[1])
+    }
+
+    /// Symbol of the function in the impl blocks of the type or the traits implemented
+    /// by the type. Functions of a generic parameter are found in the traits of its bounds,
+    /// and called on the type argument.
+    fn type_fn_symbol(&self, type_info: &TypeInfo, fn_name: &str) -> Option<String> {
+        let assoc_fn = self
+            .scope_stack
+            .cur_scope()
+            .find_type_fn(type_info, fn_name)?;
+        let type_name = type_info.substitute(&self.type_args).impl_name().unwrap();
+        Some(match &assoc_fn.trait_name {
+            Some(trait_name) => trait_fn_symbol(&type_name, trait_name, fn_name),
+            None => assoc_fn_symbol(&type_name, fn_name),
+        })
+    }
+
+    fn gen_pattern_variable(&mut self, ident_pattern: &IdentPattern) -> Place {
+        self.gen_variable(
+            ident_pattern.ident(),
+            if ident_pattern.is_mut() {
+                VarKind::LocalMut
+            } else {
+                VarKind::Local
+            },
+        )
+    }
+
+    /// Bind the variables in the pattern, the value is assumed to match the pattern.
+    ///
+    /// ## Example
+    ///
+    /// let (a, (b, c)) = t;
+    ///
+    /// <=>
+    ///
+    /// $1 = &t
+    /// a = *($1 + offset of t.0)
+    /// b = *($1 + offset of t.1 + offset of t.1.0)
+    /// c = *($1 + offset of t.1 + offset of t.1.1)
+    fn gen_destructure(
+        &mut self,
+        pattern: &Pattern,
+        type_info: &TypeInfo,
+        value: &MatchedValue,
+    ) -> Result<(), RccError> {
+        match pattern {
+            Pattern::Identifier(ident_pattern) if ident_pattern.is_ref() => {
+                let dest = self.gen_pattern_variable(ident_pattern);
+                let addr = self.gen_matched_addr(value, type_info);
+                self.ir_output
+                    .add_instructions(IRInst::load_data(dest, addr));
+            }
+            Pattern::Identifier(ident_pattern) => {
+                let dest = self.gen_pattern_variable(ident_pattern);
+                let inst = match value {
+                    MatchedValue::Operand(operand) => IRInst::load_data(dest, operand.clone()),
+                    MatchedValue::Memory(addr, offset) => IRInst::load(dest, addr.clone(), *offset),
+                };
+                self.ir_output.add_instructions(inst);
+            }
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range(..) | Pattern::Path(_) => {}
+            Pattern::Tuple(patterns) | Pattern::TupleStruct(_, patterns) => {
+                let fields = self.fields_of_matched_value(pattern, type_info, value)?;
+                for (pattern, (field_value, t)) in patterns.iter().zip(fields) {
+                    self.gen_destructure(pattern, &t, &field_value)?;
+                }
+            }
+            Pattern::Struct(struct_pattern) => {
+                let fields = self.fields_of_matched_value(pattern, type_info, value)?;
+                let indexes = struct_field_indexes(struct_pattern, type_info);
+                for (field, i) in struct_pattern.fields.iter().zip(indexes) {
+                    let (field_value, t) = &fields[i];
+                    self.gen_destructure(&field.pattern, t, field_value)?;
+                }
+            }
+            Pattern::Reference(pattern, _) => {
+                let (inner_value, t) = self.deref_matched_value(value, type_info);
+                self.gen_destructure(pattern, &t, &inner_value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Values and types of the fields of the value matched by a tuple, struct or variant pattern.
+    fn fields_of_matched_value(
+        &self,
+        pattern: &Pattern,
+        type_info: &TypeInfo,
+        value: &MatchedValue,
+    ) -> Result<Vec<(MatchedValue, TypeInfo)>, RccError> {
+        let ir_type = self.ir_type(type_info)?;
+        let (types, offsets): (Vec<TypeInfo>, Vec<u32>) = match (pattern, type_info) {
+            (Pattern::Tuple(_), TypeInfo::Tuple(types)) => (
+                types.clone(),
+                (0..types.len())
+                    .map(|i| ir_type.field_offset(i, self.addr_size))
+                    .collect(),
+            ),
+            (Pattern::Struct(_), TypeInfo::Struct { fields, .. }) => {
+                let types = fields.types();
+                let offsets = (0..types.len())
+                    .map(|i| ir_type.field_offset(i, self.addr_size))
+                    .collect();
+                (types, offsets)
+            }
+            (Pattern::TupleStruct(path, _), TypeInfo::Enum { variants, .. })
+            | (Pattern::Struct(StructPattern { path, .. }), TypeInfo::Enum { variants, .. }) => {
+                let (idx, variant) = variants.find(path.segments.last().unwrap()).unwrap();
+                (
+                    variant.field_types(),
+                    (0..variant.fields.len())
+                        .map(|i| ir_type.variant_field_offset(idx, i, self.addr_size))
+                        .collect(),
+                )
+            }
+            // `()`
+            _ => return Ok(vec![]),
+        };
+        let (addr, offset) = match value {
+            MatchedValue::Memory(addr, offset) => (addr, *offset),
+            MatchedValue::Operand(_) => unreachable!("fields of a scalar value"),
+        };
+        Ok(types
+            .into_iter()
+            .zip(offsets)
+            .map(|(t, o)| (MatchedValue::Memory(addr.clone(), offset + o), t))
+            .collect())
+    }
+
+    /// Generate the jump instructions taken if the value does not match the pattern,
+    /// and add them to the back patch link.
+    fn gen_pattern_test(
+        &mut self,
+        pattern: &mut Pattern,
+        type_info: &TypeInfo,
+        value: &MatchedValue,
+        miss_back_patch_link: &mut usize,
+    ) -> Result<(), RccError> {
+        let tests = match pattern {
+            Pattern::Identifier(_) | Pattern::Wildcard => return Ok(()),
+            Pattern::Literal(expr) => {
+                let lhs = self.gen_matched_operand(value, type_info);
+                let d = self.gen_temp_var(expr.type_info());
+                let rhs = self.visit_expr(expr, Some(d), false)?;
+                vec![(JNe, lhs, rhs)]
+            }
+            // miss if `v < lo || hi < v` for `lo..=hi`, or `v < lo || v >= hi` for `lo..hi`
+            Pattern::Range(lo, op, hi) => {
+                let v = self.gen_matched_operand(value, type_info);
+                let d = self.gen_temp_var(lo.type_info());
+                let lo = self.visit_expr(lo, Some(d), false)?;
+                let d = self.gen_temp_var(hi.type_info());
+                let hi = self.visit_expr(hi, Some(d), false)?;
+                let hi_test = match op {
+                    RangeOp::DotDotEq => (JLt, hi, v.clone()),
+                    RangeOp::DotDot => (JGe, v.clone(), hi),
+                };
+                vec![(JLt, v, lo), hi_test]
+            }
+            Pattern::Path(path)
+            | Pattern::TupleStruct(path, _)
+            | Pattern::Struct(StructPattern { path, .. })
+                if matches!(type_info, TypeInfo::Enum { .. }) =>
+            {
+                let idx = self.variant_index(&path.segments).unwrap();
+                let ir_type = self.ir_type(type_info)?;
+                let discriminant = match (&ir_type, value) {
+                    (IRType::Aggregate(fields), MatchedValue::Memory(addr, offset)) => {
+                        let disc_info = match &fields[0] {
+                            IRType::U8 => TypeInfo::LitNum(TypeLitNum::U8),
+                            _ => TypeInfo::LitNum(TypeLitNum::U32),
+                        };
+                        let temp = self.gen_temp_var(Rc::new(RefCell::new(disc_info)));
+                        self.ir_output
+                            .add_instructions(IRInst::load(temp.clone(), addr.clone(), *offset));
+                        Operand::Place(temp)
+                    }
+                    _ => self.gen_matched_operand(value, type_info),
+                };
+                vec![(JNe, discriminant, ir_type.discriminant_operand(idx))]
+            }
+            _ => vec![],
+        };
+        for (cond, lhs, rhs) in tests {
+            let inst = IRInst::jump_if_cond(cond, lhs, rhs, *miss_back_patch_link);
+            *miss_back_patch_link = self.ir_output.next_inst_id();
+            self.ir_output.add_instructions(inst);
+        }
+
+        let fields = self.fields_of_matched_value(pattern, type_info, value)?;
+        match pattern {
+            Pattern::Tuple(patterns) | Pattern::TupleStruct(_, patterns) => {
+                for (pattern, (field_value, t)) in patterns.iter_mut().zip(fields) {
+                    self.gen_pattern_test(pattern, &t, &field_value, miss_back_patch_link)?;
                 }
             }
+            Pattern::Struct(struct_pattern) => {
+                let indexes = struct_field_indexes(struct_pattern, type_info);
+                for (field, i) in struct_pattern.fields.iter_mut().zip(indexes) {
+                    let (field_value, t) = &fields[i];
+                    self.gen_pattern_test(&mut field.pattern, t, field_value, miss_back_patch_link)?;
+                }
+            }
+            Pattern::Reference(pattern, _) => {
+                let (inner_value, t) = self.deref_matched_value(value, type_info);
+                self.gen_pattern_test(pattern, &t, &inner_value, miss_back_patch_link)?;
+            }
+            _ => {}
         }
         Ok(())
     }
@@ -163,24 +857,37 @@ impl IRBuilder {
             Expr::LitChar(lit_char) => self.visit_lit_char(lit_char, dest, remain_temp),
             Expr::LitStr(s) => self.visit_lit_str(s, dest, remain_temp),
             Expr::Unary(unary_expr) => self.visit_unary_expr(unary_expr, dest, remain_temp),
+            Expr::Cast(cast_expr) => self.visit_cast_expr(cast_expr, dest, remain_temp),
             Expr::Block(block_expr) => self.visit_block_expr(block_expr, dest, remain_temp),
             Expr::Assign(assign_expr) => self.visit_assign_expr(assign_expr),
-            // Expr::Range(range_expr) => self.visit_range_expr(range_expr),
+            Expr::Range(range_expr) => self.visit_range_expr(range_expr, dest),
             Expr::BinOp(bin_op_expr) => self.visit_bin_op_expr(bin_op_expr, dest),
             Expr::Grouped(grouped_expr) => self.visit_grouped_expr(grouped_expr, dest, remain_temp),
-            // Expr::Array(array_expr) => self.visit_array_expr(array_expr),
-            // Expr::ArrayIndex(array_index_expr) => self.visit_array_index_expr(array_index_expr),
-            // Expr::Tuple(tuple_expr) => self.visit_tuple_expr(tuple_expr),
-            // Expr::TupleIndex(tuple_index_expr) => self.visit_tuple_index_expr(tuple_index_expr),
-            // Expr::Struct(struct_expr) => self.visit_struct_expr(struct_expr),
+            Expr::Array(array_expr) => self.visit_array_expr(array_expr, dest),
+            Expr::ArrayIndex(array_index_expr) => {
+                self.visit_array_index_expr(array_index_expr, dest)
+            }
+            Expr::Tuple(tuple_expr) => self.visit_tuple_expr(tuple_expr, dest),
+            Expr::TupleIndex(tuple_index_expr) => {
+                self.visit_tuple_index_expr(tuple_index_expr, dest)
+            }
+            Expr::Struct(struct_expr) => self.visit_struct_expr(struct_expr, dest),
             Expr::Call(call_expr) => self.visit_call_expr(call_expr, dest),
-            // Expr::FieldAccess(field_access_expr) => self.visit_field_access_expr(field_access_expr),
+            Expr::MethodCall(method_call_expr) => {
+                self.visit_method_call_expr(method_call_expr, dest)
+            }
+            Expr::FieldAccess(field_access_expr) => {
+                self.visit_field_access_expr(field_access_expr, dest)
+            }
             Expr::While(while_expr) => self.visit_while_expr(while_expr),
             Expr::Loop(loop_expr) => self.visit_loop_expr(loop_expr, dest),
+            Expr::For(for_expr) => self.visit_for_expr(for_expr),
             Expr::If(if_expr) => self.visit_if_expr(if_expr, dest),
+            Expr::Match(match_expr) => self.visit_match_expr(match_expr, dest),
             Expr::Return(return_expr) => self.visit_return_expr(return_expr, dest),
             Expr::Break(break_expr) => self.visit_break_expr(break_expr, dest),
-            _ => unimplemented!(),
+            Expr::Continue(continue_expr) => self.visit_continue_expr(continue_expr, dest),
+            Expr::Closure(closure_expr) => self.visit_closure_expr(closure_expr, dest),
         };
         debug_assert_ne!(
             ExprKind::Unknown,
@@ -188,15 +895,7 @@ impl IRBuilder {
             "unknown expr kind: {:?}",
             expr
         );
-        result
-    }
-
-    fn visit_lhs_expr(&mut self, lhs_expr: &mut LhsExpr) -> Result<Operand, RccError> {
-        let r = match lhs_expr {
-            LhsExpr::Path(expr) => self.visit_path_expr(expr, None, false)?,
-            _ => todo!("visit lhs expr"),
-        };
-        Ok(r)
+        result.map_err(|e| locate_at_expr(e, expr))
     }
 
     fn visit_grouped_expr(
@@ -208,29 +907,24 @@ impl IRBuilder {
         self.visit_expr(grouped_expr, dest, remain_temp)
     }
 
-    fn visit_pattern(&mut self, pattern: &mut Pattern) -> Result<Operand, RccError> {
-        unimplemented!()
-    }
-
-    fn visit_ident_pattern(
-        &mut self,
-        ident_pattern: &mut IdentPattern,
-    ) -> Result<Operand, RccError> {
-        unimplemented!()
-    }
-
     fn visit_path_expr(
         &mut self,
         path_expr: &mut PathExpr,
         dest: Option<Place>,
         remain_temp: bool,
     ) -> Result<Operand, RccError> {
+        if let Some(idx) = self.variant_index(&path_expr.segments) {
+            return self.gen_variant(path_expr.type_info(), idx, vec![], dest, remain_temp);
+        }
+        if let Some(symbol) = self.assoc_fn_symbol(&path_expr.segments) {
+            return self.lit(Operand::FnLabel(symbol), dest, remain_temp);
+        }
         // TODO path segmentation
         let ident = path_expr.segments.last().unwrap();
 
         let cur_scope = self.scope_stack.cur_scope();
         if let Some((var, scope_id)) = cur_scope.find_variable(ident) {
-            let ir_type = IRType::from_var_info(var)?;
+            let ir_type = self.ir_type(&var.type_info.borrow())?;
             let operand = Operand::Place(Place::variable(ident, scope_id, var.kind(), ir_type));
             if let Some(d) = dest {
                 if !d.is_temp() || remain_temp {
@@ -239,8 +933,18 @@ impl IRBuilder {
                 }
             }
             Ok(operand)
-        } else if !cur_scope.find_fn(ident).is_unknown() {
-            Ok(Operand::FnLabel(ident.clone()))
+        } else if let TypeInfo::Fn { generics, .. } = cur_scope.find_fn(ident) {
+            if generics.is_empty() {
+                return self.lit(Operand::FnLabel(ident.clone()), dest, remain_temp);
+            }
+            let args: Vec<TypeInfo> = path_expr
+                .type_args
+                .iter()
+                .map(|t| t.substitute(&self.type_args))
+                .collect();
+            let symbol = generic_fn_symbol(ident, &args);
+            self.add_instance(symbol.clone(), ident.clone(), args);
+            self.lit(Operand::FnLabel(symbol), dest, remain_temp)
         } else {
             Err("error in visit path expr: ident not found".into())
         }
@@ -271,22 +975,7 @@ impl IRBuilder {
         remain_temp: bool,
     ) -> Result<Operand, RccError> {
         let t = lit_num_expr.get_lit_type();
-        let operand = match t {
-            TypeLitNum::I8 => Operand::I8(lit_num_expr.value.parse()?),
-            TypeLitNum::I16 => Operand::I16(lit_num_expr.value.parse()?),
-            TypeLitNum::I | TypeLitNum::I32 => Operand::I32(lit_num_expr.value.parse()?),
-            TypeLitNum::I64 => Operand::I64(lit_num_expr.value.parse()?),
-            TypeLitNum::I128 => Operand::I128(lit_num_expr.value.parse()?),
-            TypeLitNum::Isize => Operand::Isize(lit_num_expr.value.parse()?),
-            TypeLitNum::U8 => Operand::U8(lit_num_expr.value.parse()?),
-            TypeLitNum::U16 => Operand::U16(lit_num_expr.value.parse()?),
-            TypeLitNum::U32 => Operand::U32(lit_num_expr.value.parse()?),
-            TypeLitNum::U64 => Operand::U64(lit_num_expr.value.parse()?),
-            TypeLitNum::U128 => Operand::U128(lit_num_expr.value.parse()?),
-            TypeLitNum::Usize => Operand::Usize(lit_num_expr.value.parse()?),
-            TypeLitNum::F32 => Operand::F32(lit_num_expr.value.parse()?),
-            TypeLitNum::F | TypeLitNum::F64 => Operand::F64(lit_num_expr.value.parse()?),
-        };
+        let operand = lit_num_operand(t, &lit_num_expr.value)?;
         self.lit(operand, dest, remain_temp)
     }
 
@@ -327,57 +1016,307 @@ impl IRBuilder {
         // let operand = self.visit_expr(&mut unary_expr.expr, d)?;
         match unary_expr.op {
             UnOp::Neg => {
+                // the negated literal is parsed as a whole, since `-128i8` is in range but `128i8` is not
+                if let Expr::LitNum(lit_num_expr) = unary_expr.expr.as_mut() {
+                    let value = format!("-{}", lit_num_expr.value);
+                    let operand = lit_num_operand(lit_num_expr.get_lit_type(), &value)?;
+                    return self.lit(operand, dest, remain_temp);
+                }
+                let temp_dest = self.gen_temp_var(unary_expr.expr.type_info());
+                let operand = self.visit_expr(&mut unary_expr.expr, Some(temp_dest), false)?;
+                let neg_overflow = || RccError::from("neg overflow");
+                let operand = match operand {
+                    Operand::I8(i) => Operand::I8(i.checked_neg().ok_or_else(neg_overflow)?),
+                    Operand::I16(i) => Operand::I16(i.checked_neg().ok_or_else(neg_overflow)?),
+                    Operand::I32(i) => Operand::I32(i.checked_neg().ok_or_else(neg_overflow)?),
+                    Operand::I64(i) => Operand::I64(i.checked_neg().ok_or_else(neg_overflow)?),
+                    Operand::I128(i) => Operand::I128(i.checked_neg().ok_or_else(neg_overflow)?),
+                    Operand::Isize(i) => Operand::Isize(i.checked_neg().ok_or_else(neg_overflow)?),
+                    Operand::F32(f) => Operand::F32(-f),
+                    Operand::F64(f) => Operand::F64(-f),
+                    // 0 - x, or -0.0 - x for floats, which is -x for all the floats including the zeros
+                    Operand::Place(ref p) => {
+                        let zero = match p.ir_type {
+                            IRType::F32 => Operand::F32(-0.0),
+                            IRType::F64 => Operand::F64(-0.0),
+                            ref t => Operand::zero(t),
+                        };
+                        return match dest {
+                            Some(d) => self.bin_op(zero, operand, BinOperator::Minus, d),
+                            None => Ok(Operand::Unit),
+                        };
+                    }
+                    // unsigned integers are rejected by the symbol resolver
+                    o => unreachable!("cannot apply `-` to {:?}", o),
+                };
+                self.lit(operand, dest, remain_temp)
+            }
+            UnOp::Not => {
                 let temp_dest = self.gen_temp_var(unary_expr.expr.type_info());
                 let operand = self.visit_expr(&mut unary_expr.expr, Some(temp_dest), false)?;
                 let operand = match operand {
-                    Operand::I8(i) => Operand::I8(-i),
-                    Operand::I16(i) => Operand::I16(-i),
-                    Operand::I32(i) => Operand::I32(-i),
-                    _ => todo!(),
+                    Operand::Bool(b) => Operand::Bool(!b),
+                    Operand::I8(i) => Operand::I8(!i),
+                    Operand::I16(i) => Operand::I16(!i),
+                    Operand::I32(i) => Operand::I32(!i),
+                    Operand::I64(i) => Operand::I64(!i),
+                    Operand::I128(i) => Operand::I128(!i),
+                    Operand::Isize(i) => Operand::Isize(!i),
+                    Operand::U8(i) => Operand::U8(!i),
+                    Operand::U16(i) => Operand::U16(!i),
+                    Operand::U32(i) => Operand::U32(!i),
+                    Operand::U64(i) => Operand::U64(!i),
+                    Operand::U128(i) => Operand::U128(!i),
+                    Operand::Usize(i) => Operand::Usize(!i),
+                    // x ^ 1 for bools, and x ^ -1, namely `not`, for integers
+                    Operand::Place(ref p) => {
+                        let ones = match p.ir_type {
+                            IRType::Bool => Operand::Bool(true),
+                            ref t => Operand::all_ones(t),
+                        };
+                        return match dest {
+                            Some(d) => self.bin_op(operand, ones, BinOperator::Caret, d),
+                            None => Ok(Operand::Unit),
+                        };
+                    }
+                    o => unreachable!("cannot apply `!` to {:?}", o),
+                };
+                self.lit(operand, dest, remain_temp)
+            }
+            UnOp::Borrow | UnOp::BorrowMut => {
+                let (addr, offset) = self.visit_place_addr(&mut unary_expr.expr)?;
+                let operand = if offset == 0 {
+                    addr
+                } else {
+                    let d = self.gen_temp_var(unary_expr.type_info());
+                    self.bin_op(addr, Operand::Usize(offset as usize), BinOperator::Plus, d)?
                 };
                 self.lit(operand, dest, remain_temp)
             }
-            _ => todo!(),
+            UnOp::Deref => {
+                let (addr, offset) = self.visit_deref_place(unary_expr)?;
+                match dest {
+                    Some(d) => {
+                        self.ir_output
+                            .add_instructions(IRInst::load(d.clone(), addr, offset));
+                        Ok(Operand::Place(d))
+                    }
+                    None => Ok(Operand::Unit),
+                }
+            }
+            UnOp::Unsize => self.visit_unsize_expr(unary_expr, dest),
+            // a closure capturing nothing takes no environment, so its function is
+            // compatible with the function pointer
+            UnOp::ClosureFnPtr => {
+                let closure = self.closure_info(&unary_expr.expr.type_info());
+                self.visit_expr(&mut unary_expr.expr, None, false)?;
+                let symbol = closure_symbol(&self.fn_symbol, closure.scope_id());
+                self.lit(Operand::FnLabel(symbol), dest, remain_temp)
+            }
+            // references and raw pointers are all addresses
+            UnOp::PtrCoercion => self.visit_expr(&mut unary_expr.expr, dest, remain_temp),
         }
     }
 
-    fn visit_block_expr(
+    /// ## Example
+    ///
+    /// let a = b as i8;
+    ///
+    /// <=>
+    ///
+    /// a = trunc b
+    ///
+    /// Casts between integers of the same size (except the sub-word ones of different
+    /// signedness), and between pointers and `usize`, only copy the bits.
+    fn visit_cast_expr(
         &mut self,
-        block_expr: &mut BlockExpr,
+        cast_expr: &mut CastExpr,
         dest: Option<Place>,
         remain_temp: bool,
     ) -> Result<Operand, RccError> {
-        self.scope_stack.enter_scope(block_expr);
-        for stmt in block_expr.stmts.iter_mut() {
-            self.visit_stmt(stmt)?;
+        let temp = self.gen_temp_var(cast_expr.expr.type_info());
+        let mut src = self.visit_expr(&mut cast_expr.expr, Some(temp.clone()), false)?;
+        let d = match dest {
+            Some(d) => d,
+            None => return Ok(Operand::Unit),
+        };
+        if let Some(operand) = ir::cast_may_constant_fold(&src, &d.ir_type) {
+            return self.lit(operand, Some(d), remain_temp);
         }
-
-        let result = Ok(if let Some(expr) = &mut block_expr.last_expr {
-            let is_none = dest.is_none();
-            let res = self.visit_expr(&mut *expr, dest, remain_temp)?;
-            if is_none && !res.is_unit_or_never() {
-                return Err(format!(
-                    "error in visiting block expr: expected `()`, found {:?}",
-                    res
-                )
-                .into());
-            }
-            res
-        } else {
-            Operand::Unit
-        });
-        self.scope_stack.exit_scope();
-        result
+        // the sizes of `isize` and `usize` are unknown when folding
+        if src.is_imm() {
+            self.ir_output
+                .add_instructions(IRInst::load_data(temp.clone(), src));
+            src = Operand::Place(temp);
+        }
+        let src_type = self.ir_type(&cast_expr.expr.type_info().borrow())?;
+        match src_type.cast_op(&d.ir_type, self.addr_size) {
+            Some(op) => self
+                .ir_output
+                .add_instructions(IRInst::cast(op, d.clone(), src)),
+            None => self
+                .ir_output
+                .add_instructions(IRInst::load_data(d.clone(), src)),
+        }
+        Ok(Operand::Place(d))
     }
 
-    fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr) -> Result<Operand, RccError> {
-        let operand = self.visit_lhs_expr(&mut assign_expr.lhs)?;
-        let p = match operand {
-            Operand::Place(p) => p,
-            _ => unimplemented!(),
-        };
+    /// The place of `*p` is at the address `p`.
+    fn visit_deref_place(&mut self, unary_expr: &mut UnAryExpr) -> Result<(Operand, u32), RccError> {
+        debug_assert_eq!(unary_expr.op, UnOp::Deref);
+        let temp = self.gen_temp_var(unary_expr.expr.type_info());
+        let addr = self.visit_expr(&mut unary_expr.expr, Some(temp), false)?;
+        Ok((addr, 0))
+    }
 
-        macro_rules! add_inst {
+    /// ## Example
+    ///
+    /// s: &dyn Shape = &c
+    ///
+    /// <=>
+    ///
+    /// $1 = &$2
+    /// *($1 + 0) = &c
+    /// *($1 + addr size) = vtable of Circle for Shape
+    /// s = $2
+    fn visit_unsize_expr(
+        &mut self,
+        unary_expr: &mut UnAryExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let type_info = unary_expr.type_info();
+        let trait_name = match type_info.borrow().deref() {
+            TypeInfo::Ptr { type_info, .. } => match type_info.as_ref() {
+                TypeInfo::TraitObject(name) => name.clone(),
+                t => unreachable!("unsize to {:?}", t),
+            },
+            t => unreachable!("unsize to {:?}", t),
+        };
+        let type_name = match unary_expr.expr.type_info().borrow().deref() {
+            TypeInfo::Ptr { type_info, .. } => type_info.substitute(&self.type_args).impl_name(),
+            _ => None,
+        }
+        .unwrap();
+
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info.clone()),
+        };
+        let addr = self.gen_addr_of(place.clone(), &type_info);
+        let data_dest = self.gen_temp_var(unary_expr.expr.type_info());
+        let data = self.visit_expr(&mut unary_expr.expr, Some(data_dest), false)?;
+        self.ir_output
+            .add_instructions(IRInst::store(addr.clone(), 0, data));
+
+        let trait_info = self.scope_stack.cur_scope().find_trait(&trait_name).unwrap();
+        let fns = trait_info
+            .fns
+            .iter()
+            .map(|f| trait_fn_symbol(&type_name, &trait_name, &f.name))
+            .collect();
+        let vtable = self.ir_output.add_vtable(&type_name, &trait_name, fns);
+        let vtable_addr = self.gen_erased_ptr_temp_var();
+        self.ir_output.add_instructions(IRInst::load_addr(
+            vtable_addr.clone(),
+            Operand::Place(vtable),
+        ));
+        self.ir_output.add_instructions(IRInst::store(
+            addr,
+            self.addr_size / 8,
+            Operand::Place(vtable_addr),
+        ));
+
+        match dest {
+            Some(d) => {
+                if d != place {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(d.clone(), Operand::Place(place)));
+                }
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    fn visit_block_expr(
+        &mut self,
+        block_expr: &mut BlockExpr,
+        dest: Option<Place>,
+        remain_temp: bool,
+    ) -> Result<Operand, RccError> {
+        self.scope_stack.enter_scope(block_expr);
+        for stmt in block_expr.stmts.iter_mut() {
+            self.visit_stmt(stmt)?;
+        }
+
+        let result = Ok(if let Some(expr) = &mut block_expr.last_expr {
+            let is_none = dest.is_none();
+            let res = self.visit_expr(&mut *expr, dest, remain_temp)?;
+            if is_none && !res.is_unit_or_never() {
+                return Err(format!(
+                    "error in visiting block expr: expected `()`, found {:?}",
+                    res
+                )
+                .into());
+            }
+            res
+        } else {
+            Operand::Unit
+        });
+        self.scope_stack.exit_scope();
+        result
+    }
+
+    fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr) -> Result<Operand, RccError> {
+        let p = match &mut assign_expr.lhs {
+            LhsExpr::FieldAccess(field_access_expr) => {
+                let (addr, offset) = self.visit_field_place(field_access_expr)?;
+                return self.visit_store_assign_expr(
+                    (addr, offset),
+                    field_access_expr.type_info(),
+                    &assign_expr.assign_op,
+                    &mut assign_expr.rhs,
+                );
+            }
+            LhsExpr::ArrayIndex(array_index_expr) => {
+                let (addr, offset) = self.visit_index_place(array_index_expr)?;
+                return self.visit_store_assign_expr(
+                    (addr, offset),
+                    array_index_expr.type_info(),
+                    &assign_expr.assign_op,
+                    &mut assign_expr.rhs,
+                );
+            }
+            LhsExpr::TupleIndex(tuple_index_expr) => {
+                let (addr, offset) = self.visit_tuple_index_place(tuple_index_expr)?;
+                return self.visit_store_assign_expr(
+                    (addr, offset),
+                    tuple_index_expr.type_info(),
+                    &assign_expr.assign_op,
+                    &mut assign_expr.rhs,
+                );
+            }
+            LhsExpr::Deref(unary_expr) => {
+                let (addr, offset) = self.visit_deref_place(unary_expr)?;
+                return self.visit_store_assign_expr(
+                    (addr, offset),
+                    unary_expr.type_info(),
+                    &assign_expr.assign_op,
+                    &mut assign_expr.rhs,
+                );
+            }
+            LhsExpr::Path(path_expr) => match self.visit_path_expr(path_expr, None, false)? {
+                Operand::Place(p) => p,
+                _ => {
+                    return Err(RccError::with_span(
+                        "invalid assignment target",
+                        path_expr.span.0,
+                    ))
+                }
+            },
+        };
+
+        macro_rules! add_inst {
             ($bin_op:path) => {{
                 let rhs_dest = self.gen_temp_var(assign_expr.lhs.type_info());
                 let rhs = self.visit_expr(&mut assign_expr.rhs, Some(rhs_dest), false)?;
@@ -391,7 +1330,7 @@ impl IRBuilder {
         }
         match assign_expr.assign_op {
             AssignOp::Eq => {
-                let rhs = self.visit_expr(&mut assign_expr.rhs, Some(p.clone()), false)?;
+                self.visit_expr(&mut assign_expr.rhs, Some(p.clone()), false)?;
             }
             AssignOp::ShrEq => add_inst!(BinOperator::Shr),
             AssignOp::ShlEq => add_inst!(BinOperator::Shl),
@@ -407,8 +1346,172 @@ impl IRBuilder {
         Ok(Operand::Unit)
     }
 
-    fn visit_range_expr(&mut self, range_expr: &mut RangeExpr) -> Result<Operand, RccError> {
-        unimplemented!()
+    /// Assign to the place at `addr + offset`, e.g. a field or an array element.
+    ///
+    /// ## Example
+    ///
+    /// a.b += c
+    ///
+    /// <=>
+    ///
+    /// $1 = &a
+    /// $2 = *($1 + offset of b)
+    /// $2 = $2 + c
+    /// *($1 + offset of b) = $2
+    fn visit_store_assign_expr(
+        &mut self,
+        (addr, offset): (Operand, u32),
+        type_info: Rc<RefCell<TypeInfo>>,
+        assign_op: &AssignOp,
+        rhs: &mut Expr,
+    ) -> Result<Operand, RccError> {
+        let rhs_dest = self.gen_temp_var(type_info.clone());
+        let bin_op = match assign_op {
+            AssignOp::Eq => {
+                let rhs = self.visit_expr(rhs, Some(rhs_dest), false)?;
+                self.ir_output
+                    .add_instructions(IRInst::store(addr, offset, rhs));
+                return Ok(Operand::Unit);
+            }
+            AssignOp::ShrEq => BinOperator::Shr,
+            AssignOp::ShlEq => BinOperator::Shl,
+            AssignOp::PlusEq => BinOperator::Plus,
+            AssignOp::MinusEq => BinOperator::Minus,
+            AssignOp::StarEq => BinOperator::Star,
+            AssignOp::SlashEq => BinOperator::Slash,
+            AssignOp::PercentEq => BinOperator::Percent,
+            AssignOp::AndEq => BinOperator::And,
+            AssignOp::OrEq => BinOperator::Or,
+            AssignOp::CaretEq => BinOperator::Caret,
+        };
+        let lhs = self.gen_temp_var(type_info);
+        self.ir_output
+            .add_instructions(IRInst::load(lhs.clone(), addr.clone(), offset));
+        let rhs = self.visit_expr(rhs, Some(rhs_dest), false)?;
+        self.ir_output.add_instructions(IRInst::bin_op(
+            bin_op,
+            lhs.clone(),
+            Operand::Place(lhs.clone()),
+            rhs,
+        ));
+        self.ir_output
+            .add_instructions(IRInst::store(addr, offset, Operand::Place(lhs)));
+        Ok(Operand::Unit)
+    }
+
+    /// Ranges store the bounds they have like tuples.
+    ///
+    /// ## Example
+    ///
+    /// r = a..b
+    ///
+    /// <=>
+    ///
+    /// $1 = &$2
+    /// *($1 + 0) = a
+    /// *($1 + offset of end) = b
+    /// r = $2
+    fn visit_range_expr(
+        &mut self,
+        range_expr: &mut RangeExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        // `..`
+        if range_expr.lhs.is_none() && range_expr.rhs.is_none() {
+            return Ok(Operand::Unit);
+        }
+        let type_info = range_expr.type_info();
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info.clone()),
+        };
+        let ir_type = place.ir_type.clone();
+
+        let addr = self.gen_addr_of(place.clone(), &type_info);
+        let bounds = range_expr.lhs.iter_mut().chain(range_expr.rhs.iter_mut());
+        for (i, bound) in bounds.enumerate() {
+            let offset = ir_type.field_offset(i, self.addr_size);
+            let bound_dest = self.gen_temp_var(bound.type_info());
+            let src = self.visit_expr(bound, Some(bound_dest), false)?;
+            self.ir_output
+                .add_instructions(IRInst::store(addr.clone(), offset, src));
+        }
+
+        match dest {
+            Some(d) => {
+                if d != place {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(d.clone(), Operand::Place(place)));
+                }
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// Return the start and the end of the range.
+    /// The bounds of range expressions are used directly instead of storing them.
+    fn gen_range_bounds(
+        &mut self,
+        expr: &mut Expr,
+    ) -> Result<(Option<Operand>, Option<Operand>), RccError> {
+        match expr {
+            Expr::Grouped(e) => self.gen_range_bounds(e),
+            // `.rev()` does not change the value
+            Expr::MethodCall(method_call_expr) => {
+                self.gen_range_bounds(&mut method_call_expr.receiver)
+            }
+            Expr::Range(range_expr) => {
+                let mut bounds = vec![];
+                for bound in [range_expr.lhs.as_mut(), range_expr.rhs.as_mut()] {
+                    bounds.push(match bound {
+                        Some(e) => {
+                            let d = self.gen_temp_var(e.type_info());
+                            Some(self.visit_expr(e, Some(d), false)?)
+                        }
+                        None => None,
+                    });
+                }
+                let end = bounds.pop().unwrap();
+                Ok((bounds.pop().unwrap(), end))
+            }
+            e => {
+                let type_info = e.type_info();
+                let (has_start, has_end, elem_type) = match type_info.borrow().deref() {
+                    TypeInfo::Range {
+                        has_start,
+                        has_end,
+                        elem_type,
+                        ..
+                    } => (*has_start, *has_end, elem_type.deref().clone()),
+                    t => unreachable!("{:?} is not a range", t),
+                };
+                let place = self.gen_temp_var(type_info.clone());
+                let place = match self.visit_expr(e, Some(place), false)? {
+                    Operand::Place(p) => p,
+                    operand => unreachable!("invalid range {:?}", operand),
+                };
+                let ir_type = place.ir_type.clone();
+                let addr = self.gen_addr_of(place, &type_info);
+                let elem_info = Rc::new(RefCell::new(elem_type));
+                let mut field_index = 0;
+                let mut load_bound = |builder: &mut Self, has_bound: bool| {
+                    if !has_bound {
+                        return None;
+                    }
+                    let offset = ir_type.field_offset(field_index, builder.addr_size);
+                    field_index += 1;
+                    let bound = builder.gen_temp_var(elem_info.clone());
+                    builder
+                        .ir_output
+                        .add_instructions(IRInst::load(bound.clone(), addr.clone(), offset));
+                    Some(Operand::Place(bound))
+                };
+                let start = load_bound(self, has_start);
+                let end = load_bound(self, has_end);
+                Ok((start, end))
+            }
+        }
     }
 
     fn bin_op(
@@ -428,6 +1531,13 @@ impl IRBuilder {
         bin_op_expr: &mut BinOpExpr,
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
+        if let BinOperator::AndAnd | BinOperator::OrOr = bin_op_expr.bin_op {
+            let d = match dest {
+                Some(d) => d,
+                None => self.gen_temp_var(bin_op_expr.type_info()),
+            };
+            return self.visit_logic_bin_expr(bin_op_expr, d);
+        }
         let d = self.gen_temp_var(bin_op_expr.lhs.type_info());
         let lhs = self.visit_expr(&mut bin_op_expr.lhs, Some(d), false)?;
         let d = self.gen_temp_var(bin_op_expr.rhs.type_info());
@@ -446,43 +1556,21 @@ impl IRBuilder {
         }
     }
 
-    /// ## Example1
+    /// The rhs is evaluated only if the lhs does not determine the result.
+    /// Conditions of `if` and `while` are translated by `gen_jump_if_false` instead.
     ///
-    /// let a = A() && B() || C() || D();
+    /// ## Example
     ///
-    /// <=>
-    /// (1) a_0 = A()
-    /// (2) if not a_0 goto (6)
-    /// (3) a_0 = B()
-    /// (4) if not a_0 goto (6)
-    /// (5) goto ()
-    /// (6) a_0 = C()
-    /// (7) if a_0 goto ()
-    /// (8) a_0 = D()
-    /// (9) if a_0 goto ()
-    /// a_0 = C()
-    /// if a_0 goto LABEL
-    /// a_0 = D()
-    /// if a_0 goto LABEL
-    /// LABEL:
-    /// ...
-    ///
-    /// ## Example2
-    ///
-    /// if A() && B() || C() && (D() || E()) {
-    ///     ...
-    /// }
+    /// let a = A() && B() || C();
     ///
     /// <=>
     ///
-    /// (1) if not A() goto (4)
-    /// (2) if not B() goto (4)
-    /// (3) goto (7)
-    /// (4) if not C() goto ()
-    /// (5) if D() goto (7)
-    /// (6) if E() goto (7)
-    /// (7) ... // do something
-    /// (8) ...
+    /// $1 = A()
+    /// if not $1 goto (4)
+    /// $1 = B()
+    /// (4) if $1 goto (6)
+    /// $1 = C()
+    /// (6) a = $1
     fn visit_logic_bin_expr(
         &mut self,
         bin_op_expr: &mut BinOpExpr,
@@ -492,77 +1580,755 @@ impl IRBuilder {
             bin_op_expr.bin_op,
             BinOperator::AndAnd | BinOperator::OrOr
         ));
-        // let lhs = self.visit_expr(&mut bin_op_expr.lhs, dest)?;
-        // let if_inst = if bin_op_expr.bin_op == BinOperator::AndAnd {
-        //     IRInst::jump_if_not(lhs)
-        // } else {
-        //     IRInst::jump_if(lhs)
-        // };
-        // let if_idx = self.ir_output.instructions.len();
-        // self.ir_output.add_instructions(if_inst);
-        // let rhs = self.visit_expr(
-        //     &mut bin_op_expr.rhs,
-        //     dest.clone(),
-        // );
-        todo!()
+        // the rhs may read the variable to assign
+        let d = if dest.is_temp() {
+            dest.clone()
+        } else {
+            self.gen_temp_var(bin_op_expr.type_info())
+        };
+        let lhs = self.visit_expr(&mut bin_op_expr.lhs, Some(d.clone()), true)?;
+        let next_back_patch_link = self.ir_output.next_inst_id();
+        let ir_inst = if bin_op_expr.bin_op == BinOperator::AndAnd {
+            IRInst::jump_if_not(lhs, 0)
+        } else {
+            IRInst::jump_if(lhs, 0)
+        };
+        self.ir_output.add_instructions(ir_inst);
+        self.visit_expr(&mut bin_op_expr.rhs, Some(d.clone()), true)?;
+        let next_idx = self.ir_output.next_inst_id();
+        self.back_patch(next_back_patch_link, next_idx);
+        if d != dest {
+            self.ir_output
+                .add_instructions(IRInst::load_data(dest.clone(), Operand::Place(d)));
+        }
+        Ok(Operand::Place(dest))
+    }
+
+    /// ## Example
+    ///
+    /// a = [b, c]
+    ///
+    /// <=>
+    ///
+    /// $1 = &$2
+    /// *($1 + 0) = b
+    /// *($1 + size of elem) = c
+    /// a = $2
+    ///
+    /// a = [b; 3]
+    ///
+    /// <=>
+    ///
+    /// $1 = &$2
+    /// $3 = b
+    /// $4 = 0
+    /// if $4 >= 3 goto NEXT
+    /// *($1 + 0) = $3
+    /// $1 = $1 + size of elem
+    /// $4 = $4 + 1
+    /// goto (if ...)
+    /// NEXT: a = $2
+    ///
+    /// Like struct expressions, the array is built in place if `dest` is a temp variable.
+    /// Repeat expressions are always built in place, since the element is evaluated
+    /// before the array is written.
+    fn visit_array_expr(
+        &mut self,
+        array_expr: &mut ArrayExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let type_info = array_expr.type_info();
+        let place = match &dest {
+            Some(d) if d.is_temp() || array_expr.is_repeat() => d.clone(),
+            _ => self.gen_temp_var(type_info.clone()),
+        };
+        let (elem_size, len) = match &place.ir_type {
+            IRType::Array(elem, len) => (elem.byte_size(self.addr_size), *len),
+            t => unreachable!("array expr has type {:?}", t),
+        };
+
+        let addr = self.gen_addr_of(place.clone(), &type_info);
+        if array_expr.is_repeat() {
+            let elem = &mut array_expr.elems[0];
+            let elem_dest = self.gen_temp_var(elem.type_info());
+            let src = self.visit_expr(elem, Some(elem_dest), false)?;
+            let usize_info = Rc::new(RefCell::new(TypeInfo::LitNum(TypeLitNum::Usize)));
+            let counter = self.gen_temp_var(usize_info);
+            self.ir_output
+                .add_instructions(IRInst::load_data(counter.clone(), Operand::Usize(0)));
+
+            let loop_start_id = self.ir_output.next_inst_id();
+            self.ir_output.add_instructions(IRInst::jump_if_cond(
+                JGe,
+                Operand::Place(counter.clone()),
+                Operand::Usize(len as usize),
+                0,
+            ));
+            self.ir_output
+                .add_instructions(IRInst::store(addr.clone(), 0, src));
+            let addr_place = match &addr {
+                Operand::Place(p) => p.clone(),
+                _ => unreachable!(),
+            };
+            self.bin_op(
+                addr,
+                Operand::Usize(elem_size as usize),
+                BinOperator::Plus,
+                addr_place,
+            )?;
+            self.bin_op(
+                Operand::Place(counter.clone()),
+                Operand::Usize(1),
+                BinOperator::Plus,
+                counter,
+            )?;
+            self.ir_output
+                .add_instructions(IRInst::jump(loop_start_id));
+            let next_id = self.ir_output.next_inst_id();
+            self.back_patch(loop_start_id, next_id);
+        } else {
+            for (i, elem) in array_expr.elems.iter_mut().enumerate() {
+                let elem_dest = self.gen_temp_var(elem.type_info());
+                let src = self.visit_expr(elem, Some(elem_dest), false)?;
+                self.ir_output
+                    .add_instructions(IRInst::store(addr.clone(), i as u32 * elem_size, src));
+            }
+        }
+
+        match dest {
+            Some(d) => {
+                if d != place {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(d.clone(), Operand::Place(place)));
+                }
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// ## Example
+    ///
+    /// c = a[i]
+    ///
+    /// <=>
+    ///
+    /// $1 = &a
+    /// if i < len of a goto (4)
+    /// call bounds check panic routine(i, len of a)
+    /// $2 = i * size of elem
+    /// $3 = $1 + $2
+    /// c = *($3 + 0)
+    fn visit_array_index_expr(
+        &mut self,
+        array_index_expr: &mut ArrayIndexExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let (addr, offset) = self.visit_index_place(array_index_expr)?;
+        match dest {
+            Some(d) => {
+                self.ir_output
+                    .add_instructions(IRInst::load(d.clone(), addr, offset));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// Return the address and the offset of the indexed element.
+    /// Constant indexes are checked at compile time, others at runtime.
+    fn visit_index_place(
+        &mut self,
+        array_index_expr: &mut ArrayIndexExpr,
+    ) -> Result<(Operand, u32), RccError> {
+        let base_info = array_index_expr.expr.type_info();
+        // `a[i]` where `a` is a pointer to an array is `(*a)[i]`
+        let (array_info, is_ptr) = match base_info.borrow().deref() {
+            TypeInfo::Ptr { type_info, .. } => (type_info.deref().clone(), true),
+            t => (t.clone(), false),
+        };
+        let (elem_size, len) = match self.ir_type(&array_info)? {
+            IRType::Array(elem, len) => (elem.byte_size(self.addr_size), len),
+            t => unreachable!("cannot index into {:?}", t),
+        };
+
+        let (addr, offset) = if is_ptr {
+            let temp = self.gen_temp_var(base_info.clone());
+            (self.visit_expr(&mut array_index_expr.expr, Some(temp), false)?, 0)
+        } else {
+            self.visit_place_addr(&mut array_index_expr.expr)?
+        };
+
+        let index_dest = self.gen_temp_var(array_index_expr.index_expr.type_info());
+        let index = self.visit_expr(&mut array_index_expr.index_expr, Some(index_dest), false)?;
+        if let Operand::Usize(i) = index {
+            return if i < len as usize {
+                Ok((addr, offset + i as u32 * elem_size))
+            } else {
+                Err(format!(
+                    "index out of bounds: the length is {} but the index is {}",
+                    len, i
+                )
+                .into())
+            };
+        }
+
+        let check_id = self.ir_output.next_inst_id();
+        self.ir_output.add_instructions(IRInst::jump_if_cond(
+            JLt,
+            index.clone(),
+            Operand::Usize(len as usize),
+            0,
+        ));
+        self.ir_output.add_instructions(IRInst::call(
+            Operand::FnLabel(PANIC_BOUNDS_CHECK.into()),
+            vec![index.clone(), Operand::Usize(len as usize)],
+        ));
+        let next_id = self.ir_output.next_inst_id();
+        self.back_patch(check_id, next_id);
+
+        let usize_info = Rc::new(RefCell::new(TypeInfo::LitNum(TypeLitNum::Usize)));
+        let byte_offset = self.gen_temp_var(usize_info);
+        let byte_offset = self.bin_op(
+            index,
+            Operand::Usize(elem_size as usize),
+            BinOperator::Star,
+            byte_offset,
+        )?;
+        let addr_place = self.gen_ptr_temp_var(&array_index_expr.type_info());
+        let elem_addr = self.bin_op(addr, byte_offset, BinOperator::Plus, addr_place)?;
+        Ok((elem_addr, offset))
+    }
+
+    /// Return the address of the memory holding the value of `expr` and the offset of the value.
+    /// Places like `a.b[i]` are accessed in place, and values are stored in temp variables.
+    fn visit_place_addr(&mut self, expr: &mut Expr) -> Result<(Operand, u32), RccError> {
+        match expr {
+            Expr::FieldAccess(field_access_expr) => self.visit_field_place(field_access_expr),
+            Expr::ArrayIndex(array_index_expr) => self.visit_index_place(array_index_expr),
+            Expr::TupleIndex(tuple_index_expr) => self.visit_tuple_index_place(tuple_index_expr),
+            Expr::Grouped(grouped_expr) => self.visit_place_addr(grouped_expr),
+            Expr::Unary(unary_expr) if unary_expr.op == UnOp::Deref => {
+                self.visit_deref_place(unary_expr)
+            }
+            expr => {
+                let type_info = expr.type_info();
+                let temp = self.gen_temp_var(type_info.clone());
+                let place = match self.visit_expr(expr, Some(temp.clone()), false)? {
+                    Operand::Place(p) => p,
+                    operand => {
+                        self.ir_output
+                            .add_instructions(IRInst::load_data(temp.clone(), operand));
+                        temp
+                    }
+                };
+                Ok((self.gen_addr_of(place, &type_info), 0))
+            }
+        }
+    }
+
+    /// ## Example
+    ///
+    /// a = (1, b)
+    ///
+    /// <=>
+    ///
+    /// $1 = &$2
+    /// *($1 + offset of .0) = 1
+    /// *($1 + offset of .1) = b
+    /// a = $2
+    ///
+    /// Like struct expressions, the tuple is built in place if `dest` is a temp variable.
+    fn visit_tuple_expr(
+        &mut self,
+        tuple_expr: &mut TupleExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        // `()`
+        if tuple_expr.elems.is_empty() {
+            return Ok(Operand::Unit);
+        }
+        let type_info = tuple_expr.type_info();
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info.clone()),
+        };
+        let ir_type = place.ir_type.clone();
+
+        let addr = self.gen_addr_of(place.clone(), &type_info);
+        for (i, elem) in tuple_expr.elems.iter_mut().enumerate() {
+            let offset = ir_type.field_offset(i, self.addr_size);
+            let elem_dest = self.gen_temp_var(elem.type_info());
+            let src = self.visit_expr(elem, Some(elem_dest), false)?;
+            self.ir_output
+                .add_instructions(IRInst::store(addr.clone(), offset, src));
+        }
+
+        match dest {
+            Some(d) => {
+                if d != place {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(d.clone(), Operand::Place(place)));
+                }
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// ## Example
+    ///
+    /// c = a.1
+    ///
+    /// <=>
+    ///
+    /// $1 = &a
+    /// c = *($1 + offset of .1)
+    fn visit_tuple_index_expr(
+        &mut self,
+        tuple_index_expr: &mut TupleIndexExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let (addr, offset) = self.visit_tuple_index_place(tuple_index_expr)?;
+        match dest {
+            Some(d) => {
+                self.ir_output
+                    .add_instructions(IRInst::load(d.clone(), addr, offset));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// Return the address of the tuple and the offset of the element.
+    fn visit_tuple_index_place(
+        &mut self,
+        tuple_index_expr: &mut TupleIndexExpr,
+    ) -> Result<(Operand, u32), RccError> {
+        let tuple_type = self.ir_type(&tuple_index_expr.expr.type_info().borrow())?;
+        let field_offset = tuple_type.field_offset(tuple_index_expr.index, self.addr_size);
+        let (addr, offset) = self.visit_place_addr(&mut tuple_index_expr.expr)?;
+        Ok((addr, offset + field_offset))
+    }
+
+    /// ## Example
+    ///
+    /// a = A { x: 1, y: b }
+    ///
+    /// <=>
+    ///
+    /// $1 = &$2
+    /// *($1 + offset of x) = 1
+    /// *($1 + offset of y) = b
+    /// a = $2
+    ///
+    /// The struct is built in place if `dest` is a temp variable,
+    /// so that `a = A { x: a.y, y: a.x }` reads the fields of the old `a`.
+    fn visit_struct_expr(
+        &mut self,
+        struct_expr: &mut StructExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let type_info = struct_expr.type_info();
+        if let Some(idx) = self.variant_index(&struct_expr.path.segments) {
+            let names: Vec<String> = match type_info.borrow().deref() {
+                TypeInfo::Enum { variants, .. } => variants.get()[idx]
+                    .fields
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+                t => unreachable!("struct variant expr has type {:?}", t),
+            };
+            let fields = struct_expr
+                .fields
+                .iter_mut()
+                .map(|f| (names.iter().position(|n| n == &f.name).unwrap(), &mut f.expr))
+                .collect();
+            return self.gen_variant(type_info, idx, fields, dest, true);
+        }
+        let fields = match type_info.borrow().deref() {
+            TypeInfo::Struct { fields, .. } => fields.clone(),
+            t => unreachable!("struct expr has type {:?}", t),
+        };
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info.clone()),
+        };
+        let ir_type = place.ir_type.clone();
+
+        let addr = self.gen_addr_of(place.clone(), &type_info);
+        for field in struct_expr.fields.iter_mut() {
+            let (idx, _) = fields.find(&field.name).unwrap();
+            let offset = ir_type.field_offset(idx, self.addr_size);
+            let field_dest = self.gen_temp_var(field.expr.type_info());
+            let src = self.visit_expr(&mut field.expr, Some(field_dest), false)?;
+            self.ir_output
+                .add_instructions(IRInst::store(addr.clone(), offset, src));
+        }
+
+        match dest {
+            Some(d) => {
+                if d != place {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(d.clone(), Operand::Place(place)));
+                }
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// ## Example
+    ///
+    /// a = Shape::Rect(w, 2)
+    ///
+    /// <=>
+    ///
+    /// $1 = &$2
+    /// *($1 + 0) = discriminant of Rect
+    /// *($1 + offset of Rect.0) = w
+    /// *($1 + offset of Rect.1) = 2
+    /// a = $2
+    ///
+    /// Variants of enums without data are their discriminants.
+    fn gen_variant(
+        &mut self,
+        type_info: Rc<RefCell<TypeInfo>>,
+        variant: usize,
+        fields: Vec<(usize, &mut Expr)>,
+        dest: Option<Place>,
+        remain_temp: bool,
+    ) -> Result<Operand, RccError> {
+        let ir_type = self.ir_type(&type_info.borrow())?;
+        if !ir_type.is_aggregate() {
+            return self.lit(ir_type.discriminant_operand(variant), dest, remain_temp);
+        }
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info.clone()),
+        };
+
+        let addr = self.gen_addr_of(place.clone(), &type_info);
+        self.ir_output.add_instructions(IRInst::store(
+            addr.clone(),
+            0,
+            ir_type.discriminant_operand(variant),
+        ));
+        for (i, expr) in fields {
+            let offset = ir_type.variant_field_offset(variant, i, self.addr_size);
+            let field_dest = self.gen_temp_var(expr.type_info());
+            let src = self.visit_expr(expr, Some(field_dest), false)?;
+            self.ir_output
+                .add_instructions(IRInst::store(addr.clone(), offset, src));
+        }
+
+        match dest {
+            Some(d) => {
+                if d != place {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(d.clone(), Operand::Place(place)));
+                }
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        call_expr: &mut CallExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        if let Expr::Path(path_expr) = call_expr.expr.as_ref() {
+            if let Some(idx) = self.variant_index(&path_expr.segments) {
+                let type_info = call_expr.type_info();
+                let fields = call_expr.call_params.iter_mut().enumerate().collect();
+                return self.gen_variant(type_info, idx, fields, dest, true);
+            }
+        }
+        let callee_info = call_expr.expr.type_info();
+        let callee_place = self.gen_temp_var(callee_info.clone());
+        let callee = self.visit_expr(&mut call_expr.expr, Some(callee_place), false)?;
+        let ret_info = call_expr.type_info();
+        if let TypeInfo::Closure(_) = callee_info.borrow().deref() {
+            return self.gen_closure_call(callee, &callee_info, call_expr, dest);
+        }
+        self.gen_call(callee, None, call_expr.call_params.iter_mut(), ret_info, dest)
+    }
+
+    /// The address of the environment is passed to a closure capturing something,
+    /// like the `self` argument of a method.
+    fn gen_closure_call(
+        &mut self,
+        env: Operand,
+        type_info: &Rc<RefCell<TypeInfo>>,
+        call_expr: &mut CallExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let closure = self.closure_info(type_info);
+        let symbol = closure_symbol(&self.fn_symbol, closure.scope_id());
+        let env_addr = match env {
+            Operand::Place(p) if !closure.captures().is_empty() => {
+                Some(self.gen_addr_of(p, type_info))
+            }
+            _ => None,
+        };
+        let ret_info = call_expr.type_info();
+        self.gen_call(
+            Operand::FnLabel(symbol),
+            env_addr,
+            call_expr.call_params.iter_mut(),
+            ret_info,
+            dest,
+        )
     }
 
-    fn visit_array_expr(&mut self, array_expr: &mut ArrayExpr) -> Result<Operand, RccError> {
-        unimplemented!()
+    fn closure_info(&self, type_info: &Rc<RefCell<TypeInfo>>) -> ClosureInfo {
+        match type_info.borrow().substitute(&self.type_args) {
+            TypeInfo::Closure(closure) => closure,
+            t => unreachable!("{:?} is not a closure", t),
+        }
     }
 
-    fn visit_array_index_expr(
+    /// The closure is generated after the function defining it, and its value is
+    /// the environment storing the captured variables or their addresses.
+    ///
+    /// ## Example
+    ///
+    /// f = |x| x + y
+    ///
+    /// <=>
+    ///
+    /// $1 = &$2
+    /// $3 = &y
+    /// *($1 + 0) = $3
+    /// f = $2
+    fn visit_closure_expr(
         &mut self,
-        array_index_expr: &mut ArrayIndexExpr,
+        closure_expr: &mut ClosureExpr,
+        dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        unimplemented!()
-    }
+        let type_info = closure_expr.type_info();
+        let closure = self.closure_info(&type_info);
+        self.pending_closures.push(PendingClosure {
+            closure_expr,
+            closure: closure.clone(),
+            scope: self.scope_stack.cur_scope_mut(),
+            fn_symbol: self.fn_symbol.clone(),
+            type_args: self.type_args.clone(),
+        });
+        let captures = closure.captures();
+        if captures.is_empty() {
+            return Ok(Operand::Unit);
+        }
+
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info.clone()),
+        };
+        let addr = self.gen_addr_of(place.clone(), &type_info);
+        for (i, capture) in captures.iter().enumerate() {
+            let offset = place.ir_type.field_offset(i, self.addr_size);
+            let var = self.gen_captured_variable(capture);
+            let value = if capture.by_ref {
+                let var_info = Rc::new(RefCell::new(capture.type_info.clone()));
+                self.gen_addr_of(var, &var_info)
+            } else {
+                Operand::Place(var)
+            };
+            self.ir_output
+                .add_instructions(IRInst::store(addr.clone(), offset, value));
+        }
 
-    fn visit_tuple_expr(&mut self, tuple_expr: &mut TupleExpr) -> Result<Operand, RccError> {
-        unimplemented!()
+        match dest {
+            Some(d) => {
+                if d != place {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(d.clone(), Operand::Place(place)));
+                }
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
     }
 
-    fn visit_tuple_index_expr(
+    /// The receiver has been adjusted to the type of `self` by the symbol resolver,
+    /// so `a.get()` is the same as `Foo::get(&a)`.
+    fn visit_method_call_expr(
         &mut self,
-        tuple_index_expr: &mut TupleIndexExpr,
+        method_call_expr: &mut MethodCallExpr,
+        dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        unimplemented!()
+        let receiver_info = method_call_expr.receiver.type_info();
+        let self_type = match receiver_info.borrow().deref() {
+            TypeInfo::Ptr { type_info, .. } => type_info.deref().clone(),
+            type_info => type_info.clone(),
+        };
+        let ret_info = method_call_expr.type_info();
+        if let TypeInfo::TraitObject(trait_name) = &self_type {
+            let (callee, data) = self.gen_vtable_lookup(method_call_expr, trait_name)?;
+            let args = method_call_expr.call_params.iter_mut();
+            return self.gen_call(callee, Some(data), args, ret_info, dest);
+        }
+        // `.rev()` of ranges
+        if let TypeInfo::Range { .. } = self_type {
+            return self.visit_expr(&mut method_call_expr.receiver, dest, false);
+        }
+        let symbol = self.type_fn_symbol(&self_type, &method_call_expr.method);
+        let callee = Operand::FnLabel(symbol.unwrap());
+        let args = std::iter::once(method_call_expr.receiver.as_mut())
+            .chain(method_call_expr.call_params.iter_mut());
+        self.gen_call(callee, None, args, ret_info, dest)
     }
 
-    fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) -> Result<Operand, RccError> {
-        unimplemented!()
+    /// Methods of trait objects are called through the function pointers in the vtables,
+    /// with the data pointer as `self`. Return the function pointer and the data pointer.
+    ///
+    /// ## Example
+    ///
+    /// s.area()
+    ///
+    /// <=>
+    ///
+    /// $1 = &s
+    /// $2 = *($1 + 0)
+    /// $3 = *($1 + addr size)
+    /// $4 = *($3 + index of area * addr size)
+    /// call $4($2)
+    fn gen_vtable_lookup(
+        &mut self,
+        method_call_expr: &mut MethodCallExpr,
+        trait_name: &str,
+    ) -> Result<(Operand, Operand), RccError> {
+        let ptr_size = self.addr_size / 8;
+        let (addr, offset) = self.visit_place_addr(&mut method_call_expr.receiver)?;
+        let data = self.gen_erased_ptr_temp_var();
+        self.ir_output
+            .add_instructions(IRInst::load(data.clone(), addr.clone(), offset));
+        let vtable = self.gen_erased_ptr_temp_var();
+        self.ir_output
+            .add_instructions(IRInst::load(vtable.clone(), addr, offset + ptr_size));
+
+        let trait_info = self.scope_stack.cur_scope().find_trait(trait_name).unwrap();
+        let idx = trait_info.vtable_index(&method_call_expr.method).unwrap();
+        let fn_ptr = self.gen_erased_ptr_temp_var();
+        self.ir_output.add_instructions(IRInst::load(
+            fn_ptr.clone(),
+            Operand::Place(vtable),
+            idx as u32 * ptr_size,
+        ));
+        Ok((Operand::Place(fn_ptr), Operand::Place(data)))
     }
 
-    fn visit_call_expr(
+    /// `self_arg` is the evaluated receiver of a method of a trait object.
+    fn gen_call<'a>(
         &mut self,
-        call_expr: &mut CallExpr,
+        callee: Operand,
+        self_arg: Option<Operand>,
+        args: impl Iterator<Item = &'a mut Expr>,
+        ret_info: Rc<RefCell<TypeInfo>>,
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        let callee_place = self.gen_temp_var(call_expr.type_info());
-        let callee = self.visit_expr(&mut call_expr.expr, Some(callee_place), false)?;
-
         let mut params = vec![];
-        for e in call_expr.call_params.iter_mut() {
+        let ret_type = self.ir_type(&ret_info.borrow())?;
+        // the address to write the large aggregate result is passed as the first argument
+        let sret_dest = if ret_type.is_returned_by_sret(self.addr_size) {
+            let d = match &dest {
+                Some(d) => d.clone(),
+                None => self.gen_temp_var(ret_info.clone()),
+            };
+            params.push(self.gen_addr_of(d.clone(), &ret_info));
+            Some(d)
+        } else {
+            None
+        };
+        params.extend(self_arg);
+
+        for e in args {
             let param_place = self.gen_temp_var(e.type_info());
-            params.push(self.visit_expr(e, Some(param_place), false)?);
+            let param = self.visit_expr(e, Some(param_place), false)?;
+            params.push(param);
         }
         self.ir_output
             .add_instructions(IRInst::call(callee, params));
+        if let Some(d) = sret_dest {
+            return Ok(Operand::Place(d));
+        }
         match dest {
             Some(d) => {
                 self.ir_output
-                    .add_instructions(IRInst::load_data(d.clone(), Operand::FnRetPlace(d.ir_type)));
+                    .add_instructions(IRInst::load_data(d.clone(), Operand::FnRetPlace(d.ir_type.clone())));
                 Ok(Operand::Place(d))
             }
             None => Ok(Operand::Unit),
         }
     }
 
+    /// ## Example
+    ///
+    /// c = a.b
+    ///
+    /// <=>
+    ///
+    /// $1 = &a
+    /// c = *($1 + offset of b)
     fn visit_field_access_expr(
         &mut self,
         field_access_expr: &mut FieldAccessExpr,
+        dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        unimplemented!()
+        let (addr, offset) = self.visit_field_place(field_access_expr)?;
+        match dest {
+            Some(d) => {
+                self.ir_output
+                    .add_instructions(IRInst::load(d.clone(), addr, offset));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// Return the address of the struct containing the field and the offset of the field.
+    /// Offsets of nested field accesses like `a.b.c` are accumulated.
+    fn visit_field_place(
+        &mut self,
+        field_access_expr: &mut FieldAccessExpr,
+    ) -> Result<(Operand, u32), RccError> {
+        let lhs_info = field_access_expr.lhs.type_info();
+        // the fields accessed through references, e.g. `self.x`
+        let mut struct_info = lhs_info.borrow().clone();
+        let mut derefs = 0;
+        while let TypeInfo::Ptr { type_info, .. } = struct_info {
+            struct_info = *type_info;
+            derefs += 1;
+        }
+        let idx = match &struct_info {
+            TypeInfo::Struct { fields, .. } => {
+                let name = field_access_expr.field_name().unwrap();
+                fields.find(name).unwrap().0
+            }
+            t => unreachable!("no field on type {:?}", t),
+        };
+        let field_offset = self.ir_type(&struct_info)?.field_offset(idx, self.addr_size);
+
+        if derefs == 0 {
+            let (addr, offset) = self.visit_place_addr(&mut field_access_expr.lhs)?;
+            return Ok((addr, offset + field_offset));
+        }
+        let temp = self.gen_temp_var(lhs_info.clone());
+        let mut addr = self.visit_expr(&mut field_access_expr.lhs, Some(temp), false)?;
+        let mut ptr_info = lhs_info.borrow().clone();
+        for _ in 1..derefs {
+            ptr_info = match ptr_info {
+                TypeInfo::Ptr { type_info, .. } => *type_info,
+                _ => unreachable!(),
+            };
+            let ptr = self.gen_temp_var(Rc::new(RefCell::new(ptr_info.clone())));
+            self.ir_output
+                .add_instructions(IRInst::load(ptr.clone(), addr, 0));
+            addr = Operand::Place(ptr);
+        }
+        Ok((addr, field_offset))
     }
 
     fn visit_loop_block(
@@ -573,14 +2339,30 @@ impl IRBuilder {
         let operand = self.visit_block_expr(loop_block, None, false)?;
         assert!(operand.is_unit_or_never());
         self.ir_output.add_instructions(IRInst::jump(loop_start_id));
-        let (d, mut link) = self.loop_var_stack.pop().unwrap();
+        self.exit_loop(loop_start_id);
+        Ok(())
+    }
+
+    /// Back patch the jumps of `break` to the next instruction,
+    /// and the jumps of `continue` to `continue_id`.
+    fn exit_loop(&mut self, continue_id: usize) {
+        let loop_context = self.loop_var_stack.pop().unwrap();
         let next_id = self.ir_output.next_inst_id();
-        while link != 0 {
-            let inst = self.ir_output.get_inst_by_id(link);
-            link = inst.jump_label();
-            inst.set_jump_label(next_id);
+        self.back_patch(loop_context.break_link, next_id);
+        self.back_patch(loop_context.continue_link, continue_id);
+    }
+
+    /// The loop which `break` or `continue` jumps out of, the innermost one without label.
+    fn target_loop(&mut self, label: &Option<String>) -> &mut LoopContext {
+        match label {
+            Some(label) => self
+                .loop_var_stack
+                .iter_mut()
+                .rev()
+                .find(|loop_context| loop_context.label.as_ref() == Some(label))
+                .expect("undeclared label"),
+            None => self.loop_var_stack.last_mut().unwrap(),
         }
-        Ok(())
     }
 
     /// While Expr always values ()
@@ -589,63 +2371,157 @@ impl IRBuilder {
 
         let mut next_back_patch_link = 0;
         // while condition
-        match while_expr.0.as_mut() {
-            Expr::BinOp(e) => match e.bin_op {
-                BinOperator::AndAnd => {
-                    todo!()
-                }
-                BinOperator::OrOr => {
-                    todo!()
-                }
-                BinOperator::Ne => {
-                    self.gen_jump_cond(e, JEq, &mut next_back_patch_link)?;
-                }
-                BinOperator::EqEq => {
-                    self.gen_jump_cond(e, JNe, &mut next_back_patch_link)?;
-                }
-                BinOperator::Le => {
-                    self.gen_jump_cond_reverse(e, JLt, &mut next_back_patch_link)?;
-                }
-                BinOperator::Lt => {
-                    self.gen_jump_cond(e, JGe, &mut next_back_patch_link)?;
-                }
-                BinOperator::Gt => {
-                    self.gen_jump_cond_reverse(e, JGe, &mut next_back_patch_link)?;
-                }
-                BinOperator::Ge => {
-                    self.gen_jump_cond(e, JLt, &mut next_back_patch_link)?;
-                }
-                _ => {
-                    let d = self.gen_temp_var(e.type_info());
-                    let operand = self.visit_bin_op_expr(e, Some(d))?;
-
-                    next_back_patch_link = self.ir_output.next_inst_id();
-                    let ir_inst = IRInst::jump_if_not(operand, 0);
-                    self.ir_output.add_instructions(ir_inst);
-                }
-            },
-            // todo: unary expr, lit bool
-            e => {
-                let d = self.gen_temp_var(e.type_info());
-                let operand = self.visit_expr(e, Some(d), false)?;
+        self.gen_jump_if_false(while_expr.0.as_mut(), &mut next_back_patch_link)?;
+        let loop_context = LoopContext::new(while_expr.2.clone(), None, next_back_patch_link);
+        self.loop_var_stack.push(loop_context);
+        self.visit_loop_block(&mut while_expr.1, loop_start_id)?;
+        Ok(Operand::Unit)
+    }
 
-                next_back_patch_link = self.ir_output.next_inst_id();
-                let ir_inst = IRInst::jump_if_not(operand, 0);
-                self.ir_output.add_instructions(ir_inst);
+    /// The counter goes from the start to the end, or from the end to the start
+    /// if the range is reversed. The counter of inclusive ranges is compared with
+    /// the last value after each iteration, so that it does not overflow.
+    ///
+    /// ## Example
+    ///
+    /// for i in a..b {
+    ///     ...
+    /// }
+    ///
+    /// <=>
+    ///
+    /// $1 = a
+    /// $2 = b
+    /// (3) if $1 >= $2 goto NEXT
+    /// i = $1
+    /// $1 = $1 + 1
+    /// ...
+    /// goto (3)
+    /// NEXT: ...
+    ///
+    /// for i in (a..=b).rev() {
+    ///     ...
+    /// }
+    ///
+    /// <=>
+    ///
+    /// $1 = b
+    /// $2 = a
+    /// if $1 < $2 goto NEXT
+    /// (4) i = $1
+    /// ...
+    /// if $1 == $2 goto NEXT
+    /// $1 = $1 - 1
+    /// goto (4)
+    /// NEXT: ...
+    fn visit_for_expr(&mut self, for_expr: &mut ForExpr) -> Result<Operand, RccError> {
+        let (range_op, elem_type, rev) = match for_expr.expr.type_info().borrow().deref() {
+            TypeInfo::Range {
+                range_op,
+                elem_type,
+                rev,
+                ..
+            } => (*range_op, elem_type.deref().clone(), *rev),
+            t => unreachable!("{:?} is not a range", t),
+        };
+        let elem_info = Rc::new(RefCell::new(elem_type.clone()));
+        let (start, end) = self.gen_range_bounds(&mut for_expr.expr)?;
+        let (first, last) = if rev {
+            (end.unwrap(), start)
+        } else {
+            (start.unwrap(), end)
+        };
+        let cur = self.gen_temp_var(elem_info.clone());
+        self.ir_output
+            .add_instructions(IRInst::load_data(cur.clone(), first));
+        // the bounds are copied before the loop
+        let last = last.map(|last| match last {
+            Operand::Place(p) if !p.is_temp() => {
+                let d = self.gen_temp_var(elem_info.clone());
+                self.ir_output
+                    .add_instructions(IRInst::load_data(d.clone(), Operand::Place(p)));
+                Operand::Place(d)
             }
+            operand => operand,
+        });
+        let is_inclusive = range_op == RangeOp::DotDotEq;
+        let counter = Operand::Place(cur.clone());
+
+        // an inclusive range is empty if the start is greater than the end
+        let mut next_back_patch_link = 0usize;
+        if let (Some(last), true) = (&last, is_inclusive) {
+            let (src1, src2) = if rev {
+                (counter.clone(), last.clone())
+            } else {
+                (last.clone(), counter.clone())
+            };
+            next_back_patch_link = self.ir_output.next_inst_id();
+            self.ir_output
+                .add_instructions(IRInst::jump_if_cond(JLt, src1, src2, 0));
         }
-        self.loop_var_stack.push((None, next_back_patch_link));
-        self.visit_loop_block(&mut while_expr.1, loop_start_id)?;
+        let loop_start_id = self.ir_output.next_inst_id();
+        if let (Some(last), false) = (&last, is_inclusive) {
+            let (src1, src2) = if rev {
+                (last.clone(), counter.clone())
+            } else {
+                (counter.clone(), last.clone())
+            };
+            next_back_patch_link = self.ir_output.next_inst_id();
+            self.ir_output
+                .add_instructions(IRInst::jump_if_cond(JGe, src1, src2, 0));
+        }
+
+        if !is_inclusive && rev {
+            self.gen_step(&cur, BinOperator::Minus)?;
+        }
+        self.scope_stack.enter(&mut for_expr.block.scope);
+        let value = MatchedValue::Operand(counter.clone());
+        let result = self.gen_destructure(&for_expr.pattern, &elem_type, &value);
+        self.scope_stack.exit_scope();
+        result?;
+        if !is_inclusive && !rev {
+            self.gen_step(&cur, BinOperator::Plus)?;
+        }
+
+        let loop_context = LoopContext::new(for_expr.label.clone(), None, next_back_patch_link);
+        self.loop_var_stack.push(loop_context);
+        let operand = self.visit_block_expr(&mut for_expr.block, None, false)?;
+        assert!(operand.is_unit_or_never());
+        let mut continue_id = loop_start_id;
+        if let (Some(last), true) = (last, is_inclusive) {
+            // the last value
+            let jump_id = self.ir_output.next_inst_id();
+            continue_id = jump_id;
+            let link = &mut self.loop_var_stack.last_mut().unwrap().break_link;
+            let ir_inst = IRInst::jump_if_cond(JEq, counter, last, *link);
+            *link = jump_id;
+            self.ir_output.add_instructions(ir_inst);
+            let step_op = if rev {
+                BinOperator::Minus
+            } else {
+                BinOperator::Plus
+            };
+            self.gen_step(&cur, step_op)?;
+        }
+        self.ir_output.add_instructions(IRInst::jump(loop_start_id));
+        self.exit_loop(continue_id);
         Ok(Operand::Unit)
     }
 
+    /// counter = counter + 1 or counter = counter - 1
+    fn gen_step(&mut self, counter: &Place, op: BinOperator) -> Result<Operand, RccError> {
+        let one = Operand::one(&counter.ir_type);
+        self.bin_op(Operand::Place(counter.clone()), one, op, counter.clone())
+    }
+
     fn visit_loop_expr(
         &mut self,
         loop_expr: &mut LoopExpr,
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
         let loop_start_id = self.ir_output.next_inst_id();
-        self.loop_var_stack.push((dest.clone(), 0));
+        let loop_context = LoopContext::new(loop_expr.label.clone(), dest.clone(), 0);
+        self.loop_var_stack.push(loop_context);
         self.visit_loop_block(&mut loop_expr.expr, loop_start_id)?;
         match dest {
             Some(p) => Ok(Operand::Place(p)),
@@ -759,94 +2635,219 @@ impl IRBuilder {
     ) -> Result<Operand, RccError> {
         let mut next_back_patch_link = 0usize;
 
-        macro_rules! visit_block {
-            ($i:ident, $ir_inst:ident) => {
-                self.visit_block_expr(if_expr.blocks.get_mut($i).unwrap(), dest.clone(), true)?;
-                if $i != if_expr.blocks.len() - 1 {
-                    self.ir_output
-                        .add_instructions(IRInst::jump(next_back_patch_link));
-                    next_back_patch_link = self.ir_output.next_inst_id() - 1;
-                }
-            };
-        };
-
         for (i, cond) in if_expr.conditions.iter_mut().enumerate() {
-            match cond {
-                Expr::BinOp(e) => match e.bin_op {
-                    BinOperator::AndAnd => {
-                        todo!()
-                    }
-                    BinOperator::OrOr => {
-                        todo!()
-                    }
-                    BinOperator::Ne => {
-                        self.gen_jump_cond(e, JEq, &mut next_back_patch_link)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    BinOperator::EqEq => {
-                        self.gen_jump_cond(e, JNe, &mut next_back_patch_link)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    BinOperator::Le => {
-                        self.gen_jump_cond_reverse(e, JLt, &mut next_back_patch_link)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    BinOperator::Lt => {
-                        self.gen_jump_cond(e, JGe, &mut next_back_patch_link)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    BinOperator::Gt => {
-                        self.gen_jump_cond_reverse(e, JGe, &mut next_back_patch_link)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    BinOperator::Ge => {
-                        self.gen_jump_cond(e, JLt, &mut next_back_patch_link)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    _ => {
-                        let d = self.gen_temp_var(e.type_info());
-                        let operand = self.visit_bin_op_expr(e, Some(d))?;
-                        let ir_inst = IRInst::jump_if_not(operand, next_back_patch_link);
-                        next_back_patch_link = self.ir_output.next_inst_id();
-                        self.ir_output.add_instructions(ir_inst);
-                        visit_block!(i, ir_inst);
-                    }
-                },
-                // todo: unary expr, lit bool
-                e => {
-                    let d = self.gen_temp_var(e.type_info());
-                    let operand = self.visit_expr(e, Some(d), false)?;
-                    let ir_inst = IRInst::jump_if_not(operand, next_back_patch_link);
-                    next_back_patch_link = self.ir_output.next_inst_id();
-                    self.ir_output.add_instructions(ir_inst);
-                    visit_block!(i, ir_inst);
-                }
+            // jump to the next condition or the else block if missed
+            let mut miss_back_patch_link = 0usize;
+            self.gen_jump_if_false(cond, &mut miss_back_patch_link)?;
+            self.visit_block_expr(if_expr.blocks.get_mut(i).unwrap(), dest.clone(), true)?;
+            if i != if_expr.blocks.len() - 1 {
+                self.ir_output
+                    .add_instructions(IRInst::jump(next_back_patch_link));
+                next_back_patch_link = self.ir_output.next_inst_id() - 1;
             }
+            let next_idx = self.ir_output.next_inst_id();
+            self.back_patch(miss_back_patch_link, next_idx);
         }
         if if_expr.blocks.len() == if_expr.conditions.len() + 1 {
             self.visit_block_expr(if_expr.blocks.last_mut().unwrap(), dest.clone(), true)?;
         }
         let next_idx = self.ir_output.next_inst_id();
-        while next_back_patch_link != 0 {
-            let inst = self.ir_output.get_inst_by_id(next_back_patch_link);
-            next_back_patch_link = inst.jump_label();
-            inst.set_jump_label(next_idx);
+        self.back_patch(next_back_patch_link, next_idx);
+        match dest {
+            Some(d) => Ok(Operand::Place(d)),
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// Arms are tested one by one in order.
+    ///
+    /// ## Example
+    ///
+    /// a = match s {
+    ///     Shape::Rect(w, h) if w == h => A,
+    ///     Shape::Circle(_) => B,
+    ///     _ => C,
+    /// }
+    ///
+    /// <=>
+    ///
+    /// $1 = &s
+    /// $2 = *($1 + 0)
+    /// if $2 != discriminant of Rect goto ARM2
+    /// w = *($1 + offset of Rect.0)
+    /// h = *($1 + offset of Rect.1)
+    /// if w != h goto ARM2
+    /// a = A
+    /// goto NEXT
+    /// ARM2: $3 = *($1 + 0)
+    /// if $3 != discriminant of Circle goto ARM3
+    /// a = B
+    /// goto NEXT
+    /// ARM3: a = C
+    /// NEXT: ...
+    fn visit_match_expr(
+        &mut self,
+        match_expr: &mut MatchExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let type_info = match_expr.expr.type_info().borrow().clone();
+        let has_ref_binding = match_expr.arms.iter().any(|arm| arm.pattern.has_ref_binding());
+        let value = self.visit_matched_expr(&mut match_expr.expr, has_ref_binding)?;
+
+        let mut next_back_patch_link = 0usize;
+        let arm_count = match_expr.arms.len();
+        for (i, arm) in match_expr.arms.iter_mut().enumerate() {
+            self.scope_stack.enter(&mut arm.scope);
+            // jump to the next arm if missed
+            let mut miss_back_patch_link = 0usize;
+            self.gen_pattern_test(&mut arm.pattern, &type_info, &value, &mut miss_back_patch_link)?;
+            self.gen_destructure(&arm.pattern, &type_info, &value)?;
+            if let Some(guard) = arm.guard.as_mut() {
+                self.gen_jump_if_false(guard, &mut miss_back_patch_link)?;
+            }
+            self.visit_expr(&mut arm.expr, dest.clone(), true)?;
+            self.scope_stack.exit_scope();
+
+            if i != arm_count - 1 {
+                self.ir_output
+                    .add_instructions(IRInst::jump(next_back_patch_link));
+                next_back_patch_link = self.ir_output.next_inst_id() - 1;
+            }
+            let next_idx = self.ir_output.next_inst_id();
+            self.back_patch(miss_back_patch_link, next_idx);
         }
+        let next_idx = self.ir_output.next_inst_id();
+        self.back_patch(next_back_patch_link, next_idx);
         match dest {
             Some(d) => Ok(Operand::Place(d)),
             None => Ok(Operand::Unit),
         }
     }
 
+    /// Set the jump labels of the instructions in the back patch link to `label`.
+    fn back_patch(&mut self, mut back_patch_link: usize, label: usize) {
+        while back_patch_link != 0 {
+            let inst = self.ir_output.get_inst_by_id(back_patch_link);
+            back_patch_link = inst.jump_label();
+            inst.set_jump_label(label);
+        }
+    }
+
+    /// Generate the jump instruction taken if `cond` is false,
+    /// and add it to the back patch link.
+    fn gen_jump_if_false(
+        &mut self,
+        cond: &mut Expr,
+        next_back_patch_link: &mut usize,
+    ) -> Result<(), RccError> {
+        match cond {
+            Expr::Grouped(e) => self.gen_jump_if_false(e, next_back_patch_link),
+            Expr::Unary(e) if e.op == UnOp::Not => {
+                self.gen_jump_if_true(&mut e.expr, next_back_patch_link)
+            }
+            // float orderings are evaluated and tested like other bools instead of being flipped
+            Expr::BinOp(e) if !is_float_ordering(e) => match e.bin_op {
+                BinOperator::AndAnd => {
+                    self.gen_jump_if_false(&mut e.lhs, next_back_patch_link)?;
+                    self.gen_jump_if_false(&mut e.rhs, next_back_patch_link)
+                }
+                BinOperator::OrOr => {
+                    // jump over the rhs if the lhs is true
+                    let mut hit_back_patch_link = 0usize;
+                    self.gen_jump_if_true(&mut e.lhs, &mut hit_back_patch_link)?;
+                    self.gen_jump_if_false(&mut e.rhs, next_back_patch_link)?;
+                    let next_idx = self.ir_output.next_inst_id();
+                    self.back_patch(hit_back_patch_link, next_idx);
+                    Ok(())
+                }
+                BinOperator::Ne => self.gen_jump_cond(e, JEq, next_back_patch_link),
+                BinOperator::EqEq => self.gen_jump_cond(e, JNe, next_back_patch_link),
+                BinOperator::Le => self.gen_jump_cond_reverse(e, JLt, next_back_patch_link),
+                BinOperator::Lt => self.gen_jump_cond(e, JGe, next_back_patch_link),
+                BinOperator::Gt => self.gen_jump_cond_reverse(e, JGe, next_back_patch_link),
+                BinOperator::Ge => self.gen_jump_cond(e, JLt, next_back_patch_link),
+                _ => {
+                    let d = self.gen_temp_var(e.type_info());
+                    let operand = self.visit_bin_op_expr(e, Some(d))?;
+                    let ir_inst = IRInst::jump_if_not(operand, *next_back_patch_link);
+                    *next_back_patch_link = self.ir_output.next_inst_id();
+                    self.ir_output.add_instructions(ir_inst);
+                    Ok(())
+                }
+            },
+            // todo: lit bool
+            e => {
+                let d = self.gen_temp_var(e.type_info());
+                let operand = self.visit_expr(e, Some(d), false)?;
+                let ir_inst = IRInst::jump_if_not(operand, *next_back_patch_link);
+                *next_back_patch_link = self.ir_output.next_inst_id();
+                self.ir_output.add_instructions(ir_inst);
+                Ok(())
+            }
+        }
+    }
+
+    /// Generate the jump instruction taken if `cond` is true,
+    /// and add it to the back patch link.
+    fn gen_jump_if_true(
+        &mut self,
+        cond: &mut Expr,
+        hit_back_patch_link: &mut usize,
+    ) -> Result<(), RccError> {
+        match cond {
+            Expr::Grouped(e) => self.gen_jump_if_true(e, hit_back_patch_link),
+            Expr::Unary(e) if e.op == UnOp::Not => {
+                self.gen_jump_if_false(&mut e.expr, hit_back_patch_link)
+            }
+            Expr::BinOp(e) => match e.bin_op {
+                BinOperator::AndAnd => {
+                    // jump over the rhs if the lhs is false
+                    let mut miss_back_patch_link = 0usize;
+                    self.gen_jump_if_false(&mut e.lhs, &mut miss_back_patch_link)?;
+                    self.gen_jump_if_true(&mut e.rhs, hit_back_patch_link)?;
+                    let next_idx = self.ir_output.next_inst_id();
+                    self.back_patch(miss_back_patch_link, next_idx);
+                    Ok(())
+                }
+                BinOperator::OrOr => {
+                    self.gen_jump_if_true(&mut e.lhs, hit_back_patch_link)?;
+                    self.gen_jump_if_true(&mut e.rhs, hit_back_patch_link)
+                }
+                BinOperator::Ne => self.gen_jump_cond(e, JNe, hit_back_patch_link),
+                BinOperator::EqEq => self.gen_jump_cond(e, JEq, hit_back_patch_link),
+                BinOperator::Le => self.gen_jump_cond_reverse(e, JGe, hit_back_patch_link),
+                BinOperator::Lt => self.gen_jump_cond(e, JLt, hit_back_patch_link),
+                BinOperator::Gt => self.gen_jump_cond_reverse(e, JLt, hit_back_patch_link),
+                BinOperator::Ge => self.gen_jump_cond(e, JGe, hit_back_patch_link),
+                _ => {
+                    let d = self.gen_temp_var(e.type_info());
+                    let operand = self.visit_bin_op_expr(e, Some(d))?;
+                    let ir_inst = IRInst::jump_if(operand, *hit_back_patch_link);
+                    *hit_back_patch_link = self.ir_output.next_inst_id();
+                    self.ir_output.add_instructions(ir_inst);
+                    Ok(())
+                }
+            },
+            e => {
+                let d = self.gen_temp_var(e.type_info());
+                let operand = self.visit_expr(e, Some(d), false)?;
+                let ir_inst = IRInst::jump_if(operand, *hit_back_patch_link);
+                *hit_back_patch_link = self.ir_output.next_inst_id();
+                self.ir_output.add_instructions(ir_inst);
+                Ok(())
+            }
+        }
+    }
+
     fn gen_jump_cond(
         &mut self,
         e: &mut BinOpExpr,
         jump: Jump,
         next_back_patch_link: &mut usize,
     ) -> Result<(), RccError> {
-        let d = self.gen_temp_var(e.type_info());
+        let d = self.gen_temp_var(e.lhs.type_info());
         let lhs = self.visit_expr(&mut e.lhs, Some(d), false)?;
-        let d = self.gen_temp_var(e.type_info());
+        let d = self.gen_temp_var(e.rhs.type_info());
         let rhs = self.visit_expr(&mut e.rhs, Some(d), false)?;
         let ir_inst = IRInst::jump_if_cond(jump, lhs, rhs, *next_back_patch_link);
         *next_back_patch_link = self.ir_output.next_inst_id();
@@ -860,9 +2861,9 @@ impl IRBuilder {
         jump: Jump,
         next_back_patch_link: &mut usize,
     ) -> Result<(), RccError> {
-        let d = self.gen_temp_var(e.type_info());
+        let d = self.gen_temp_var(e.lhs.type_info());
         let lhs = self.visit_expr(&mut e.lhs, Some(d), false)?;
-        let d = self.gen_temp_var(e.type_info());
+        let d = self.gen_temp_var(e.rhs.type_info());
         let rhs = self.visit_expr(&mut e.rhs, Some(d), false)?;
         let ir_inst = IRInst::jump_if_cond(jump, rhs, lhs, *next_back_patch_link);
         *next_back_patch_link = self.ir_output.next_inst_id();
@@ -879,9 +2880,10 @@ impl IRBuilder {
             Some(e) => {
                 let ret_place = self.fn_ret_temp_var.last().unwrap();
                 let operand = self.visit_expr(e.as_mut(), Some(ret_place.clone()), false)?;
-                self.ir_output.add_instructions(IRInst::Ret(operand));
+                self.gen_ret(operand);
             }
             None => {
+                self.gen_write_back();
                 self.ir_output.add_instructions(IRInst::Ret(Operand::Unit));
             }
         };
@@ -900,11 +2902,10 @@ impl IRBuilder {
         break_expr: &mut BreakExpr,
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        let (break_place, _) = self.loop_var_stack.last_mut().unwrap();
-        match &mut break_expr.0 {
+        let break_place = self.target_loop(&break_expr.label).break_place.clone();
+        match &mut break_expr.expr {
             Some(e) => {
                 if let Some(p) = break_place {
-                    let p = p.clone();
                     let temp_v = self.gen_temp_var(e.type_info());
                     let rhs = self.visit_expr(e, Some(temp_v), false)?;
                     self.ir_output.add_instructions(IRInst::load_data(p, rhs));
@@ -920,10 +2921,31 @@ impl IRBuilder {
         }
         let jump_id = self.ir_output.next_inst_id();
 
-        let (_, loop_start_id) = self.loop_var_stack.last_mut().unwrap();
-        self.ir_output
-            .add_instructions(IRInst::jump(*loop_start_id));
-        *loop_start_id = jump_id;
+        let link = &mut self.target_loop(&break_expr.label).break_link;
+        let ir_inst = IRInst::jump(*link);
+        *link = jump_id;
+        self.ir_output.add_instructions(ir_inst);
+
+        match dest {
+            Some(d) => {
+                self.ir_output
+                    .add_instructions(IRInst::load_data(d.clone(), Operand::Never));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Never),
+        }
+    }
+
+    fn visit_continue_expr(
+        &mut self,
+        continue_expr: &mut ContinueExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let jump_id = self.ir_output.next_inst_id();
+        let link = &mut self.target_loop(&continue_expr.0).continue_link;
+        let ir_inst = IRInst::jump(*link);
+        *link = jump_id;
+        self.ir_output.add_instructions(ir_inst);
 
         match dest {
             Some(d) => {
@@ -935,3 +2957,49 @@ impl IRBuilder {
         }
     }
 }
+
+/// Indexes of the fields in the struct pattern, in the order of the struct or variant declaration.
+fn struct_field_indexes(struct_pattern: &StructPattern, type_info: &TypeInfo) -> Vec<usize> {
+    let names = match type_info {
+        TypeInfo::Struct { fields, .. } => fields.names(),
+        TypeInfo::Enum { variants, .. } => {
+            let (_, variant) = variants
+                .find(struct_pattern.path.segments.last().unwrap())
+                .unwrap();
+            variant.fields.into_iter().map(|(name, _)| name).collect()
+        }
+        t => unreachable!("struct pattern of type {:?}", t),
+    };
+    struct_pattern
+        .fields
+        .iter()
+        .map(|f| names.iter().position(|name| name == &f.name).unwrap())
+        .collect()
+}
+
+/// `a < b` and the like of floats, whose negation `!(a < b)` is not `a >= b`
+/// if one of the floats is NaN.
+fn is_float_ordering(e: &BinOpExpr) -> bool {
+    e.bin_op.is_ordering() && e.lhs.type_info().borrow().is_float()
+}
+
+/// `value` is the literal without the suffix, which may be negated like `-128` of `i8`.
+fn lit_num_operand(t: TypeLitNum, value: &str) -> Result<Operand, RccError> {
+    let operand = match t {
+        TypeLitNum::I8 => Operand::I8(value.parse()?),
+        TypeLitNum::I16 => Operand::I16(value.parse()?),
+        TypeLitNum::I | TypeLitNum::I32 => Operand::I32(value.parse()?),
+        TypeLitNum::I64 => Operand::I64(value.parse()?),
+        TypeLitNum::I128 => Operand::I128(value.parse()?),
+        TypeLitNum::Isize => Operand::Isize(value.parse()?),
+        TypeLitNum::U8 => Operand::U8(value.parse()?),
+        TypeLitNum::U16 => Operand::U16(value.parse()?),
+        TypeLitNum::U32 => Operand::U32(value.parse()?),
+        TypeLitNum::U64 => Operand::U64(value.parse()?),
+        TypeLitNum::U128 => Operand::U128(value.parse()?),
+        TypeLitNum::Usize => Operand::Usize(value.parse()?),
+        TypeLitNum::F32 => Operand::F32(value.parse()?),
+        TypeLitNum::F | TypeLitNum::F64 => Operand::F64(value.parse()?),
+    };
+    Ok(operand)
+}
@@ -1,14 +1,19 @@
-use crate::ast::item::{ItemFn, FnSignature};
+use crate::analyser::sym_resolver::TypeInfo;
+use crate::ast::item::{FnParam, FnSignature, ItemFn};
 use crate::ast::pattern::Pattern;
+use crate::ast::types::PtrKind;
 use crate::ast::Visibility;
+use crate::ir::var_name::{pattern_param, CLOSURE_ENV, SRET};
 use crate::ir::{IRInst, IRType, Operand, Place};
 use crate::rcc::RccError;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 
 pub struct LinearIR {
     pub funcs: Vec<Func>,
     /// label, value
     pub ro_local_strs: HashMap<String, String>,
+    /// label, symbols of the functions
+    pub vtables: BTreeMap<String, Vec<String>>,
 }
 
 impl LinearIR {
@@ -16,6 +21,7 @@ impl LinearIR {
         LinearIR {
             funcs: vec![],
             ro_local_strs: HashMap::new(),
+            vtables: BTreeMap::new(),
         }
     }
 
@@ -25,26 +31,51 @@ impl LinearIR {
         Operand::Place(Place::lit_const(label, IRType::Char))
     }
 
-    pub fn add_func(&mut self, item_fn: &ItemFn) -> Result<(), RccError> {
-        let fn_name = item_fn.name.clone();
+    /// Return the read-only place holding the vtable of `type_name` for `trait_name`.
+    pub fn add_vtable(&mut self, type_name: &str, trait_name: &str, fns: Vec<String>) -> Place {
+        let label = vtable_symbol(type_name, trait_name);
+        self.vtables.entry(label.clone()).or_insert(fns);
+        Place::lit_const(label, IRType::Addr)
+    }
+
+    /// Aggregate arguments are passed as values, and the target decides how to pass them.
+    /// An aggregate result returned by sret is written to the address passed in the hidden first argument.
+    /// The address of the environment of a closure is passed after it if `has_env`.
+    pub fn add_func(
+        &mut self,
+        fn_name: String,
+        item_fn: &ItemFn,
+        has_env: bool,
+        param_types: &[IRType],
+        ret_type: &IRType,
+        addr_size: u32,
+    ) -> Result<(), RccError> {
         let is_global = item_fn.vis() == Visibility::Pub;
 
-        let scope = &item_fn.fn_block.scope;
-        let scope_id = scope.scope_id;
+        let scope_id = item_fn.fn_block.scope.scope_id;
         debug_assert_ne!(0, scope_id);
 
         let mut fn_args = Vec::new();
-        for param in item_fn.fn_params.params.iter() {
-            fn_args.push(match &param.pattern {
-                Pattern::Identifier(i) => {
-                    let (var_info, _) = scope.find_variable(i.ident()).unwrap();
-                    (i.ident().to_string(), IRType::from_var_info(var_info)?)
-                }
-            });
+        if ret_type.is_returned_by_sret(addr_size) {
+            fn_args.push((SRET.to_string(), IRType::Addr));
+        }
+        if has_env {
+            fn_args.push((CLOSURE_ENV.to_string(), IRType::Addr));
+        }
+        let params = item_fn.fn_params.params.iter().enumerate();
+        for ((i, param), ir_type) in params.zip(param_types) {
+            let name = param_name(i, param);
+            fn_args.push((name, ir_type.clone()));
         }
 
-        self.funcs
-            .push(Func::new(fn_name, is_global, fn_args, scope_id));
+        let instance = self
+            .funcs
+            .iter()
+            .filter(|f| f.block_scope_id == scope_id)
+            .count();
+        let mut func = Func::new(fn_name, is_global, fn_args, scope_id);
+        func.instance = instance;
+        self.funcs.push(func);
         Ok(())
     }
 
@@ -66,12 +97,104 @@ impl LinearIR {
     }
 }
 
+/// Functions in impl blocks are mangled like the legacy Rust symbols,
+/// e.g. `Foo::new` is `_ZN3Foo3newE`.
+pub fn assoc_fn_symbol(type_name: &str, fn_name: &str) -> String {
+    format!(
+        "_ZN{}{}{}{}E",
+        type_name.len(),
+        type_name,
+        fn_name.len(),
+        fn_name
+    )
+}
+
+/// Functions of traits are named by the implemented type and the trait,
+/// e.g. `<i32 as Num>::zero` is `_ZN3i323Num4zeroE`.
+pub fn trait_fn_symbol(type_name: &str, trait_name: &str, fn_name: &str) -> String {
+    format!(
+        "_ZN{}{}{}{}{}{}E",
+        type_name.len(),
+        type_name,
+        trait_name.len(),
+        trait_name,
+        fn_name.len(),
+        fn_name
+    )
+}
+
+/// Vtables are named like the legacy Rust symbols,
+/// e.g. the vtable of `Circle` for `Shape` is `_ZTVN6Circle5ShapeE`.
+pub fn vtable_symbol(type_name: &str, trait_name: &str) -> String {
+    format!(
+        "_ZTVN{}{}{}{}E",
+        type_name.len(),
+        type_name,
+        trait_name.len(),
+        trait_name
+    )
+}
+
+/// Closures are named by the function defining them and the scope of their bodies,
+/// e.g. the closure in `main` whose body is the scope 3 is `main.closure.3`.
+pub fn closure_symbol(fn_symbol: &str, scope_id: u64) -> String {
+    format!("{}.closure.{}", fn_symbol, scope_id)
+}
+
+/// Instances of generic functions are named by the type arguments,
+/// e.g. `max::<i32>` is `_ZN3maxI3i32EE`.
+pub fn generic_fn_symbol(fn_name: &str, type_args: &[TypeInfo]) -> String {
+    let args: String = type_args.iter().map(mangled_type).collect();
+    format!("_ZN{}{}I{}EE", fn_name.len(), fn_name, args)
+}
+
+fn mangled_type(type_info: &TypeInfo) -> String {
+    match type_info {
+        TypeInfo::Struct { name, fields, .. } if fields.is_generic() => {
+            let args: String = fields.args().iter().map(mangled_type).collect();
+            format!("{}{}I{}E", name.len(), name, args)
+        }
+        TypeInfo::Ptr { kind, type_info } => {
+            let prefix = match kind {
+                PtrKind::Ref => "R",
+                PtrKind::MutRef => "M",
+                PtrKind::ConstRawPtr => "P",
+                PtrKind::MutRawPtr => "Q",
+            };
+            format!("{}{}", prefix, mangled_type(type_info))
+        }
+        TypeInfo::Tuple(types) => {
+            let types: String = types.iter().map(mangled_type).collect();
+            format!("T{}E", types)
+        }
+        TypeInfo::Array { elem_type, len } => format!("A{}_{}", len, mangled_type(elem_type)),
+        TypeInfo::Unit => "TE".into(),
+        TypeInfo::Str => "3str".into(),
+        t => {
+            let name = t.impl_name().unwrap();
+            format!("{}{}", name.len(), name)
+        }
+    }
+}
+
+/// Parameters bound by patterns like `(a, b): (i32, i32)` are passed in hidden variables.
+pub fn param_name(index: usize, param: &FnParam) -> String {
+    match &param.pattern {
+        Pattern::Identifier(ident_pattern) if !ident_pattern.is_ref() => {
+            ident_pattern.ident().to_string()
+        }
+        _ => pattern_param(index),
+    }
+}
+
 pub struct Func {
     pub name: String,
     pub insts: VecDeque<IRInst>,
     pub is_global: bool,
     pub fn_args: Vec<(String, IRType)>,
     pub block_scope_id: u64,
+    /// number of the instances of the same generic function generated before
+    pub instance: usize,
 }
 
 impl Func {
@@ -87,6 +210,7 @@ impl Func {
             is_global,
             fn_args,
             block_scope_id,
+            instance: 0,
         }
     }
 }
@@ -1,17 +1,18 @@
-use std::collections::VecDeque;
 use std::fmt::Debug;
-use std::ops::Deref;
+use std::rc::Rc;
 
-use crate::analyser::sym_resolver::{TypeInfo, VarInfo, VarKind};
+use crate::analyser::sym_resolver::{TypeInfo, VarKind};
 use crate::ast::expr::BinOperator;
 use crate::ast::types::TypeLitNum;
 use crate::ir::var_name::{is_temp_var, local_var};
 use crate::rcc::RccError;
 
 pub mod cfg;
-mod dataflow;
+pub(crate) mod dataflow;
 pub mod ir_build;
 mod linear_ir;
+pub mod optimize;
+#[cfg(test)]
 pub(crate) mod tests;
 pub mod var_name;
 
@@ -23,6 +24,21 @@ pub enum Jump {
     JGe,
 }
 
+/// Conversions of `as` between primitive types, casts which only reinterpret
+/// the bits are `LoadData`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CastOp {
+    /// sign extend the narrower signed integer
+    SExt,
+    /// zero extend the narrower unsigned integer, `bool` or `char`
+    ZExt,
+    /// truncate to the narrower integer, which is then extended by its own signedness
+    Trunc,
+    IntToFloat,
+    FloatToInt,
+    FloatCast,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Operand {
     F32(f32),
@@ -52,12 +68,97 @@ impl Operand {
     pub fn byte_size(&self, addr_size: u32) -> u32 {
         match self {
             Self::Unit | Self::Never => 0,
-            Self::Bool(_) | Self::Char(_)| Self::I8(_) | Self::U8(_) => 1,
-            Self::I32(_) | Self::U32(_) => 4,
-            Self::I64(_) | Self::U64(_) => 8,
+            Self::Bool(_) | Self::I8(_) | Self::U8(_) => 1,
+            Self::I16(_) | Self::U16(_) => 2,
+            Self::Char(_) | Self::I32(_) | Self::U32(_) | Self::F32(_) => 4,
+            Self::I64(_) | Self::U64(_) | Self::F64(_) => 8,
+            Self::I128(_) | Self::U128(_) => 16,
+            Self::Isize(_) | Self::Usize(_) | Self::FnLabel(_) => addr_size / 8,
             Self::Place(p) => p.ir_type.byte_size(addr_size),
             Self::FnRetPlace(ir_type) => ir_type.byte_size(addr_size),
-            _ => unimplemented!("{:?}", self),
+        }
+    }
+
+    pub fn ir_type(&self) -> IRType {
+        match self {
+            Self::F32(_) => IRType::F32,
+            Self::F64(_) => IRType::F64,
+            Self::Bool(_) => IRType::Bool,
+            Self::Char(_) => IRType::Char,
+            Self::I8(_) => IRType::I8,
+            Self::I16(_) => IRType::I16,
+            Self::I32(_) => IRType::I32,
+            Self::I64(_) => IRType::I64,
+            Self::I128(_) => IRType::I128,
+            Self::Isize(_) => IRType::Isize,
+            Self::U8(_) => IRType::U8,
+            Self::U16(_) => IRType::U16,
+            Self::U32(_) => IRType::U32,
+            Self::U64(_) => IRType::U64,
+            Self::U128(_) => IRType::U128,
+            Self::Usize(_) => IRType::Usize,
+            Self::Place(p) => p.ir_type.clone(),
+            Self::FnLabel(_) => IRType::Addr,
+            Self::Unit => IRType::Unit,
+            Self::Never => IRType::Never,
+            Self::FnRetPlace(ir_type) => ir_type.clone(),
+        }
+    }
+
+    /// The integer `0` of the type.
+    pub fn zero(ir_type: &IRType) -> Operand {
+        match ir_type {
+            IRType::I8 => Self::I8(0),
+            IRType::I16 => Self::I16(0),
+            IRType::I32 => Self::I32(0),
+            IRType::I64 => Self::I64(0),
+            IRType::I128 => Self::I128(0),
+            IRType::Isize => Self::Isize(0),
+            IRType::U8 => Self::U8(0),
+            IRType::U16 => Self::U16(0),
+            IRType::U32 => Self::U32(0),
+            IRType::U64 => Self::U64(0),
+            IRType::U128 => Self::U128(0),
+            IRType::Usize => Self::Usize(0),
+            t => unreachable!("{:?} is not an integer type", t),
+        }
+    }
+
+    /// The integer of the type with all the bits set, `!x` is `x ^ all_ones`.
+    pub fn all_ones(ir_type: &IRType) -> Operand {
+        match ir_type {
+            IRType::I8 => Self::I8(-1),
+            IRType::I16 => Self::I16(-1),
+            IRType::I32 => Self::I32(-1),
+            IRType::I64 => Self::I64(-1),
+            IRType::I128 => Self::I128(-1),
+            IRType::Isize => Self::Isize(-1),
+            IRType::U8 => Self::U8(u8::MAX),
+            IRType::U16 => Self::U16(u16::MAX),
+            IRType::U32 => Self::U32(u32::MAX),
+            IRType::U64 => Self::U64(u64::MAX),
+            IRType::U128 => Self::U128(u128::MAX),
+            IRType::Usize => Self::Usize(usize::MAX),
+            t => unreachable!("{:?} is not an integer type", t),
+        }
+    }
+
+    /// The integer `1` of the type.
+    pub fn one(ir_type: &IRType) -> Operand {
+        match ir_type {
+            IRType::I8 => Self::I8(1),
+            IRType::I16 => Self::I16(1),
+            IRType::I32 => Self::I32(1),
+            IRType::I64 => Self::I64(1),
+            IRType::I128 => Self::I128(1),
+            IRType::Isize => Self::Isize(1),
+            IRType::U8 => Self::U8(1),
+            IRType::U16 => Self::U16(1),
+            IRType::U32 => Self::U32(1),
+            IRType::U64 => Self::U64(1),
+            IRType::U128 => Self::U128(1),
+            IRType::Usize => Self::Usize(1),
+            t => unreachable!("{:?} is not an integer type", t),
         }
     }
 
@@ -71,12 +172,17 @@ impl Operand {
                        Self::I128(_) | Self::U128(_) |
                        Self::Isize(_) | Self::Usize(_))
     }
-    pub fn is_unit_or_never(&self) -> bool {
-        matches!(self, Self::Unit | Self::Never)
+    pub fn is_float(&self) -> bool {
+        match self {
+            Self::F32(_) | Self::F64(_) => true,
+            Self::Place(p) => p.ir_type.is_float(),
+            Self::FnRetPlace(ir_type) => ir_type.is_float(),
+            _ => false,
+        }
     }
 
-    pub fn eq_or_is_never(&self, other: Operand) -> bool {
-        self == &other || self == &Self::Never
+    pub fn is_unit_or_never(&self) -> bool {
+        matches!(self, Self::Unit | Self::Never)
     }
 }
 
@@ -108,14 +214,6 @@ impl Place {
         }
     }
 
-    pub fn local_mut(label: String, ir_type: IRType) -> Place {
-        Place {
-            label,
-            kind: VarKind::LocalMut,
-            ir_type,
-        }
-    }
-
     pub fn lit_const(label: String, ir_type: IRType) -> Place {
         Place {
             label,
@@ -129,7 +227,7 @@ impl Place {
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum IRType {
     F32,
     F64,
@@ -152,21 +250,198 @@ pub enum IRType {
     Never,
     /// address
     Addr,
+    /// Struct stored in memory, fields are laid out in declaration order,
+    /// and each field is aligned to its natural alignment.
+    Aggregate(Rc<Vec<IRType>>),
+    /// `[elem; len]` stored in memory
+    Array(Rc<IRType>, u32),
+    /// Members share the same memory, e.g. the payloads of enum variants.
+    Union(Rc<Vec<IRType>>),
 }
 
 impl IRType {
     pub fn byte_size(&self, addr_size: u32) -> u32 {
         match self {
-            IRType::I8 | IRType::U8 | IRType::Char | IRType::Bool => 1,
+            IRType::I8 | IRType::U8 | IRType::Bool => 1,
             IRType::I16 | IRType::U16 => 2,
-            IRType::I32 | IRType::U32 | IRType::F32 => 4,
+            // a char holds a whole unicode scalar value
+            IRType::Char | IRType::I32 | IRType::U32 | IRType::F32 => 4,
             IRType::I64 | IRType::U64 | IRType::F64 => 8,
             IRType::I128 | IRType::U128 => 16,
             IRType::Isize | IRType::Usize | IRType::Addr => {
-                debug_assert!(addr_size % 8 == 0);
+                debug_assert!(addr_size.is_multiple_of(8));
                 addr_size / 8
             }
             IRType::Unit | IRType::Never => 0,
+            IRType::Aggregate(fields) => match fields.last() {
+                Some(last) => {
+                    let end = self.field_offset(fields.len() - 1, addr_size)
+                        + last.byte_size(addr_size);
+                    end.next_multiple_of(self.align(addr_size))
+                }
+                None => 0,
+            },
+            IRType::Array(elem, len) => elem.byte_size(addr_size) * len,
+            IRType::Union(members) => members
+                .iter()
+                .map(|m| m.byte_size(addr_size))
+                .max()
+                .unwrap_or(0)
+                .next_multiple_of(self.align(addr_size)),
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            IRType::I8 | IRType::I16 | IRType::I32 | IRType::I64 | IRType::I128 | IRType::Isize
+        )
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, IRType::F32 | IRType::F64)
+    }
+
+    /// The conversion of `self as target`, `None` if the bits are only reinterpreted.
+    /// Integers narrower than a register are kept extended by their signedness.
+    pub fn cast_op(&self, target: &IRType, addr_size: u32) -> Option<CastOp> {
+        match (self.is_float(), target.is_float()) {
+            (true, true) if self != target => Some(CastOp::FloatCast),
+            (true, true) => None,
+            (true, false) => Some(CastOp::FloatToInt),
+            (false, true) => Some(CastOp::IntToFloat),
+            (false, false) => {
+                let size = self.byte_size(addr_size);
+                let target_size = target.byte_size(addr_size);
+                if size < target_size {
+                    Some(if self.is_signed() {
+                        CastOp::SExt
+                    } else {
+                        CastOp::ZExt
+                    })
+                } else if size > target_size
+                    || target_size < addr_size / 8 && self.is_signed() != target.is_signed()
+                {
+                    Some(CastOp::Trunc)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    pub fn align(&self, addr_size: u32) -> u32 {
+        match self {
+            IRType::Aggregate(fields) | IRType::Union(fields) => fields
+                .iter()
+                .map(|f| f.align(addr_size))
+                .max()
+                .unwrap_or(1),
+            IRType::Array(elem, _) => elem.align(addr_size),
+            t => t.byte_size(addr_size).max(1),
+        }
+    }
+
+    /// Byte offset of the `index`th field of an aggregate.
+    pub fn field_offset(&self, index: usize, addr_size: u32) -> u32 {
+        match self {
+            IRType::Aggregate(fields) => {
+                let mut offset: u32 = 0;
+                for field in fields[..index].iter() {
+                    offset = offset.next_multiple_of(field.align(addr_size));
+                    offset += field.byte_size(addr_size);
+                }
+                offset.next_multiple_of(fields[index].align(addr_size))
+            }
+            t => unreachable!("{:?} has no fields", t),
+        }
+    }
+
+    /// Byte offset of the `index`th field of the `variant`th variant of a data-carrying enum.
+    pub fn variant_field_offset(&self, variant: usize, index: usize, addr_size: u32) -> u32 {
+        match self {
+            IRType::Aggregate(fields) => match &fields[1] {
+                IRType::Union(payloads) => {
+                    self.field_offset(1, addr_size)
+                        + payloads[variant].field_offset(index, addr_size)
+                }
+                t => unreachable!("{:?} is not a payload of an enum", t),
+            },
+            t => unreachable!("{:?} is not a data-carrying enum", t),
+        }
+    }
+
+    /// Type of the discriminant of an enum with `variant_count` variants.
+    pub fn discriminant(variant_count: usize) -> IRType {
+        if variant_count <= 1 << 8 {
+            IRType::U8
+        } else {
+            IRType::U32
+        }
+    }
+
+    /// Operand holding the discriminant of the `variant`th variant.
+    pub fn discriminant_operand(&self, variant: usize) -> Operand {
+        match self {
+            IRType::U8 => Operand::U8(variant as u8),
+            IRType::U32 => Operand::U32(variant as u32),
+            IRType::Aggregate(fields) => fields[0].discriminant_operand(variant),
+            t => unreachable!("{:?} is not an enum", t),
+        }
+    }
+
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, IRType::Aggregate(_) | IRType::Array(..) | IRType::Union(_))
+    }
+
+    /// Aggregates larger than two registers are returned through the address
+    /// in the hidden first argument, unless they are returned in float registers.
+    /// Smaller ones are returned in a0 and a1.
+    pub fn is_returned_by_sret(&self, addr_size: u32) -> bool {
+        self.is_aggregate()
+            && self.byte_size(addr_size) > 2 * addr_size / 8
+            && self.float_fields(addr_size).is_none()
+    }
+
+    /// (offset, type) of the fields of an aggregate passed in float registers
+    /// by the hardware floating-point calling convention of RISC-V,
+    /// which are a float, two floats, or a float and an integer no wider than an address,
+    /// after flattening the nested structs and arrays.
+    pub fn float_fields(&self, addr_size: u32) -> Option<Vec<(u32, IRType)>> {
+        if !self.is_aggregate() {
+            return None;
+        }
+        let mut fields = vec![];
+        if !self.flatten_fields(0, addr_size, &mut fields) {
+            return None;
+        }
+        let is_int = |t: &IRType| !t.is_float() && t.byte_size(addr_size) <= addr_size / 8;
+        match fields.as_slice() {
+            [(_, a)] if a.is_float() => Some(fields),
+            [(_, a), (_, b)] if a.is_float() && (b.is_float() || is_int(b)) => Some(fields),
+            [(_, a), (_, b)] if is_int(a) && b.is_float() => Some(fields),
+            _ => None,
+        }
+    }
+
+    /// Push the scalar fields in `self` at `base` to `fields`,
+    /// false if there is a union or more than two fields.
+    fn flatten_fields(&self, base: u32, addr_size: u32, fields: &mut Vec<(u32, IRType)>) -> bool {
+        match self {
+            IRType::Aggregate(members) => (0..members.len()).all(|i| {
+                let offset = base + self.field_offset(i, addr_size);
+                members[i].flatten_fields(offset, addr_size, fields)
+            }),
+            IRType::Array(elem, len) => (0..*len).all(|i| {
+                let offset = base + i * elem.byte_size(addr_size);
+                elem.flatten_fields(offset, addr_size, fields)
+            }),
+            IRType::Union(_) => false,
+            IRType::Unit | IRType::Never => true,
+            t => {
+                fields.push((base, t.clone()));
+                fields.len() <= 2
+            }
         }
     }
 
@@ -192,17 +467,86 @@ impl IRType {
             TypeInfo::Char => IRType::Char,
             TypeInfo::Unit => IRType::Unit,
             TypeInfo::Never => IRType::Never,
+            // pointers to trait objects are (data pointer, vtable pointer)
+            TypeInfo::Ptr { type_info, .. } if matches!(**type_info, TypeInfo::TraitObject(_)) => {
+                IRType::Aggregate(Rc::new(vec![IRType::Addr, IRType::Addr]))
+            }
             TypeInfo::Ptr { .. } => IRType::Addr,
+            // functions are referred to by their addresses
+            TypeInfo::Fn { .. } | TypeInfo::FnPtr(_) => IRType::Addr,
+            // the environment of a closure stores the captured variables or their addresses
+            TypeInfo::Closure(closure) => {
+                let captures = closure.captures();
+                if captures.is_empty() {
+                    return Ok(IRType::Unit);
+                }
+                let fields: Result<Vec<IRType>, RccError> = captures
+                    .iter()
+                    .map(|c| {
+                        if c.by_ref {
+                            Ok(IRType::Addr)
+                        } else {
+                            Self::from_type_info(&c.type_info)
+                        }
+                    })
+                    .collect();
+                IRType::Aggregate(Rc::new(fields?))
+            }
+            TypeInfo::Struct { fields, .. } => {
+                let fields: Result<Vec<IRType>, RccError> =
+                    fields.types().iter().map(Self::from_type_info).collect();
+                IRType::Aggregate(Rc::new(fields?))
+            }
+            TypeInfo::Array { elem_type, len } => {
+                IRType::Array(Rc::new(Self::from_type_info(elem_type)?), *len)
+            }
+            TypeInfo::Tuple(types) => {
+                let types: Result<Vec<IRType>, RccError> =
+                    types.iter().map(Self::from_type_info).collect();
+                IRType::Aggregate(Rc::new(types?))
+            }
+            // ranges store the bounds they have, `..` is `()`
+            TypeInfo::Range {
+                has_start,
+                has_end,
+                elem_type,
+                ..
+            } => {
+                let bound_count = *has_start as usize + *has_end as usize;
+                if bound_count == 0 {
+                    return Ok(IRType::Unit);
+                }
+                let elem_type = Self::from_type_info(elem_type)?;
+                IRType::Aggregate(Rc::new(vec![elem_type; bound_count]))
+            }
+            // Enums without data are their discriminants, and others are
+            // (discriminant, union of the fields of the variants).
+            TypeInfo::Enum { variants, .. } => {
+                let discriminant = Self::discriminant(variants.len());
+                if variants.is_empty() {
+                    IRType::Never
+                } else if variants.is_c_like() {
+                    discriminant
+                } else {
+                    let payloads: Result<Vec<IRType>, RccError> = variants
+                        .get()
+                        .iter()
+                        .map(|v| {
+                            let fields: Result<Vec<IRType>, RccError> =
+                                v.fields.iter().map(|(_, t)| Self::from_type_info(t)).collect();
+                            Ok(IRType::Aggregate(Rc::new(fields?)))
+                        })
+                        .collect();
+                    IRType::Aggregate(Rc::new(vec![
+                        discriminant,
+                        IRType::Union(Rc::new(payloads?)),
+                    ]))
+                }
+            }
             t => return Err(RccError::Parse(format!("invalid type {:?}", t))),
         };
         Ok(ir_type)
     }
-
-    pub fn from_var_info(var_info: &VarInfo) -> Result<IRType, RccError> {
-        let t = var_info.type_info.borrow();
-        let tp = t.deref();
-        Self::from_type_info(tp)
-    }
 }
 
 /// Immediate Presentation's Instructions
@@ -246,18 +590,39 @@ pub enum IRInst {
         symbol: Operand,
     },
 
+    /// dest = *(addr + offset)
+    Load {
+        dest: Place,
+        addr: Operand,
+        offset: u32,
+    },
+
+    /// *(addr + offset) = src
+    Store {
+        addr: Operand,
+        offset: u32,
+        src: Operand,
+    },
+
     Call {
         callee: Operand,
         args: Vec<Operand>,
     },
 
+    /// dest = src as type of dest
+    Cast {
+        op: CastOp,
+        dest: Place,
+        src: Operand,
+    },
+
     Ret(Operand),
 }
 
 impl IRInst {
     pub fn bin_op(op: BinOperator, dest: Place, src1: Operand, src2: Operand) -> IRInst {
         debug_assert!(!src1.is_imm() || !src2.is_imm());
-        if src2.is_imm() {
+        if src2.is_imm() || !op.is_commutative() {
             IRInst::BinOp {
                 op,
                 dest,
@@ -278,6 +643,18 @@ impl IRInst {
         IRInst::LoadData { dest, src }
     }
 
+    pub fn load_addr(dest: Place, symbol: Operand) -> IRInst {
+        IRInst::LoadAddr { dest, symbol }
+    }
+
+    pub fn load(dest: Place, addr: Operand, offset: u32) -> IRInst {
+        IRInst::Load { dest, addr, offset }
+    }
+
+    pub fn store(addr: Operand, offset: u32, src: Operand) -> IRInst {
+        IRInst::Store { addr, offset, src }
+    }
+
     pub fn jump(label: usize) -> IRInst {
         IRInst::Jump { label }
     }
@@ -303,17 +680,74 @@ impl IRInst {
         IRInst::Call { callee, args }
     }
 
+    pub fn cast(op: CastOp, dest: Place, src: Operand) -> IRInst {
+        IRInst::Cast { op, dest, src }
+    }
+
+    /// The place defined by this instruction
+    pub fn dest(&self) -> Option<&Place> {
+        match self {
+            Self::BinOp { dest, .. }
+            | Self::LoadData { dest, .. }
+            | Self::LoadAddr { dest, .. }
+            | Self::Load { dest, .. }
+            | Self::Cast { dest, .. } => Some(dest),
+            _ => None,
+        }
+    }
+
+    /// Operands read by this instruction
+    pub fn src_operands(&self) -> Vec<&Operand> {
+        match self {
+            Self::BinOp { src1, src2, .. } | Self::JumpIfCond { src1, src2, .. } => {
+                vec![src1, src2]
+            }
+            Self::JumpIf { cond, .. } | Self::JumpIfNot { cond, .. } => vec![cond],
+            Self::LoadData { src, .. } | Self::Cast { src, .. } => vec![src],
+            Self::LoadAddr { symbol, .. } => vec![symbol],
+            Self::Load { addr, .. } => vec![addr],
+            Self::Store { addr, src, .. } => vec![addr, src],
+            Self::Call { callee, args } => {
+                let mut operands = vec![callee];
+                operands.extend(args.iter());
+                operands
+            }
+            Self::Ret(operand) => vec![operand],
+            Self::Jump { .. } => vec![],
+        }
+    }
+
+    /// The instruction is lowered to a call of a runtime helper like `__muldi3`,
+    /// because the target has no instruction for integers wider than its registers.
+    pub fn calls_helper(&self, addr_size: u32) -> bool {
+        let is_wide_int = |operand: &Operand| {
+            !operand.is_float() && operand.byte_size(addr_size) > addr_size / 8
+        };
+        match self {
+            Self::BinOp { op, src1, .. } => {
+                matches!(op, BinOperator::Star | BinOperator::Slash | BinOperator::Percent)
+                    && is_wide_int(src1)
+            }
+            Self::Cast {
+                op: CastOp::IntToFloat,
+                src,
+                ..
+            } => is_wide_int(src),
+            Self::Cast {
+                op: CastOp::FloatToInt,
+                dest,
+                ..
+            } => is_wide_int(&Operand::Place(dest.clone())),
+            _ => false,
+        }
+    }
+
     pub fn set_jump_label(&mut self, new_label: usize) {
         match self {
             Self::Jump { label } => *label = new_label,
-            Self::JumpIfNot { cond, label } => *label = new_label,
-            Self::JumpIf { cond, label } => *label = new_label,
-            Self::JumpIfCond {
-                cond,
-                src1,
-                src2,
-                label,
-            } => *label = new_label,
+            Self::JumpIfNot { label, .. } => *label = new_label,
+            Self::JumpIf { label, .. } => *label = new_label,
+            Self::JumpIfCond { label, .. } => *label = new_label,
             _ => unreachable!(),
         }
     }
@@ -321,24 +755,14 @@ impl IRInst {
     pub fn jump_label(&self) -> usize {
         *match self {
             Self::Jump { label } => label,
-            Self::JumpIfNot { cond, label } => label,
-            Self::JumpIf { cond, label } => label,
-            Self::JumpIfCond {
-                cond,
-                src1,
-                src2,
-                label,
-            } => label,
+            Self::JumpIfNot { label, .. } => label,
+            Self::JumpIf { label, .. } => label,
+            Self::JumpIfCond { label, .. } => label,
             ir => unreachable!("{:?}", ir),
         }
     }
 }
 
-pub enum StrKind {
-    Lit,
-    Const,
-}
-
 /// Constant fold optimization.
 /// a = 2 * 3 -> a = 6
 /// TODO other primitive type
@@ -372,14 +796,6 @@ pub fn bin_op_may_constant_fold(
                 BinOperator::Ge => Some(Operand::Bool($l >= $r)),
                 BinOperator::Ne => Some(Operand::Bool($l != $r)),
                 BinOperator::EqEq => Some(Operand::Bool($l == $r)),
-                BinOperator::Shl => Some($i(match $l.checked_shl(*$r as u32) {
-                    Some(res) => res,
-                    None => return Err("shl overflow".into()),
-                })),
-                BinOperator::Shr => Some($i(match $l.checked_shr(*$r as u32) {
-                    Some(res) => res,
-                    None => return Err("shr overflow".into()),
-                })),
                 BinOperator::And => Some($i($l & $r)),
                 BinOperator::Or => Some($i($l | $r)),
                 BinOperator::Caret => Some($i($l ^ $r)),
@@ -391,10 +807,143 @@ pub fn bin_op_may_constant_fold(
             }
         };
     }
+    macro_rules! try_fold_float {
+        ($f:path, $l:ident, $r:ident) => {
+            match op {
+                BinOperator::Plus => Some($f($l + $r)),
+                BinOperator::Minus => Some($f($l - $r)),
+                BinOperator::Star => Some($f($l * $r)),
+                BinOperator::Slash => Some($f($l / $r)),
+                BinOperator::Percent => Some($f($l % $r)),
+                BinOperator::Lt => Some(Operand::Bool($l < $r)),
+                BinOperator::Le => Some(Operand::Bool($l <= $r)),
+                BinOperator::Gt => Some(Operand::Bool($l > $r)),
+                BinOperator::Ge => Some(Operand::Bool($l >= $r)),
+                BinOperator::Ne => Some(Operand::Bool($l != $r)),
+                BinOperator::EqEq => Some(Operand::Bool($l == $r)),
+                _ => None,
+            }
+        };
+    }
+    macro_rules! try_fold_shift {
+        ($i:path, $l:ident, $r:ident) => {
+            match op {
+                BinOperator::Shl => Some($i(match $l.checked_shl($r) {
+                    Some(res) => res,
+                    None => return Err("shl overflow".into()),
+                })),
+                BinOperator::Shr => Some($i(match $l.checked_shr($r) {
+                    Some(res) => res,
+                    None => return Err("shr overflow".into()),
+                })),
+                _ => None,
+            }
+        };
+    }
+    // the type of the shift amount may differ from the type of lhs
+    if let BinOperator::Shl | BinOperator::Shr = op {
+        let r = match shift_amount(src2) {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+        return Ok(match src1 {
+            Operand::I8(l) => try_fold_shift!(Operand::I8, l, r),
+            Operand::I16(l) => try_fold_shift!(Operand::I16, l, r),
+            Operand::I32(l) => try_fold_shift!(Operand::I32, l, r),
+            Operand::I64(l) => try_fold_shift!(Operand::I64, l, r),
+            Operand::I128(l) => try_fold_shift!(Operand::I128, l, r),
+            Operand::Isize(l) => try_fold_shift!(Operand::Isize, l, r),
+            Operand::U8(l) => try_fold_shift!(Operand::U8, l, r),
+            Operand::U16(l) => try_fold_shift!(Operand::U16, l, r),
+            Operand::U32(l) => try_fold_shift!(Operand::U32, l, r),
+            Operand::U64(l) => try_fold_shift!(Operand::U64, l, r),
+            Operand::U128(l) => try_fold_shift!(Operand::U128, l, r),
+            Operand::Usize(l) => try_fold_shift!(Operand::Usize, l, r),
+            _ => None,
+        });
+    }
     Ok(match (src1, src2) {
+        (Operand::F32(l), Operand::F32(r)) => try_fold_float!(Operand::F32, l, r),
+        (Operand::F64(l), Operand::F64(r)) => try_fold_float!(Operand::F64, l, r),
+        (Operand::I8(l), Operand::I8(r)) => try_fold_int!(Operand::I8, l, r),
+        (Operand::I16(l), Operand::I16(r)) => try_fold_int!(Operand::I16, l, r),
         (Operand::I32(l), Operand::I32(r)) => try_fold_int!(Operand::I32, l, r),
         (Operand::I64(l), Operand::I64(r)) => try_fold_int!(Operand::I64, l, r),
         (Operand::I128(l), Operand::I128(r)) => try_fold_int!(Operand::I128, l, r),
+        (Operand::Isize(l), Operand::Isize(r)) => try_fold_int!(Operand::Isize, l, r),
+        (Operand::U8(l), Operand::U8(r)) => try_fold_int!(Operand::U8, l, r),
+        (Operand::U16(l), Operand::U16(r)) => try_fold_int!(Operand::U16, l, r),
+        (Operand::U32(l), Operand::U32(r)) => try_fold_int!(Operand::U32, l, r),
+        (Operand::U64(l), Operand::U64(r)) => try_fold_int!(Operand::U64, l, r),
+        (Operand::U128(l), Operand::U128(r)) => try_fold_int!(Operand::U128, l, r),
+        (Operand::Usize(l), Operand::Usize(r)) => try_fold_int!(Operand::Usize, l, r),
         _ => None,
     })
 }
+
+/// The shift amount of a constant rhs, `None` if it is negative or not an integer.
+fn shift_amount(operand: &Operand) -> Option<u32> {
+    let amount = match *operand {
+        Operand::I8(i) => i as i128,
+        Operand::I16(i) => i as i128,
+        Operand::I32(i) => i as i128,
+        Operand::I64(i) => i as i128,
+        Operand::I128(i) => i,
+        Operand::Isize(i) => i as i128,
+        Operand::U8(i) => i as i128,
+        Operand::U16(i) => i as i128,
+        Operand::U32(i) => i as i128,
+        Operand::U64(i) => i as i128,
+        Operand::U128(i) => i as i128,
+        Operand::Usize(i) => i as i128,
+        _ => return None,
+    };
+    if amount < 0 || amount > u32::MAX as i128 {
+        None
+    } else {
+        Some(amount as u32)
+    }
+}
+
+/// Constant fold casts between primitive types.
+/// a = 300i32 as u8 -> a = 44u8
+pub fn cast_may_constant_fold(src: &Operand, ir_type: &IRType) -> Option<Operand> {
+    macro_rules! cast_to {
+        ($v:expr) => {
+            match ir_type {
+                IRType::I8 => Operand::I8($v as i8),
+                IRType::I16 => Operand::I16($v as i16),
+                IRType::I32 => Operand::I32($v as i32),
+                IRType::I64 => Operand::I64($v as i64),
+                IRType::I128 => Operand::I128($v as i128),
+                IRType::U8 => Operand::U8($v as u8),
+                IRType::U16 => Operand::U16($v as u16),
+                IRType::U32 => Operand::U32($v as u32),
+                IRType::U64 => Operand::U64($v as u64),
+                IRType::U128 => Operand::U128($v as u128),
+                IRType::F32 => Operand::F32($v as f32),
+                IRType::F64 => Operand::F64($v as f64),
+                IRType::Char => Operand::Char($v as u8 as char),
+                // the sizes of the target are unknown
+                _ => return None,
+            }
+        };
+    }
+    Some(match *src {
+        Operand::Bool(b) => cast_to!(b as i128),
+        Operand::Char(c) => cast_to!(c as i128),
+        Operand::I8(i) => cast_to!(i),
+        Operand::I16(i) => cast_to!(i),
+        Operand::I32(i) => cast_to!(i),
+        Operand::I64(i) => cast_to!(i),
+        Operand::I128(i) => cast_to!(i),
+        Operand::U8(i) => cast_to!(i),
+        Operand::U16(i) => cast_to!(i),
+        Operand::U32(i) => cast_to!(i),
+        Operand::U64(i) => cast_to!(i),
+        Operand::U128(i) => cast_to!(i),
+        Operand::F32(f) => cast_to!(f),
+        Operand::F64(f) => cast_to!(f),
+        _ => return None,
+    })
+}
@@ -0,0 +1,116 @@
+use crate::ir::cfg::CFG;
+use crate::ir::optimize::address_taken_vars;
+use crate::ir::{bin_op_may_constant_fold, cast_may_constant_fold, IRInst, Operand};
+use std::collections::HashMap;
+
+/// Local constant propagation and folding.
+///
+/// (1) a = 3
+/// (2) b = a + 4
+/// (3) c = b * a
+///
+/// =>
+///
+/// (1) a = 3
+/// (2) b = 7
+/// (3) c = 21
+///
+/// Returns `true` if any instruction is changed.
+pub fn constant_propagation(cfg: &mut CFG) -> bool {
+    let address_taken = address_taken_vars(cfg);
+    let mut changed = false;
+
+    for bb in cfg.basic_blocks.iter_mut() {
+        // variable label -> constant
+        let mut consts: HashMap<String, Operand> = HashMap::new();
+
+        for inst in bb.instructions.iter_mut() {
+            match inst {
+                IRInst::LoadData { src, .. } => changed |= propagate(src, &consts),
+                IRInst::BinOp { src1, src2, .. } | IRInst::JumpIfCond { src1, src2, .. } => {
+                    changed |= propagate(src1, &consts);
+                    changed |= propagate(src2, &consts);
+                }
+                IRInst::JumpIf { cond, .. } | IRInst::JumpIfNot { cond, .. } => {
+                    changed |= propagate(cond, &consts)
+                }
+                IRInst::Call { args, .. } => {
+                    for arg in args.iter_mut() {
+                        changed |= propagate(arg, &consts);
+                    }
+                }
+                IRInst::Ret(operand) => changed |= propagate(operand, &consts),
+                IRInst::Store { src, .. } => changed |= propagate(src, &consts),
+                IRInst::LoadAddr { .. }
+                | IRInst::Load { .. }
+                | IRInst::Jump { .. }
+                | IRInst::Cast { .. } => {}
+            }
+
+            // constant folding
+            if let IRInst::BinOp {
+                op,
+                dest,
+                src1,
+                src2,
+            } = inst
+            {
+                if let Ok(Some(operand)) = bin_op_may_constant_fold(op, src1, src2) {
+                    *inst = IRInst::load_data(dest.clone(), operand);
+                    changed = true;
+                }
+            }
+            // the source of a cast which is not folded stays in a register
+            if let IRInst::Cast { dest, src, .. } = inst {
+                let mut src = src.clone();
+                propagate(&mut src, &consts);
+                if let Some(operand) = cast_may_constant_fold(&src, &dest.ir_type) {
+                    *inst = IRInst::load_data(dest.clone(), operand);
+                    changed = true;
+                }
+            }
+
+            match inst {
+                IRInst::LoadData { dest, src } => {
+                    // a cast which only reinterprets the bits loads its source as is,
+                    // so the constant takes the type of the destination
+                    if src.is_imm() && src.ir_type() != dest.ir_type {
+                        match cast_may_constant_fold(src, &dest.ir_type) {
+                            Some(operand) => {
+                                *src = operand;
+                                changed = true;
+                            }
+                            None => {
+                                consts.remove(&dest.label);
+                                continue;
+                            }
+                        }
+                    }
+                    if src.is_imm() && !address_taken.contains(&dest.label) {
+                        consts.insert(dest.label.clone(), src.clone());
+                    } else {
+                        consts.remove(&dest.label);
+                    }
+                }
+                IRInst::BinOp { dest, .. }
+                | IRInst::LoadAddr { dest, .. }
+                | IRInst::Load { dest, .. }
+                | IRInst::Cast { dest, .. } => {
+                    consts.remove(&dest.label);
+                }
+                _ => {}
+            }
+        }
+    }
+    changed
+}
+
+fn propagate(operand: &mut Operand, consts: &HashMap<String, Operand>) -> bool {
+    if let Operand::Place(p) = operand {
+        if let Some(c) = consts.get(&p.label) {
+            *operand = c.clone();
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,55 @@
+use crate::ir::cfg::CFG;
+use crate::ir::dataflow::live_variable::LiveVariableAnalysis;
+use crate::ir::optimize::address_taken_vars;
+use crate::ir::IRInst;
+use std::collections::LinkedList;
+
+/// Remove assignments to variables which are not live after the assignment.
+///
+/// Returns `true` if any instruction is removed.
+pub fn dead_code_elimination(cfg: &mut CFG) -> bool {
+    let address_taken = address_taken_vars(cfg);
+
+    // ids of dead instructions in each basic block
+    let mut dead_insts: Vec<Vec<usize>> = vec![];
+    {
+        let mut analysis = LiveVariableAnalysis::new(cfg);
+        analysis.apply();
+
+        for bb in cfg.basic_blocks.iter() {
+            let mut dead = vec![];
+            let mut state = analysis.out_states[bb.id].clone();
+            for (i, inst) in bb.instructions.iter().enumerate().rev() {
+                if let IRInst::LoadData { dest, .. }
+                | IRInst::BinOp { dest, .. }
+                | IRInst::Cast { dest, .. } = inst
+                {
+                    if let Some(id) = analysis.var_id(dest) {
+                        if !state.get(id).unwrap() && !address_taken.contains(&dest.label) {
+                            dead.push(i);
+                            continue;
+                        }
+                    }
+                }
+                analysis.transfer(inst, &mut state);
+            }
+            dead_insts.push(dead);
+        }
+    }
+
+    let mut changed = false;
+    for (bb, dead) in cfg.basic_blocks.iter_mut().zip(dead_insts) {
+        if dead.is_empty() {
+            continue;
+        }
+        changed = true;
+        let instructions = std::mem::take(&mut bb.instructions);
+        bb.instructions = instructions
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| !dead.contains(i))
+            .map(|(_, inst)| inst)
+            .collect::<LinkedList<IRInst>>();
+    }
+    changed
+}
@@ -0,0 +1,45 @@
+//! Optimization passes over the control flow graph, enabled by `-O1`.
+
+use crate::ir::cfg::{CFG, CFGIR};
+use crate::ir::{IRInst, Operand};
+use std::collections::HashSet;
+
+mod const_propagation;
+mod dead_code_elimination;
+#[cfg(test)]
+mod tests;
+
+pub use const_propagation::constant_propagation;
+pub use dead_code_elimination::dead_code_elimination;
+
+pub fn optimize(cfg_ir: &mut CFGIR) {
+    for cfg in cfg_ir.cfgs.iter_mut() {
+        optimize_cfg(cfg);
+    }
+}
+
+/// Run the passes until none of them changes the CFG.
+pub fn optimize_cfg(cfg: &mut CFG) {
+    loop {
+        let propagated = constant_propagation(cfg);
+        let eliminated = dead_code_elimination(cfg);
+        if !propagated && !eliminated {
+            break;
+        }
+        cfg.update_local_infos();
+    }
+}
+
+/// Variables whose address is taken may be read or written through pointers,
+/// so their loads and stores must be kept.
+fn address_taken_vars(cfg: &CFG) -> HashSet<String> {
+    cfg.iter_inst()
+        .filter_map(|inst| match inst {
+            IRInst::LoadAddr {
+                symbol: Operand::Place(p),
+                ..
+            } => Some(p.label.clone()),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,86 @@
+use crate::ir::cfg::CFG;
+use crate::ir::optimize::{constant_propagation, dead_code_elimination, optimize_cfg};
+use crate::ir::tests::ir_build;
+use crate::ir::{IRInst, IRType, Operand, Place};
+use crate::rcc::RccError;
+
+fn get_cfg(input: &str) -> Result<CFG, RccError> {
+    let mut ir = ir_build(input)?;
+    let cfg = CFG::new(ir.funcs.pop().unwrap());
+    Ok(cfg)
+}
+
+#[test]
+fn constant_propagation_test() {
+    let mut cfg = get_cfg(
+        r#"
+        fn foo() -> i32 {
+            let a = 3;
+            let b = a + 4;
+            let c = b * a;
+            c
+        }
+    "#,
+    )
+    .unwrap();
+    assert!(constant_propagation(&mut cfg));
+    let insts: Vec<&IRInst> = cfg.iter_inst().collect();
+    assert_eq!(
+        vec![
+            &IRInst::load_data(Place::local("a_2".into(), IRType::I32), Operand::I32(3)),
+            &IRInst::load_data(Place::local("b_2".into(), IRType::I32), Operand::I32(7)),
+            &IRInst::load_data(Place::local("c_2".into(), IRType::I32), Operand::I32(21)),
+            &IRInst::Ret(Operand::I32(21)),
+        ],
+        insts
+    );
+    assert!(!constant_propagation(&mut cfg));
+
+    assert!(dead_code_elimination(&mut cfg));
+    let insts: Vec<&IRInst> = cfg.iter_inst().collect();
+    assert_eq!(vec![&IRInst::Ret(Operand::I32(21))], insts);
+}
+
+#[test]
+fn dead_code_elimination_test() {
+    let mut cfg = get_cfg(
+        r#"
+        fn foo(x: i32) -> i32 {
+            let mut a = x + 1;
+            let b = x * 2;
+            while a < 10 {
+                let c = a * 3;
+                a += 1;
+            }
+            a
+        }
+    "#,
+    )
+    .unwrap();
+    optimize_cfg(&mut cfg);
+    let insts = format!("{:?}", cfg.iter_inst().collect::<Vec<&IRInst>>());
+    assert!(!insts.contains("b_2"));
+    assert!(!insts.contains("c_4"));
+    assert!(insts.contains("a_2"));
+    assert!(!cfg.local_infos.contains_key("b_2"));
+    assert!(!cfg.local_infos.contains_key("c_4"));
+    assert_eq!(2, cfg.local_infos.len());
+}
+
+#[test]
+fn constant_propagation_cast_test() {
+    let mut cfg = get_cfg(
+        r#"
+        fn foo() -> bool {
+            let a: i32 = -5;
+            let b = a as u32;
+            b < 3
+        }
+    "#,
+    )
+    .unwrap();
+    while constant_propagation(&mut cfg) {}
+    let insts = format!("{:?}", cfg.iter_inst().collect::<Vec<&IRInst>>());
+    assert!(insts.contains("U32(4294967291)"), "{}", insts);
+    assert!(insts.contains("Ret(Bool(false))"), "{}", insts);
+}
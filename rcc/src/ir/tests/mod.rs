@@ -1,12 +1,13 @@
 use crate::analyser::sym_resolver::SymbolResolver;
 use crate::ast::AST;
+use crate::code_gen::TargetPlatform;
 use crate::ir::cfg::CFG;
 use crate::ir::ir_build::IRBuilder;
 use crate::ir::linear_ir::LinearIR;
 use crate::ir::IRInst;
 use crate::lexer::Lexer;
 use crate::parser::{Parse, ParseCursor};
-use crate::rcc::{OptimizeLevel, RccError};
+use crate::rcc::RccError;
 use crate::tests;
 use crate::tests::{assert_fmt_eq, assert_pretty_fmt_eq};
 
@@ -17,8 +18,8 @@ fn expected_from_file(file_name: &str) -> String {
     tests::read_from_file(file_name, "./src/ir/tests")
 }
 
-fn ir_build_with_optimize(input: &str, opt_level: OptimizeLevel) -> Result<LinearIR, RccError> {
-    let mut ir_builder = IRBuilder::new(opt_level);
+pub(crate) fn ir_build(input: &str) -> Result<LinearIR, RccError> {
+    let mut ir_builder = IRBuilder::new(TargetPlatform::Riscv32.addr_size());
     let mut lexer = Lexer::new(input);
     let mut cursor = ParseCursor::new(lexer.tokenize());
     let mut ast = AST::parse(&mut cursor)?;
@@ -29,14 +30,6 @@ fn ir_build_with_optimize(input: &str, opt_level: OptimizeLevel) -> Result<Linea
     Ok(ir)
 }
 
-pub(crate) fn ir_build(input: &str) -> Result<LinearIR, RccError> {
-    ir_build_with_optimize(input, OptimizeLevel::Zero)
-}
-
-fn ir_build_o1(input: &str) -> Result<LinearIR, RccError> {
-    ir_build_with_optimize(input, OptimizeLevel::One)
-}
-
 fn test_cfg_iter(expected: &str, cfg: &CFG) {
     let iter: Vec<&IRInst> = cfg.iter_inst().collect();
     assert_eq!(expected, format!("{:#?}", iter));
@@ -72,11 +65,72 @@ fn test_lit_num() {
     .err()
     .unwrap();
     assert_eq!(
-        "ParseInt(ParseIntError { kind: PosOverflow })",
+        "Spanned(\"number too large to fit in target type\", Span { lo: 23, hi: 52 })",
         format!("{:?}", ir)
     );
 }
 
+#[test]
+fn test_neg_lit_num() {
+    let ir = ir_build(
+        r#"
+        fn fff(a: i64) -> bool {
+            a == -5000000000
+        }
+    "#,
+    )
+    .unwrap();
+    assert_fmt_eq(
+        "[BinOp { op: ==, dest: Place { label: \"$0_1\", kind: Local, ir_type: Bool }, \
+        src1: Place(Place { label: \"a_2\", kind: Local, ir_type: I64 }), src2: I64(-5000000000) }, \
+        Ret(Place(Place { label: \"$0_1\", kind: Local, ir_type: Bool }))]",
+        &ir.funcs.first().unwrap().insts,
+    );
+}
+
+#[test]
+fn test_sub_word_fold() {
+    let ir = ir_build(
+        r#"
+        fn fff() -> u8 {
+            1u8 << 7 << 1
+        }
+        fn ggg() -> i8 {
+            -128i8 + 27
+        }
+    "#,
+    )
+    .unwrap();
+    assert_fmt_eq("[Ret(U8(0))]", &ir.funcs[0].insts);
+    assert_fmt_eq("[Ret(I8(-101))]", &ir.funcs[1].insts);
+
+    let err = ir_build(r#"fn main() {let b: u8 = 200 + 100;}"#)
+        .err()
+        .unwrap();
+    assert_eq!("add overflow", format!("{}", err));
+}
+
+#[test]
+fn test_unsigned_fold() {
+    let ir = ir_build(
+        r#"
+        fn fff() -> bool {
+            3000000000u32 > 7
+        }
+        fn ggg() -> u32 {
+            3000000000u32 >> 28
+        }
+        fn hhh() -> u32 {
+            3000000000u32 / 7
+        }
+    "#,
+    )
+    .unwrap();
+    assert_fmt_eq("[Ret(Bool(true))]", &ir.funcs[0].insts);
+    assert_fmt_eq("[Ret(U32(11))]", &ir.funcs[1].insts);
+    assert_fmt_eq("[Ret(U32(428571428))]", &ir.funcs[2].insts);
+}
+
 #[test]
 fn test_lit_char() {
     let ir = ir_build(
@@ -101,7 +155,21 @@ fn test_math_overflow() {
     .err()
     .unwrap();
     assert_eq!(
-        "ParseInt(ParseIntError { kind: InvalidDigit })",
+        "Spanned(\"invalid digit found in string\", Span { lo: 24, hi: 34 })",
+        format!("{:?}", ir)
+    );
+}
+
+#[test]
+fn test_neg_overflow() {
+    let ir = ir_build(
+        r#"fn main() {let b: i8 = -(-128i8);
+    }"#,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        "Spanned(\"neg overflow\", Span { lo: 23, hi: 32 })",
         format!("{:?}", ir)
     );
 }
@@ -304,3 +372,308 @@ fn fn_call_test() {
         );
     }
 }
+
+#[test]
+fn struct_test() {
+    let ir = ir_build(
+        r#"
+        struct Point { x: i32, y: i32 }
+        fn swap(p: Point) -> Point {
+            Point { x: p.y, y: p.x }
+        }
+        fn main() {
+            let mut p = swap(Point { x: 1, y: 2 });
+            p.x += 3;
+        }
+    "#,
+    )
+    .unwrap();
+    assert_eq!(2, ir.funcs.len());
+    assert_eq!(
+        "[(\"p\", Aggregate([I32, I32]))]",
+        format!("{:?}", ir.funcs[0].fn_args)
+    );
+
+    for i in 0..=1 {
+        let expected_ir = expected_from_file(&format!("test_struct_ir{}.txt", i));
+        assert_eq!(
+            expected_ir,
+            format!("{:#?}", ir.funcs.get(i).unwrap().insts)
+        );
+    }
+}
+
+#[test]
+fn array_test() {
+    let ir = ir_build(
+        r#"
+        fn get(a: &[i32; 3], i: usize) -> i32 {
+            a[i]
+        }
+        fn main() {
+            let mut a = [0; 3];
+            a[1] = 2;
+            let b = get(&a, 2);
+        }
+    "#,
+    )
+    .unwrap();
+    assert_eq!(2, ir.funcs.len());
+
+    for i in 0..=1 {
+        let expected_ir = expected_from_file(&format!("test_array_ir{}.txt", i));
+        assert_eq!(
+            expected_ir,
+            format!("{:#?}", ir.funcs.get(i).unwrap().insts)
+        );
+    }
+
+    let result = ir_build(
+        r#"
+        fn main() {
+            let a = [1, 2];
+            let b = a[2];
+        }
+    "#,
+    );
+    assert_eq!(
+        Err("index out of bounds: the length is 2 but the index is 2".to_string()),
+        result.map(|_| ()).map_err(|e| e.to_string())
+    );
+}
+
+#[test]
+fn tuple_test() {
+    let ir = ir_build(
+        r#"
+        fn pair(a: i32) -> (i32, bool) {
+            (a, true)
+        }
+        fn triple(a: i32) -> (i32, i32, i32) {
+            (a, a, a)
+        }
+        fn main() {
+            let (a, b) = pair(1);
+            let mut t = triple(a);
+            t.1 = t.2;
+        }
+    "#,
+    )
+    .unwrap();
+    assert_eq!(3, ir.funcs.len());
+    // small tuples are returned in registers, large ones through the hidden argument
+    assert_eq!("[(\"a\", I32)]", format!("{:?}", ir.funcs[0].fn_args));
+    assert_eq!(
+        "[(\"%sret\", Addr), (\"a\", I32)]",
+        format!("{:?}", ir.funcs[1].fn_args)
+    );
+
+    for i in 0..=2 {
+        let expected_ir = expected_from_file(&format!("test_tuple_ir{}.txt", i));
+        assert_eq!(
+            expected_ir,
+            format!("{:#?}", ir.funcs.get(i).unwrap().insts)
+        );
+    }
+}
+
+#[test]
+fn enum_test() {
+    let ir = ir_build(
+        r#"
+        enum Opt {
+            None,
+            Some(i32),
+        }
+        fn unwrap_or(o: Opt, default: i32) -> i32 {
+            match o {
+                Opt::Some(x) if x > 0 => x,
+                Opt::None => default,
+                _ => 0,
+            }
+        }
+        fn main() {
+            let a = unwrap_or(Opt::Some(3), 1);
+        }
+    "#,
+    )
+    .unwrap();
+    assert_eq!(2, ir.funcs.len());
+    // enums with fields are aggregates, and aggregate arguments are passed as values
+    assert_eq!(
+        "[(\"o\", Aggregate([U8, Union([Aggregate([]), Aggregate([I32])])])), (\"default\", I32)]",
+        format!("{:?}", ir.funcs[0].fn_args)
+    );
+
+    for i in 0..=1 {
+        let expected_ir = expected_from_file(&format!("test_enum_ir{}.txt", i));
+        assert_eq!(
+            expected_ir,
+            format!("{:#?}", ir.funcs.get(i).unwrap().insts)
+        );
+    }
+}
+
+#[test]
+fn pattern_test() {
+    let ir = ir_build(
+        r#"
+        fn f((a, b): (i32, i32), &c: &i32) -> i32 {
+            match a {
+                0..=9 => b,
+                _ => c,
+            }
+        }
+    "#,
+    )
+    .unwrap();
+    // parameters bound by patterns are passed in hidden variables
+    assert_eq!(
+        "[(\"%param0\", Aggregate([I32, I32])), (\"%param1\", Addr)]",
+        format!("{:?}", ir.funcs[0].fn_args)
+    );
+    let expected_ir = expected_from_file("test_pattern_ir.txt");
+    assert_eq!(expected_ir, format!("{:#?}", ir.funcs[0].insts));
+}
+
+#[test]
+fn impl_test() {
+    let ir = ir_build(
+        r#"
+        struct Foo { a: i32, b: i32 }
+        impl Foo {
+            fn new() -> Self { Self { a: 1, b: 2 } }
+            fn get(&self) -> i32 { self.b }
+        }
+        fn main() -> i32 {
+            let r = &Foo::new();
+            Foo::new().get() + r.get()
+        }
+    "#,
+    )
+    .unwrap();
+    // associated functions are named by the mangled symbols
+    let names: Vec<&str> = ir.funcs.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(vec!["_ZN3Foo3newE", "_ZN3Foo3getE", "main"], names);
+    let insts: Vec<_> = ir.funcs[1..].iter().map(|f| &f.insts).collect();
+    let expected_ir = expected_from_file("test_impl_ir.txt");
+    assert_eq!(expected_ir, format!("{:#?}", insts));
+}
+
+#[test]
+fn generic_test() {
+    let ir = ir_build(
+        r#"
+        trait Zero {
+            fn zero() -> Self;
+            fn is_zero(&self) -> bool { false }
+        }
+        impl Zero for i32 { fn zero() -> i32 { 0 } }
+        impl Zero for bool { fn zero() -> bool { false } fn is_zero(&self) -> bool { true } }
+        fn zero<T: Zero>() -> T { T::zero() }
+        fn check<T: Zero>(a: T) -> bool { a.is_zero() }
+        fn main() -> i32 {
+            let a = zero::<i32>();
+            let b = zero::<bool>();
+            check(a);
+            check(b);
+            check(zero::<i32>());
+            a
+        }
+    "#,
+    )
+    .unwrap();
+    // generic functions and default methods are generated for each type argument
+    let mut names: Vec<&str> = ir.funcs.iter().map(|f| f.name.as_str()).collect();
+    names.sort_unstable();
+    assert_eq!(
+        vec![
+            "_ZN3i324Zero4zeroE",
+            "_ZN3i324Zero7is_zeroE",
+            "_ZN4bool4Zero4zeroE",
+            "_ZN4bool4Zero7is_zeroE",
+            "_ZN4zeroI3i32EE",
+            "_ZN4zeroI4boolEE",
+            "_ZN5checkI3i32EE",
+            "_ZN5checkI4boolEE",
+            "main",
+        ],
+        names
+    );
+}
+
+#[test]
+fn trait_object_test() {
+    let ir = ir_build(
+        r#"
+        trait Shape {
+            fn area(&self) -> i32;
+            fn scale(&self, k: i32) -> i32 { self.area() * k }
+        }
+        impl Shape for i32 { fn area(&self) -> i32 { 1 } }
+        fn scale(s: &dyn Shape) -> i32 { s.scale(2) }
+        fn main() -> i32 {
+            let a = 3;
+            scale(&a)
+        }
+    "#,
+    )
+    .unwrap();
+    // the vtable holds the functions in the declaration order of the trait
+    assert_eq!(
+        vec![(
+            &"_ZTVN3i325ShapeE".to_string(),
+            &vec![
+                "_ZN3i325Shape4areaE".to_string(),
+                "_ZN3i325Shape5scaleE".to_string()
+            ]
+        )],
+        ir.vtables.iter().collect::<Vec<_>>()
+    );
+    let insts: Vec<_> = ir.funcs[1..3].iter().map(|f| &f.insts).collect();
+    let expected_ir = expected_from_file("test_trait_object_ir.txt");
+    assert_eq!(expected_ir, format!("{:#?}", insts));
+}
+
+#[test]
+fn logic_test() {
+    let ir = ir_build(
+        r#"
+        fn f(a: i32, b: i32) -> bool {
+            if a > 0 && (b > 0 || !(a == b)) {
+                return true;
+            }
+            let c = a < b || b == 3;
+            c
+        }
+    "#,
+    )
+    .unwrap();
+
+    // conditions are translated into jumps without evaluating the bool values
+    let expected = expected_from_file("test_logic_ir.txt");
+    assert_eq!(expected, format!("{:#?}", ir.funcs.last().unwrap().insts));
+}
+
+#[test]
+fn for_test() {
+    let ir = ir_build(
+        r#"
+        fn main() {
+            let mut a = 0;
+            for i in 0..3 {
+                a += i;
+            }
+            for i in (1..=a).rev() {
+                if i == 2 {
+                    break;
+                }
+            }
+        }
+    "#,
+    )
+    .unwrap();
+
+    let expected = expected_from_file("test_for_ir.txt");
+    assert_eq!(expected, format!("{:#?}", ir.funcs.last().unwrap().insts));
+}
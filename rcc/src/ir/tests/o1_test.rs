@@ -1,4 +1,4 @@
-use crate::ir::tests::ir_build_o1;
+use crate::ir::tests::ir_build;
 use crate::ir::cfg::CFG;
 use crate::ir::{Operand, IRInst, Place, IRType};
 use crate::ir::Operand::I32;
@@ -6,7 +6,7 @@ use std::collections::VecDeque;
 
 #[test]
 fn test_ir_builder() {
-    let mut ir = ir_build_o1("fn main() {let a = 2 + 3 + 4 * 1;}").unwrap();
+    let mut ir = ir_build("fn main() {let a = 2 + 3 + 4 * 1;}").unwrap();
 
     let insts = VecDeque::from(vec![
         IRInst::load_data(Place::local("a_2".into(), IRType::I32), I32(9)),
@@ -1,5 +1,17 @@
 pub const RA: &str = "%ra";
 pub const FP: &str = "%fp";
+/// Hidden first argument holding the address where an aggregate result is written.
+pub const SRET: &str = "%sret";
+/// Hidden argument of a closure holding the address of its environment.
+pub const CLOSURE_ENV: &str = "%env";
+
+/// Routine called when an array index is out of bounds, with the index and the length.
+pub const PANIC_BOUNDS_CHECK: &str = "__rc_panic_bounds_check";
+
+/// Hidden variable holding the `index`th parameter, which is bound by a pattern.
+pub fn pattern_param(index: usize) -> String {
+    format!("%param{}", index)
+}
 
 pub fn local_var(ident: &str, scope_id: u64) -> String {
     format!("{}_{}", ident, scope_id)
@@ -13,6 +25,12 @@ pub fn is_temp_var(var_name: &str) -> bool {
     var_name.starts_with('$')
 }
 
-pub fn branch_name(func_scope_id: u64, bb_id: usize) -> String{
-    format!(".L{}_{}",  func_scope_id,bb_id)
+/// Instances of a generic function share the scope id of the function block,
+/// so their labels are distinguished by the instance number.
+pub fn branch_name(func_scope_id: u64, func_instance: usize, bb_id: usize) -> String {
+    if func_instance == 0 {
+        format!(".L{}_{}", func_scope_id, bb_id)
+    } else {
+        format!(".L{}_{}_{}", func_scope_id, func_instance, bb_id)
+    }
 }
\ No newline at end of file
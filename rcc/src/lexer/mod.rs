@@ -4,8 +4,8 @@ use std::str::FromStr;
 use self::token::Token::*;
 use self::token::*;
 use self::token::LiteralKind::*;
-use std::usize::MAX;
 
+#[cfg(test)]
 mod tests;
 pub mod token;
 
@@ -25,23 +25,20 @@ impl<'a: 'b, 'b> Lexer<'a> {
         }
     }
 
-    pub fn new_line(&mut self, input: &'a str) {
-        self.cursor = Cursor::new(input);
-        self.input = input;
-    }
-
-    pub fn tokenize(&'b mut self) -> Vec<Token<'a>> {
+    pub fn tokenize(&'b mut self) -> Vec<TokenWithSpan<'a>> {
         let mut tokens = vec![];
         while !self.cursor.is_eof() {
+            let lo = self.cursor.eaten_len();
             let token = self.advance_token();
+            let span = Span::new(lo, self.cursor.eaten_len());
 
             match token {
                 Unknown => {
-                    tokens.push(Unknown);
+                    tokens.push(TokenWithSpan { token: Unknown, span });
                     break;
                 }
                 WhiteSpace | Comment => {}
-                _ => tokens.push(token)
+                _ => tokens.push(TokenWithSpan { token, span })
             }
         }
         tokens
@@ -59,7 +56,7 @@ impl<'a: 'b, 'b> Lexer<'a> {
                 Token::from_str(&c.to_string()).unwrap()
             }
             '0'..='9' => self.integer_or_float_literal(),
-            '\'' => self.char_literal(self.cursor.eaten_len()),
+            '\'' => self.char_literal_or_lifetime(self.cursor.eaten_len()),
             '"' => self.string_literal(self.cursor.eaten_len()),
             c if "+*%^!".contains(c) => {
                 static TABLE: [[Token; 5]; 2] = [
@@ -96,7 +93,7 @@ impl<'a: 'b, 'b> Lexer<'a> {
                 TABLE[i][j].clone()
             }
             '/' => {
-                let slash_count = self.cursor.eat_equals('/', MAX);
+                let slash_count = self.cursor.eat_equals('/', usize::MAX);
                 debug_assert!(slash_count >= 1);
                 if slash_count == 1 {
                     match self.cursor.next() {
@@ -115,10 +112,8 @@ impl<'a: 'b, 'b> Lexer<'a> {
                                             comment_count -= 1;
                                         }
                                     }
-                                    '/' => {
-                                        if self.cursor.bump() == '*' {
-                                            comment_count += 1;
-                                        }
+                                    '/' if self.cursor.bump() == '*' => {
+                                        comment_count += 1;
                                     }
                                     _ => {}
                                 }
@@ -172,10 +167,11 @@ impl<'a: 'b, 'b> Lexer<'a> {
     }
 
     fn identifier_or_keyword(&'b mut self) -> Token<'a> {
-        let len = self.cursor.eat_id();
+        let start = self.cursor.eaten_len();
+        self.cursor.eat_id();
         let str = self
             .input
-            .get(self.cursor.eaten_len() - len..self.cursor.eaten_len())
+            .get(start..self.cursor.eaten_len())
             .unwrap();
         if let Ok(token) = Token::from_str(str) {
             token
@@ -229,12 +225,8 @@ impl<'a: 'b, 'b> Lexer<'a> {
                         self.digits_with_underscore(start, radix, Self::make_integer)
                     }
 
-                    // 001 01.23
-                    '0'..='9' => self.decimal_or_float_literal_no_prefix(start),
-                    _ => Literal {
-                        literal_kind: self.make_integer(),
-                        value: "0",
-                    },
+                    // 0 001 01.23 0.5 0u8
+                    _ => self.decimal_or_float_literal_no_prefix(start),
                 }
             }
             '1'..='9' => self.decimal_or_float_literal_no_prefix(start),
@@ -261,7 +253,7 @@ impl<'a: 'b, 'b> Lexer<'a> {
     /// FLOAT_SUFFIX :
     ///     f32 | f64
     fn decimal_or_float_literal_no_prefix(&'b mut self, start: usize) -> Token<'a> {
-        debug_assert!(self.cursor.prev().is_digit(10));
+        debug_assert!(self.cursor.prev().is_ascii_digit());
         // (DEC_DIGIT|_)*
         self.cursor.eat_digits_or_underscore(10);
         match self.cursor.next() {
@@ -381,13 +373,17 @@ impl<'a: 'b, 'b> Lexer<'a> {
         }
     }
 
-    fn char_literal(&'b mut self, start: usize) -> Token<'a> {
+    fn char_literal_or_lifetime(&'b mut self, start: usize) -> Token<'a> {
         debug_assert!(self.cursor.next() == '\'');
         self.cursor.bump();
 
         // ''
         if self.cursor.next() == '\'' {
             Unknown
+        } else if is_id_start(self.cursor.next()) && self.cursor.nth(1) != '\'' {
+            // 'outer
+            self.cursor.eat_id();
+            Lifetime(&self.input[start..self.cursor.eaten_len()])
         } else if self.cursor.eat_ascii_character() && self.cursor.bump() == '\'' {
             self.lit(start, self.cursor.eaten_len(), Char)
         } else {
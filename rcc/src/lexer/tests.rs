@@ -1,12 +1,12 @@
 mod lexer_tests {
     use crate::lexer::token::Token::*;
-    use crate::lexer::token::{LiteralKind, LiteralKind::*, Token};
+    use crate::lexer::token::{LiteralKind, LiteralKind::*, Span, Token};
     use crate::lexer::Lexer;
 
     fn validate_tokenize(inputs: Vec<&str>, excepted_outputs: Vec<Vec<Token>>) {
         for (input, excepted) in inputs.iter().zip(excepted_outputs.iter()) {
             let mut lexer = Lexer::new(input);
-            let res = lexer.tokenize();
+            let res: Vec<Token> = lexer.tokenize().into_iter().map(|t| t.token).collect();
             assert_eq!(*excepted, res);
         }
     }
@@ -127,7 +127,7 @@ mod lexer_tests {
     #[test]
     fn number_literal_test() {
         validate_tokenize(
-            vec!["3f32", "0o", "0b__", "12.3 1e9 0x37ffhello2  1usize"],
+            vec!["3f32", "0o", "0b__", "12.3 1e9 0x37ffhello2  1usize", "0.5 0u8 0."],
             vec![
                 vec![Literal {
                     literal_kind: LiteralKind::f32(),
@@ -154,6 +154,20 @@ mod lexer_tests {
                         value: "1",
                     },
                 ],
+                vec![
+                    Literal {
+                        literal_kind: LiteralKind::float_no_suffix(),
+                        value: "0.5",
+                    },
+                    Literal {
+                        literal_kind: Integer { suffix: "u8" },
+                        value: "0",
+                    },
+                    Literal {
+                        literal_kind: LiteralKind::float_no_suffix(),
+                        value: "0.",
+                    },
+                ],
             ],
         );
     }
@@ -199,6 +213,24 @@ mod lexer_tests {
         );
     }
 
+    #[test]
+    fn lifetime_test() {
+        validate_tokenize(
+            vec!["'outer: loop", "break 'a", "'a' 'ab"],
+            vec![
+                vec![Lifetime("'outer"), Colon, Loop],
+                vec![Break, Lifetime("'a")],
+                vec![
+                    Literal {
+                        literal_kind: Char,
+                        value: "'a'",
+                    },
+                    Lifetime("'ab"),
+                ],
+            ],
+        );
+    }
+
     #[test]
     fn and_or_test() {
         validate_tokenize(
@@ -325,6 +357,30 @@ mod lexer_tests {
             vec![vec![Lt, Le, Shl, ShlEq, Gt, Ge, Shr, ShrEq], vec![Shl, Lt]],
         );
     }
+
+    #[test]
+    fn span_test() {
+        let input = "let 变量 = \"你好\"; // 注释\n  x <<= 1";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.tokenize();
+        let spans: Vec<(&str, Span)> = tokens
+            .iter()
+            .map(|t| (&input[t.span.lo..t.span.hi], t.span))
+            .collect();
+        assert_eq!(
+            vec![
+                ("let", Span::new(0, 3)),
+                ("变量", Span::new(4, 10)),
+                ("=", Span::new(11, 12)),
+                ("\"你好\"", Span::new(13, 21)),
+                (";", Span::new(21, 22)),
+                ("x", Span::new(35, 36)),
+                ("<<=", Span::new(37, 40)),
+                ("1", Span::new(41, 42)),
+            ],
+            spans
+        );
+    }
 }
 
 mod token_tests {
@@ -1,3 +1,4 @@
+#[cfg(test)]
 use crate::lexer::token::LiteralKind::{Float, Integer};
 use strenum::StrEnum;
 
@@ -80,6 +81,11 @@ pub enum Token<'a> {
     #[strenum(disabled)]
     LitString(&'a str),
 
+    /// 'outer
+    /// field 0 contains quote
+    #[strenum(disabled)]
+    Lifetime(&'a str),
+
     /// Symbols
     #[strenum("+")]
     Plus,
@@ -246,6 +252,20 @@ impl Token<'_> {
     pub fn is_range_op(&self) -> bool {
         matches!(self, Self::DotDot | Self::DotDotEq)
     }
+
+    /// The token as written in the source, used in diagnostics.
+    pub fn source(&self) -> String {
+        match self {
+            Self::Identifier(s)
+            | Self::LitString(s)
+            | Self::Lifetime(s)
+            | Self::Literal { value: s, .. } => s.to_string(),
+            Self::WhiteSpace => "whitespace".into(),
+            Self::Comment => "comment".into(),
+            Self::Unknown => "unknown token".into(),
+            tk => tk.to_string(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -259,6 +279,7 @@ pub enum LiteralKind<'a> {
     },
 }
 
+#[cfg(test)]
 impl<'a> LiteralKind<'a> {
     pub const fn f32() -> LiteralKind<'a> {
         Float {
@@ -273,3 +294,27 @@ impl<'a> LiteralKind<'a> {
         Float {suffix: ""}
     }
 }
+
+/// Byte range `[lo, hi)` in the source file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub lo: usize,
+    pub hi: usize,
+}
+
+impl Span {
+    pub const fn new(lo: usize, hi: usize) -> Span {
+        Span { lo, hi }
+    }
+
+    /// Span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.lo.min(other.lo), self.hi.max(other.hi))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenWithSpan<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
+}
@@ -1,4 +1,4 @@
-#![feature(map_first_last)]
+#![allow(clippy::upper_case_acronyms, clippy::enum_variant_names)]
 
 use crate::rcc::{OptimizeLevel, RcCompiler, RccError};
 use clap::Clap;
@@ -12,41 +12,56 @@ mod ir;
 mod lexer;
 mod parser;
 mod rcc;
+#[cfg(test)]
 mod tests;
 
 #[derive(Clap)]
 struct Opts {
-    /// output asm file
+    /// output asm file, which is the only kind of output
     #[clap(short = 'S')]
+    #[allow(dead_code)]
     output_asm: bool,
     /// input file
     input: String,
     /// output file
     #[clap(short = 'o')]
     output: String,
-    /// target platform
+    /// target platform (riscv32 or riscv64)
     #[clap(short = 't', default_value = "riscv32")]
     target: String,
+    /// optimize level (0 or 1)
+    #[clap(short = 'O', default_value = "0")]
+    opt_level: String,
 }
 
-fn compile(opts: Opts) -> Result<(), RccError> {
+fn compile(opts: &Opts, source: &str) -> Result<(), RccError> {
+    let opt_level = match OptimizeLevel::from_str(&opts.opt_level) {
+        Ok(opt_level) => opt_level,
+        Err(_) => return Err(format!("invalid optimize level {}", opts.opt_level).into()),
+    };
     match TargetPlatform::from_str(&opts.target) {
         Ok(target_platform) => {
-            let input = std::fs::File::open(opts.input)?;
-            let output = std::fs::File::create(opts.output)?;
-            // TODO: set opt level
+            let output = std::fs::File::create(&opts.output)?;
             let mut rc_compiler =
-                RcCompiler::new(target_platform, input, output, OptimizeLevel::Zero);
+                RcCompiler::new(target_platform, source.as_bytes(), output, opt_level);
             rc_compiler.compile()?;
             Ok(())
         }
-        Err(_) => Err(format!("invalid target platform {}", opts.input).into()),
+        Err(_) => Err(format!("invalid target platform {}", opts.target).into()),
     }
 }
 
 fn main() {
     let opts = Opts::parse();
-    if let Err(e) = compile(opts) {
-        eprintln!("{:?}", e);
+    let source = match std::fs::read_to_string(&opts.input) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error: {}: {}", opts.input, e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = compile(&opts, &source) {
+        eprintln!("{}", e.diagnostic(&opts.input, &source));
+        std::process::exit(1);
     }
 }
@@ -14,15 +14,18 @@ impl Parse for Expr {
 
 /// Expression having precedences
 pub mod prec {
-    use crate::ast::expr::Expr::{ArrayIndex, Assign, Call, FieldAccess, Range, Unary};
+    use crate::ast::expr::Expr::{
+        ArrayIndex, Assign, Call, FieldAccess, MethodCall, Path, Range, TupleIndex, Unary,
+    };
     use crate::ast::expr::UnOp::{Borrow, BorrowMut};
     use crate::ast::expr::{
-        ArrayIndexExpr, AssignExpr, BinOpExpr, BinOperator, CallExpr, CallParams, Expr,
-        FieldAccessExpr, LhsExpr, Precedence, RangeExpr, UnAryExpr, UnOp,
+        ArrayIndexExpr, AssignExpr, BinOpExpr, BinOperator, CallExpr, CallParams, CastExpr, Expr,
+        FieldAccessExpr, LhsExpr, MethodCallExpr, Precedence, RangeExpr, TupleIndexExpr, UnAryExpr, UnOp,
     };
-    use crate::ast::FromToken;
+    use crate::ast::types::TypeAnnotation;
     use crate::ast::TokenStart;
-    use crate::lexer::token::Token;
+    use crate::ast::{FromToken, NodeSpan};
+    use crate::lexer::token::{LiteralKind, Span, Token};
     use crate::parser::expr::primitive::primitive_expr;
     use crate::parser::{Parse, ParseCursor};
     use crate::rcc::RccError;
@@ -35,11 +38,14 @@ pub mod prec {
     ///             | RangeExpr(lhs expr) AssignOp AssignExpr
     /// (Associativity: right to left)
     fn assign_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        let lo = cursor.next_span();
         let mut expr = range_expr(cursor)?;
         while let Some(assign_op) = cursor.eat_token_if_from() {
             let lhs = LhsExpr::from_expr(expr)?;
             let rhs = assign_expr(cursor)?;
-            expr = Assign(AssignExpr::new(lhs, assign_op, rhs));
+            let mut assign_expr = AssignExpr::new(lhs, assign_op, rhs);
+            assign_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+            expr = Assign(assign_expr);
         }
         Ok(expr)
     }
@@ -66,8 +72,12 @@ pub mod prec {
             if let Some(expr) = lhs {
                 range_expr.set_lhs(expr);
             }
-            if let Ok(rhs) = bin_op_expr(cursor) {
-                range_expr.set_rhs(rhs);
+            // `for i in 0.. {}`
+            let next_token = cursor.next_token();
+            let is_block = cursor.no_struct_literal
+                && matches!(next_token, Ok(Token::LeftCurlyBraces));
+            if !is_block && next_token.is_ok_and(Expr::is_token_start) {
+                range_expr.set_rhs(bin_op_expr(cursor)?);
             }
             Range(range_expr)
         } else {
@@ -101,19 +111,28 @@ pub mod prec {
         // 1|2|3&4+4+4+5*6*7+7&8
         fn reduce(
             bin_ops: &mut Vec<BinOperator>,
-            exprs: &mut Vec<Expr>,
+            exprs: &mut Vec<(Expr, Span)>,
             next_prec: Precedence,
         ) -> Result<(), RccError> {
             while !bin_ops.is_empty() && bin_ops.last().unwrap().prec_gt(&next_prec)? {
-                let rhs = exprs.pop().unwrap();
-                let lhs = exprs.pop().unwrap();
+                let (rhs, rhs_span) = exprs.pop().unwrap();
+                let (lhs, lhs_span) = exprs.pop().unwrap();
                 let last_op = bin_ops.pop().unwrap();
-                exprs.push(Expr::BinOp(BinOpExpr::new(lhs, last_op, rhs)));
+                let mut bin_op_expr = BinOpExpr::new(lhs, last_op, rhs);
+                let span = lhs_span.to(rhs_span);
+                bin_op_expr.span = NodeSpan(span);
+                exprs.push((Expr::BinOp(bin_op_expr), span));
             }
             Ok(())
-        };
+        }
 
-        let mut exprs = vec![unary_expr(cursor)?];
+        fn operand(cursor: &mut ParseCursor) -> Result<(Expr, Span), RccError> {
+            let lo = cursor.next_span();
+            let expr = cast_expr(cursor)?;
+            Ok((expr, lo.to(cursor.prev_span())))
+        }
+
+        let mut exprs = vec![operand(cursor)?];
         let mut bin_ops: Vec<BinOperator> = vec![];
         let mut next_is_op = true;
 
@@ -138,20 +157,33 @@ pub mod prec {
                         reduce(&mut bin_ops, &mut exprs, Precedence::Min)?;
                         debug_assert!(bin_ops.is_empty());
                         debug_assert_eq!(exprs.len(), 1);
-                        return Ok(exprs.pop().unwrap());
+                        return Ok(exprs.pop().unwrap().0);
                     }
                 }
                 next_is_op = false;
             } else {
-                exprs.push(unary_expr(cursor)?);
+                exprs.push(operand(cursor)?);
                 next_is_op = true;
             }
         }
     }
 
+    /// CastExpr -> UnAryExpr ( `as` Type )*
+    fn cast_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        let lo = cursor.next_span();
+        let mut expr = unary_expr(cursor)?;
+        while cursor.eat_token_if_eq(Token::As) {
+            let mut cast_expr = CastExpr::new(expr, TypeAnnotation::parse(cursor)?);
+            cast_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+            expr = Expr::Cast(cast_expr);
+        }
+        Ok(expr)
+    }
+
     /// UnAryExpr -> CallExpr
     ///            | ( `!` | `*` | `-` | `&` | `& mut` ) UnAryExpr
     fn unary_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        let lo = cursor.next_span();
         Ok(
             if let Some(tk) = cursor.eat_token_if_in(&[
                 Token::Not,
@@ -166,14 +198,20 @@ pub mod prec {
                     } else {
                         Borrow
                     };
-                    let expr = Unary(UnAryExpr::new(op, unary_expr(cursor)?));
-                    Unary(UnAryExpr::new(Borrow, expr))
+                    let mut expr = UnAryExpr::new(op, unary_expr(cursor)?);
+                    // the inner borrow starts from the second `&`
+                    expr.span = NodeSpan(Span::new(lo.lo + 1, cursor.prev_span().hi));
+                    let mut expr = UnAryExpr::new(Borrow, Unary(expr));
+                    expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                    Unary(expr)
                 } else {
                     let mut op = UnOp::from_token(tk.clone()).unwrap();
                     if op == UnOp::Borrow && cursor.eat_token_if_eq(Token::Mut) {
                         op = UnOp::BorrowMut;
                     }
-                    Unary(UnAryExpr::new(op, unary_expr(cursor)?))
+                    let mut expr = UnAryExpr::new(op, unary_expr(cursor)?);
+                    expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                    Unary(expr)
                 }
             } else {
                 call_expr(cursor)?
@@ -185,29 +223,43 @@ pub mod prec {
     ///           | CallExpr `(` CallParams? `)`
     ///           | CallExpr ArrayIndexExpr
     ///           | CallExpr `.` PrimitiveExpr
+    ///           | CallExpr `.` TupleIndex
     fn call_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        let lo = cursor.next_span();
         let mut expr = primitive_expr(cursor)?;
         while let Ok(tk) = cursor.next_token() {
             expr = match tk {
                 Token::LeftParen => {
-                    cursor.bump_token()?;
-                    let mut call_expr = CallExpr::new(expr);
-
-                    if !cursor.eat_token_if_eq(Token::RightParen) {
-                        let call_params = CallParams::parse(cursor)?;
-                        cursor.eat_token_eq(Token::RightParen)?;
-                        call_expr = call_expr.call_params(call_params);
-                    }
+                    let mut call_expr = CallExpr::new(expr).call_params(parse_call_params(cursor)?);
+                    call_expr.span = NodeSpan(lo.to(cursor.prev_span()));
                     Call(call_expr)
                 }
                 Token::LeftSquareBrackets => {
                     let index_expr = ArrayIndexExpr::parse_index(cursor)?;
-                    ArrayIndex(ArrayIndexExpr::new(expr, index_expr))
+                    let mut array_index_expr = ArrayIndexExpr::new(expr, index_expr);
+                    array_index_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                    ArrayIndex(array_index_expr)
                 }
                 Token::Dot => {
                     cursor.bump_token()?;
+                    if let Token::Literal { .. } = cursor.next_token()? {
+                        expr = TupleIndexExpr::parse_indexes(cursor, expr, lo)?;
+                        continue;
+                    }
                     let rhs = primitive_expr(cursor)?;
-                    FieldAccess(FieldAccessExpr::new(expr, rhs))
+                    if let (Path(path_expr), Ok(Token::LeftParen)) = (&rhs, cursor.next_token()) {
+                        if let [method] = path_expr.segments.as_slice() {
+                            let method = method.clone();
+                            let mut method_call_expr =
+                                MethodCallExpr::new(expr, method, parse_call_params(cursor)?);
+                            method_call_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                            expr = MethodCall(method_call_expr);
+                            continue;
+                        }
+                    }
+                    let mut field_access_expr = FieldAccessExpr::new(expr, rhs);
+                    field_access_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                    FieldAccess(field_access_expr)
                 }
                 _ => return Ok(expr),
             }
@@ -215,6 +267,17 @@ pub mod prec {
         Ok(expr)
     }
 
+    /// `(` CallParams? `)`
+    fn parse_call_params(cursor: &mut ParseCursor) -> Result<CallParams, RccError> {
+        cursor.eat_token_eq(Token::LeftParen)?;
+        if cursor.eat_token_if_eq(Token::RightParen) {
+            return Ok(vec![]);
+        }
+        let call_params = CallParams::parse(cursor)?;
+        cursor.eat_token_eq(Token::RightParen)?;
+        Ok(call_params)
+    }
+
     /// CallParams -> Expr ( , Expr )* ,?
     impl Parse for CallParams {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
@@ -228,6 +291,31 @@ pub mod prec {
         }
     }
 
+    /// TupleIndex -> IntegerLiteral
+    impl TupleIndexExpr {
+        /// `a.0.1` is lexed as `a`, `.` and the float literal `0.1`,
+        /// so the float literal is split into two tuple indexes.
+        fn parse_indexes(cursor: &mut ParseCursor, expr: Expr, lo: Span) -> Result<Expr, RccError> {
+            let (literal_kind, value) = cursor.eat_literal()?;
+            if !matches!(
+                literal_kind,
+                LiteralKind::Integer { suffix: "" } | LiteralKind::Float { suffix: "" }
+            ) {
+                return Err(format!("invalid tuple index `{}`", value).into());
+            }
+            let mut expr = expr;
+            for index in value.split('.') {
+                let index = index
+                    .parse::<usize>()
+                    .map_err(|_| RccError::from(format!("invalid tuple index `{}`", value)))?;
+                let mut tuple_index_expr = TupleIndexExpr::new(expr, index);
+                tuple_index_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                expr = TupleIndex(tuple_index_expr);
+            }
+            Ok(expr)
+        }
+    }
+
     /// ArrayIndexExpr -> `[` Expr `]`
     impl ArrayIndexExpr {
         fn parse_index(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
@@ -243,63 +331,200 @@ pub mod prec {
 pub mod primitive {
     use std::str::FromStr;
 
-    use crate::ast::expr::Expr::{Array, Block, If, LitBool, LitNum, Loop, Path, While};
+    use crate::ast::expr::Expr::{Array, Block, If, LitBool, Loop, Path, While};
     use crate::ast::expr::*;
+    use crate::ast::item::{FnParam, FnParams, ItemFn};
+    use crate::ast::pattern::Pattern;
     use crate::ast::stmt::Stmt;
-    use crate::ast::types::TypeLitNum;
-    use crate::ast::TokenStart;
+    use crate::ast::types::{TypeAnnotation, TypeLitNum};
+    use crate::ast::{NodeSpan, TokenStart, Visibility};
     use crate::lexer::token::LiteralKind::*;
     use crate::lexer::token::Token;
     use crate::parser::expr::prec::range_expr;
     use crate::parser::stmt::{parse_stmt_or_expr_without_block, StmtOrExpr};
+    use crate::parser::types::parse_generic_args;
     use crate::parser::{Parse, ParseCursor};
     use crate::rcc::RccError;
 
     /// PrimitiveExpr -> PathExpr | LitExpr | LitChar | LitStr | LitBool | BlockExpr
-    ///                | GroupedExpr | TupleExpr | ArrayExpr
-    ///                | ReturnExpr | BreakExpr
+    ///                | GroupedExpr | TupleExpr | ArrayExpr | StructExpr
+    ///                | ReturnExpr | BreakExpr | ContinueExpr | ClosureExpr
+    ///                | LabeledLoopExpr | UnsafeBlockExpr
     ///                | RangeExpr(without lhs)
     pub fn primitive_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
         let expr = match cursor.next_token()? {
-            Token::Identifier(_) | Token::PathSep => Path(PathExpr::parse(cursor)?),
+            Token::Identifier(_) | Token::PathSep | Token::SelfValue | Token::SelfType => {
+                let lo = cursor.next_span();
+                let path_expr = PathExpr::parse(cursor)?;
+                if !cursor.no_struct_literal
+                    && matches!(cursor.next_token(), Ok(Token::LeftCurlyBraces))
+                {
+                    let mut struct_expr = StructExpr::parse_fields(cursor, path_expr)?;
+                    struct_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                    Expr::Struct(struct_expr)
+                } else {
+                    Path(path_expr)
+                }
+            }
             Token::Literal { .. } => parse_literal(cursor)?,
             Token::LitString(_) => Expr::LitStr(parse_lit_string(cursor)?),
             Token::True | Token::False => LitBool(*cursor.bump_token()? == Token::True),
             Token::LeftCurlyBraces => Block(BlockExpr::parse(cursor)?),
+            Token::Unsafe => Block(parse_unsafe_block(cursor)?),
             Token::LeftParen => parse_grouped_or_tuple_expr(cursor)?,
-            Token::LeftSquareBrackets => Array(ArrayExpr::parse(cursor)?),
+            Token::LeftSquareBrackets => {
+                let lo = cursor.next_span();
+                let mut array_expr = ArrayExpr::parse(cursor)?;
+                array_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                Array(array_expr)
+            }
             Token::While => While(WhileExpr::parse(cursor)?),
             Token::Loop => Loop(LoopExpr::parse(cursor)?),
+            Token::For => Expr::For(ForExpr::parse(cursor)?),
             Token::If => If(IfExpr::parse(cursor)?),
+            Token::Match => Expr::Match(MatchExpr::parse(cursor)?),
             Token::Return => Expr::Return(ReturnExpr::parse(cursor)?),
             Token::Break => Expr::Break(BreakExpr::parse(cursor)?),
+            Token::Continue => Expr::Continue(ContinueExpr::parse(cursor)?),
+            Token::Lifetime(_) => parse_labeled_loop(cursor)?,
             Token::DotDot | Token::DotDotEq => range_expr(cursor)?,
-            _ => unreachable!(),
+            Token::Or | Token::OrOr | Token::Move => Expr::Closure(ClosureExpr::parse(cursor)?),
+            tk => return Err(format!("expected expression, found `{}`", tk.source()).into()),
         };
         Ok(expr)
     }
 
+    /// Parse the expression with struct literals allowed or not.
+    fn parse_with_restriction<T>(
+        cursor: &mut ParseCursor,
+        no_struct_literal: bool,
+        f: impl FnOnce(&mut ParseCursor) -> Result<T, RccError>,
+    ) -> Result<T, RccError> {
+        let restriction = std::mem::replace(&mut cursor.no_struct_literal, no_struct_literal);
+        let result = f(cursor);
+        cursor.no_struct_literal = restriction;
+        result
+    }
+
+    /// Condition of `if` and `while`, which can not be a struct literal.
+    fn parse_cond(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        parse_with_restriction(cursor, true, Expr::parse)
+    }
+
+    /// StructExprFields -> `{` ( StructExprField , )* StructExprField? `}`
+    /// StructExprField -> Identifier | Identifier `:` Expr
+    impl StructExpr {
+        fn parse_fields(cursor: &mut ParseCursor, path: PathExpr) -> Result<Self, RccError> {
+            cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+            let fields = parse_with_restriction(cursor, false, |cursor| {
+                let mut fields = vec![];
+                while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+                    let span = cursor.next_span();
+                    let name = cursor.eat_identifier()?.to_string();
+                    let expr = if cursor.eat_token_if_eq(Token::Colon) {
+                        Expr::parse(cursor)?
+                    } else {
+                        // shorthand `Point { x }`
+                        let mut path_expr = PathExpr::from(vec![name.clone()]);
+                        path_expr.span = NodeSpan(span);
+                        Path(path_expr)
+                    };
+                    fields.push(StructExprField::new(name, expr));
+                    if !cursor.eat_token_if_eq(Token::Comma) {
+                        cursor.eat_token_eq(Token::RightCurlyBraces)?;
+                        break;
+                    }
+                }
+                Ok(fields)
+            })?;
+            Ok(StructExpr::new(path, fields))
+        }
+    }
+
+    /// ClosureExpr -> `move`? ( `||` | `|` ( ClosureParam , )* ClosureParam? `|` )
+    ///                ( Expr | `->` Type BlockExpr )
+    /// ClosureParam -> Pattern ( `:` Type )?
+    ///
+    /// A body which is not a block is wrapped in a block, where the parameters are declared.
+    impl Parse for ClosureExpr {
+        fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+            let lo = cursor.next_span();
+            let is_move = cursor.eat_token_if_eq(Token::Move);
+            let mut fn_params = FnParams::new();
+            if !cursor.eat_token_if_eq(Token::OrOr) {
+                cursor.eat_token_eq(Token::Or)?;
+                while !cursor.eat_token_if_eq(Token::Or) {
+                    let pattern = Pattern::parse(cursor)?;
+                    let _type = if cursor.eat_token_if_eq(Token::Colon) {
+                        TypeAnnotation::parse(cursor)?
+                    } else {
+                        TypeAnnotation::Unknown
+                    };
+                    fn_params.push(FnParam::new(pattern, _type));
+                    if !cursor.eat_token_if_eq(Token::Comma) {
+                        cursor.eat_token_eq(Token::Or)?;
+                        break;
+                    }
+                }
+            }
+            let (ret_type, fn_block) = if cursor.eat_token_if_eq(Token::RArrow) {
+                (TypeAnnotation::parse(cursor)?, BlockExpr::parse(cursor)?)
+            } else {
+                match Expr::parse(cursor)? {
+                    Block(block_expr) => (TypeAnnotation::Unknown, block_expr),
+                    expr => {
+                        let scope_id = cursor.scope_count;
+                        cursor.scope_count += 1;
+                        let mut block_expr = BlockExpr::new(scope_id);
+                        block_expr.last_expr = Some(Box::new(expr));
+                        (TypeAnnotation::Unknown, block_expr)
+                    }
+                }
+            };
+            let item_fn = ItemFn::new(
+                Visibility::Priv,
+                "{{closure}}".into(),
+                fn_params,
+                ret_type,
+                fn_block,
+            );
+            let mut closure_expr = ClosureExpr::new(is_move, item_fn);
+            closure_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+            Ok(closure_expr)
+        }
+    }
+
     /// GroupedExpr | TupleExpr
     fn parse_grouped_or_tuple_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        let lo = cursor.next_span();
         cursor.eat_token_eq(Token::LeftParen)?;
-        let expr = Expr::parse(cursor)?;
+        if cursor.eat_token_if_eq(Token::RightParen) {
+            let mut tuple_expr = TupleExpr::new(vec![]);
+            tuple_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+            return Ok(Expr::Tuple(tuple_expr));
+        }
+        let expr = parse_with_restriction(cursor, false, Expr::parse)?;
         match cursor.next_token()? {
             Token::RightParen => {
                 cursor.bump_token()?;
                 Ok(Expr::Grouped(GroupedExpr::new(expr)))
             }
-            Token::Comma => Ok(Expr::Tuple(TupleExpr::parse_from_second(cursor, expr)?)),
+            Token::Comma => {
+                let mut tuple_expr = TupleExpr::parse_from_second(cursor, expr)?;
+                tuple_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                Ok(Expr::Tuple(tuple_expr))
+            }
             _ => Err("expected ',' or ')'".into()),
         }
     }
 
-    ///  TupleExpr -> `(` ( Expr , )+ Expr? `)`
+    ///  TupleExpr -> `(` `)` | `(` ( Expr , )+ Expr? `)`
     impl TupleExpr {
         fn parse_from_second(cursor: &mut ParseCursor, first_expr: Expr) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::Comma)?;
-            let mut tuple_expr = TupleExpr(vec![first_expr]);
+            let mut tuple_expr = TupleExpr::new(vec![first_expr]);
             while cursor.next_token()? != &Token::RightParen {
-                tuple_expr.0.push(Expr::parse(cursor)?);
+                tuple_expr.elems.push(Expr::parse(cursor)?);
                 if !cursor.eat_token_if_eq(Token::Comma) {
                     break;
                 }
@@ -312,9 +537,9 @@ pub mod primitive {
         }
     }
 
-    /// PathExpr -> identifier (:: identifier)*
+    /// PathExpr -> identifier (:: identifier)* ( :: GenericArgs )?
     /// # Examples
-    /// `a::b::c`, `a`
+    /// `a::b::c`, `a`, `max::<i32>`
     impl Parse for PathExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             #[derive(PartialEq)]
@@ -325,6 +550,7 @@ pub mod primitive {
             }
 
             let mut path_expr = Self::new();
+            let lo = cursor.next_span();
             let mut state = State::Init;
             while let Ok(tk) = cursor.next_token() {
                 match tk {
@@ -334,18 +560,30 @@ pub mod primitive {
                         }
                         state = State::PathSep;
                     }
-                    Token::Identifier(s) => {
+                    // `max::<i32>`
+                    Token::Lt if state == State::PathSep => {
+                        cursor.bump_token()?;
+                        path_expr.generic_args = parse_generic_args(cursor)?;
+                        path_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+                        return Ok(path_expr);
+                    }
+                    Token::Identifier(_) | Token::SelfValue | Token::SelfType => {
                         if state == State::Segment {
                             return Err("invalid path".into());
                         }
                         state = State::Segment;
-                        path_expr.segments.push(s.to_string());
+                        path_expr.segments.push(match tk {
+                            Token::Identifier(s) => s.to_string(),
+                            Token::SelfValue => "self".to_string(),
+                            _ => "Self".to_string(),
+                        });
                     }
                     _ => break,
                 }
                 cursor.bump_token()?;
             }
             if state == State::Segment {
+                path_expr.span = NodeSpan(lo.to(cursor.prev_span()));
                 Ok(path_expr)
             } else {
                 Err("invalid path".into())
@@ -362,25 +600,23 @@ pub mod primitive {
         }
     }
 
-    fn parse_literal(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+    pub(crate) fn parse_literal(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
         let (literal_kind, value) = cursor.eat_literal()?;
-        Ok(match literal_kind {
-            Char => Expr::LitChar(value.chars().nth(1).unwrap()),
-            Integer { suffix } => {
-                Expr::LitNum(LitNumExpr::integer(value).lit_type(if suffix.is_empty() {
-                    TypeLitNum::I
-                } else {
-                    TypeLitNum::from_str(suffix).unwrap()
-                }))
-            }
-            Float { suffix } => {
-                Expr::LitNum(LitNumExpr::integer(value).lit_type(if suffix.is_empty() {
-                    TypeLitNum::F
-                } else {
-                    TypeLitNum::from_str(suffix).unwrap()
-                }))
-            }
-        })
+        let mut lit_num = match literal_kind {
+            Char => return Ok(Expr::LitChar(value.chars().nth(1).unwrap())),
+            Integer { suffix } => LitNumExpr::integer(value).lit_type(if suffix.is_empty() {
+                TypeLitNum::I
+            } else {
+                TypeLitNum::from_str(suffix).unwrap()
+            }),
+            Float { suffix } => LitNumExpr::integer(value).lit_type(if suffix.is_empty() {
+                TypeLitNum::F
+            } else {
+                TypeLitNum::from_str(suffix).unwrap()
+            }),
+        };
+        lit_num.span = NodeSpan(cursor.prev_span());
+        Ok(Expr::LitNum(lit_num))
     }
 
     /// Local type definitions are analysed here.
@@ -388,38 +624,58 @@ pub mod primitive {
     /// BlockExpr -> `{` Stmt* Expr(without block)? `}`
     impl Parse for BlockExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-            cursor.eat_token_eq(Token::LeftCurlyBraces)?;
-            let mut block_expr = BlockExpr::new(cursor.scope_count);
-            cursor.scope_count += 1;
-            while cursor.next_token()? != &Token::RightCurlyBraces {
-                match parse_stmt_or_expr_without_block(cursor)? {
-                    StmtOrExpr::Stmt(stmt) => {
-                        if let crate::ast::stmt::Stmt::Item(item) = &stmt {
-                            block_expr.scope.add_typedef(item);
-                        }
-                        block_expr.stmts.push(stmt)
+            parse_with_restriction(cursor, false, parse_block_expr)
+        }
+    }
+
+    /// UnsafeBlockExpr -> `unsafe` BlockExpr
+    pub fn parse_unsafe_block(cursor: &mut ParseCursor) -> Result<BlockExpr, RccError> {
+        cursor.eat_token_eq(Token::Unsafe)?;
+        let mut block_expr = BlockExpr::parse(cursor)?;
+        block_expr.is_unsafe = true;
+        Ok(block_expr)
+    }
+
+    fn parse_block_expr(cursor: &mut ParseCursor) -> Result<BlockExpr, RccError> {
+        cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+        let mut block_expr = BlockExpr::new(cursor.scope_count);
+        cursor.scope_count += 1;
+        while cursor.next_token()? != &Token::RightCurlyBraces {
+            let lo = cursor.next_span();
+            match parse_stmt_or_expr_without_block(cursor) {
+                Ok(StmtOrExpr::Stmt(stmt)) => {
+                    if let crate::ast::stmt::Stmt::Item(item) = &stmt {
+                        block_expr.scope.add_typedef(item);
                     }
-                    StmtOrExpr::Expr(expr) => {
-                        if block_expr.last_expr.is_none() {
-                            block_expr.last_expr = Some(Box::new(expr));
-                        } else {
-                            return Err("expected `;`".into());
-                        }
+                    block_expr.stmts.push(stmt)
+                }
+                Ok(StmtOrExpr::Expr(expr)) => {
+                    if block_expr.last_expr.is_none() {
+                        block_expr.last_expr = Some(Box::new(expr));
+                    } else {
+                        cursor.report(RccError::with_span("expected `;`", lo));
                     }
                 }
-            }
-
-            if block_expr.last_expr.is_none() && !block_expr.stmts.is_empty() {
-                if let Stmt::ExprStmt(e) = block_expr.stmts.last().unwrap() {
-                    if e.with_block() {
-                        block_expr.set_last_stmt_as_expr();
+                Err(e) => {
+                    let e = e.or_span(cursor.next_span());
+                    if !cursor.synchronize_stmt() {
+                        return Err(e);
                     }
+                    cursor.report(e);
                 }
             }
+        }
 
-            cursor.eat_token_eq(Token::RightCurlyBraces)?;
-            Ok(block_expr)
+        if block_expr.last_expr.is_none() && !block_expr.stmts.is_empty() {
+            if let Stmt::ExprStmt(e) = block_expr.stmts.last().unwrap() {
+                if e.with_block() {
+                    block_expr.set_last_stmt_as_expr();
+                }
+            }
         }
+
+        cursor.eat_token_eq(Token::RightCurlyBraces)?;
+        Ok(block_expr)
     }
 
     /// ArrayExpr -> `[` Expression ( , Expression )* ,? `]`
@@ -427,11 +683,17 @@ pub mod primitive {
     impl Parse for ArrayExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::LeftSquareBrackets)?;
+            if cursor.eat_token_if_eq(Token::RightSquareBrackets) {
+                return Ok(ArrayExpr::elems(vec![]));
+            }
             let mut elems = vec![Expr::parse(cursor)?];
             let mut last_is_comma = false;
             loop {
                 match cursor.next_token()? {
-                    Token::RightCurlyBraces => return Ok(ArrayExpr::elems(elems)),
+                    Token::RightSquareBrackets => {
+                        cursor.bump_token()?;
+                        return Ok(ArrayExpr::elems(elems));
+                    }
                     Token::Semi => {
                         cursor.bump_token()?;
                         return if elems.len() == 1 {
@@ -466,8 +728,9 @@ pub mod primitive {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::While)?;
             Ok(WhileExpr(
-                Box::new(Expr::parse(cursor)?),
+                Box::new(parse_cond(cursor)?),
                 Box::new(BlockExpr::parse(cursor)?),
+                None,
             ))
         }
     }
@@ -479,16 +742,57 @@ pub mod primitive {
         }
     }
 
+    /// LabeledLoopExpr -> Lifetime `:` (WhileExpr | LoopExpr | ForExpr)
+    pub fn parse_labeled_loop(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        let label = Some(cursor.eat_lifetime()?.to_string());
+        cursor.eat_token_eq(Token::Colon)?;
+        match cursor.next_token()? {
+            Token::While => {
+                let mut while_expr = WhileExpr::parse(cursor)?;
+                while_expr.2 = label;
+                Ok(While(while_expr))
+            }
+            Token::Loop => {
+                let mut loop_expr = LoopExpr::parse(cursor)?;
+                loop_expr.label = label;
+                Ok(Loop(loop_expr))
+            }
+            Token::For => {
+                let mut for_expr = ForExpr::parse(cursor)?;
+                for_expr.label = label;
+                Ok(Expr::For(for_expr))
+            }
+            tk => Err(RccError::with_span(
+                format!(
+                    "expected `while`, `loop` or `for` after a label, found `{}`",
+                    tk.source()
+                ),
+                cursor.next_span(),
+            )),
+        }
+    }
+
+    /// ForExpr -> `for` Pattern `in` Expr BlockExpr
+    impl Parse for ForExpr {
+        fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+            cursor.eat_token_eq(Token::For)?;
+            let pattern = Pattern::parse(cursor)?;
+            cursor.eat_token_eq(Token::In)?;
+            let expr = parse_cond(cursor)?;
+            Ok(ForExpr::new(pattern, expr, BlockExpr::parse(cursor)?))
+        }
+    }
+
     /// IfExpr -> `if` Expr BlockExpr ( `else` (BlockExpr | IfExpr) )?
     impl Parse for IfExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::If)?;
             let mut if_expr = IfExpr::new();
-            if_expr.add_cond(Expr::parse(cursor)?);
+            if_expr.add_cond(parse_cond(cursor)?);
             if_expr.add_block(BlockExpr::parse(cursor)?);
             while cursor.eat_token_if_eq(Token::Else) {
                 if cursor.eat_token_if_eq(Token::If) {
-                    if_expr.add_cond(Expr::parse(cursor)?);
+                    if_expr.add_cond(parse_cond(cursor)?);
                 }
                 if_expr.add_block(BlockExpr::parse(cursor)?);
             }
@@ -496,6 +800,54 @@ pub mod primitive {
         }
     }
 
+    /// MatchExpr -> `match` Expr `{` MatchArm* `}`
+    ///
+    /// MatchArm -> Pattern ( `if` Expr )? `=>` Expr `,`
+    ///
+    /// The comma is optional after a block-like expression or the last arm.
+    impl Parse for MatchExpr {
+        fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+            let lo = cursor.next_span();
+            cursor.eat_token_eq(Token::Match)?;
+            let expr = parse_cond(cursor)?;
+            cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+            let arms = parse_with_restriction(cursor, false, |cursor| {
+                let mut arms = vec![];
+                while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+                    let scope_id = cursor.scope_count;
+                    cursor.scope_count += 1;
+                    let pattern_lo = cursor.next_span();
+                    let pattern = Pattern::parse(cursor)?;
+                    let pattern_span = pattern_lo.to(cursor.prev_span());
+                    let guard = if cursor.eat_token_if_eq(Token::If) {
+                        Some(Expr::parse(cursor)?)
+                    } else {
+                        None
+                    };
+                    cursor.eat_token_eq(Token::FatArrow)?;
+                    // a block-like arm body ends the arm, e.g. `A => {} (a, b) => {}`
+                    let expr = if Expr::is_with_block_token_start(cursor.next_token()?) {
+                        Expr::parse_with_block(cursor)?
+                    } else {
+                        Expr::parse(cursor)?
+                    };
+                    let with_block = expr.with_block();
+                    let mut arm = MatchArm::new(scope_id, pattern, guard, expr);
+                    arm.pattern_span = NodeSpan(pattern_span);
+                    arms.push(arm);
+                    if !cursor.eat_token_if_eq(Token::Comma) && !with_block {
+                        cursor.eat_token_eq(Token::RightCurlyBraces)?;
+                        break;
+                    }
+                }
+                Ok(arms)
+            })?;
+            let mut match_expr = MatchExpr::new(expr, arms);
+            match_expr.span = NodeSpan(lo.to(cursor.prev_span()));
+            Ok(match_expr)
+        }
+    }
+
     /// ReturnExpr -> `return` Expr?
     impl Parse for ReturnExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
@@ -510,17 +862,33 @@ pub mod primitive {
         }
     }
 
-    /// BreakExpr -> `break` Expr?
+    fn parse_label(cursor: &mut ParseCursor) -> Option<String> {
+        cursor.eat_lifetime().ok().map(|label| label.to_string())
+    }
+
+    /// BreakExpr -> `break` Lifetime? Expr?
     impl Parse for BreakExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::Break)?;
+            let label = parse_label(cursor);
             if let Ok(tk) = cursor.next_token() {
                 if Expr::is_token_start(tk) {
                     let expr = Expr::parse(cursor)?;
-                    return Ok(BreakExpr(Some(Box::new(expr))));
+                    return Ok(BreakExpr {
+                        label,
+                        expr: Some(Box::new(expr)),
+                    });
                 }
             }
-            Ok(BreakExpr(None))
+            Ok(BreakExpr { label, expr: None })
+        }
+    }
+
+    /// ContinueExpr -> `continue` Lifetime?
+    impl Parse for ContinueExpr {
+        fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+            cursor.eat_token_eq(Token::Continue)?;
+            Ok(ContinueExpr(parse_label(cursor)))
         }
     }
 }
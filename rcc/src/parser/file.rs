@@ -10,10 +10,25 @@ impl Parse for File {
         let mut file = File::new(cursor.scope_count);
         cursor.scope_count += 1;
         while !cursor.is_eof() {
-            let item = Item::parse(cursor)?;
-            file.scope.add_typedef(&item);
-            file.items.push(item);
+            let start_idx = cursor.token_idx;
+            match Item::parse(cursor) {
+                Ok(item) => {
+                    file.scope.add_typedef(&item);
+                    file.items.push(item);
+                }
+                Err(e) => {
+                    cursor.report(e);
+                    if !cursor.synchronize_item(start_idx) {
+                        break;
+                    }
+                }
+            }
+        }
+        let errors = cursor.take_errors();
+        if errors.is_empty() {
+            Ok(file)
+        } else {
+            Err(RccError::from_errors(errors))
         }
-        Ok(file)
     }
 }
@@ -1,11 +1,12 @@
 use crate::ast::expr::BlockExpr;
 use crate::ast::item::{
-    ExternalItem, ExternalItemFn, FnParam, FnParams, Item, ItemExternalBlock, ItemFn, ItemStruct,
-    StructField, TupleField, TypeEnum, ABI,
+    EnumVariant, ExternalItem, ExternalItemFn, Fields, FnDecl, FnParam, FnParams, GenericParam,
+    Item, ItemExternalBlock, ItemFn, ItemImpl, ItemStruct, ItemTrait, StructField, TraitItemFn,
+    TupleField, TypeEnum, ABI,
 };
-use crate::ast::pattern::Pattern;
-use crate::ast::types::TypeAnnotation;
-use crate::ast::{TokenStart, Visibility};
+use crate::ast::pattern::{IdentPattern, Pattern};
+use crate::ast::types::{PtrKind, TypeAnnotation, TypePtr};
+use crate::ast::{NodeSpan, TokenStart, Visibility};
 use crate::lexer::token::Token;
 use crate::parser::expr::primitive::parse_lit_string;
 use crate::parser::{Parse, ParseCursor};
@@ -22,28 +23,38 @@ impl Parse for Item {
             Token::Enum => Ok(Self::Enum(TypeEnum::parse_with_attr(cursor, vis)?)),
             Token::Static => unimplemented!(),
             Token::Const => unimplemented!(),
-            Token::Impl => unimplemented!(),
+            Token::Impl if vis == Visibility::Pub => Err("unnecessary visibility qualifier".into()),
+            Token::Impl => Ok(Self::Impl(ItemImpl::parse(cursor)?)),
+            Token::Trait => Ok(Self::Trait(ItemTrait::parse_with_attr(cursor, vis)?)),
             Token::Extern => Ok(Self::ExternalBlock(ItemExternalBlock::parse(cursor)?)),
-            _ => unreachable!("inner item must be fn, struct, enum, static, const or impl"),
+            tk => Err(format!("expected item, found `{}`", tk.source()).into()),
         }
     }
 }
 
 /// Parse struct definition
-/// ItemStruct -> struct Identifier ; | TupleField ; | StructField
+/// ItemStruct -> struct Identifier Generics? ( ; | TupleField WhereClause? ; | WhereClause? StructField )
 impl ItemStruct {
     fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
         debug_assert!(cursor.next_token()? == &Token::Struct);
         cursor.bump_token()?;
-        let ident = cursor.bump_token()?;
-        if let Token::Identifier(struct_name) = ident {
-            let type_struct = Self::new(vis, struct_name.to_string());
+        if let Token::Identifier(struct_name) = cursor.next_token()? {
+            let struct_name = struct_name.to_string();
+            cursor.bump_token()?;
+            let mut generics = parse_generics(cursor)?;
+            if cursor.next_token()? != &Token::LeftParen {
+                parse_where_clause(cursor, &mut generics)?;
+            }
+            let type_struct = Self::new(vis, struct_name).generic_params(generics);
             match cursor.next_token()? {
                 // struct Foo;
                 Token::Semi => Ok(type_struct),
                 // struct Foo(i32);
                 Token::LeftParen => {
                     let tuple_fields = Vec::<TupleField>::parse(cursor)?;
+                    let mut generics = type_struct.generics().to_vec();
+                    parse_where_clause(cursor, &mut generics)?;
+                    let type_struct = type_struct.generic_params(generics);
                     // eat semicolon
                     let tk = cursor.bump_token()?;
                     if tk == &Token::Semi {
@@ -65,25 +76,132 @@ impl ItemStruct {
     }
 }
 
+/// Parse enum definition
+/// TypeEnum -> enum Identifier { (EnumVariant ,)* EnumVariant? }
+///
+/// EnumVariant -> Identifier (TupleField | StructField)?
 impl TypeEnum {
     fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
-        unimplemented!()
+        cursor.eat_token_eq(Token::Enum)?;
+        let enum_name = cursor.eat_identifier()?.to_string();
+        cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+        let mut enum_items = vec![];
+        while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+            let name = cursor.eat_identifier()?.to_string();
+            let fields = match cursor.next_token()? {
+                // Foo(i32)
+                Token::LeftParen => Fields::Tuple(Vec::<TupleField>::parse(cursor)?),
+                // Foo {id: i32}
+                Token::LeftCurlyBraces => Fields::Struct(Vec::<StructField>::parse(cursor)?),
+                _ => Fields::None,
+            };
+            enum_items.push(EnumVariant::new(name, fields));
+            if !cursor.eat_token_if_eq(Token::Comma) {
+                cursor.eat_token_eq(Token::RightCurlyBraces)?;
+                break;
+            }
+        }
+        Ok(Self::new(vis, enum_name).enum_items(enum_items))
     }
 }
 
+/// Generics -> `<` ( GenericParam `,` )* GenericParam? `>`
+///
+/// GenericParam -> identifier ( `:` Bounds )?
+///
+/// Return an empty list if the next token is not `<`.
+fn parse_generics(cursor: &mut ParseCursor) -> Result<Vec<GenericParam>, RccError> {
+    let mut generics: Vec<GenericParam> = vec![];
+    if !cursor.eat_token_if_eq(Token::Lt) {
+        return Ok(generics);
+    }
+    while !cursor.eat_token_if_eq(Token::Gt) {
+        let name = cursor.eat_identifier()?.to_string();
+        if generics.iter().any(|p| p.name == name) {
+            return Err(format!(
+                "the name `{}` is already used for a generic parameter",
+                name
+            )
+            .into());
+        }
+        let bounds = if cursor.eat_token_if_eq(Token::Colon) {
+            parse_bounds(cursor)?
+        } else {
+            vec![]
+        };
+        generics.push(GenericParam::new(name, bounds));
+        if !cursor.eat_token_if_eq(Token::Comma) {
+            cursor.eat_token_eq(Token::Gt)?;
+            break;
+        }
+    }
+    Ok(generics)
+}
+
+/// Bounds -> identifier ( `+` identifier )*
+fn parse_bounds(cursor: &mut ParseCursor) -> Result<Vec<String>, RccError> {
+    let mut bounds = vec![cursor.eat_identifier()?.to_string()];
+    while cursor.eat_token_if_eq(Token::Plus) {
+        bounds.push(cursor.eat_identifier()?.to_string());
+    }
+    Ok(bounds)
+}
+
+/// WhereClause -> `where` ( identifier `:` Bounds `,` )* ( identifier `:` Bounds )?
+///
+/// The bounds are added to the generic parameters.
+fn parse_where_clause(
+    cursor: &mut ParseCursor,
+    generics: &mut [GenericParam],
+) -> Result<(), RccError> {
+    if !cursor.eat_token_if_eq(Token::Where) {
+        return Ok(());
+    }
+    while let Token::Identifier(name) = cursor.next_token()? {
+        let name = name.to_string();
+        cursor.bump_token()?;
+        cursor.eat_token_eq(Token::Colon)?;
+        let bounds = parse_bounds(cursor)?;
+        match generics.iter_mut().find(|p| p.name == name) {
+            Some(param) => param.bounds.extend(bounds),
+            None => return Err(format!("cannot find type `{}` in this scope", name).into()),
+        }
+        if !cursor.eat_token_if_eq(Token::Comma) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The `self` parameter is only allowed in associated functions.
 fn parse_fn_signature(
     cursor: &mut ParseCursor,
-) -> Result<(String, FnParams, TypeAnnotation), RccError> {
+    is_assoc: bool,
+) -> Result<(String, Vec<GenericParam>, FnParams, TypeAnnotation), RccError> {
     cursor.eat_token_eq(Token::Fn)?;
     let fn_name = cursor.eat_identifier()?.to_string();
+    let mut generics = parse_generics(cursor)?;
 
     cursor.eat_token_eq(Token::LeftParen)?;
-    let fn_params = if cursor.eat_token_if_eq(Token::RightParen) {
-        FnParams::new()
-    } else {
-        let fn_params = FnParams::parse(cursor)?;
-        cursor.eat_token_eq(Token::RightParen)?;
-        fn_params
+    let self_param = parse_self_param(cursor);
+    if self_param.is_some() && !is_assoc {
+        return Err("`self` parameter is only allowed in associated functions".into());
+    }
+    let fn_params = match self_param {
+        Some(self_param) => {
+            let mut fn_params = FnParams::from(vec![self_param]);
+            if cursor.eat_token_if_eq(Token::Comma) && cursor.next_token()? != &Token::RightParen {
+                fn_params.params.extend(FnParams::parse(cursor)?.params);
+            }
+            cursor.eat_token_eq(Token::RightParen)?;
+            fn_params
+        }
+        None if cursor.eat_token_if_eq(Token::RightParen) => FnParams::new(),
+        None => {
+            let fn_params = FnParams::parse(cursor)?;
+            cursor.eat_token_eq(Token::RightParen)?;
+            fn_params
+        }
     };
 
     let ret_type = match cursor.next_token()? {
@@ -91,18 +209,161 @@ fn parse_fn_signature(
             cursor.bump_token()?;
             TypeAnnotation::parse(cursor)?
         }
-        Token::Semi | Token::LeftCurlyBraces => TypeAnnotation::Unit,
+        Token::Semi | Token::LeftCurlyBraces | Token::Where => TypeAnnotation::Unit,
         _ => return Err("except '->' or '{'".into()),
     };
-    Ok((fn_name, fn_params, ret_type))
+    parse_where_clause(cursor, &mut generics)?;
+    Ok((fn_name, generics, fn_params, ret_type))
 }
 
-/// ItemFn -> vis? `fn` identifier `(` FnParams? `)` ( `->` Type )? BlockExpr
+/// SelfParam -> `mut`? `self` | `&` `mut`? `self`
+///
+/// The type of `self` is `Self`, `&Self` or `&mut Self`.
+/// Return `None` without consuming any token if there is no `self` parameter.
+fn parse_self_param(cursor: &mut ParseCursor) -> Option<FnParam> {
+    let start_idx = cursor.token_idx;
+    let ptr_kind = if cursor.eat_token_if_eq(Token::And) {
+        Some(if cursor.eat_token_if_eq(Token::Mut) {
+            PtrKind::MutRef
+        } else {
+            PtrKind::Ref
+        })
+    } else {
+        None
+    };
+    let is_mut = ptr_kind.is_none() && cursor.eat_token_if_eq(Token::Mut);
+    if !cursor.eat_token_if_eq(Token::SelfValue) {
+        cursor.token_idx = start_idx;
+        return None;
+    }
+
+    let self_type = TypeAnnotation::from("Self");
+    let (pattern, _type) = match ptr_kind {
+        Some(kind) => (
+            IdentPattern::new_const("self".into()),
+            TypeAnnotation::Ptr(TypePtr::new(kind, self_type)),
+        ),
+        None if is_mut => (IdentPattern::new_mut("self".into()), self_type),
+        None => (IdentPattern::new_const("self".into()), self_type),
+    };
+    Some(FnParam::new(Pattern::Identifier(pattern), _type))
+}
+
+/// ItemFn -> vis? `fn` identifier Generics? `(` FnParams? `)` ( `->` Type )? WhereClause? BlockExpr
 impl ItemFn {
     fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
-        let (fn_name, fn_params, ret_type) = parse_fn_signature(cursor)?;
+        Self::parse_fn(cursor, vis, false)
+    }
+
+    fn parse_fn(cursor: &mut ParseCursor, vis: Visibility, is_assoc: bool) -> Result<Self, RccError> {
+        let lo = cursor.next_span();
+        let (fn_name, generics, fn_params, ret_type) = parse_fn_signature(cursor, is_assoc)?;
+        let span = NodeSpan(lo.to(cursor.prev_span()));
         let fn_block = BlockExpr::parse(cursor)?;
-        Ok(ItemFn::new(vis, fn_name, fn_params, ret_type, fn_block))
+        let mut item_fn = ItemFn::new(vis, fn_name, fn_params, ret_type, fn_block);
+        item_fn.generics = generics;
+        item_fn.span = span;
+        Ok(item_fn)
+    }
+}
+
+/// ItemImpl -> `impl` ( identifier `for` )? Type `{` AssociatedFn* `}`
+///
+/// AssociatedFn -> vis? `fn` identifier `(` ( SelfParam ,? )? FnParams? `)` ( `->` Type )? BlockExpr
+///
+/// Only functions are monomorphized, so impl blocks and associated functions are not generic,
+/// e.g. `impl<T> Shape for T` and `impl<T> Pair<T>` are rejected.
+impl Parse for ItemImpl {
+    fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+        let lo = cursor.next_span();
+        cursor.eat_token_eq(Token::Impl)?;
+        if cursor.next_token()? == &Token::Lt {
+            parse_generics(cursor)?;
+            return Err(RccError::with_span(
+                "generic impl blocks are not supported",
+                lo.to(cursor.prev_span()),
+            ));
+        }
+        let mut self_type = TypeAnnotation::parse(cursor)?;
+        let mut trait_name = None;
+        if cursor.eat_token_if_eq(Token::For) {
+            trait_name = match self_type {
+                TypeAnnotation::Identifier(name) => Some(name),
+                t => return Err(format!("expected trait, found type `{:?}`", t).into()),
+            };
+            self_type = TypeAnnotation::parse(cursor)?;
+        }
+        let span = NodeSpan(lo.to(cursor.prev_span()));
+        let scope_id = cursor.scope_count;
+        cursor.scope_count += 1;
+        cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+        let mut items = vec![];
+        while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+            let vis = Visibility::parse(cursor)?;
+            match cursor.next_token()? {
+                Token::Fn => {
+                    let item_fn = ItemFn::parse_fn(cursor, vis, true)?;
+                    if item_fn.is_generic() {
+                        return Err("generic associated functions are not supported".into());
+                    }
+                    items.push(item_fn);
+                }
+                tk => {
+                    let msg = format!("expected associated function, found `{}`", tk.source());
+                    return Err(msg.into());
+                }
+            }
+        }
+        let mut item_impl = ItemImpl::new(self_type, items, scope_id);
+        if let Some(trait_name) = trait_name {
+            item_impl = item_impl.for_trait(trait_name);
+        }
+        item_impl.span = span;
+        Ok(item_impl)
+    }
+}
+
+/// ItemTrait -> `trait` identifier `{` TraitFn* `}`
+///
+/// TraitFn -> `fn` identifier `(` ( SelfParam ,? )? FnParams? `)` ( `->` Type )? ( `;` | BlockExpr )
+impl ItemTrait {
+    fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
+        let lo = cursor.next_span();
+        cursor.eat_token_eq(Token::Trait)?;
+        let name = cursor.eat_identifier()?.to_string();
+        let span = NodeSpan(lo.to(cursor.prev_span()));
+        let scope_id = cursor.scope_count;
+        cursor.scope_count += 1;
+        cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+        let mut items = vec![];
+        while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+            if Visibility::parse(cursor)? == Visibility::Pub {
+                return Err("unnecessary visibility qualifier".into());
+            }
+            let fn_lo = cursor.next_span();
+            let (fn_name, generics, fn_params, ret_type) = match cursor.next_token()? {
+                Token::Fn => parse_fn_signature(cursor, true)?,
+                tk => {
+                    let msg = format!("expected associated function, found `{}`", tk.source());
+                    return Err(msg.into());
+                }
+            };
+            if !generics.is_empty() {
+                return Err("generic associated functions are not supported".into());
+            }
+            if cursor.eat_token_if_eq(Token::Semi) {
+                items.push(TraitItemFn::Required(FnDecl::new(fn_name, fn_params, ret_type)));
+                continue;
+            }
+            let fn_span = NodeSpan(fn_lo.to(cursor.prev_span()));
+            let fn_block = BlockExpr::parse(cursor)?;
+            let mut item_fn = ItemFn::new(Visibility::Priv, fn_name, fn_params, ret_type, fn_block);
+            item_fn.span = fn_span;
+            items.push(TraitItemFn::Provided(Box::new(item_fn)));
+        }
+        let mut item_trait = ItemTrait::new(vis, name, items, scope_id);
+        item_trait.span = span;
+        Ok(item_trait)
     }
 }
 
@@ -174,7 +435,10 @@ impl ExternalItemFn {
         cursor: &mut ParseCursor,
         vis: Visibility,
     ) -> Result<ExternalItemFn, RccError> {
-        let (fn_name, fn_params, ret_type) = parse_fn_signature(cursor)?;
+        let (fn_name, generics, fn_params, ret_type) = parse_fn_signature(cursor, false)?;
+        if !generics.is_empty() {
+            return Err("foreign items may not have type parameters".into());
+        }
         cursor.eat_token_eq(Token::Semi)?;
         Ok(ExternalItemFn::new(vis, fn_name, fn_params, ret_type))
     }
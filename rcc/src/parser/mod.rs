@@ -28,7 +28,7 @@
 
 use crate::ast::FromToken;
 use crate::ast::{Visibility, AST};
-use crate::lexer::token::{LiteralKind, Token};
+use crate::lexer::token::{LiteralKind, Span, Token, TokenWithSpan};
 use crate::rcc::RccError;
 use std::fmt::Debug;
 
@@ -46,26 +46,32 @@ pub trait Parse: Sized + Debug + PartialEq {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError>;
 }
 
-#[derive(Clone)]
 pub struct ParseCursor<'a> {
-    token_stream: Vec<Token<'a>>,
+    token_stream: Vec<TokenWithSpan<'a>>,
     token_idx: usize,
     scope_count: u64,
+    /// errors which the parser has recovered from
+    errors: Vec<RccError>,
+    /// Struct literals are not allowed in the conditions of `if` and `while`,
+    /// `if a == b { c }` is not parsed as `if a == (b { c })`.
+    no_struct_literal: bool,
 }
 
 impl<'a> ParseCursor<'a> {
-    pub fn new(token_stream: Vec<Token<'a>>) -> Self {
+    pub fn new(token_stream: Vec<TokenWithSpan<'a>>) -> Self {
         ParseCursor {
             token_stream,
             token_idx: 0,
             scope_count: 1,
+            errors: vec![],
+            no_struct_literal: false,
         }
     }
 
     pub fn next_token(&self) -> Result<&Token<'a>, RccError> {
         match self.token_stream.get(self.token_idx) {
-            Some(tk) => Ok(tk),
-            None => Err("EOF token".into()),
+            Some(tk) => Ok(&tk.token),
+            None => Err(RccError::with_span("EOF token", self.next_span())),
         }
     }
 
@@ -73,58 +79,102 @@ impl<'a> ParseCursor<'a> {
         match self.token_stream.get(self.token_idx) {
             Some(tk) => {
                 self.token_idx += 1;
-                Ok(tk)
+                Ok(&tk.token)
             }
-            None => Err("EOF token".into()),
+            None => Err(RccError::with_span("EOF token", self.next_span())),
         }
     }
 
+    /// Span of the next token, or an empty span at the end of the file.
+    pub fn next_span(&self) -> Span {
+        match self.token_stream.get(self.token_idx) {
+            Some(tk) => tk.span,
+            None => {
+                let hi = self.token_stream.last().map_or(0, |tk| tk.span.hi);
+                Span::new(hi, hi)
+            }
+        }
+    }
+
+    /// Span of the last bumped token.
+    pub fn prev_span(&self) -> Span {
+        match self.token_idx.checked_sub(1) {
+            Some(idx) => self.token_stream[idx].span,
+            None => Span::default(),
+        }
+    }
+
+    // The `eat_*` methods do not consume the next token if it is unexpected,
+    // so that the parser can resynchronize from it.
+
     pub fn eat_identifier(&mut self) -> Result<&'a str, RccError> {
-        match self.bump_token()? {
-            Token::Identifier(s) => Ok(s),
-            _ => Err(self.err("identifier".to_string()).into()),
+        match self.next_token()? {
+            Token::Identifier(s) => {
+                let s = *s;
+                self.token_idx += 1;
+                Ok(s)
+            }
+            _ => Err(self.err("identifier".to_string(), self.next_span())),
         }
     }
 
-    pub fn eat_literal(&mut self) -> Result<(LiteralKind, String), RccError> {
-        match self.bump_token()? {
+    pub fn eat_lifetime(&mut self) -> Result<&'a str, RccError> {
+        match self.next_token()? {
+            Token::Lifetime(s) => {
+                let s = *s;
+                self.token_idx += 1;
+                Ok(s)
+            }
+            _ => Err(self.err("lifetime".to_string(), self.next_span())),
+        }
+    }
+
+    pub fn eat_literal(&mut self) -> Result<(LiteralKind<'_>, String), RccError> {
+        match self.next_token()? {
             Token::Literal {
                 literal_kind,
                 value,
-            } => Ok((literal_kind.clone(), value.to_string())),
-            _ => Err(self.err("literal".to_string()).into()),
+            } => {
+                let literal = (literal_kind.clone(), value.to_string());
+                self.token_idx += 1;
+                Ok(literal)
+            }
+            _ => Err(self.err("literal".to_string(), self.next_span())),
         }
     }
 
     pub fn eat_token_eq(&mut self, tk: Token) -> Result<(), RccError> {
-        if self.bump_token()? != &tk {
-            Err(self.err(tk.to_string()).into())
+        if self.next_token()? != &tk {
+            Err(self.err(tk.to_string(), self.next_span()))
         } else {
+            self.token_idx += 1;
             Ok(())
         }
     }
 
-    pub fn eat_token_in(&mut self, tks: &[Token]) -> Result<&Token, RccError> {
+    #[cfg(test)]
+    pub fn eat_token_in(&mut self, tks: &[Token]) -> Result<&Token<'_>, RccError> {
         let next_token = self.next_token()?;
         for tk in tks {
             if next_token == tk {
-                return Ok(self.bump_token()?);
+                return self.bump_token();
             }
         }
-        Err(self.err(format!("{:?}", tks)).into())
+        let expect: Vec<String> = tks.iter().map(|tk| format!("`{}`", tk.source())).collect();
+        Err(self.err(expect.join(" or "), self.next_span()))
     }
 
     pub fn eat_token_if_eq(&mut self, tk: Token) -> bool {
         if let Ok(next_tk) = self.next_token() {
             if next_tk == &tk {
-                self.bump_token();
+                self.bump_token().unwrap();
                 return true;
             }
         }
         false
     }
 
-    pub fn eat_token_if_in(&mut self, tks: &[Token]) -> Option<&Token> {
+    pub fn eat_token_if_in(&mut self, tks: &[Token]) -> Option<&Token<'_>> {
         for tk in tks {
             if let Ok(next_tk) = self.next_token() {
                 if next_tk == tk {
@@ -147,13 +197,101 @@ impl<'a> ParseCursor<'a> {
         }
     }
 
-    fn err(&self, expect: String) -> String {
-        format!("error in parsing: except {}", expect)
+    fn err(&self, expect: String, span: Span) -> RccError {
+        RccError::with_span(format!("error in parsing: except {}", expect), span)
     }
 
     pub fn is_eof(&self) -> bool {
         self.token_idx == self.token_stream.len()
     }
+
+    /// Record an error and go on parsing.
+    pub fn report(&mut self, e: RccError) {
+        let e = e.or_span(self.next_span());
+        self.errors.push(e);
+    }
+
+    pub fn take_errors(&mut self) -> Vec<RccError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Skip tokens until the end of the current statement.
+    /// Stop after `;`, or before `}` of the enclosing block, `let`, `fn` and `struct`.
+    ///
+    /// Return `false` if reaching EOF.
+    pub fn synchronize_stmt(&mut self) -> bool {
+        let mut depth = 0usize;
+        while let Some(tk) = self.token_stream.get(self.token_idx) {
+            match tk.token {
+                Token::Semi if depth == 0 => {
+                    self.token_idx += 1;
+                    return true;
+                }
+                Token::RightCurlyBraces | Token::Let | Token::Fn | Token::Struct if depth == 0 => {
+                    return true
+                }
+                Token::LeftCurlyBraces => depth += 1,
+                Token::RightCurlyBraces => {
+                    depth -= 1;
+                    if depth == 0 {
+                        // end of a statement with block
+                        self.token_idx += 1;
+                        return true;
+                    }
+                }
+                _ => {}
+            }
+            self.token_idx += 1;
+        }
+        false
+    }
+
+    /// Skip tokens until the start of the next item in the current file or block.
+    /// `start_idx` is the index of the first token of the invalid item,
+    /// whose braces opened before the error are skipped up to the matching `}`.
+    ///
+    /// Return `false` if reaching EOF.
+    pub fn synchronize_item(&mut self, start_idx: usize) -> bool {
+        // always make progress
+        if self.token_idx == start_idx {
+            self.token_idx += 1;
+        }
+        // the error may occur in nested blocks
+        let mut depth = self.token_stream[start_idx..self.token_idx]
+            .iter()
+            .fold(0isize, |depth, tk| match tk.token {
+                Token::LeftCurlyBraces => depth + 1,
+                Token::RightCurlyBraces => depth - 1,
+                _ => depth,
+            });
+        while let Some(tk) = self.token_stream.get(self.token_idx) {
+            match tk.token {
+                Token::Fn
+                | Token::Struct
+                | Token::Enum
+                | Token::Impl
+                | Token::Trait
+                | Token::Extern
+                | Token::Pub
+                    if depth <= 0 =>
+                {
+                    return true
+                }
+                Token::LeftCurlyBraces => depth += 1,
+                Token::RightCurlyBraces => {
+                    depth -= 1;
+                    if depth == 0 {
+                        // end of the invalid item
+                        self.token_idx += 1;
+                        return self.token_idx < self.token_stream.len();
+                    }
+                }
+                _ => {}
+            }
+            self.token_idx += 1;
+        }
+        false
+    }
 }
 
 impl Parse for Visibility {
@@ -170,7 +308,8 @@ impl Parse for Visibility {
 
 impl Parse for AST {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        let file = crate::ast::file::File::parse(cursor)?;
+        // errors without location are reported at the token where parsing stopped
+        let file = crate::ast::file::File::parse(cursor).map_err(|e| e.or_span(cursor.next_span()))?;
         Ok(AST { file })
     }
 }
@@ -1,30 +1,203 @@
-use crate::ast::pattern::{IdentPattern, Pattern};
+use crate::ast::expr::{Expr, PathExpr, RangeOp, UnAryExpr, UnOp};
+use crate::ast::pattern::{FieldPattern, IdentPattern, Pattern, StructPattern};
+use crate::ast::{FromToken, NodeSpan};
 use crate::lexer::token::Token;
+use crate::parser::expr::primitive::parse_literal;
 use crate::parser::{Parse, ParseCursor};
 use crate::rcc::RccError;
 
-/// Pattern -> IdentifierPattern
+/// Pattern -> IdentifierPattern | WildcardPattern | LiteralPattern | RangePattern
+///          | PathPattern | TupleStructPattern | StructPattern | TuplePattern
+///          | ReferencePattern
 ///
-/// IdentifierPattern -> `mut`? identifier
+/// IdentifierPattern -> `ref`? `mut`? identifier
+///
+/// WildcardPattern -> `_`
+///
+/// LiteralPattern -> `true` | `false` | RangePatternBound
+///
+/// RangePattern -> RangePatternBound ( `..=` | `..` ) RangePatternBound
+///
+/// RangePatternBound -> CharLiteral | `-`? NumberLiteral
+///
+/// PathPattern -> PathExpr
+///
+/// TupleStructPattern -> PathExpr `(` ( Pattern , )* Pattern? `)`
+///
+/// StructPattern -> PathExpr `{` ( FieldPattern , )* ( FieldPattern | `..` )? `}`
+///
+/// FieldPattern -> identifier `:` Pattern | `ref`? `mut`? identifier
+///
+/// TuplePattern -> `(` ( Pattern , )* Pattern? `)`
+///
+/// ReferencePattern -> ( `&` | `&&` ) `mut`? Pattern
 impl Parse for Pattern {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
         match cursor.next_token()? {
-            Token::Mut => {
-                cursor.bump_token()?;
-                if let Token::Identifier(s) = cursor.bump_token()? {
-                    Ok(Self::Identifier(IdentPattern::new_mut(
-                        s.to_string(),
-                    )))
+            Token::LeftParen => {
+                let (mut patterns, is_tuple) = parse_patterns_in_paren(cursor)?;
+                // `(a)` is the same as `a`
+                if patterns.len() == 1 && !is_tuple {
+                    Ok(patterns.pop().unwrap())
                 } else {
-                    Err("expect identifier".into())
+                    Ok(Self::Tuple(patterns))
                 }
             }
-            Token::Identifier(s) => {
-                let s = s.to_string();
+            Token::Mut | Token::Ref => Ok(Self::Identifier(parse_ident_pattern(cursor)?)),
+            Token::Identifier(_) | Token::SelfType => {
+                let path = parse_pattern_path(cursor)?;
+                match cursor.next_token()? {
+                    Token::LeftParen => {
+                        let (patterns, _) = parse_patterns_in_paren(cursor)?;
+                        Ok(Self::TupleStruct(path, patterns))
+                    }
+                    Token::LeftCurlyBraces => Ok(Self::Struct(parse_struct_pattern(cursor, path)?)),
+                    _ if path.segments.len() > 1 || path.segments[0] == "Self" => {
+                        Ok(Self::Path(path))
+                    }
+                    _ if path.segments[0] == "_" => Ok(Self::Wildcard),
+                    _ => Ok(Self::Identifier(IdentPattern::new_const(
+                        path.segments[0].clone(),
+                    ))),
+                }
+            }
+            Token::Literal { .. } | Token::Minus => {
+                let lo = parse_range_pattern_bound(cursor)?;
+                match cursor.next_token() {
+                    Ok(tk) if tk.is_range_op() => {
+                        let op = RangeOp::from_token(cursor.bump_token()?.clone()).unwrap();
+                        let hi = parse_range_pattern_bound(cursor)?;
+                        Ok(Self::Range(Box::new(lo), op, Box::new(hi)))
+                    }
+                    _ => Ok(Self::Literal(Box::new(lo))),
+                }
+            }
+            Token::True | Token::False => {
+                let b = cursor.bump_token()? == &Token::True;
+                Ok(Self::Literal(Box::new(Expr::LitBool(b))))
+            }
+            Token::And => {
                 cursor.bump_token()?;
-                Ok(Self::Identifier(IdentPattern::new_const(s)))
+                let is_mut = cursor.eat_token_if_eq(Token::Mut);
+                Ok(Self::Reference(Box::new(Pattern::parse(cursor)?), is_mut))
+            }
+            Token::AndAnd => {
+                cursor.bump_token()?;
+                let is_mut = cursor.eat_token_if_eq(Token::Mut);
+                let pattern = Self::Reference(Box::new(Pattern::parse(cursor)?), is_mut);
+                Ok(Self::Reference(Box::new(pattern), false))
             }
             _ => Err("invalid pattern".into()),
         }
     }
 }
+
+/// `a`, `mut a`, `ref a` or `ref mut a`
+fn parse_ident_pattern(cursor: &mut ParseCursor) -> Result<IdentPattern, RccError> {
+    let is_ref = cursor.eat_token_if_eq(Token::Ref);
+    let is_mut = cursor.eat_token_if_eq(Token::Mut);
+    let ident = match cursor.bump_token()? {
+        Token::Identifier(s) if *s != "_" => s.to_string(),
+        _ => return Err("expect identifier".into()),
+    };
+    Ok(match (is_ref, is_mut) {
+        (true, _) => IdentPattern::new_ref(ident, is_mut),
+        (false, true) => IdentPattern::new_mut(ident),
+        (false, false) => IdentPattern::new_const(ident),
+    })
+}
+
+fn parse_range_pattern_bound(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+    if cursor.next_token()? == &Token::Minus {
+        let lo = cursor.next_span();
+        cursor.bump_token()?;
+        let lit = parse_literal(cursor)?;
+        if let Expr::LitNum(_) = lit {
+            let mut expr = UnAryExpr::new(UnOp::Neg, lit);
+            expr.span = NodeSpan(lo.to(cursor.prev_span()));
+            Ok(Expr::Unary(expr))
+        } else {
+            Err("invalid pattern".into())
+        }
+    } else {
+        parse_literal(cursor)
+    }
+}
+
+fn parse_struct_pattern(cursor: &mut ParseCursor, path: PathExpr) -> Result<StructPattern, RccError> {
+    cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+    let mut fields = vec![];
+    let mut has_rest = false;
+    while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+        if cursor.eat_token_if_eq(Token::DotDot) {
+            // `..` must be the last one
+            has_rest = true;
+            cursor.eat_token_eq(Token::RightCurlyBraces)?;
+            break;
+        }
+        fields.push(parse_field_pattern(cursor)?);
+        if !cursor.eat_token_if_eq(Token::Comma) {
+            cursor.eat_token_eq(Token::RightCurlyBraces)?;
+            break;
+        }
+    }
+    Ok(StructPattern {
+        path,
+        fields,
+        has_rest,
+    })
+}
+
+fn parse_field_pattern(cursor: &mut ParseCursor) -> Result<FieldPattern, RccError> {
+    if let Token::Identifier(name) = cursor.next_token()? {
+        let name = name.to_string();
+        cursor.bump_token()?;
+        if cursor.eat_token_if_eq(Token::Colon) {
+            let pattern = Pattern::parse(cursor)?;
+            return Ok(FieldPattern { name, pattern });
+        }
+        let pattern = Pattern::Identifier(IdentPattern::new_const(name.clone()));
+        return Ok(FieldPattern { name, pattern });
+    }
+    let ident_pattern = parse_ident_pattern(cursor)?;
+    Ok(FieldPattern {
+        name: ident_pattern.ident().to_string(),
+        pattern: Pattern::Identifier(ident_pattern),
+    })
+}
+
+/// Unlike `PathExpr::parse`, the token following the path is not checked,
+/// so that `(a b)` is reported as a missing `)`.
+fn parse_pattern_path(cursor: &mut ParseCursor) -> Result<PathExpr, RccError> {
+    let mut path = PathExpr::new();
+    let lo = cursor.next_span();
+    loop {
+        match cursor.bump_token()? {
+            Token::Identifier(s) => path.segments.push(s.to_string()),
+            Token::SelfType => path.segments.push("Self".to_string()),
+            _ => return Err("invalid path".into()),
+        }
+        if !cursor.eat_token_if_eq(Token::PathSep) {
+            break;
+        }
+    }
+    path.span = NodeSpan(lo.to(cursor.prev_span()));
+    Ok(path)
+}
+
+/// Return the patterns and whether there is a comma.
+fn parse_patterns_in_paren(cursor: &mut ParseCursor) -> Result<(Vec<Pattern>, bool), RccError> {
+    cursor.eat_token_eq(Token::LeftParen)?;
+    let mut patterns = vec![];
+    let mut has_comma = false;
+    while !cursor.eat_token_if_eq(Token::RightParen) {
+        patterns.push(Pattern::parse(cursor)?);
+        if cursor.eat_token_if_eq(Token::Comma) {
+            has_comma = true;
+        } else {
+            cursor.eat_token_eq(Token::RightParen)?;
+            break;
+        }
+    }
+    Ok((patterns, has_comma))
+}
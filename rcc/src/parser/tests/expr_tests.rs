@@ -2,12 +2,17 @@ use crate::ast::expr::Expr::*;
 use crate::ast::expr::RangeOp::{DotDot, DotDotEq};
 use crate::ast::expr::UnOp::{Borrow, BorrowMut};
 use crate::ast::expr::{
-    AssignExpr, AssignOp, BinOpExpr, BinOperator, BlockExpr, CallExpr, Expr, FieldAccessExpr,
-    GroupedExpr, IfExpr, LhsExpr, PathExpr, RangeExpr, ReturnExpr, TupleExpr,
+    ArrayExpr, ArrayIndexExpr, AssignExpr, AssignOp, BinOpExpr, BinOperator, BlockExpr, BreakExpr, CallExpr, CastExpr,
+    ClosureExpr, ContinueExpr, Expr, FieldAccessExpr, ForExpr,
+    GroupedExpr, IfExpr, LhsExpr, LoopExpr, MethodCallExpr, PathExpr, RangeExpr, ReturnExpr, StructExpr, StructExprField,
+    TupleExpr, TupleIndexExpr,
 };
 use crate::ast::expr::{LitNumExpr, UnAryExpr, UnOp};
+use crate::ast::item::{FnParam, ItemFn};
+use crate::ast::pattern::{IdentPattern, Pattern};
 use crate::ast::stmt::Stmt;
-use crate::ast::types::TypeLitNum;
+use crate::ast::types::{TypeAnnotation, TypeLitNum};
+use crate::ast::Visibility::Priv;
 use crate::parser::tests::{parse_validate, parse_input, expected_from_file};
 use crate::rcc::RccError;
 use crate::tests::assert_pretty_fmt_eq;
@@ -128,10 +133,36 @@ fn left_paren_test() {
     parse_validate(
         vec!["('1',)", "(1)", "(1,2)", "(1,22,)"],
         vec![
-            Ok(Tuple(TupleExpr(vec![LitChar('1')]))),
+            Ok(Tuple(TupleExpr::new(vec![LitChar('1')]))),
             Ok(Grouped(GroupedExpr::new(LitNum(1.into())))),
-            Ok(Tuple(TupleExpr(vec![LitNum(1.into()), LitNum(2.into())]))),
-            Ok(Tuple(TupleExpr(vec![LitNum(1.into()), LitNum(22.into())]))),
+            Ok(Tuple(TupleExpr::new(vec![LitNum(1.into()), LitNum(2.into())]))),
+            Ok(Tuple(TupleExpr::new(vec![LitNum(1.into()), LitNum(22.into())]))),
+        ],
+    );
+}
+
+#[test]
+fn tuple_index_expr_test() {
+    parse_validate(
+        vec!["a.0", "a.1.0 = 2", "(1, 2).1.x", "a.1u8"],
+        vec![
+            Ok(TupleIndex(TupleIndexExpr::new("a".into(), 0))),
+            Ok(Assign(AssignExpr::new(
+                LhsExpr::TupleIndex(TupleIndexExpr::new(
+                    TupleIndex(TupleIndexExpr::new("a".into(), 1)),
+                    0,
+                )),
+                AssignOp::Eq,
+                LitNum(2.into()),
+            ))),
+            Ok(FieldAccess(FieldAccessExpr::new(
+                TupleIndex(TupleIndexExpr::new(
+                    Tuple(TupleExpr::new(vec![LitNum(1.into()), LitNum(2.into())])),
+                    1,
+                )),
+                "x".into(),
+            ))),
+            Err("invalid tuple index `1`".into()),
         ],
     );
 }
@@ -206,10 +237,47 @@ fn if_expr_test() {
 #[test]
 fn call_expr_test() {
     parse_validate(
-        vec!["1.hello()()"],
-        vec![Ok(Call(CallExpr::new(Call(CallExpr::new(FieldAccess(
-            FieldAccessExpr::new(LitNum(1.into()), "hello".into()),
-        ))))))],
+        vec!["1.hello()()", "a.b.get(1, 2)", "(a.f)()", "self.x", "Self::new()"],
+        vec![
+            Ok(Call(CallExpr::new(MethodCall(MethodCallExpr::new(
+                LitNum(1.into()),
+                "hello".into(),
+                vec![],
+            ))))),
+            Ok(MethodCall(MethodCallExpr::new(
+                FieldAccess(FieldAccessExpr::new("a".into(), "b".into())),
+                "get".into(),
+                vec![LitNum(1.into()), LitNum(2.into())],
+            ))),
+            Ok(Call(CallExpr::new(Grouped(GroupedExpr::new(FieldAccess(
+                FieldAccessExpr::new("a".into(), "f".into()),
+            )))))),
+            Ok(FieldAccess(FieldAccessExpr::new("self".into(), "x".into()))),
+            Ok(Call(CallExpr::new(Path(vec!["Self", "new"].into())))),
+        ],
+    );
+}
+
+#[test]
+fn struct_expr_test() {
+    parse_validate(
+        vec!["Foo { a: 1, b }", "a::Foo {}", "if a {}", "Foo { a: 1 b }"],
+        vec![
+            Ok(Struct(StructExpr::new(
+                vec!["Foo"].into(),
+                vec![
+                    StructExprField::new("a".into(), LitNum(1.into())),
+                    StructExprField::new("b".into(), "b".into()),
+                ],
+            ))),
+            Ok(Struct(StructExpr::new(vec!["a", "Foo"].into(), vec![]))),
+            // `a {}` is not a struct literal in the condition
+            Ok(If(IfExpr::from_exprs(
+                vec!["a".into()],
+                vec![BlockExpr::new(0)],
+            ))),
+            Err("error in parsing: except }".into()),
+        ],
     );
 }
 
@@ -218,7 +286,7 @@ fn place_expr_test() {
     let expecteds: Vec<Result<Expr, RccError>> = vec![
         Err("invalid lhs expr".into()),
         Ok(Expr::Assign(AssignExpr::new(
-            LhsExpr::Deref(Box::new("a".into())),
+            LhsExpr::Deref(UnAryExpr::new(UnOp::Deref, "a".into())),
             AssignOp::Eq,
             Expr::LitNum(4.into()),
         ))),
@@ -236,3 +304,198 @@ fn array_expr_test() {
     let expected = expected_from_file("array_expr_test.txt");
     assert_pretty_fmt_eq(&expected, &result.unwrap());
 }
+
+#[test]
+fn array_index_expr_test() {
+    parse_validate(
+        vec!["[1, 2,]", "[]", "a[1][i]", "[1 2]", "[1, 2; 3]"],
+        vec![
+            Ok(Array(ArrayExpr::elems(vec![
+                LitNum(1.into()),
+                LitNum(2.into()),
+            ]))),
+            Ok(Array(ArrayExpr::elems(vec![]))),
+            Ok(ArrayIndex(ArrayIndexExpr::new(
+                ArrayIndex(ArrayIndexExpr::new("a".into(), LitNum(1.into()))),
+                "i".into(),
+            ))),
+            Err("expected `,`".into()),
+            Err("length of elems should be 1".into()),
+        ],
+    );
+}
+
+#[test]
+fn match_expr_test() {
+    let result = parse_input::<Expr>(
+        r#"
+        match s {
+            Shape::Rect(w, _) if w > 0 => w,
+            Shape::Dot => { 0 }
+            (-1, true) => 1,
+            _ => 2
+        }
+    "#,
+    );
+    let expected = expected_from_file("match_expr_test.txt");
+    assert_pretty_fmt_eq(&expected, &result.unwrap());
+    parse_validate::<Expr>(
+        vec!["match a { 1 => 2 3 => 4 }"],
+        vec![Err("error in parsing: except }".into())],
+    );
+}
+
+#[test]
+fn closure_expr_test() {
+    let add = ItemFn::new(
+        Priv,
+        "{{closure}}".into(),
+        vec![
+            FnParam::new(
+                Pattern::Identifier(IdentPattern::new_const("x".into())),
+                TypeAnnotation::Unknown,
+            ),
+            FnParam::new(
+                Pattern::Identifier(IdentPattern::new_const("y".into())),
+                TypeAnnotation::Identifier("i32".into()),
+            ),
+        ]
+        .into(),
+        TypeAnnotation::Unknown,
+        BlockExpr::new(0).expr_without_block(BinOp(BinOpExpr::new(
+            Path("x".into()),
+            BinOperator::Plus,
+            Path("y".into()),
+        ))),
+    );
+    let one = ItemFn::new(
+        Priv,
+        "{{closure}}".into(),
+        vec![].into(),
+        TypeAnnotation::Identifier("i32".into()),
+        BlockExpr::new(0).expr_without_block(LitNum(1.into())),
+    );
+    parse_validate(
+        vec!["|x, y: i32| x + y", "move || -> i32 { 1 }", "|x| -> i32 x"],
+        vec![
+            Ok(Closure(ClosureExpr::new(false, add))),
+            Ok(Closure(ClosureExpr::new(true, one))),
+            Err("error in parsing: except {".into()),
+        ],
+    );
+}
+
+#[test]
+fn for_expr_test() {
+    let i = || Pattern::Identifier(IdentPattern::new_const("i".into()));
+    let range = |op| RangeExpr::new(op).lhs(LitNum(0.into())).rhs(Path("n".into()));
+    parse_validate(
+        vec![
+            "for i in 0..n {}",
+            "for i in (0..=n).rev() {}",
+            "for _ in 0.. {}",
+            "for i 0..n {}",
+        ],
+        vec![
+            Ok(For(ForExpr::new(i(), Range(range(DotDot)), BlockExpr::new(0)))),
+            Ok(For(ForExpr::new(
+                i(),
+                MethodCall(MethodCallExpr::new(
+                    Grouped(GroupedExpr::new(Range(range(DotDotEq)))),
+                    "rev".into(),
+                    vec![],
+                )),
+                BlockExpr::new(0),
+            ))),
+            Ok(For(ForExpr::new(
+                Pattern::Wildcard,
+                Range(RangeExpr::new(DotDot).lhs(LitNum(0.into()))),
+                BlockExpr::new(0),
+            ))),
+            Err("error in parsing: except in".into()),
+        ],
+    );
+}
+
+#[test]
+fn label_test() {
+    let label = || Some("'a".to_string());
+    let mut loop_expr = LoopExpr::new(BlockExpr::new(0));
+    loop_expr.label = label();
+    let mut for_expr = ForExpr::new(
+        Pattern::Wildcard,
+        Range(RangeExpr::new(DotDot).lhs(LitNum(0.into()))),
+        BlockExpr::new(0),
+    );
+    for_expr.label = label();
+    parse_validate(
+        vec![
+            "'a: loop {}",
+            "'a: for _ in 0.. {}",
+            "break 'a 1",
+            "break 'a",
+            "break",
+            "continue 'a",
+            "continue",
+            "'a: {}",
+        ],
+        vec![
+            Ok(Loop(loop_expr)),
+            Ok(For(for_expr)),
+            Ok(Break(BreakExpr {
+                label: label(),
+                expr: Some(Box::new(LitNum(1.into()))),
+            })),
+            Ok(Break(BreakExpr {
+                label: label(),
+                expr: None,
+            })),
+            Ok(Break(BreakExpr {
+                label: None,
+                expr: None,
+            })),
+            Ok(Continue(ContinueExpr(label()))),
+            Ok(Continue(ContinueExpr(None))),
+            Err("expected `while`, `loop` or `for` after a label, found `{`".into()),
+        ],
+    );
+}
+
+#[test]
+fn unsafe_block_test() {
+    let mut block_expr = BlockExpr::new(0);
+    block_expr.is_unsafe = true;
+    parse_validate(
+        vec!["unsafe {}", "unsafe 1"],
+        vec![
+            Ok(Block(block_expr)),
+            Err("error in parsing: except {".into()),
+        ],
+    );
+}
+
+#[test]
+fn cast_expr_test() {
+    let cast = |expr, type_anno: &str| {
+        Cast(CastExpr::new(expr, TypeAnnotation::Identifier(type_anno.into())))
+    };
+    parse_validate(
+        vec!["a as u8", "-1 as u8 as i32", "a + b as i64", "a as"],
+        vec![
+            Ok(cast(Path(vec!["a"].into()), "u8")),
+            Ok(cast(
+                cast(
+                    Unary(UnAryExpr::new(UnOp::Neg, LitNum(1.into()))),
+                    "u8",
+                ),
+                "i32",
+            )),
+            Ok(BinOp(BinOpExpr::new(
+                Path(vec!["a"].into()),
+                BinOperator::Plus,
+                cast(Path(vec!["b"].into()), "i64"),
+            ))),
+            Err("EOF token".into()),
+        ],
+    );
+}
@@ -5,6 +5,8 @@ use crate::ast::file::File;
 use crate::ast::item::{FnParams, Item, ItemFn};
 use crate::ast::types::{TypeLitNum, TypeAnnotation};
 use crate::ast::Visibility::Priv;
+use crate::lexer::token::Span;
+use crate::rcc::RccError;
 
 use super::parse_input;
 
@@ -20,3 +22,48 @@ fn file_test() {
     ))]));
     assert_eq!(excepted, result);
 }
+
+#[test]
+fn recovery_test() {
+    let result = parse_input::<File>(
+        r#"fn main() {
+    let a = ;
+    let b = 1
+    let c = 2;
+    )
+}
+fn foo(a i32) { a }
+struct
+fn bar() {}
+"#,
+    );
+    let expected = Err(RccError::Multiple(vec![
+        RccError::with_span("expected start token of Expr", Span::new(24, 25)),
+        RccError::with_span("error in parsing: except ;", Span::new(44, 47)),
+        RccError::with_span("expected statement, found `)`", Span::new(59, 60)),
+        RccError::with_span("error in parsing: except :", Span::new(72, 75)),
+        RccError::with_span("no identifier for struct", Span::new(90, 92)),
+    ]));
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn recovery_in_nested_item_test() {
+    let result = parse_input::<File>(
+        r#"impl S {
+    fn m(&self, ) -> { 1 }
+    fn n(&self) -> i32 { self.a }
+}
+struct S { a: i32 b: i32 }
+fn f(x: i32) -> i32 {
+    match x { 1 => { x + } _ => 0 }
+}
+"#,
+    );
+    let expected = Err(RccError::Multiple(vec![
+        RccError::with_span("invalid token `{` for type annotation", Span::new(32, 33)),
+        RccError::with_span("invalid struct field: except ','", Span::new(91, 92)),
+        RccError::with_span("expected expression, found `}`", Span::new(146, 147)),
+    ]));
+    assert_eq!(expected, result);
+}
@@ -1,9 +1,12 @@
-use crate::ast::expr::Expr::{BinOp, LitNum};
+use crate::ast::expr::Expr::{BinOp, LitNum, Path};
 use crate::ast::expr::{BinOpExpr, BinOperator, BlockExpr};
-use crate::ast::item::{FnParam, FnParams, Item, ItemExternalBlock, ItemFn};
+use crate::ast::item::{
+    EnumVariant, Fields, FnDecl, FnParam, FnParams, GenericParam, Item, ItemExternalBlock, ItemFn,
+    ItemImpl, ItemStruct, ItemTrait, StructField, TraitItemFn, TupleField, TypeEnum,
+};
 use crate::ast::pattern::{IdentPattern, Pattern};
-use crate::ast::types::TypeAnnotation;
-use crate::ast::Visibility::Priv;
+use crate::ast::types::{PtrKind, TypeAnnotation, TypeFnPtr, TypeGeneric, TypePtr};
+use crate::ast::Visibility::{Priv, Pub};
 use crate::parser::tests::{expected_from_file, parse_input, parse_validate};
 use crate::tests::assert_pretty_fmt_eq;
 
@@ -72,3 +75,297 @@ fn item_external_block_test() {
     let expected = expected_from_file("item_external_block.txt");
     assert_pretty_fmt_eq(&expected, &result.unwrap());
 }
+
+#[test]
+fn item_enum_test() {
+    let i32_type = || TypeAnnotation::Identifier("i32".into());
+    parse_validate(
+        vec![
+            "pub enum Shape { Dot, Rect(i32, i32), Square { side: i32 }, }",
+            "enum Never {}",
+            "enum Color { Red Green }",
+        ],
+        vec![
+            Ok(Item::Enum(TypeEnum::new(Pub, "Shape".into()).enum_items(vec![
+                EnumVariant::new("Dot".into(), Fields::None),
+                EnumVariant::new(
+                    "Rect".into(),
+                    Fields::Tuple(vec![
+                        TupleField { vis: Priv, _type: i32_type() },
+                        TupleField { vis: Priv, _type: i32_type() },
+                    ]),
+                ),
+                EnumVariant::new(
+                    "Square".into(),
+                    Fields::Struct(vec![StructField {
+                        vis: Priv,
+                        name: "side".into(),
+                        _type: i32_type(),
+                    }]),
+                ),
+            ]))),
+            Ok(Item::Enum(TypeEnum::new(Priv, "Never".into()))),
+            Err("error in parsing: except }".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_impl_test() {
+    let param = |pattern, _type| FnParam::new(Pattern::Identifier(pattern), _type);
+    let ref_self = |kind| TypeAnnotation::Ptr(TypePtr::new(kind, "Self".into()));
+    parse_validate(
+        vec![
+            r##"
+                impl Foo {
+                    fn new() -> Self { 0 }
+                    pub fn get(&self) -> i32 { 0 }
+                    fn set(&mut self, a: i32,) {}
+                    fn into(mut self) {}
+                }
+            "##,
+            "fn get(&self) {}",
+            "pub impl Foo {}",
+            "impl Foo { struct Bar; }",
+        ],
+        vec![
+            Ok(Item::Impl(ItemImpl::new(
+                "Foo".into(),
+                vec![
+                    ItemFn::new(
+                        Priv,
+                        "new".into(),
+                        FnParams::new(),
+                        "Self".into(),
+                        BlockExpr::new(0).expr_without_block(LitNum(0.into())),
+                    ),
+                    ItemFn::new(
+                        Pub,
+                        "get".into(),
+                        vec![param(
+                            IdentPattern::new_const("self".into()),
+                            ref_self(PtrKind::Ref),
+                        )]
+                        .into(),
+                        "i32".into(),
+                        BlockExpr::new(0).expr_without_block(LitNum(0.into())),
+                    ),
+                    ItemFn::new(
+                        Priv,
+                        "set".into(),
+                        vec![
+                            param(
+                                IdentPattern::new_const("self".into()),
+                                ref_self(PtrKind::MutRef),
+                            ),
+                            param(IdentPattern::new_const("a".into()), "i32".into()),
+                        ]
+                        .into(),
+                        TypeAnnotation::Unit,
+                        BlockExpr::new(0),
+                    ),
+                    ItemFn::new(
+                        Priv,
+                        "into".into(),
+                        vec![param(
+                            IdentPattern::new_mut("self".into()),
+                            "Self".into(),
+                        )]
+                        .into(),
+                        TypeAnnotation::Unit,
+                        BlockExpr::new(0),
+                    ),
+                ],
+                0,
+            ))),
+            Err("`self` parameter is only allowed in associated functions".into()),
+            Err("unnecessary visibility qualifier".into()),
+            Err("expected associated function, found `struct`".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_trait_test() {
+    let self_param = || {
+        FnParam::new(
+            Pattern::Identifier(IdentPattern::new_const("self".into())),
+            TypeAnnotation::Ptr(TypePtr::new(PtrKind::Ref, "Self".into())),
+        )
+    };
+    let area = || {
+        ItemFn::new(
+            Priv,
+            "area".into(),
+            vec![self_param()].into(),
+            "i32".into(),
+            BlockExpr::new(0).expr_without_block(LitNum(0.into())),
+        )
+    };
+    parse_validate(
+        vec![
+            r##"
+                pub trait Shape {
+                    fn area(&self) -> i32;
+                    fn double(&self) -> i32 { 0 }
+                }
+            "##,
+            "impl Shape for Rect { fn area(&self) -> i32 { 0 } }",
+            "trait Shape { pub fn area(&self) -> i32; }",
+            "trait Shape { struct Foo; }",
+            "impl<T> Shape for T {}",
+        ],
+        vec![
+            Ok(Item::Trait(ItemTrait::new(
+                Pub,
+                "Shape".into(),
+                vec![
+                    TraitItemFn::Required(FnDecl::new(
+                        "area".into(),
+                        vec![self_param()].into(),
+                        "i32".into(),
+                    )),
+                    TraitItemFn::Provided(Box::new(ItemFn::new(
+                        Priv,
+                        "double".into(),
+                        vec![self_param()].into(),
+                        "i32".into(),
+                        BlockExpr::new(0).expr_without_block(LitNum(0.into())),
+                    ))),
+                ],
+                0,
+            ))),
+            Ok(Item::Impl(
+                ItemImpl::new("Rect".into(), vec![area()], 0).for_trait("Shape".into()),
+            )),
+            Err("unnecessary visibility qualifier".into()),
+            Err("expected associated function, found `struct`".into()),
+            Err("generic impl blocks are not supported".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_generics_test() {
+    let max = || {
+        let param = |name: &str| {
+            FnParam::new(
+                Pattern::Identifier(IdentPattern::new_const(name.into())),
+                "T".into(),
+            )
+        };
+        let mut max = ItemFn::new(
+            Priv,
+            "max".into(),
+            vec![param("a"), param("b")].into(),
+            "T".into(),
+            BlockExpr::new(0).expr_without_block(Path("a".into())),
+        );
+        max.generics = vec![GenericParam::new(
+            "T".into(),
+            vec!["Ord".into(), "Copy".into()],
+        )];
+        max
+    };
+    let pair = ItemStruct::new(Priv, "Pair".into())
+        .generic_params(vec![
+            GenericParam::new("A".into(), vec![]),
+            GenericParam::new("B".into(), vec!["Num".into()]),
+        ])
+        .struct_fields(vec![
+            StructField {
+                vis: Priv,
+                name: "a".into(),
+                _type: "A".into(),
+            },
+            StructField {
+                vis: Priv,
+                name: "b".into(),
+                _type: TypeAnnotation::Generic(TypeGeneric::new(
+                    "Pair".into(),
+                    vec!["B".into(), "i32".into()],
+                )),
+            },
+        ]);
+    parse_validate(
+        vec![
+            "fn max<T: Ord + Copy>(a: T, b: T) -> T { a }",
+            "fn max<T>(a: T, b: T) -> T where T: Ord + Copy { a }",
+            "struct Pair<A, B> where B: Num { a: A, b: Pair<B, i32> }",
+            "fn max<T, T>() {}",
+            "fn max<T>() where U: Ord {}",
+        ],
+        vec![
+            Ok(Item::Fn(max())),
+            Ok(Item::Fn(max())),
+            Ok(Item::Struct(pair)),
+            Err("the name `T` is already used for a generic parameter".into()),
+            Err("cannot find type `U` in this scope".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_trait_object_test() {
+    let area = ItemFn::new(
+        Priv,
+        "area".into(),
+        vec![FnParam::new(
+            Pattern::Identifier(IdentPattern::new_const("s".into())),
+            TypeAnnotation::Ptr(TypePtr::new(
+                PtrKind::Ref,
+                TypeAnnotation::TraitObject("Shape".into()),
+            )),
+        )]
+        .into(),
+        TypeAnnotation::Ptr(TypePtr::new(
+            PtrKind::MutRef,
+            TypeAnnotation::TraitObject("Shape".into()),
+        )),
+        BlockExpr::new(0).expr_without_block(Path("s".into())),
+    );
+    parse_validate(
+        vec!["fn area(s: &dyn Shape) -> &mut dyn Shape { s }", "fn area(s: &dyn) {}"],
+        vec![
+            Ok(Item::Fn(area)),
+            Err("invalid token `)` for trait object type".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_fn_ptr_test() {
+    let apply = ItemFn::new(
+        Priv,
+        "apply".into(),
+        vec![
+            FnParam::new(
+                Pattern::Identifier(IdentPattern::new_const("f".into())),
+                TypeAnnotation::FnPtr(TypeFnPtr::new(
+                    vec![
+                        TypeAnnotation::Identifier("i32".into()),
+                        TypeAnnotation::Identifier("bool".into()),
+                    ],
+                    TypeAnnotation::Identifier("i32".into()),
+                )),
+            ),
+            FnParam::new(
+                Pattern::Identifier(IdentPattern::new_const("g".into())),
+                TypeAnnotation::FnPtr(TypeFnPtr::new(vec![], TypeAnnotation::Unit)),
+            ),
+        ]
+        .into(),
+        TypeAnnotation::Unit,
+        BlockExpr::new(0),
+    );
+    parse_validate(
+        vec![
+            "fn apply(f: fn(i32, bool) -> i32, g: fn()) {}",
+            "fn apply(f: fn i32) {}",
+        ],
+        vec![
+            Ok(Item::Fn(apply)),
+            Err("error in parsing: except (".into()),
+        ],
+    );
+}
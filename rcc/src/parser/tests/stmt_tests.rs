@@ -1,10 +1,12 @@
-use crate::ast::expr::Expr::{Block, LitBool, Loop};
-use crate::ast::expr::UnOp::Borrow;
-use crate::ast::expr::{BlockExpr, Expr, LoopExpr, UnAryExpr, UnOp};
-use crate::ast::pattern::IdentPattern;
-use crate::ast::pattern::Pattern::Identifier;
+use crate::ast::expr::Expr::LitBool;
+use crate::ast::expr::{BlockExpr, Expr, LoopExpr, TupleExpr, UnAryExpr, UnOp};
+use crate::ast::expr::RangeOp;
+use crate::ast::pattern::Pattern::{Identifier, Tuple};
+use crate::ast::pattern::{FieldPattern, IdentPattern, Pattern, StructPattern};
 use crate::ast::stmt::Stmt::ExprStmt;
 use crate::ast::stmt::{LetStmt, Stmt};
+use crate::ast::types::{PtrKind, TypeAnnotation, TypeArray, TypePtr};
+use crate::lexer::token::Span;
 use crate::parser::stmt::{parse_stmt_or_expr_without_block, StmtOrExpr};
 use crate::parser::tests::{get_parser, parse_validate};
 use crate::parser::Parse;
@@ -44,15 +46,74 @@ fn let_stmt_test() {
     validate(inputs, outputs);
 }
 
+#[test]
+fn let_array_stmt_test() {
+    let inputs = vec!["let a: [i32; 3];", "let b: &[[u8; 2]; 4];", "let c: [i32; n];"];
+    let outputs = vec![
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Identifier(IdentPattern::new_const("a".into())))
+                ._type(TypeAnnotation::Array(TypeArray::new("i32".into(), 3))),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Identifier(IdentPattern::new_const("b".into())))._type(
+                TypeAnnotation::Ptr(TypePtr::new(
+                    PtrKind::Ref,
+                    TypeAnnotation::Array(TypeArray::new(
+                        TypeAnnotation::Array(TypeArray::new("u8".into(), 2)),
+                        4,
+                    )),
+                )),
+            ),
+        ))),
+        Err(RccError::with_span(
+            "error in parsing: except literal",
+            Span::new(13, 14),
+        )),
+    ];
+    validate(inputs, outputs);
+}
+
+#[test]
+fn let_tuple_stmt_test() {
+    let inputs = vec![
+        "let (a, (mut b,)): (i32, (bool,)) = t;",
+        "let (c): () = ();",
+        "let (d e) = t;",
+    ];
+    let outputs = vec![
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Tuple(vec![
+                Identifier(IdentPattern::new_const("a".into())),
+                Tuple(vec![Identifier(IdentPattern::new_mut("b".into()))]),
+            ]))
+            ._type(TypeAnnotation::Tuple(vec![
+                "i32".into(),
+                TypeAnnotation::Tuple(vec!["bool".into()]),
+            ]))
+            .expr("t".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Identifier(IdentPattern::new_const("c".into())))
+                ._type(TypeAnnotation::Unit)
+                .expr(Expr::Tuple(TupleExpr::new(vec![]))),
+        ))),
+        Err(RccError::with_span(
+            "error in parsing: except )",
+            Span::new(7, 8),
+        )),
+    ];
+    validate(inputs, outputs);
+}
+
 #[test]
 fn not_end_with_semicolon() {
     validate(
         vec![";", "let a=1", "let a: i32 = 4", "let mut bbb"],
         vec![
             Ok(StmtOrExpr::Stmt(Stmt::Semi)),
-            Err("EOF token".into()),
-            Err("EOF token".into()),
-            Err("EOF token".into()),
+            Err(RccError::with_span("EOF token", Span::new(7, 7))),
+            Err(RccError::with_span("EOF token", Span::new(14, 14))),
+            Err(RccError::with_span("EOF token", Span::new(11, 11))),
         ],
     );
 }
@@ -69,3 +130,64 @@ fn expr_stmt_test() {
         ))
     );
 }
+
+#[test]
+fn pattern_test() {
+    parse_validate(
+        vec![
+            "&mut (a, ref b)",
+            "&&ref mut c",
+            "Point { x, y: 0, mut z, .. }",
+            "-1..=5",
+            "'a'..'z'",
+            "Point { .., x }",
+            "ref _",
+        ],
+        vec![
+            Ok(Pattern::Reference(
+                Box::new(Tuple(vec![
+                    Identifier(IdentPattern::new_const("a".into())),
+                    Identifier(IdentPattern::new_ref("b".into(), false)),
+                ])),
+                true,
+            )),
+            Ok(Pattern::Reference(
+                Box::new(Pattern::Reference(
+                    Box::new(Identifier(IdentPattern::new_ref("c".into(), true))),
+                    false,
+                )),
+                false,
+            )),
+            Ok(Pattern::Struct(StructPattern {
+                path: vec!["Point"].into(),
+                fields: vec![
+                    FieldPattern {
+                        name: "x".into(),
+                        pattern: Identifier(IdentPattern::new_const("x".into())),
+                    },
+                    FieldPattern {
+                        name: "y".into(),
+                        pattern: Pattern::Literal(Box::new(Expr::LitNum(0.into()))),
+                    },
+                    FieldPattern {
+                        name: "z".into(),
+                        pattern: Identifier(IdentPattern::new_mut("z".into())),
+                    },
+                ],
+                has_rest: true,
+            })),
+            Ok(Pattern::Range(
+                Box::new(Expr::Unary(UnAryExpr::new(UnOp::Neg, Expr::LitNum(1.into())))),
+                RangeOp::DotDotEq,
+                Box::new(Expr::LitNum(5.into())),
+            )),
+            Ok(Pattern::Range(
+                Box::new(Expr::LitChar('a')),
+                RangeOp::DotDot,
+                Box::new(Expr::LitChar('z')),
+            )),
+            Err("error in parsing: except }".into()),
+            Err("expect identifier".into()),
+        ],
+    );
+}
@@ -1,11 +1,13 @@
-use crate::ast::item::{StructField, TupleField, TypeEnum};
-use crate::ast::types::TypeAnnotation::Ptr;
+use crate::ast::item::{StructField, TupleField};
 use crate::ast::types::{
-    PtrKind, TypeAnnotation, TypeArray, TypeFnPtr, TypePtr, TypeSlice, TypeTuple,
+    PtrKind, TypeAnnotation, TypeArray, TypeFnPtr, TypeGeneric, TypePtr, TypeSlice, TypeTuple,
 };
 use crate::ast::Visibility;
-use crate::lexer::token::Token;
-use crate::lexer::token::Token::{Comma, LeftParen, RightParen, Semi};
+use crate::lexer::token::Token::{
+    Colon, Comma, LeftCurlyBraces, LeftParen, RightCurlyBraces, RightParen, RightSquareBrackets,
+    Semi,
+};
+use crate::lexer::token::{LiteralKind, Token};
 use crate::parser::{Parse, ParseCursor};
 use crate::rcc::RccError;
 
@@ -16,17 +18,59 @@ enum TypeArrayOrSlice {
     Slice(TypeSlice),
 }
 
+/// TypeArrayOrSlice -> Type `;` IntegerLiteral `]` | Type `]`
 impl Parse for TypeArrayOrSlice {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        unimplemented!()
+        let _type = TypeAnnotation::parse(cursor)?;
+        match cursor.bump_token()? {
+            Semi => {
+                let len = match cursor.eat_literal()? {
+                    (LiteralKind::Integer { .. }, value) => value
+                        .parse::<u32>()
+                        .map_err(|_| RccError::from("invalid array length"))?,
+                    _ => return Err("invalid array length: expected integer literal".into()),
+                };
+                cursor.eat_token_eq(RightSquareBrackets)?;
+                Ok(Self::Array(TypeArray::new(_type, len)))
+            }
+            RightSquareBrackets => Ok(Self::Slice(Box::new(_type))),
+            tk => Err(format!("invalid token `{}` for array type", tk.source()).into()),
+        }
     }
 }
 
 impl Parse for TypeAnnotation {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
         match cursor.bump_token()? {
-            Token::Identifier(s) => Ok(Self::Identifier(s.to_string())),
-            Token::LeftParen => Ok(Self::Tuple(TypeTuple::parse(cursor)?)),
+            Token::Identifier(s) => {
+                let name = s.to_string();
+                if cursor.eat_token_if_eq(Token::Lt) {
+                    let args = parse_generic_args(cursor)?;
+                    Ok(Self::Generic(TypeGeneric::new(name, args)))
+                } else {
+                    Ok(Self::Identifier(name))
+                }
+            }
+            Token::SelfType => Ok(Self::Identifier("Self".into())),
+            Token::LeftParen => {
+                // `()`, `(T)` or `(T, ...)`
+                let mut types = vec![];
+                let mut is_tuple = false;
+                while !cursor.eat_token_if_eq(RightParen) {
+                    types.push(TypeAnnotation::parse(cursor)?);
+                    if cursor.eat_token_if_eq(Comma) {
+                        is_tuple = true;
+                    } else {
+                        cursor.eat_token_eq(RightParen)?;
+                        break;
+                    }
+                }
+                Ok(match types.len() {
+                    0 => Self::Unit,
+                    1 if !is_tuple => types.pop().unwrap(),
+                    _ => Self::Tuple(types),
+                })
+            }
             Token::LeftSquareBrackets => {
                 let arr_or_slice = TypeArrayOrSlice::parse(cursor)?;
                 match arr_or_slice {
@@ -34,32 +78,83 @@ impl Parse for TypeAnnotation {
                     TypeArrayOrSlice::Slice(slice) => Ok(Self::Slice(slice)),
                 }
             }
+            Token::Dyn => match cursor.bump_token()? {
+                Token::Identifier(s) => Ok(Self::TraitObject(s.to_string())),
+                tk => Err(format!("invalid token `{}` for trait object type", tk.source()).into()),
+            },
             Token::Fn => Ok(Self::FnPtr(TypeFnPtr::parse(cursor)?)),
             Token::Not => Ok(Self::Never),
             tk if matches!(tk, Token::And | Token::AndAnd | Token::Star) => {
                 let tk = tk.clone();
                 Ok(Self::Ptr(TypePtr::parse_from_first(cursor, tk)?))
             }
-            tk => Err(format!("invalid token `{:?}` for type annotation", tk).into()),
+            tk => Err(format!("invalid token `{}` for type annotation", tk.source()).into()),
         }
     }
 }
 
+/// GenericArgs -> `<` ( Type `,` )* Type? `>`
+///
+/// The leading `<` has been eaten. The `>>` in `Pair<Pair<i32>>` is split into two `>`.
+pub(crate) fn parse_generic_args(cursor: &mut ParseCursor) -> Result<Vec<TypeAnnotation>, RccError> {
+    let mut args = vec![];
+    loop {
+        match cursor.next_token()? {
+            Token::Gt => {
+                cursor.bump_token()?;
+                break;
+            }
+            Token::Shr => {
+                let tk = &mut cursor.token_stream[cursor.token_idx];
+                tk.token = Token::Gt;
+                tk.span.lo += 1;
+                break;
+            }
+            _ => {}
+        }
+        args.push(TypeAnnotation::parse(cursor)?);
+        if !cursor.eat_token_if_eq(Comma) && !matches!(cursor.next_token()?, Token::Gt | Token::Shr) {
+            return Err("invalid generic arguments: expected `,` or `>`".into());
+        }
+    }
+    if args.is_empty() {
+        return Err("expected generic arguments".into());
+    }
+    Ok(args)
+}
+
 impl Parse for TypeTuple {
-    fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+    fn parse(_cursor: &mut ParseCursor) -> Result<Self, RccError> {
         unimplemented!()
     }
 }
 
 impl Parse for TypeArray {
-    fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+    fn parse(_cursor: &mut ParseCursor) -> Result<Self, RccError> {
         unimplemented!()
     }
 }
 
+/// TypeFnPtr -> `fn` `(` ( Type `,` )* Type? `)` ( `->` Type )?
+///
+/// The leading `fn` has been eaten.
 impl Parse for TypeFnPtr {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        unimplemented!()
+        cursor.eat_token_eq(LeftParen)?;
+        let mut params = vec![];
+        while !cursor.eat_token_if_eq(RightParen) {
+            params.push(TypeAnnotation::parse(cursor)?);
+            if !cursor.eat_token_if_eq(Comma) {
+                cursor.eat_token_eq(RightParen)?;
+                break;
+            }
+        }
+        let ret_type = if cursor.eat_token_if_eq(Token::RArrow) {
+            TypeAnnotation::parse(cursor)?
+        } else {
+            TypeAnnotation::Unit
+        };
+        Ok(TypeFnPtr::new(params, ret_type))
     }
 }
 
@@ -100,7 +195,28 @@ impl TypePtr {
 
 impl Parse for Vec<StructField> {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        unimplemented!()
+        if cursor.bump_token()? != &LeftCurlyBraces {
+            return Err("invalid struct field: except '{'".into());
+        }
+
+        let mut struct_fields = vec![];
+        loop {
+            if cursor.next_token()? == &RightCurlyBraces {
+                cursor.bump_token()?;
+                break;
+            }
+            let vis = Visibility::parse(cursor)?;
+            let name = cursor.eat_identifier()?.to_string();
+            cursor.eat_token_eq(Colon)?;
+            let _type = TypeAnnotation::parse(cursor)?;
+            struct_fields.push(StructField { vis, name, _type });
+            match cursor.bump_token()? {
+                Comma => {}
+                RightCurlyBraces => break,
+                _ => return Err("invalid struct field: except ','".into()),
+            }
+        }
+        Ok(struct_fields)
     }
 }
 
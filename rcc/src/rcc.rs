@@ -1,22 +1,28 @@
 use crate::analyser::sym_resolver::SymbolResolver;
 use crate::ast::AST;
-use crate::code_gen::riscv32::Riscv32CodeGen;
+use crate::code_gen::riscv::RiscvCodeGen;
 use crate::code_gen::TargetPlatform;
 use crate::ir::cfg::CFGIR;
 use crate::ir::ir_build::IRBuilder;
+use crate::ir::optimize::optimize;
+use crate::lexer::token::Span;
 use crate::lexer::Lexer;
 use crate::parser::{Parse, ParseCursor};
 use std::io::{BufReader, BufWriter, Read, Write};
+use strenum::StrEnum;
 
-#[derive(Copy, Clone)]
+#[derive(StrEnum, Copy, Clone)]
 pub enum OptimizeLevel {
+    #[strenum("0")]
     Zero,
+    #[strenum("1")]
     One,
 }
 
 pub struct RcCompiler<R: Read, W: Write> {
     input: BufReader<R>,
     pub output: BufWriter<W>,
+    target_platform: TargetPlatform,
     opt_level: OptimizeLevel,
 }
 
@@ -30,6 +36,7 @@ impl<R: Read, W: Write> RcCompiler<R, W> {
         RcCompiler {
             input: BufReader::new(input),
             output: BufWriter::new(output),
+            target_platform,
             opt_level,
         }
     }
@@ -49,19 +56,21 @@ impl<R: Read, W: Write> RcCompiler<R, W> {
         let mut sym_resolver = SymbolResolver::new();
         sym_resolver.visit_file(&mut ast.file)?;
 
-        let mut ir_builder = IRBuilder::new(self.opt_level);
+        let mut ir_builder = IRBuilder::new(self.target_platform.addr_size());
         let linear_ir = ir_builder.generate_ir(&mut ast)?;
-        let cfg_ir = CFGIR::new(linear_ir);
+        let mut cfg_ir = CFGIR::new(linear_ir);
 
-        match self.opt_level {
-            OptimizeLevel::Zero => {
-                let mut code_gen = Riscv32CodeGen::new(cfg_ir, &mut self.output, self.opt_level);
-                code_gen.run()?;
-            }
-            OptimizeLevel::One => {
-                todo!()
-            }
+        if let OptimizeLevel::One = self.opt_level {
+            optimize(&mut cfg_ir);
         }
+
+        let mut code_gen = RiscvCodeGen::new(
+            cfg_ir,
+            &mut self.output,
+            self.opt_level,
+            self.target_platform.addr_size(),
+        );
+        code_gen.run()?;
         Ok(())
     }
 }
@@ -76,6 +85,102 @@ pub enum RccError {
     ParseFloat(#[from] std::num::ParseFloatError),
     #[error("{0}")]
     Parse(String),
+    /// Error located in the source file.
+    #[error("{0}")]
+    Spanned(String, Span),
+    #[error("{}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n"))]
+    Multiple(Vec<RccError>),
+}
+
+impl RccError {
+    pub fn with_span<S: Into<String>>(msg: S, span: Span) -> RccError {
+        RccError::Spanned(msg.into(), span)
+    }
+
+    /// Merge the errors collected in one pass.
+    pub fn from_errors(mut errors: Vec<RccError>) -> RccError {
+        debug_assert!(!errors.is_empty());
+        if errors.len() == 1 {
+            errors.pop().unwrap()
+        } else {
+            RccError::Multiple(errors)
+        }
+    }
+
+    /// Attach `span` to the error if it has no location yet.
+    pub fn or_span(self, span: Span) -> RccError {
+        match self {
+            RccError::Parse(msg) => RccError::Spanned(msg, span),
+            RccError::ParseInt(e) => RccError::Spanned(e.to_string(), span),
+            RccError::ParseFloat(e) => RccError::Spanned(e.to_string(), span),
+            e => e,
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            RccError::Spanned(_, span) => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// Format the error as `file:line:col` followed by
+    /// the source line, the error location is underlined by carets.
+    ///
+    /// ```text
+    /// error: identifier `b` not found
+    ///  --> main.rs:2:13
+    ///   |
+    /// 2 |     let a = b;
+    ///   |             ^
+    /// ```
+    pub fn diagnostic(&self, file_name: &str, source: &str) -> String {
+        if let RccError::Multiple(errors) = self {
+            let diagnostics: Vec<String> = errors
+                .iter()
+                .map(|e| e.diagnostic(file_name, source))
+                .collect();
+            return format!(
+                "{}\n\nerror: aborting due to {} previous errors",
+                diagnostics.join("\n\n"),
+                errors.len()
+            );
+        }
+        let span = match self.span() {
+            Some(span) => span,
+            None => return format!("error: {}", self),
+        };
+        let lo = span.lo.min(source.len());
+        let line_start = source[..lo].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[lo..].find('\n').map_or(source.len(), |i| lo + i);
+        let line = &source[line_start..line_end];
+        let line_no = source[..lo].matches('\n').count() + 1;
+        let col = source[line_start..lo].chars().count() + 1;
+        let width = source[lo..span.hi.clamp(lo, line_end)].chars().count().max(1);
+
+        // keep tabs so that carets are aligned with the source line
+        let indent: String = source[line_start..lo]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let line_no = line_no.to_string();
+        let pad = " ".repeat(line_no.len());
+        format!(
+            "error: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+            self,
+            pad,
+            file_name,
+            line_no,
+            col,
+            pad,
+            line_no,
+            line,
+            pad,
+            indent,
+            "^".repeat(width)
+        )
+    }
 }
 
 impl From<String> for RccError {
@@ -105,6 +210,18 @@ impl PartialEq for RccError {
                 }
                 false
             }
+            RccError::Spanned(s, span) => {
+                if let RccError::Spanned(o, o_span) = other {
+                    return s == o && span == o_span;
+                }
+                false
+            }
+            RccError::Multiple(errors) => {
+                if let RccError::Multiple(o) = other {
+                    return errors == o;
+                }
+                false
+            }
             RccError::ParseInt(p) => {
                 if let RccError::ParseInt(o) = other {
                     return p == o;
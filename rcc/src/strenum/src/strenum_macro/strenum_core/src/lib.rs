@@ -13,15 +13,15 @@ pub fn add_impl_items(ast: syn::DeriveInput) -> proc_macro2::TokenStream {
     let DeriveInput {
         generics,
         data,
-        attrs,
         ident,
-        vis,
+        ..
     } = ast;
 
     match data {
         syn::Data::Enum(DataEnum { variants, .. }) => {
             for v in variants {
-                if v.attrs.is_empty() {
+                // doc comments are not `strenum` attributes
+                if v.attrs.iter().all(|attr| !attr.path.is_ident(STR_ENUM)) {
                     strs.push(v.ident.to_string().to_lowercase());
                     enums.push(format_ident!("{}", v.ident));
                 } else {
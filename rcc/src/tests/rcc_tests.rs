@@ -1,20 +1,39 @@
 use crate::code_gen::TargetPlatform;
 use crate::rcc::{OptimizeLevel, RcCompiler, RccError};
-use std::io::Read;
+use std::io::{Read, Write};
+use std::process::Command;
 
 fn file_path(file_name: &str) -> String {
     format!("./src/tests/{}", file_name)
 }
 
 fn test_compile(input: &str, expected_output: &str) -> Result<(), RccError> {
+    test_compile_with_optimize(input, expected_output, OptimizeLevel::Zero)
+}
+
+fn test_compile_with_optimize(
+    input: &str,
+    expected_output: &str,
+    opt_level: OptimizeLevel,
+) -> Result<(), RccError> {
+    test_compile_on(TargetPlatform::Riscv32, input, expected_output, opt_level)
+}
+
+fn test_compile_on(
+    target_platform: TargetPlatform,
+    input: &str,
+    expected_output: &str,
+    opt_level: OptimizeLevel,
+) -> Result<(), RccError> {
     let input = std::fs::File::open(file_path(input))?;
     let mut expected_output = std::fs::File::open(file_path(expected_output))?;
     let output = Vec::<u8>::new();
-    let mut rcc = RcCompiler::new(TargetPlatform::Riscv32, input, output, OptimizeLevel::Zero);
+    let mut rcc = RcCompiler::new(target_platform, input, output, opt_level);
 
     rcc.compile()?;
+    rcc.output.flush()?;
 
-    let s = std::str::from_utf8(rcc.output.buffer()).unwrap();
+    let s = std::str::from_utf8(rcc.output.get_ref()).unwrap();
     let mut expected = String::new();
     expected_output.read_to_string(&mut expected)?;
     assert_eq!(expected, s);
@@ -23,7 +42,157 @@ fn test_compile(input: &str, expected_output: &str) -> Result<(), RccError> {
 
 #[test]
 fn rcc_test() {
-    for i in 1..=5 {
+    for i in 1..=30 {
         test_compile(&format!("in{}.txt", i), &format!("out{}.txt", i)).unwrap();
     }
 }
+
+#[test]
+fn rcc_o1_test() {
+    for i in 1..=30 {
+        test_compile_with_optimize(
+            &format!("in{}.txt", i),
+            &format!("out{}_o1.txt", i),
+            OptimizeLevel::One,
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn rcc_riscv64_test() {
+    for i in [13, 23, 24, 25, 26, 27, 28, 29, 30] {
+        test_compile_on(
+            TargetPlatform::Riscv64,
+            &format!("in{}.txt", i),
+            &format!("out{}_rv64.txt", i),
+            OptimizeLevel::Zero,
+        )
+        .unwrap();
+        test_compile_on(
+            TargetPlatform::Riscv64,
+            &format!("in{}.txt", i),
+            &format!("out{}_rv64_o1.txt", i),
+            OptimizeLevel::One,
+        )
+        .unwrap();
+    }
+}
+
+/// The goldens are assembled by llvm-mc if it is installed,
+/// e.g. immediates out of range are rejected by the assembler.
+#[test]
+fn rcc_assemble_test() {
+    let mut goldens: Vec<String> = std::fs::read_dir(file_path(""))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("out"))
+        .collect();
+    goldens.sort();
+    for golden in goldens {
+        let triple = if golden.contains("_rv64") {
+            "riscv64"
+        } else {
+            "riscv32"
+        };
+        let status = Command::new("llvm-mc")
+            .arg(format!("-triple={}", triple))
+            .arg("-mattr=+m,+f,+d")
+            .arg("-filetype=null")
+            .arg(file_path(&golden))
+            .status();
+        match status {
+            Ok(status) => assert!(status.success(), "{} is rejected by llvm-mc", golden),
+            // llvm-mc is not installed
+            Err(_) => return,
+        }
+    }
+}
+
+#[test]
+fn diagnostic_test() {
+    fn compile_err(source: &str) -> String {
+        let mut rcc = RcCompiler::new(
+            TargetPlatform::Riscv32,
+            source.as_bytes(),
+            Vec::<u8>::new(),
+            OptimizeLevel::Zero,
+        );
+        rcc.compile().unwrap_err().diagnostic("main.rs", source)
+    }
+
+    let source = "fn main() {\n    let a = 1;\n    let b = a + c;\n}\n";
+    assert_eq!(
+        "error: identifier `c` not found\n \
+         --> main.rs:3:17\n  \
+         |\n\
+         3 |     let b = a + c;\n  \
+         |                 ^",
+        compile_err(source)
+    );
+
+    let source = "fn main() {\n\tlet a: i32 = 1\n}\n";
+    assert_eq!(
+        "error: error in parsing: except ;\n \
+         --> main.rs:3:1\n  \
+         |\n\
+         3 | }\n  \
+         | ^",
+        compile_err(source)
+    );
+
+    let source = "fn main() {\n\tlet a: bool = 1 + 2;\n}\n";
+    assert_eq!(
+        "error: invalid type in let stmt: expected `Bool`, found `LitNum(#i)`\n \
+         --> main.rs:2:2\n  \
+         |\n\
+         2 | \tlet a: bool = 1 + 2;\n  \
+         | \t^^^^^^^^^^^^^^^^^^^^",
+        compile_err(source)
+    );
+
+    let source = "fn main() {\n    let a = 0x10;\n}\n";
+    assert_eq!(
+        "error: invalid digit found in string\n \
+         --> main.rs:2:13\n  \
+         |\n\
+         2 |     let a = 0x10;\n  \
+         |             ^^^^",
+        compile_err(source)
+    );
+
+    let source = "fn main() {\n    let a: i32 = 1 + 99999999999;\n}\n";
+    assert_eq!(
+        "error: number too large to fit in target type\n \
+         --> main.rs:2:22\n  \
+         |\n\
+         2 |     let a: i32 = 1 + 99999999999;\n  \
+         |                      ^^^^^^^^^^^",
+        compile_err(source)
+    );
+
+    let source = "trait Area { fn area(&self) -> i32; }\nimpl<T: Copy> Area for T {\n    fn area(&self) -> i32 { 0 }\n}\n";
+    assert_eq!(
+        "error: generic impl blocks are not supported\n \
+         --> main.rs:2:1\n  \
+         |\n\
+         2 | impl<T: Copy> Area for T {\n  \
+         | ^^^^^^^^^^^^^",
+        compile_err(source)
+    );
+
+    let source = "struct Pair<T> { a: T, b: T }\nimpl Pair<i32> {\n    fn sum(&self) -> i32 { self.a + self.b }\n}\n";
+    assert_eq!(
+        "error: impl blocks of generic struct `Pair` are not supported\n \
+         --> main.rs:2:1\n  \
+         |\n\
+         2 | impl Pair<i32> {\n  \
+         | ^^^^^^^^^^^^^^",
+        compile_err(source)
+    );
+
+    assert_eq!(
+        "error: invalid optimize level 2",
+        RccError::from("invalid optimize level 2").diagnostic("main.rs", "")
+    );
+}